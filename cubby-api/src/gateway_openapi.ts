@@ -374,6 +374,15 @@ export function generateGatewayOpenAPISpec(baseUrl: string): OpenAPISpec {
             { name: "window_name", in: "query", schema: { type: "string" } },
             { name: "include_frames", in: "query", schema: { type: "boolean", default: false } },
             { name: "speaker_ids", in: "query", schema: { type: "array", items: { type: "integer" } }, style: "form", explode: true },
+            { name: "mode", in: "query", schema: { type: "string", enum: ["fts", "hybrid"], default: "fts" }, description: "hybrid ranks by keywords and meaning together, with a relevance per result" },
+            { name: "ocr_weight", in: "query", schema: { type: "number", default: 1 } },
+            { name: "audio_weight", in: "query", schema: { type: "number", default: 1 } },
+            { name: "ui_weight", in: "query", schema: { type: "number", default: 1 } },
+            { name: "recency_weight", in: "query", schema: { type: "number", default: 0.2 }, description: "boost of recent results in hybrid mode, 0 turns it off" },
+            { name: "recency_half_life_hours", in: "query", schema: { type: "number", default: 168 } },
+            { name: "cursor", in: "query", schema: { type: "string" }, description: "next_cursor of the previous page, pages stay stable while new content is recorded" },
+            { name: "order", in: "query", schema: { type: "string", enum: ["asc", "desc"], default: "desc" }, description: "timestamp order of fts results" },
+            { name: "include_total", in: "query", schema: { type: "boolean" }, description: "count all matches, defaults to true without a cursor" },
           ],
           responses: {
             200: {
@@ -387,6 +396,63 @@ export function generateGatewayOpenAPISpec(baseUrl: string): OpenAPISpec {
           },
         },
       },
+      "/devices/{deviceId}/search/aggregate": {
+        get: {
+          summary: "[device] aggregate search results",
+          description: "counts and estimated screen and speech time of what search matches, per app, window, browser domain, device and speaker, with a time histogram",
+          tags: ["device - search"],
+          operationId: "deviceSearchAggregate",
+          parameters: [
+            { $ref: "#/components/parameters/DeviceId" },
+            { name: "q", in: "query", schema: { type: "string" }, description: "search query text" },
+            { name: "content_type", in: "query", schema: { $ref: "#/components/schemas/ContentType" } },
+            { name: "start_time", in: "query", schema: { type: "string", format: "date-time" } },
+            { name: "end_time", in: "query", schema: { type: "string", format: "date-time" } },
+            { name: "app_name", in: "query", schema: { type: "string" }, description: "filter by application" },
+            { name: "window_name", in: "query", schema: { type: "string" } },
+            { name: "speaker_ids", in: "query", schema: { type: "array", items: { type: "integer" } }, style: "form", explode: true },
+            { name: "bucket_seconds", in: "query", schema: { type: "integer", default: 3600 }, description: "histogram bucket width" },
+            { name: "top", in: "query", schema: { type: "integer", default: 10 }, description: "values kept per facet" },
+            { name: "max_frame_gap_seconds", in: "query", schema: { type: "number", default: 60 }, description: "longest gap between frames counted as screen time" },
+          ],
+          responses: {
+            200: {
+              description: "search aggregate",
+              content: {
+                "application/json": {
+                  schema: { $ref: "#/components/schemas/SearchAggregate" },
+                },
+              },
+            },
+          },
+        },
+      },
+      "/devices/{deviceId}/activity": {
+        get: {
+          summary: "[device] activity report",
+          description: "time spent per app, browser domain or window, from focused windows rolled up into sessions with idle time left out",
+          tags: ["device - search"],
+          operationId: "deviceActivity",
+          parameters: [
+            { $ref: "#/components/parameters/DeviceId" },
+            { name: "start", in: "query", schema: { type: "string", format: "date-time" }, description: "a day before end by default" },
+            { name: "end", in: "query", schema: { type: "string", format: "date-time" }, description: "now by default" },
+            { name: "group_by", in: "query", schema: { type: "string", enum: ["app", "domain", "window"], default: "app" } },
+            { name: "include_sessions", in: "query", schema: { type: "boolean", default: false }, description: "also list the sessions" },
+          ],
+          responses: {
+            200: {
+              description: "activity report",
+              content: {
+                "application/json": {
+                  schema: { $ref: "#/components/schemas/ActivityReport" },
+                },
+              },
+            },
+            400: { description: "start after end" },
+          },
+        },
+      },
       "/devices/{deviceId}/search/keyword": {
         get: {
           summary: "[device] keyword search",
@@ -420,7 +486,7 @@ export function generateGatewayOpenAPISpec(baseUrl: string): OpenAPISpec {
       "/devices/{deviceId}/semantic-search": {
         get: {
           summary: "[device] semantic search",
-          description: "vector similarity search using embeddings",
+          description: "vector similarity search over screen text, audio transcriptions, and ui elements, closest first",
           tags: ["device - search"],
           operationId: "deviceSemanticSearch",
           parameters: [
@@ -428,6 +494,12 @@ export function generateGatewayOpenAPISpec(baseUrl: string): OpenAPISpec {
             { name: "text", in: "query", required: true, schema: { type: "string" } },
             { name: "limit", in: "query", schema: { type: "integer", default: 10 } },
             { name: "threshold", in: "query", schema: { type: "number", format: "float" } },
+            { name: "content_type", in: "query", schema: { $ref: "#/components/schemas/ContentType" } },
+            { name: "start_time", in: "query", schema: { type: "string", format: "date-time" } },
+            { name: "end_time", in: "query", schema: { type: "string", format: "date-time" } },
+            { name: "app_name", in: "query", schema: { type: "string" }, description: "filter by application" },
+            { name: "window_name", in: "query", schema: { type: "string" } },
+            { name: "speaker_ids", in: "query", schema: { type: "array", items: { type: "integer" } }, style: "form", explode: true },
           ],
           responses: {
             200: {
@@ -436,7 +508,7 @@ export function generateGatewayOpenAPISpec(baseUrl: string): OpenAPISpec {
                 "application/json": {
                   schema: {
                     type: "array",
-                    items: { $ref: "#/components/schemas/OCRResult" },
+                    items: { $ref: "#/components/schemas/ContentItem" },
                   },
                 },
               },
@@ -1055,38 +1127,107 @@ export function generateGatewayOpenAPISpec(baseUrl: string): OpenAPISpec {
         },
         PaginationInfo: {
           type: "object",
-          required: ["limit", "offset", "total"],
+          required: ["limit", "offset"],
           properties: {
             limit: { type: "integer" },
             offset: { type: "integer" },
-            total: { type: "integer" },
+            total: { type: "integer", description: "estimated number of matches, absent when not counted" },
+            next_cursor: { type: "string", description: "cursor of the next page, absent on the last page" },
           },
         },
-        SearchMatch: {
+        Facet: {
           type: "object",
-          required: ["frame_id", "timestamp", "text", "app_name", "window_name"],
+          required: ["value", "count", "seconds"],
           properties: {
-            frame_id: { type: "integer" },
-            timestamp: { type: "string", format: "date-time" },
-            text: { type: "string" },
+            value: { type: "string" },
+            count: { type: "integer" },
+            seconds: { type: "number" },
+          },
+        },
+        SearchAggregate: {
+          type: "object",
+          properties: {
+            ocr_count: { type: "integer" },
+            audio_count: { type: "integer" },
+            ui_count: { type: "integer" },
+            screen_seconds: { type: "number" },
+            speech_seconds: { type: "number" },
+            apps: { type: "array", items: { $ref: "#/components/schemas/Facet" } },
+            windows: { type: "array", items: { $ref: "#/components/schemas/Facet" } },
+            domains: { type: "array", items: { $ref: "#/components/schemas/Facet" } },
+            devices: { type: "array", items: { $ref: "#/components/schemas/Facet" } },
+            speakers: {
+              type: "array",
+              items: {
+                type: "object",
+                properties: {
+                  speaker_id: { type: "integer", nullable: true },
+                  name: { type: "string", nullable: true },
+                  count: { type: "integer" },
+                  seconds: { type: "number" },
+                },
+              },
+            },
+            histogram: {
+              type: "array",
+              items: {
+                type: "object",
+                properties: {
+                  start: { type: "string", format: "date-time" },
+                  ocr_count: { type: "integer" },
+                  audio_count: { type: "integer" },
+                  ui_count: { type: "integer" },
+                  screen_seconds: { type: "number" },
+                  speech_seconds: { type: "number" },
+                },
+              },
+            },
+          },
+        },
+        ActivitySession: {
+          type: "object",
+          properties: {
+            id: { type: "integer" },
             app_name: { type: "string" },
             window_name: { type: "string" },
-            confidence: { type: "number", format: "float" },
-            url: { type: "string" },
+            domain: { type: "string", nullable: true },
+            start_time: { type: "string", format: "date-time" },
+            end_time: { type: "string", format: "date-time" },
+            frame_count: { type: "integer" },
+          },
+        },
+        ActivityReport: {
+          type: "object",
+          properties: {
+            start: { type: "string", format: "date-time" },
+            end: { type: "string", format: "date-time" },
+            total_seconds: { type: "number" },
+            groups: {
+              type: "array",
+              items: {
+                type: "object",
+                properties: {
+                  name: { type: "string", description: "app, domain or window title" },
+                  app_name: { type: "string", nullable: true, description: "app of the window when grouping by window" },
+                  seconds: { type: "number" },
+                  sessions: { type: "integer" },
+                },
+              },
+            },
+            sessions: { type: "array", items: { $ref: "#/components/schemas/ActivitySession" } },
           },
         },
-        OCRResult: {
+        SearchMatch: {
           type: "object",
-          required: ["frame_id", "ocr_text", "timestamp", "file_path", "app_name"],
+          required: ["frame_id", "timestamp", "text", "app_name", "window_name"],
           properties: {
             frame_id: { type: "integer" },
-            frame_name: { type: "string" },
-            ocr_text: { type: "string" },
             timestamp: { type: "string", format: "date-time" },
-            file_path: { type: "string" },
+            text: { type: "string" },
             app_name: { type: "string" },
             window_name: { type: "string" },
-            tags: { type: "array", items: { type: "string" } },
+            confidence: { type: "number", format: "float" },
+            url: { type: "string" },
           },
         },
         AudioDevice: {
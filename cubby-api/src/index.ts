@@ -128,6 +128,7 @@ app.use(
     allowHeaders: [
       "Content-Type",
       "Authorization",
+      "X-Cubby-Token", // device api token, forwarded to the device
       "mcp-protocol-version", // MCP protocol header
     ],
     exposeHeaders: ["mcp-protocol-version"],
@@ -649,6 +650,7 @@ app.all(
       const url = new URL(c.req.url);
       const targetUrl = `https://${deviceId}.${c.env.TUNNEL_DOMAIN}${path}${url.search}`;
       const requestId = crypto.randomUUID();
+      const deviceToken = c.req.header("X-Cubby-Token");
       const isWebSocketUpgrade =
         method === "GET" &&
         path === "/ws/events" &&
@@ -673,6 +675,11 @@ app.all(
           headers.set("CF-Access-Client-Id", c.env.ACCESS_CLIENT_ID);
           headers.set("CF-Access-Client-Secret", c.env.ACCESS_CLIENT_SECRET);
           headers.set("X-Cubby-Request-Id", requestId);
+          // The device checks its own api tokens, passed separately from the OAuth token
+          if (deviceToken) {
+            headers.delete("X-Cubby-Token");
+            headers.set("Authorization", `Bearer ${deviceToken}`);
+          }
 
           const upstreamResponse = await fetch(forwardedRequest);
 
@@ -715,6 +722,9 @@ app.all(
       proxyHeaders.set("CF-Access-Client-Id", c.env.ACCESS_CLIENT_ID);
       proxyHeaders.set("CF-Access-Client-Secret", c.env.ACCESS_CLIENT_SECRET);
       proxyHeaders.set("X-Cubby-Request-Id", requestId);
+      if (deviceToken) {
+        proxyHeaders.set("Authorization", `Bearer ${deviceToken}`);
+      }
       
       // Copy content-type if present
       const contentType = c.req.header("content-type");
@@ -882,6 +892,7 @@ app.all(
             resources: {
               ...(bodyJson.result.capabilities?.resources || {}),
             },
+            prompts: { ...(bodyJson.result.capabilities?.prompts || {}) },
           };
         }
 
@@ -1087,7 +1098,9 @@ app.all(
 
       case "resources/list":
       case "resources/read":
-      case "resources/templates/list": {
+      case "resources/templates/list":
+      case "prompts/list":
+      case "prompts/get": {
         // Persist auth if provided (for future requests)
         persistAuthIfNeeded();
 
@@ -1117,13 +1130,15 @@ app.all(
           }
         }
 
-        // Default: return empty resources
+        // Default: return empty resources and prompts
         const emptyResult =
           rpcMethod === "resources/list"
             ? { resources: [] }
             : rpcMethod === "resources/templates/list"
               ? { resourceTemplates: [] }
-              : null;
+              : rpcMethod === "prompts/list"
+                ? { prompts: [] }
+                : null;
 
         return c.json({
           jsonrpc: "2.0",
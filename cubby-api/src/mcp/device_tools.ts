@@ -38,6 +38,10 @@ const searchSchema = z.object({
   window_name: z.string().optional(),
   include_frames: z.boolean().optional(),
   speaker_ids: z.array(z.number().int()).optional(),
+  mode: z.enum(["fts", "hybrid"]).optional(),
+  cursor: z.string().optional(),
+  order: z.enum(["asc", "desc"]).optional(),
+  include_total: z.boolean().optional(),
 });
 
 const keywordSearchSchema = z.object({
@@ -53,6 +57,51 @@ const semanticSearchSchema = z.object({
   text: z.string(),
   limit: z.number().int().optional(),
   threshold: z.number().optional(),
+  content_type: z
+    .enum([
+      "all",
+      "ocr",
+      "audio",
+      "ui",
+      "audio+ui",
+      "ocr+ui",
+      "audio+ocr",
+    ])
+    .optional(),
+  start_time: z.string().optional(),
+  end_time: z.string().optional(),
+  app_name: z.string().optional(),
+  window_name: z.string().optional(),
+  speaker_ids: z.array(z.number().int()).optional(),
+});
+
+const searchAggregateSchema = z.object({
+  q: z.string().optional(),
+  content_type: z
+    .enum([
+      "all",
+      "ocr",
+      "audio",
+      "ui",
+      "audio+ui",
+      "ocr+ui",
+      "audio+ocr",
+    ])
+    .optional(),
+  start_time: z.string().optional(),
+  end_time: z.string().optional(),
+  app_name: z.string().optional(),
+  window_name: z.string().optional(),
+  speaker_ids: z.array(z.number().int()).optional(),
+  bucket_seconds: z.number().int().optional(),
+  top: z.number().int().optional(),
+});
+
+const activitySchema = z.object({
+  start: z.string().optional(),
+  end: z.string().optional(),
+  group_by: z.enum(["app", "domain", "window"]).optional(),
+  include_sessions: z.boolean().optional(),
 });
 
 const frameGetSchema = z.object({
@@ -131,6 +180,16 @@ export const DEVICE_TOOLS = [
     description: "[device] semantic search",
     inputSchema: z.toJSONSchema(semanticSearchSchema),
   },
+  {
+    name: "device/search/aggregate",
+    description: "[device] screen time, speech time and counts per app, window, domain, device and speaker, with a time histogram",
+    inputSchema: z.toJSONSchema(searchAggregateSchema),
+  },
+  {
+    name: "device/activity",
+    description: "[device] time spent per app, browser domain or window, idle time left out",
+    inputSchema: z.toJSONSchema(activitySchema),
+  },
   {
     name: "device/audio/list",
     description: "[device] list audio devices",
@@ -226,6 +285,10 @@ export async function callDeviceTool(
       if (parsed.window_name) qs.set("window_name", parsed.window_name);
       if (parsed.include_frames !== undefined) qs.set("include_frames", String(parsed.include_frames));
       if (parsed.speaker_ids) parsed.speaker_ids.forEach((v) => qs.append("speaker_ids", String(v)));
+      if (parsed.mode) qs.set("mode", parsed.mode);
+      if (parsed.cursor) qs.set("cursor", parsed.cursor);
+      if (parsed.order) qs.set("order", parsed.order);
+      if (parsed.include_total !== undefined) qs.set("include_total", String(parsed.include_total));
       const resp = await callDeviceRest(env, deviceId, "GET", `/search?${qs.toString()}`, { userId, gwSessionId });
       const json = await resp.json();
       return { content: [{ type: "text" as const, text: "device search results" }], structuredContent: json };
@@ -249,10 +312,43 @@ export async function callDeviceTool(
       qs.set("text", parsed.text);
       if (parsed.limit !== undefined) qs.set("limit", String(parsed.limit));
       if (parsed.threshold !== undefined) qs.set("threshold", String(parsed.threshold));
+      if (parsed.content_type) qs.set("content_type", parsed.content_type);
+      if (parsed.start_time) qs.set("start_time", parsed.start_time);
+      if (parsed.end_time) qs.set("end_time", parsed.end_time);
+      if (parsed.app_name) qs.set("app_name", parsed.app_name);
+      if (parsed.window_name) qs.set("window_name", parsed.window_name);
+      if (parsed.speaker_ids) parsed.speaker_ids.forEach((v) => qs.append("speaker_ids", String(v)));
       const resp = await callDeviceRest(env, deviceId, "GET", `/semantic-search?${qs.toString()}`, { userId, gwSessionId });
       const json = await resp.json();
       return { content: [{ type: "text" as const, text: "semantic search results" }], structuredContent: json };
     }
+    case "device/search/aggregate": {
+      const parsed = searchAggregateSchema.parse(args || {});
+      const qs = new URLSearchParams();
+      if (parsed.q) qs.set("q", parsed.q);
+      if (parsed.content_type) qs.set("content_type", parsed.content_type);
+      if (parsed.start_time) qs.set("start_time", parsed.start_time);
+      if (parsed.end_time) qs.set("end_time", parsed.end_time);
+      if (parsed.app_name) qs.set("app_name", parsed.app_name);
+      if (parsed.window_name) qs.set("window_name", parsed.window_name);
+      if (parsed.speaker_ids) parsed.speaker_ids.forEach((v) => qs.append("speaker_ids", String(v)));
+      if (parsed.bucket_seconds !== undefined) qs.set("bucket_seconds", String(parsed.bucket_seconds));
+      if (parsed.top !== undefined) qs.set("top", String(parsed.top));
+      const resp = await callDeviceRest(env, deviceId, "GET", `/search/aggregate?${qs.toString()}`, { userId, gwSessionId });
+      const json = await resp.json();
+      return { content: [{ type: "text" as const, text: "search aggregate" }], structuredContent: json };
+    }
+    case "device/activity": {
+      const parsed = activitySchema.parse(args || {});
+      const qs = new URLSearchParams();
+      if (parsed.start) qs.set("start", parsed.start);
+      if (parsed.end) qs.set("end", parsed.end);
+      if (parsed.group_by) qs.set("group_by", parsed.group_by);
+      if (parsed.include_sessions !== undefined) qs.set("include_sessions", String(parsed.include_sessions));
+      const resp = await callDeviceRest(env, deviceId, "GET", `/activity?${qs.toString()}`, { userId, gwSessionId });
+      const json = await resp.json();
+      return { content: [{ type: "text" as const, text: "activity report" }], structuredContent: json };
+    }
     case "device/audio/list": {
       emptySchema.parse(args || {});
       const resp = await callDeviceRest(env, deviceId, "GET", "/audio/list", { userId, gwSessionId });
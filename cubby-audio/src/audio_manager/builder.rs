@@ -8,6 +8,7 @@ use crate::{
     core::{
         device::{default_input_device, default_output_device},
         engine::AudioTranscriptionEngine,
+        source::AudioSource,
     },
     transcription::deepgram::CUSTOM_DEEPGRAM_API_TOKEN,
     vad::{VadEngineEnum, VadSensitivity},
@@ -34,6 +35,8 @@ pub struct AudioManagerOptions {
     pub vad_sensitivity: VadSensitivity,
     pub health_check_grace_period: u64,
     pub enabled_devices: HashSet<String>,
+    /// Files, pipes or generated signals recorded alongside (or instead of) devices
+    pub sources: Vec<AudioSource>,
     pub use_all_devices: bool,
     pub db_path: Option<String>,
     pub deepgram_url: Option<String>,
@@ -60,6 +63,7 @@ impl Default for AudioManagerOptions {
             vad_sensitivity: VadSensitivity::High,
             health_check_grace_period: 15,
             enabled_devices,
+            sources: vec![],
             use_all_devices: false,
             db_path: None,
             deepgram_url,
@@ -131,6 +135,16 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn sources(mut self, sources: Vec<AudioSource>) -> Self {
+        self.options.sources = sources;
+        self
+    }
+
+    pub fn source(mut self, source: AudioSource) -> Self {
+        self.options.sources.push(source);
+        self
+    }
+
     pub fn use_all_devices(mut self, use_all_devices: bool) -> Self {
         self.options.use_all_devices = use_all_devices;
         self
@@ -155,7 +169,19 @@ impl AudioManagerBuilder {
         self.validate_options()?;
         let options = &mut self.options;
 
-        if options.enabled_devices.is_empty() {
+        // sources can be given in place of a device name
+        let source_names: Vec<String> = options
+            .enabled_devices
+            .iter()
+            .filter(|name| AudioSource::is_source_name(name))
+            .cloned()
+            .collect();
+        for name in source_names {
+            options.enabled_devices.remove(&name);
+            options.sources.push(name.parse()?);
+        }
+
+        if options.enabled_devices.is_empty() && options.sources.is_empty() {
             options.enabled_devices = HashSet::from_iter(vec![
                 default_input_device()?.to_string(),
                 default_output_device().await?.to_string(),
@@ -20,6 +20,7 @@ use crate::{
     core::{
         device::{parse_audio_device, AudioDevice},
         record_and_transcribe,
+        source::AudioSource,
     },
     device::device_manager::DeviceManager,
     segmentation::segmentation_manager::SegmentationManager,
@@ -119,6 +120,13 @@ impl AudioManager {
 
         start_device_monitor(self_arc.clone(), self.device_manager.clone()).await?;
 
+        let sources = self.options.read().await.sources.clone();
+        for source in sources {
+            if let Err(e) = self.start_source(&source).await {
+                error!("failed to start audio source {}: {}", source, e);
+            }
+        }
+
         info!("audio manager started");
 
         Ok(())
@@ -183,6 +191,12 @@ impl AudioManager {
             .enabled_devices
             .remove(device_name);
 
+        self.options
+            .write()
+            .await
+            .sources
+            .retain(|source| source.device() != device);
+
         self.device_manager.stop_device(&device).await?;
 
         if let Some(pair) = self.recording_handles.get(&device) {
@@ -231,6 +245,26 @@ impl AudioManager {
         Ok(())
     }
 
+    /// Records a source like a device. Sources are not watched by the device monitor, they
+    /// stop on their own when their input runs out.
+    pub async fn start_source(&self, source: &AudioSource) -> Result<()> {
+        let device = self.device_manager.start_source(source).await?;
+
+        if let Some(is_running) = self.device_manager.is_running_mut(&device) {
+            is_running.store(true, Ordering::Relaxed);
+        }
+        let handle = self.record_device(&device).await?;
+        self.recording_handles
+            .insert(device, Arc::new(Mutex::new(handle)));
+
+        let mut options = self.options.write().await;
+        if !options.sources.contains(source) {
+            options.sources.push(source.clone());
+        }
+
+        Ok(())
+    }
+
     pub async fn use_all_devices(&self) -> bool {
         self.options.read().await.use_all_devices
     }
@@ -398,6 +432,10 @@ impl AudioManager {
     pub async fn enabled_devices(&self) -> HashSet<String> {
         self.options.read().await.enabled_devices.clone()
     }
+
+    pub async fn sources(&self) -> Vec<AudioSource> {
+        self.options.read().await.sources.clone()
+    }
 }
 
 impl Drop for AudioManager {
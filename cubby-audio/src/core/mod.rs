@@ -1,6 +1,7 @@
 pub mod device;
 pub mod engine;
 mod run_record_and_transcribe;
+pub mod source;
 pub mod stream;
 use crate::transcription::deepgram::streaming::stream_transcription_deepgram;
 use crate::AudioInput;
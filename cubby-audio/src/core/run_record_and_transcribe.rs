@@ -40,6 +40,10 @@ pub async fn run_record_and_transcribe(
     {
         while collected_audio.len() < max_samples && is_running.load(Ordering::Relaxed) {
             match receiver.recv().await {
+                Ok(chunk) if chunk.is_empty() && audio_stream.is_disconnected() => {
+                    // the source ended, flush what was collected
+                    break;
+                }
                 Ok(chunk) => {
                     collected_audio.extend(chunk);
                     update_device_capture_time(&device_name);
@@ -0,0 +1,377 @@
+use std::{
+    f64::consts::PI,
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use rand::{rng, Rng};
+
+use crate::utils::audio::{audio_to_mono, pcm_decode};
+
+use super::device::{AudioDevice, DeviceType};
+
+/// Length of the chunks a source sends to its stream
+pub const SOURCE_CHUNK_DURATION: Duration = Duration::from_millis(100);
+
+const PCM_DEFAULT_SAMPLE_RATE: u32 = 16000;
+const SYNTHETIC_SAMPLE_RATE: u32 = 16000;
+const SYNTHETIC_AMPLITUDE: f32 = 0.3;
+
+/// Sample encoding of raw PCM input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PcmFormat {
+    S16Le,
+    F32Le,
+}
+
+impl PcmFormat {
+    fn sample_size(self) -> usize {
+        match self {
+            PcmFormat::S16Le => 2,
+            PcmFormat::F32Le => 4,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Vec<f32> {
+        match self {
+            PcmFormat::S16Le => bytes
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32)
+                .collect(),
+            PcmFormat::F32Le => bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for PcmFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "s16le" => Ok(PcmFormat::S16Le),
+            "f32le" => Ok(PcmFormat::F32Le),
+            _ => Err(anyhow!("unknown pcm format {s}, expected s16le or f32le")),
+        }
+    }
+}
+
+impl fmt::Display for PcmFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PcmFormat::S16Le => write!(f, "s16le"),
+            PcmFormat::F32Le => write!(f, "f32le"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyntheticSignal {
+    Silence,
+    Sine { frequency: f32 },
+    Noise,
+}
+
+/// Audio that does not come from a capture device. Sources are streamed in real time
+/// like a device would, and end when their input runs out.
+///
+/// They can be given in place of a device name:
+/// - `file:<path>` for any file `pcm_decode` can read (WAV, MP3, ...)
+/// - `pcm:<path>` or `pcm:<format>:<sample rate>:<channels>:<path>` for raw PCM read from a
+///   FIFO or a file, `-` reads stdin. Defaults to s16le, 16000 Hz, mono.
+/// - `synthetic:silence`, `synthetic:noise` or `synthetic:sine[:<frequency>]`
+#[derive(Clone, Debug, PartialEq)]
+pub enum AudioSource {
+    File(PathBuf),
+    Pcm {
+        /// `None` reads stdin
+        path: Option<PathBuf>,
+        format: PcmFormat,
+        sample_rate: u32,
+        channels: u16,
+    },
+    Synthetic {
+        signal: SyntheticSignal,
+        sample_rate: u32,
+        /// `None` generates until the stream is stopped
+        duration: Option<Duration>,
+    },
+}
+
+impl AudioSource {
+    const PREFIXES: [&'static str; 3] = ["file:", "pcm:", "synthetic:"];
+
+    /// Whether a name given in place of a device name refers to a source
+    pub fn is_source_name(name: &str) -> bool {
+        Self::PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+    }
+
+    /// The device the audio of this source is recorded under
+    pub fn device(&self) -> AudioDevice {
+        AudioDevice::new(self.to_string(), DeviceType::Input)
+    }
+
+    /// Opens the source, decoding files up front
+    pub(crate) fn open(&self) -> Result<Box<dyn SourceReader>> {
+        match self {
+            AudioSource::File(path) => {
+                let (samples, sample_rate) = pcm_decode(path)
+                    .map_err(|e| anyhow!("failed to decode {}: {}", path.display(), e))?;
+                Ok(Box::new(FileReader {
+                    samples,
+                    position: 0,
+                    sample_rate,
+                }))
+            }
+            AudioSource::Pcm {
+                path,
+                format,
+                sample_rate,
+                channels,
+            } => {
+                if *sample_rate == 0 || *channels == 0 {
+                    return Err(anyhow!("pcm sample rate and channels must be positive"));
+                }
+                let input: Box<dyn Read + Send> = match path {
+                    Some(path) => Box::new(
+                        File::open(path)
+                            .map_err(|e| anyhow!("failed to open {}: {}", path.display(), e))?,
+                    ),
+                    None => Box::new(io::stdin()),
+                };
+                Ok(Box::new(PcmReader {
+                    input,
+                    format: *format,
+                    sample_rate: *sample_rate,
+                    channels: *channels,
+                }))
+            }
+            AudioSource::Synthetic {
+                signal,
+                sample_rate,
+                duration,
+            } => Ok(Box::new(SyntheticReader {
+                signal: *signal,
+                sample_rate: *sample_rate,
+                remaining: duration.map(|d| (d.as_secs_f64() * *sample_rate as f64) as u64),
+                position: 0,
+            })),
+        }
+    }
+}
+
+impl FromStr for AudioSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(path) = s.strip_prefix("file:") {
+            if path.is_empty() {
+                return Err(anyhow!("file source needs a path"));
+            }
+            return Ok(AudioSource::File(PathBuf::from(path)));
+        }
+
+        if let Some(spec) = s.strip_prefix("pcm:") {
+            let parts: Vec<&str> = spec.splitn(4, ':').collect();
+            let (format, sample_rate, channels, path) = match parts.as_slice() {
+                [path] => (PcmFormat::S16Le, PCM_DEFAULT_SAMPLE_RATE, 1, *path),
+                [format, sample_rate, channels, path] => (
+                    format.parse()?,
+                    sample_rate
+                        .parse()
+                        .map_err(|_| anyhow!("invalid pcm sample rate {sample_rate}"))?,
+                    channels
+                        .parse()
+                        .map_err(|_| anyhow!("invalid pcm channel count {channels}"))?,
+                    *path,
+                ),
+                _ => {
+                    return Err(anyhow!(
+                        "expected pcm:<path> or pcm:<format>:<sample rate>:<channels>:<path>"
+                    ))
+                }
+            };
+            if path.is_empty() {
+                return Err(anyhow!("pcm source needs a path, or - for stdin"));
+            }
+            return Ok(AudioSource::Pcm {
+                path: (path != "-").then(|| PathBuf::from(path)),
+                format,
+                sample_rate,
+                channels,
+            });
+        }
+
+        if let Some(spec) = s.strip_prefix("synthetic:") {
+            let signal = match spec.split_once(':') {
+                None if spec == "silence" => SyntheticSignal::Silence,
+                None if spec == "noise" => SyntheticSignal::Noise,
+                None if spec == "sine" => SyntheticSignal::Sine { frequency: 440.0 },
+                Some(("sine", frequency)) => SyntheticSignal::Sine {
+                    frequency: frequency
+                        .parse()
+                        .map_err(|_| anyhow!("invalid sine frequency {frequency}"))?,
+                },
+                _ => return Err(anyhow!("unknown synthetic signal {spec}")),
+            };
+            return Ok(AudioSource::Synthetic {
+                signal,
+                sample_rate: SYNTHETIC_SAMPLE_RATE,
+                duration: None,
+            });
+        }
+
+        Err(anyhow!("{s} is not an audio source"))
+    }
+}
+
+impl fmt::Display for AudioSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AudioSource::File(path) => write!(f, "file:{}", path.display()),
+            AudioSource::Pcm {
+                path,
+                format,
+                sample_rate,
+                channels,
+            } => write!(
+                f,
+                "pcm:{}:{}:{}:{}",
+                format,
+                sample_rate,
+                channels,
+                path.as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            ),
+            AudioSource::Synthetic { signal, .. } => match signal {
+                SyntheticSignal::Silence => write!(f, "synthetic:silence"),
+                SyntheticSignal::Noise => write!(f, "synthetic:noise"),
+                SyntheticSignal::Sine { frequency } => write!(f, "synthetic:sine:{}", frequency),
+            },
+        }
+    }
+}
+
+/// Reads the mono samples of a source, chunk by chunk
+pub(crate) trait SourceReader: Send {
+    fn sample_rate(&self) -> u32;
+
+    /// The next chunk of about `SOURCE_CHUNK_DURATION`, `None` once the source is exhausted
+    fn read_chunk(&mut self) -> Result<Option<Vec<f32>>>;
+}
+
+fn chunk_len(sample_rate: u32) -> usize {
+    ((sample_rate as f64 * SOURCE_CHUNK_DURATION.as_secs_f64()) as usize).max(1)
+}
+
+struct FileReader {
+    samples: Vec<f32>,
+    position: usize,
+    sample_rate: u32,
+}
+
+impl SourceReader for FileReader {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn read_chunk(&mut self) -> Result<Option<Vec<f32>>> {
+        if self.position >= self.samples.len() {
+            return Ok(None);
+        }
+        let end = (self.position + chunk_len(self.sample_rate)).min(self.samples.len());
+        let chunk = self.samples[self.position..end].to_vec();
+        self.position = end;
+        Ok(Some(chunk))
+    }
+}
+
+struct PcmReader {
+    input: Box<dyn Read + Send>,
+    format: PcmFormat,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl SourceReader for PcmReader {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn read_chunk(&mut self) -> Result<Option<Vec<f32>>> {
+        let frame_size = self.format.sample_size() * self.channels as usize;
+        let mut buffer = vec![0u8; chunk_len(self.sample_rate) * frame_size];
+
+        // a pipe hands out whatever the writer flushed, keep reading until the chunk is
+        // full or the writer hangs up
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match self.input.read(&mut buffer[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // drop a trailing partial frame
+        let filled = filled - filled % frame_size;
+        if filled == 0 {
+            return Ok(None);
+        }
+
+        let samples = self.format.decode(&buffer[..filled]);
+        Ok(Some(audio_to_mono(&samples, self.channels)))
+    }
+}
+
+struct SyntheticReader {
+    signal: SyntheticSignal,
+    sample_rate: u32,
+    remaining: Option<u64>,
+    position: u64,
+}
+
+impl SourceReader for SyntheticReader {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn read_chunk(&mut self) -> Result<Option<Vec<f32>>> {
+        let mut len = chunk_len(self.sample_rate) as u64;
+        if let Some(remaining) = self.remaining.as_mut() {
+            len = len.min(*remaining);
+            *remaining -= len;
+        }
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let start = self.position;
+        self.position += len;
+        let chunk = match self.signal {
+            SyntheticSignal::Silence => vec![0.0; len as usize],
+            SyntheticSignal::Noise => {
+                let mut rng = rng();
+                (0..len)
+                    .map(|_| rng.random_range(-SYNTHETIC_AMPLITUDE..SYNTHETIC_AMPLITUDE))
+                    .collect()
+            }
+            SyntheticSignal::Sine { frequency } => (start..start + len)
+                .map(|i| {
+                    let t = i as f64 / self.sample_rate as f64;
+                    SYNTHETIC_AMPLITUDE * (2.0 * PI * frequency as f64 * t).sin() as f32
+                })
+                .collect(),
+        };
+        Ok(Some(chunk))
+    }
+}
@@ -4,13 +4,15 @@ use cpal::StreamError;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, oneshot};
 use tokio::task::LocalSet;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 use crate::utils::audio::audio_to_mono;
 
 use super::device::{get_cpal_device_and_config, AudioDevice};
+use super::source::{AudioSource, SourceReader};
 
 #[derive(Clone)]
 pub struct AudioStream {
@@ -62,6 +64,93 @@ impl AudioStream {
         })
     }
 
+    /// Streams a file, a pipe or a generated signal as if it was a capture device. The
+    /// stream disconnects once the source is exhausted.
+    pub async fn from_source(source: &AudioSource) -> Result<Self> {
+        let (tx, _) = broadcast::channel::<Vec<f32>>(1000);
+        let tx_clone = tx.clone();
+
+        let reader_source = source.clone();
+        let reader = tokio::task::spawn_blocking(move || reader_source.open()).await??;
+        let sample_rate = reader.sample_rate();
+
+        let is_disconnected = Arc::new(AtomicBool::new(false));
+        let (stream_control_tx, stream_control_rx) = mpsc::channel();
+
+        let stream_thread = Self::spawn_source_thread(
+            source.to_string(),
+            reader,
+            tx,
+            stream_control_rx,
+            is_disconnected.clone(),
+        );
+
+        Ok(AudioStream {
+            device: Arc::new(source.device()),
+            // sources send mono samples at their own rate
+            device_config: cpal::SupportedStreamConfig::new(
+                1,
+                cpal::SampleRate(sample_rate),
+                cpal::SupportedBufferSize::Unknown,
+                cpal::SampleFormat::F32,
+            ),
+            transmitter: Arc::new(tx_clone),
+            stream_control: stream_control_tx,
+            stream_thread: Some(Arc::new(tokio::sync::Mutex::new(Some(stream_thread)))),
+            is_disconnected,
+        })
+    }
+
+    fn spawn_source_thread(
+        source_name: String,
+        mut reader: Box<dyn SourceReader>,
+        tx: broadcast::Sender<Vec<f32>>,
+        stream_control_rx: mpsc::Receiver<StreamControl>,
+        is_disconnected: Arc<AtomicBool>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::task::spawn_blocking(move || {
+            let sample_rate = reader.sample_rate() as f64;
+            let started = Instant::now();
+            let mut sent_samples = 0usize;
+
+            loop {
+                if let Ok(StreamControl::Stop(response)) = stream_control_rx.try_recv() {
+                    response.send(()).ok();
+                    return;
+                }
+
+                match reader.read_chunk() {
+                    Ok(Some(chunk)) => {
+                        sent_samples += chunk.len();
+                        let _ = tx.send(chunk);
+                    }
+                    Ok(None) => {
+                        info!("audio source {} ended", source_name);
+                        break;
+                    }
+                    Err(e) => {
+                        error!("failed to read audio source {}: {}", source_name, e);
+                        break;
+                    }
+                }
+
+                // pace the source like a device would deliver it
+                let due = Duration::from_secs_f64(sent_samples as f64 / sample_rate);
+                if let Some(wait) = due.checked_sub(started.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+            }
+
+            // an empty chunk after disconnecting tells the receivers nothing else is coming
+            is_disconnected.store(true, Ordering::Relaxed);
+            let _ = tx.send(Vec::new());
+
+            if let Ok(StreamControl::Stop(response)) = stream_control_rx.recv() {
+                response.send(()).ok();
+            }
+        })
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn spawn_audio_thread(
         device: cpal::Device,
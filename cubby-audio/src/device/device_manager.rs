@@ -1,5 +1,6 @@
 use crate::core::{
     device::{list_audio_devices, AudioDevice},
+    source::AudioSource,
     stream::AudioStream,
 };
 use anyhow::{anyhow, Result};
@@ -53,6 +54,24 @@ impl DeviceManager {
         Ok(())
     }
 
+    /// Starts streaming a source, returns the device its audio is recorded under
+    pub async fn start_source(&self, source: &AudioSource) -> Result<AudioDevice> {
+        let device = source.device();
+        if self.is_running(&device) {
+            return Err(anyhow!("Device {} already running.", device));
+        }
+
+        let stream = AudioStream::from_source(source).await?;
+
+        info!("starting recording for source: {}", source);
+
+        self.streams.insert(device.clone(), Arc::new(stream));
+        self.states
+            .insert(device.clone(), Arc::new(AtomicBool::new(false)));
+
+        Ok(device)
+    }
+
     pub fn stream(&self, device: &AudioDevice) -> Option<Arc<AudioStream>> {
         self.streams.get(device).map(|s| s.value().clone())
     }
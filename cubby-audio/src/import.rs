@@ -0,0 +1,235 @@
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use cubby_core::Language;
+use cubby_db::{AudioDevice as DbAudioDevice, DatabaseManager};
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::{debug, error, info};
+use whisper_rs::WhisperContext;
+
+use crate::{
+    core::{device::AudioDevice, engine::AudioTranscriptionEngine},
+    segmentation::segmentation_manager::SegmentationManager,
+    speaker::prepare_segments,
+    transcription::{
+        get_or_create_speaker_from_embedding,
+        stt::{run_stt, SAMPLE_RATE},
+        whisper::model::{create_whisper_context_parameters, download_whisper_model},
+    },
+    utils::audio::{pcm_decode, resample},
+    vad::{silero::SileroVad, webrtc::WebRtcVad, VadEngine, VadEngineEnum},
+};
+
+/// Extensions of the files `AudioImporter` picks up when walking a directory
+pub const IMPORT_AUDIO_EXTENSIONS: [&str; 6] = ["wav", "mp3", "m4a", "aac", "flac", "ogg"];
+
+#[derive(Clone)]
+pub struct AudioImportOptions {
+    pub transcription_engine: Arc<AudioTranscriptionEngine>,
+    pub vad_engine: VadEngineEnum,
+    pub languages: Vec<Language>,
+    pub deepgram_api_key: Option<String>,
+    /// Recordings are cut in windows of this length before segmentation, like live capture
+    /// cuts its chunks
+    pub window: Duration,
+}
+
+impl Default for AudioImportOptions {
+    fn default() -> Self {
+        Self {
+            transcription_engine: Arc::new(AudioTranscriptionEngine::default()),
+            vad_engine: VadEngineEnum::Silero,
+            languages: vec![],
+            deepgram_api_key: std::env::var("DEEPGRAM_API_KEY").ok(),
+            window: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedTranscription {
+    pub id: i64,
+    pub audio_chunk_id: i64,
+    pub timestamp: DateTime<Utc>,
+    /// Offsets in the imported file, in seconds
+    pub start_time: f64,
+    pub end_time: f64,
+    pub speaker_id: i64,
+    pub transcription: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedFile {
+    pub audio_chunk_id: i64,
+    /// Length of the recording, in seconds
+    pub duration: f64,
+    pub transcriptions: usize,
+}
+
+/// Transcribes existing recordings through the same VAD, segmentation, transcription and
+/// speaker matching steps as live capture
+pub struct AudioImporter {
+    options: AudioImportOptions,
+    vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>>,
+    segmentation_manager: SegmentationManager,
+    whisper_context: Arc<WhisperContext>,
+}
+
+impl AudioImporter {
+    pub async fn new(options: AudioImportOptions) -> Result<Self> {
+        let vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>> = match options.vad_engine {
+            VadEngineEnum::Silero => Arc::new(Mutex::new(Box::new(SileroVad::new().await?))),
+            VadEngineEnum::WebRtc => Arc::new(Mutex::new(Box::new(WebRtcVad::new()))),
+        };
+        let segmentation_manager = SegmentationManager::new().await?;
+
+        let model_path = download_whisper_model(options.transcription_engine.clone())?;
+        let context_param =
+            create_whisper_context_parameters(options.transcription_engine.clone())?;
+        let whisper_context = Arc::new(
+            WhisperContext::new_with_params(&model_path.to_string_lossy(), context_param)
+                .map_err(|e| anyhow!("failed to load whisper model: {}", e))?,
+        );
+
+        Ok(Self {
+            options,
+            vad_engine,
+            segmentation_manager,
+            whisper_context,
+        })
+    }
+
+    /// Imports a recording that started at `recorded_at`, calling `on_transcription` as
+    /// each transcription is stored. The file becomes the audio chunk of its transcriptions
+    /// and is not copied.
+    pub async fn import_file(
+        &self,
+        db: &DatabaseManager,
+        path: &Path,
+        device: &AudioDevice,
+        recorded_at: DateTime<Utc>,
+        mut on_transcription: impl FnMut(&ImportedTranscription),
+    ) -> Result<ImportedFile> {
+        let file_path = path.to_string_lossy().to_string();
+        let decode_path = path.to_path_buf();
+        let (samples, sample_rate) =
+            tokio::task::spawn_blocking(move || pcm_decode(decode_path)).await??;
+        let samples = if sample_rate != SAMPLE_RATE {
+            resample(&samples, sample_rate, SAMPLE_RATE)?
+        } else {
+            samples
+        };
+        let duration = samples.len() as f64 / SAMPLE_RATE as f64;
+
+        info!(
+            "importing {} ({:.0}s recorded at {})",
+            file_path, duration, recorded_at
+        );
+
+        let audio_chunk_id = db.insert_audio_chunk_at(&file_path, recorded_at).await?;
+        let db_device = DbAudioDevice {
+            name: device.name.clone(),
+            device_type: device.device_type.clone().into(),
+        };
+        let device = Arc::new(device.clone());
+        let transcription_engine = self.options.transcription_engine.to_string();
+        // results are stamped with the processing time, what gets stored is `recorded_at`
+        // plus the offset of each segment
+        let unix_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let window_len = (self.options.window.as_secs_f64() * SAMPLE_RATE as f64) as usize;
+        let mut transcriptions = 0;
+
+        for (index, window) in samples.chunks(window_len.max(1)).enumerate() {
+            let window_offset = (index * window_len) as f64 / SAMPLE_RATE as f64;
+
+            let (mut segments, _) = prepare_segments(
+                window,
+                self.vad_engine.clone(),
+                &self.segmentation_manager.segmentation_model_path,
+                self.segmentation_manager.embedding_manager.clone(),
+                self.segmentation_manager.embedding_extractor.clone(),
+                &device.to_string(),
+            )
+            .await?;
+
+            while let Some(segment) = segments.recv().await {
+                let mut result = run_stt(
+                    segment,
+                    device.clone(),
+                    self.options.transcription_engine.clone(),
+                    self.options.deepgram_api_key.clone(),
+                    self.options.languages.clone(),
+                    file_path.clone(),
+                    unix_timestamp,
+                    self.whisper_context.clone(),
+                )
+                .await?;
+
+                let transcription = match result.transcription.take() {
+                    Some(text) if !text.trim().is_empty() => text,
+                    _ => {
+                        if let Some(e) = &result.error {
+                            error!("failed to transcribe a segment of {}: {}", file_path, e);
+                        }
+                        continue;
+                    }
+                };
+
+                result.start_time += window_offset;
+                result.end_time += window_offset;
+                let timestamp = recorded_at
+                    + chrono::Duration::milliseconds((result.start_time * 1000.0) as i64);
+
+                let speaker =
+                    get_or_create_speaker_from_embedding(db, &result.speaker_embedding).await?;
+                let id = db
+                    .insert_audio_transcription_at(
+                        audio_chunk_id,
+                        &transcription,
+                        0,
+                        &transcription_engine,
+                        &db_device,
+                        Some(speaker.id),
+                        Some(result.start_time),
+                        Some(result.end_time),
+                        timestamp,
+                    )
+                    .await?;
+                if let Err(e) = db.insert_transcription_details(id, &result.details()).await {
+                    error!(
+                        "failed to insert transcription segments of {}: {}",
+                        file_path, e
+                    );
+                }
+                debug!("imported transcription {} from {}", id, file_path);
+
+                transcriptions += 1;
+                on_transcription(&ImportedTranscription {
+                    id,
+                    audio_chunk_id,
+                    timestamp,
+                    start_time: result.start_time,
+                    end_time: result.end_time,
+                    speaker_id: speaker.id,
+                    transcription,
+                });
+            }
+        }
+
+        Ok(ImportedFile {
+            audio_chunk_id,
+            duration,
+            transcriptions,
+        })
+    }
+}
@@ -9,6 +9,7 @@ pub use utils::audio::pcm_decode;
 pub use utils::audio::resample;
 pub mod audio_manager;
 mod device;
+pub mod import;
 mod segmentation;
 
 #[cfg(target_os = "macos")]
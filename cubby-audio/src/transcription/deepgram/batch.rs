@@ -7,6 +7,7 @@ use std::io::Cursor;
 use tracing::{debug, error, info};
 
 use crate::transcription::deepgram::{CUSTOM_DEEPGRAM_API_TOKEN, DEEPGRAM_API_URL};
+use crate::transcription::{Transcript, TranscriptSegment, TranscriptWord};
 
 pub async fn transcribe_with_deepgram(
     api_key: &str,
@@ -14,7 +15,7 @@ pub async fn transcribe_with_deepgram(
     device: &str,
     sample_rate: u32,
     languages: Vec<Language>,
-) -> Result<String> {
+) -> Result<Transcript> {
     debug!("starting deepgram transcription");
 
     // Use token from env var
@@ -113,7 +114,7 @@ async fn get_deepgram_response(
 async fn handle_deepgram_response(
     response: Result<Response, reqwest::Error>,
     device: &str,
-) -> Result<String> {
+) -> Result<Transcript> {
     match response {
         Ok(resp) => {
             debug!("received response from deepgram api");
@@ -142,7 +143,7 @@ async fn handle_deepgram_response(
                         );
                     }
 
-                    Ok(transcription.to_string())
+                    Ok(transcript_from_response(&result, transcription))
                 }
                 Err(e) => {
                     error!("Failed to parse JSON response: {:?}", e);
@@ -159,3 +160,40 @@ async fn handle_deepgram_response(
         }
     }
 }
+
+/// Deepgram returns one alternative per channel with word timings and confidences, it is
+/// kept as a single segment
+fn transcript_from_response(result: &Value, text: &str) -> Transcript {
+    let channel = &result["results"]["channels"][0];
+    let words: Vec<TranscriptWord> = channel["alternatives"][0]["words"]
+        .as_array()
+        .map(|words| {
+            words
+                .iter()
+                .map(|word| TranscriptWord {
+                    text: word["punctuated_word"]
+                        .as_str()
+                        .or_else(|| word["word"].as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    start: word["start"].as_f64().unwrap_or_default(),
+                    end: word["end"].as_f64().unwrap_or_default(),
+                    probability: word["confidence"].as_f64().unwrap_or_default() as f32,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut transcript = Transcript::from_text(text);
+    transcript.language = channel["detected_language"].as_str().map(str::to_string);
+    if let (Some(first), Some(last)) = (words.first(), words.last()) {
+        transcript.segments.push(TranscriptSegment {
+            text: text.to_string(),
+            start: first.start,
+            end: last.end,
+            avg_logprob: None,
+            words,
+        });
+    }
+    transcript
+}
@@ -2,8 +2,6 @@ use anyhow::Result;
 use bytes::BufMut;
 use bytes::Bytes;
 use bytes::BytesMut;
-use chrono::DateTime;
-use chrono::Utc;
 use crossbeam::channel::RecvError;
 use cubby_core::Language;
 use cubby_events::send_event;
@@ -11,7 +9,6 @@ use deepgram::common::options::Encoding;
 use deepgram::common::stream_response::StreamResponse;
 use futures::channel::mpsc::{self, Receiver as FuturesReceiver};
 use futures::{SinkExt, TryStreamExt};
-use serde::{Deserialize, Serialize};
 use serde_json;
 use serde_json::Value;
 use std::sync::atomic::{AtomicI64, Ordering};
@@ -31,17 +28,7 @@ use crate::core::stream::AudioStream;
 use crate::transcription::deepgram::CUSTOM_DEEPGRAM_API_TOKEN;
 use crate::transcription::deepgram::DEEPGRAM_WEBSOCKET_URL;
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct RealtimeTranscriptionEvent {
-    pub timestamp: DateTime<Utc>,
-    pub device: String,
-    pub transcription: String,
-    pub is_final: bool,
-    pub is_input: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub speaker: Option<String>,
-}
+pub use cubby_events::RealtimeTranscriptionEvent;
 
 /// Starts a Deepgram transcription stream for the given audio stream
 ///
@@ -17,8 +17,11 @@ pub struct AudioInput {
 
 mod text_utils;
 
+mod transcript;
 mod transcription_result;
 
+pub use transcript::{Transcript, TranscriptSegment, TranscriptWord};
+pub(crate) use transcription_result::get_or_create_speaker_from_embedding;
 pub use transcription_result::process_transcription_result;
 pub use transcription_result::TranscriptionResult;
 mod handle_new_transcript;
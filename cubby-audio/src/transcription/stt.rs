@@ -23,6 +23,7 @@ use tokio::sync::Mutex;
 use tracing::error;
 use whisper_rs::WhisperContext;
 
+use crate::transcription::Transcript;
 use crate::{AudioInput, TranscriptionResult};
 
 pub const SAMPLE_RATE: u32 = 16000;
@@ -36,7 +37,7 @@ pub async fn stt_sync(
     deepgram_api_key: Option<String>,
     languages: Vec<Language>,
     whisper_context: Arc<WhisperContext>,
-) -> Result<String> {
+) -> Result<Transcript> {
     let audio = audio.to_vec();
 
     let device = device.to_string();
@@ -62,8 +63,8 @@ pub async fn stt(
     deepgram_api_key: Option<String>,
     languages: Vec<Language>,
     whisper_context: Arc<WhisperContext>,
-) -> Result<String> {
-    let transcription: Result<String> =
+) -> Result<Transcript> {
+    let transcription: Result<Transcript> =
         if audio_transcription_engine == AudioTranscriptionEngine::Deepgram.into() {
             // Deepgram implementation
             let api_key = deepgram_api_key.unwrap_or_default();
@@ -215,20 +216,23 @@ pub async fn run_stt(
     )
     .await
     {
-        Ok(transcription) => Ok(TranscriptionResult {
+        Ok(transcript) => Ok(TranscriptionResult {
             input: AudioInput {
                 data: Arc::new(audio),
                 sample_rate,
                 channels: 1,
                 device: device.clone(),
             },
-            transcription: Some(transcription),
+            transcription: Some(transcript.text),
             path,
             timestamp,
             error: None,
             speaker_embedding: segment.embedding.clone(),
             start_time: segment.start,
             end_time: segment.end,
+            language: transcript.language,
+            avg_logprob: transcript.avg_logprob,
+            segments: transcript.segments,
         }),
         Err(e) => {
             error!("STT error for input {}: {:?}", device, e);
@@ -246,6 +250,9 @@ pub async fn run_stt(
                 speaker_embedding: Vec::new(),
                 start_time: segment.start,
                 end_time: segment.end,
+                language: None,
+                avg_logprob: None,
+                segments: Vec::new(),
             })
         }
     }
@@ -0,0 +1,39 @@
+/// A word with its offsets in the transcribed audio, in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptWord {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    pub probability: f32,
+}
+
+/// A segment with its offsets in the transcribed audio, in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptSegment {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    /// Mean log probability of the segment tokens
+    pub avg_logprob: Option<f32>,
+    pub words: Vec<TranscriptWord>,
+}
+
+/// Output of a transcription engine. Engines that only return text leave everything but
+/// `text` empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub text: String,
+    pub language: Option<String>,
+    /// Mean log probability of all tokens
+    pub avg_logprob: Option<f32>,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+impl Transcript {
+    pub fn from_text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+}
@@ -2,12 +2,13 @@ use std::sync::Arc;
 
 use cubby_db::{
     AudioDevice as DbAudioDevice, DatabaseManager, DeviceType as DbDeviceType, Speaker,
+    TranscriptionDetails, TranscriptionSegment, TranscriptionWord,
 };
 use tracing::{debug, error, info};
 
 use crate::core::engine::AudioTranscriptionEngine;
 
-use super::{text_utils::longest_common_word_substring, AudioInput};
+use super::{text_utils::longest_common_word_substring, AudioInput, TranscriptSegment};
 
 #[derive(Debug, Clone)]
 pub struct TranscriptionResult {
@@ -19,6 +20,10 @@ pub struct TranscriptionResult {
     pub error: Option<String>,
     pub start_time: f64,
     pub end_time: f64,
+    pub language: Option<String>,
+    pub avg_logprob: Option<f32>,
+    /// Segments as returned by the engine, offsets are relative to `start_time`
+    pub segments: Vec<TranscriptSegment>,
 }
 
 impl TranscriptionResult {
@@ -44,6 +49,35 @@ impl TranscriptionResult {
 
         None
     }
+
+    /// Language, confidence and segments in their stored form, with offsets relative to
+    /// the audio chunk
+    pub fn details(&self) -> TranscriptionDetails {
+        TranscriptionDetails {
+            language: self.language.clone(),
+            avg_logprob: self.avg_logprob,
+            segments: self
+                .segments
+                .iter()
+                .map(|segment| TranscriptionSegment {
+                    text: segment.text.clone(),
+                    start_time: self.start_time + segment.start,
+                    end_time: self.start_time + segment.end,
+                    avg_logprob: segment.avg_logprob,
+                    words: segment
+                        .words
+                        .iter()
+                        .map(|word| TranscriptionWord {
+                            text: word.text.clone(),
+                            start_time: self.start_time + word.start,
+                            end_time: self.start_time + word.end,
+                            probability: word.probability,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
 }
 
 pub async fn process_transcription_result(
@@ -65,6 +99,7 @@ pub async fn process_transcription_result(
 
     info!("Detected speaker: {:?}", speaker);
 
+    let details = result.details();
     let transcription = result.transcription.unwrap();
     let transcription_engine = audio_transcription_engine.to_string();
     let mut chunk_id: Option<i64> = None;
@@ -93,7 +128,7 @@ pub async fn process_transcription_result(
                 return Ok(Some(audio_chunk_id));
             }
 
-            if let Err(e) = db
+            match db
                 .insert_audio_transcription(
                     audio_chunk_id,
                     &transcription,
@@ -112,17 +147,29 @@ pub async fn process_transcription_result(
                 )
                 .await
             {
-                error!(
-                    "Failed to insert audio transcription for device {}: {}",
-                    result.input.device, e
-                );
-                return Ok(Some(audio_chunk_id));
-            } else {
-                debug!(
-                    "Inserted audio transcription for chunk {} from device {} using {}",
-                    audio_chunk_id, result.input.device, transcription_engine
-                );
-                chunk_id = Some(audio_chunk_id);
+                Err(e) => {
+                    error!(
+                        "Failed to insert audio transcription for device {}: {}",
+                        result.input.device, e
+                    );
+                    return Ok(Some(audio_chunk_id));
+                }
+                Ok(transcription_id) => {
+                    debug!(
+                        "Inserted audio transcription for chunk {} from device {} using {}",
+                        audio_chunk_id, result.input.device, transcription_engine
+                    );
+                    if let Err(e) = db
+                        .insert_transcription_details(transcription_id, &details)
+                        .await
+                    {
+                        error!(
+                            "Failed to insert transcription segments for device {}: {}",
+                            result.input.device, e
+                        );
+                    }
+                    chunk_id = Some(audio_chunk_id);
+                }
             }
         }
         Err(e) => error!(
@@ -133,12 +180,16 @@ pub async fn process_transcription_result(
     Ok(chunk_id)
 }
 
-async fn get_or_create_speaker_from_embedding(
+pub(crate) async fn get_or_create_speaker_from_embedding(
     db: &DatabaseManager,
     embedding: &[f32],
 ) -> Result<Speaker, anyhow::Error> {
     let speaker = db.get_speaker_from_embedding(embedding).await?;
     if let Some(speaker) = speaker {
+        // more samples of a voice make later matches and re-clustering more reliable
+        if let Err(e) = db.add_speaker_embedding(speaker.id, embedding).await {
+            error!("failed to store embedding of speaker {}: {}", speaker.id, e);
+        }
         Ok(speaker)
     } else {
         let speaker = db.insert_speaker(embedding).await?;
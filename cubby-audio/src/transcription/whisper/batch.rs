@@ -1,4 +1,5 @@
 use super::detect_language;
+use crate::transcription::{Transcript, TranscriptSegment, TranscriptWord};
 use anyhow::Result;
 use cubby_core::Language;
 use std::sync::Arc;
@@ -6,12 +7,12 @@ use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
 /// Processes audio data using the Whisper model to generate transcriptions.
 ///
 /// # Returns
-/// A string containing the processed transcript
+/// The transcript with its segments, word timings, confidence and detected language
 pub async fn process_with_whisper(
     audio: &[f32],
     languages: Vec<Language>,
     whisper_context: Arc<WhisperContext>,
-) -> Result<String> {
+) -> Result<Transcript> {
     let mut whisper_state = whisper_context
         .create_state()
         .expect("failed to create key");
@@ -50,16 +51,92 @@ pub async fn process_with_whisper(
         .full_n_segments()
         .expect("failed to get number of segments");
 
-    let mut transcript = String::new();
+    let mut transcript = Transcript {
+        language: lang.map(|lang| lang.to_string()),
+        ..Default::default()
+    };
+    let mut logprob_sum = 0.0;
+    let mut token_count: usize = 0;
 
     for i in 0..num_segments {
         // Get the transcribed text and timestamps for the current segment.
         let segment = whisper_state
             .full_get_segment_text(i)
             .expect("failed to get segment");
+        transcript.text.push_str(&segment);
 
-        transcript.push_str(&segment);
+        let mut words: Vec<TranscriptWord> = Vec::new();
+        let mut word_probabilities: Vec<f32> = Vec::new();
+        let mut segment_logprob_sum = 0.0;
+        let mut segment_token_count: usize = 0;
+
+        for j in 0..whisper_state.full_n_tokens(i)? {
+            let token_text = whisper_state.full_get_token_text(i, j)?;
+            // special tokens such as [_BEG_] or <|endoftext|> carry no text
+            if token_text.starts_with("[_") || token_text.starts_with("<|") {
+                continue;
+            }
+            let token = whisper_state.full_get_token_data(i, j)?;
+            segment_logprob_sum += token.plog;
+            segment_token_count += 1;
+
+            let start = centiseconds(token.t0);
+            let end = centiseconds(token.t1);
+            // tokens are word pieces, a leading space starts a new word
+            match words.last_mut() {
+                Some(word) if !token_text.starts_with(' ') => {
+                    word.text.push_str(&token_text);
+                    word.end = end;
+                    word_probabilities.push(token.p);
+                }
+                _ => {
+                    close_word(words.last_mut(), &mut word_probabilities);
+                    words.push(TranscriptWord {
+                        text: token_text.trim_start().to_string(),
+                        start,
+                        end,
+                        probability: 0.0,
+                    });
+                    word_probabilities.push(token.p);
+                }
+            }
+        }
+        close_word(words.last_mut(), &mut word_probabilities);
+
+        logprob_sum += segment_logprob_sum;
+        token_count += segment_token_count;
+        transcript.segments.push(TranscriptSegment {
+            text: segment.trim().to_string(),
+            start: centiseconds(whisper_state.full_get_segment_t0(i)?),
+            end: centiseconds(whisper_state.full_get_segment_t1(i)?),
+            avg_logprob: mean(segment_logprob_sum, segment_token_count),
+            words,
+        });
     }
 
+    transcript.avg_logprob = mean(logprob_sum, token_count);
+
     Ok(transcript)
 }
+
+/// Whisper timestamps are in hundredths of a second
+fn centiseconds(t: i64) -> f64 {
+    t as f64 / 100.0
+}
+
+fn mean(sum: f32, count: usize) -> Option<f32> {
+    if count == 0 {
+        return None;
+    }
+    Some(sum / count as f32)
+}
+
+/// Sets the probability of a finished word to the mean of its tokens
+fn close_word(word: Option<&mut TranscriptWord>, probabilities: &mut Vec<f32>) {
+    if let Some(word) = word {
+        if let Some(probability) = mean(probabilities.iter().sum(), probabilities.len()) {
+            word.probability = probability;
+        }
+    }
+    probabilities.clear();
+}
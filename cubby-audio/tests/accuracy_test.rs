@@ -144,7 +144,7 @@ async fn test_transcription_accuracy() {
                 .await
                 .unwrap();
 
-                transcription.push_str(&transcript);
+                transcription.push_str(&transcript.text);
             }
 
             let distance = levenshtein(expected_transcription, &transcription.to_lowercase());
@@ -7,6 +7,7 @@ mod tests {
     };
     use cubby_audio::core::engine::AudioTranscriptionEngine;
     use cubby_audio::core::record_and_transcribe;
+    use cubby_audio::core::source::{AudioSource, PcmFormat, SyntheticSignal};
     use cubby_audio::core::stream::AudioStream;
     use cubby_audio::speaker::embedding::EmbeddingExtractor;
     use cubby_audio::speaker::embedding_manager::EmbeddingManager;
@@ -51,6 +52,66 @@ mod tests {
         assert_eq!(spec.to_string(), "Test Device (input)");
     }
 
+    #[test]
+    fn test_parse_audio_source() {
+        let source: AudioSource = "pcm:f32le:48000:2:/tmp/cubby.fifo".parse().unwrap();
+        assert_eq!(
+            source,
+            AudioSource::Pcm {
+                path: Some(PathBuf::from("/tmp/cubby.fifo")),
+                format: PcmFormat::F32Le,
+                sample_rate: 48000,
+                channels: 2,
+            }
+        );
+        assert_eq!(source.to_string(), "pcm:f32le:48000:2:/tmp/cubby.fifo");
+
+        let stdin: AudioSource = "pcm:-".parse().unwrap();
+        assert_eq!(stdin.to_string(), "pcm:s16le:16000:1:-");
+
+        let device = source.device();
+        assert_eq!(parse_audio_device(&device.to_string()).unwrap(), device);
+
+        assert!(AudioSource::is_source_name("synthetic:sine:220"));
+        assert!(!AudioSource::is_source_name("Test Device (input)"));
+        assert!("synthetic:square".parse::<AudioSource>().is_err());
+        assert!("pcm:s16le:16000:/tmp/cubby.fifo"
+            .parse::<AudioSource>()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_record_synthetic_source() {
+        let source = AudioSource::Synthetic {
+            signal: SyntheticSignal::Sine { frequency: 440.0 },
+            sample_rate: 16000,
+            duration: Some(Duration::from_millis(1500)),
+        };
+        let audio_stream = Arc::new(AudioStream::from_source(&source).await.unwrap());
+        let (sender, receiver) = crossbeam::channel::bounded(10);
+        let is_running = Arc::new(AtomicBool::new(true));
+
+        // the source ending flushes the segment long before the chunk duration
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            record_and_transcribe(
+                audio_stream.clone(),
+                Duration::from_secs(30),
+                Arc::new(sender),
+                is_running,
+            ),
+        )
+        .await
+        .expect("recording should stop when the source ends")
+        .unwrap();
+
+        let input = receiver.try_recv().unwrap();
+        assert_eq!(input.sample_rate, 16000);
+        assert_eq!(input.device.to_string(), "synthetic:sine:440 (input)");
+        assert!(!input.data.is_empty() && input.data.len() <= 24000);
+        assert!(audio_stream.is_disconnected());
+    }
+
     #[tokio::test]
     #[ignore] // Add this if you want to skip this test in regular test runs
     async fn test_record_and_transcribe() {
@@ -283,7 +344,7 @@ mod tests {
             .await
             .unwrap();
 
-            transcription_result.push_str(&transcript);
+            transcription_result.push_str(&transcript.text);
             transcription_result.push('\n');
         }
 
@@ -384,7 +445,7 @@ mod tests {
             .await
             .unwrap();
 
-            transcription.push_str(&transcript);
+            transcription.push_str(&transcript.text);
         }
 
         let elapsed_time = start_time.elapsed();
@@ -3,7 +3,7 @@
 //! This module provides a cross-platform API for automating desktop applications
 //! through accessibility APIs, inspired by Playwright's web automation model.
 
-use std::sync::Arc;
+use std::sync::{atomic::AtomicBool, Arc};
 
 mod element;
 mod errors;
@@ -25,6 +25,21 @@ pub struct ClickResult {
     pub details: String,
 }
 
+/// Runs `f`, which searches the accessibility tree on the current thread, stopping the
+/// traversal once `cancelled` is set. The elements found until then are returned. Only
+/// the macOS traversal checks the flag, other platforms run `f` to the end.
+pub fn run_cancellable<T>(cancelled: Arc<AtomicBool>, f: impl FnOnce() -> T) -> T {
+    #[cfg(target_os = "macos")]
+    {
+        platforms::tree_search::run_cancellable(cancelled, f)
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = cancelled;
+        f()
+    }
+}
+
 /// The main entry point for UI automation
 pub struct Desktop {
     engine: Arc<dyn platforms::AccessibilityEngine>,
@@ -6,11 +6,45 @@ use std::{
     cell::{Cell, RefCell},
     collections::HashSet,
     hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     thread,
     time::{Duration, Instant},
 };
 use tracing::debug;
 
+thread_local! {
+    /// Flag stopping the traversals of this thread, see `run_cancellable`
+    static CANCELLED: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Restores the flag of the enclosing `run_cancellable`, even when `f` panics
+struct RestoreCancelled(Option<Arc<AtomicBool>>);
+
+impl Drop for RestoreCancelled {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CANCELLED.with(|cancelled| *cancelled.borrow_mut() = previous);
+    }
+}
+
+pub(crate) fn run_cancellable<T>(cancelled: Arc<AtomicBool>, f: impl FnOnce() -> T) -> T {
+    let previous = CANCELLED.with(|flag| flag.replace(Some(cancelled)));
+    let _restore = RestoreCancelled(previous);
+    f()
+}
+
+fn is_cancelled() -> bool {
+    CANCELLED.with(|cancelled| {
+        cancelled
+            .borrow()
+            .as_ref()
+            .is_some_and(|cancelled| cancelled.load(Ordering::SeqCst))
+    })
+}
+
 pub trait TreeVisitor {
     fn enter_element(&self, element: &AXUIElement) -> TreeWalkerFlow;
     fn exit_element(&self, element: &AXUIElement);
@@ -49,6 +83,10 @@ impl TreeWalkerWithWindows {
     }
 
     fn walk_one(&self, root: &AXUIElement, visitor: &dyn TreeVisitor) -> TreeWalkerFlow {
+        if is_cancelled() {
+            return TreeWalkerFlow::Exit;
+        }
+
         // Create wrapper for the element
         let element_wrapper = AXUIElementWrapper {
             element: root.clone(),
@@ -162,7 +200,7 @@ impl ElementFinderWithWindows {
             walker.walk(&self.root, self);
             let now = Instant::now();
 
-            if now >= deadline {
+            if now >= deadline || is_cancelled() {
                 return Err(Error::NotFound);
             } else {
                 let time_left = deadline.saturating_duration_since(now);
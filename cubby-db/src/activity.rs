@@ -0,0 +1,326 @@
+use chrono::{DateTime, Duration, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Sqlite, Transaction};
+use std::collections::HashMap;
+
+use crate::search_aggregate::domain_of;
+use crate::DatabaseManager;
+
+/// Longest gap between two focused frames of one session. Frames stop coming in when the
+/// screen stops changing, so a longer gap is idle time and ends the session.
+pub const ACTIVITY_IDLE_SECONDS: i64 = 300;
+
+/// Frames rolled up per transaction
+const SESSIONIZE_BATCH: i64 = 10_000;
+
+/// Time spent in one focused app and window
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct ActivitySession {
+    pub id: i64,
+    pub app_name: String,
+    pub window_name: String,
+    /// Host of the browser url, `None` outside browsers
+    pub domain: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub frame_count: i64,
+}
+
+/// What activity is totalled by
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityGroupBy {
+    #[default]
+    App,
+    Domain,
+    Window,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityGroup {
+    /// App, domain or window title
+    pub name: String,
+    /// App of the window when grouping by window
+    pub app_name: Option<String>,
+    pub seconds: f64,
+    pub sessions: i64,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityReport {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Active time in the range, idle time left out
+    pub total_seconds: f64,
+    /// Most time first. Grouping by domain leaves out time outside browsers.
+    pub groups: Vec<ActivityGroup>,
+    /// Sessions overlapping the range, oldest first, when asked for
+    pub sessions: Vec<ActivitySession>,
+}
+
+/// Session being extended by the frames of a batch
+struct OpenSession {
+    /// `None` until stored
+    id: Option<i64>,
+    app_name: String,
+    window_name: String,
+    domain: Option<String>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    frame_count: i64,
+}
+
+impl OpenSession {
+    fn same_activity(&self, app_name: &str, window_name: &str, domain: &Option<String>) -> bool {
+        self.app_name == app_name && self.window_name == window_name && &self.domain == domain
+    }
+
+    async fn save(&self, tx: &mut Transaction<'_, Sqlite>) -> Result<(), sqlx::Error> {
+        match self.id {
+            Some(id) => {
+                sqlx::query(
+                    "UPDATE activity_sessions SET end_time = ?1, frame_count = ?2 WHERE id = ?3",
+                )
+                .bind(self.end_time)
+                .bind(self.frame_count)
+                .bind(id)
+                .execute(&mut **tx)
+                .await?;
+            }
+            None => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO activity_sessions
+                        (app_name, window_name, domain, start_time, end_time, frame_count)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                    "#,
+                )
+                .bind(&self.app_name)
+                .bind(&self.window_name)
+                .bind(&self.domain)
+                .bind(self.start_time)
+                .bind(self.end_time)
+                .bind(self.frame_count)
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// (id, timestamp, app, window, browser url, focused)
+type FrameRow = (
+    i64,
+    DateTime<Utc>,
+    String,
+    String,
+    Option<String>,
+    Option<bool>,
+);
+
+impl DatabaseManager {
+    /// Rolls the focused frames recorded since the last call up into activity sessions.
+    /// Consecutive frames of the same app, window and domain make one session, which
+    /// lasts until the next frame of something else or, after `ACTIVITY_IDLE_SECONDS`
+    /// without frames, until its last frame. Returns how many frames were read, always 0
+    /// on a read-only database, whose sessions stay as the recording process left them.
+    pub async fn update_activity_sessions(&self) -> Result<usize, sqlx::Error> {
+        if self.is_read_only() {
+            return Ok(0);
+        }
+        // two runs at once would both extend the latest session
+        let _running = self.activity_sessionizer.lock().await;
+
+        let mut read = 0;
+        loop {
+            let batch = self.sessionize_batch().await?;
+            read += batch;
+            if batch < SESSIONIZE_BATCH as usize {
+                return Ok(read);
+            }
+        }
+    }
+
+    async fn sessionize_batch(&self) -> Result<usize, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let cursor: i64 =
+            sqlx::query_scalar("SELECT last_frame_id FROM activity_session_cursor WHERE id = 1")
+                .fetch_optional(&mut *tx)
+                .await?
+                .unwrap_or(0);
+        let frames: Vec<FrameRow> = sqlx::query_as(
+            r#"
+            SELECT
+                id,
+                timestamp,
+                COALESCE(app_name, ''),
+                COALESCE(window_name, ''),
+                browser_url,
+                focused
+            FROM frames
+            WHERE id > ?1
+            ORDER BY id
+            LIMIT ?2
+            "#,
+        )
+        .bind(cursor)
+        .bind(SESSIONIZE_BATCH)
+        .fetch_all(&mut *tx)
+        .await?;
+        let Some(&(last_frame_id, ..)) = frames.last() else {
+            return Ok(0);
+        };
+        let read = frames.len();
+
+        // focus is global, the focused frames of every monitor make one timeline
+        let mut focused: Vec<FrameRow> = frames
+            .into_iter()
+            .filter(|(_, _, _, _, _, focused)| focused.unwrap_or(false))
+            .collect();
+        focused.sort_by_key(|(id, timestamp, ..)| (*timestamp, *id));
+
+        let mut open = if focused.is_empty() {
+            None
+        } else {
+            sqlx::query_as::<_, ActivitySession>(
+                r#"
+                SELECT id, app_name, window_name, domain, start_time, end_time, frame_count
+                FROM activity_sessions
+                ORDER BY end_time DESC, id DESC
+                LIMIT 1
+                "#,
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|session| OpenSession {
+                id: Some(session.id),
+                app_name: session.app_name,
+                window_name: session.window_name,
+                domain: session.domain,
+                start_time: session.start_time,
+                end_time: session.end_time,
+                frame_count: session.frame_count,
+            })
+        };
+
+        let idle = Duration::seconds(ACTIVITY_IDLE_SECONDS);
+        for (_, timestamp, app_name, window_name, browser_url, _) in focused {
+            let domain = browser_url.as_deref().and_then(domain_of);
+            if let Some(session) = &mut open {
+                // frames imported out of order start sessions of their own
+                let follows = timestamp >= session.end_time && timestamp - session.end_time <= idle;
+                if follows && session.same_activity(&app_name, &window_name, &domain) {
+                    session.end_time = timestamp;
+                    session.frame_count += 1;
+                    continue;
+                }
+                if follows {
+                    // the session lasted until the switch
+                    session.end_time = timestamp;
+                }
+                session.save(&mut tx).await?;
+            }
+            open = Some(OpenSession {
+                id: None,
+                app_name,
+                window_name,
+                domain,
+                start_time: timestamp,
+                end_time: timestamp,
+                frame_count: 1,
+            });
+        }
+        if let Some(session) = &open {
+            session.save(&mut tx).await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO activity_session_cursor (id, last_frame_id, updated_at)
+            VALUES (1, ?1, ?2)
+            ON CONFLICT(id) DO UPDATE SET
+                last_frame_id = excluded.last_frame_id,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(last_frame_id)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(read)
+    }
+
+    /// Time spent between `start` and `end` grouped by app, domain or window, from the
+    /// sessions as of the last `update_activity_sessions`. Sessions crossing the range
+    /// only count their part inside it.
+    pub async fn activity_report(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        group_by: ActivityGroupBy,
+        include_sessions: bool,
+    ) -> Result<ActivityReport, sqlx::Error> {
+        let sessions: Vec<ActivitySession> = sqlx::query_as(
+            r#"
+            SELECT id, app_name, window_name, domain, start_time, end_time, frame_count
+            FROM activity_sessions
+            WHERE end_time >= ?1 AND start_time <= ?2
+            ORDER BY start_time, id
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut total_seconds = 0.0;
+        let mut groups: HashMap<(String, Option<String>), ActivityGroup> = HashMap::new();
+        for session in &sessions {
+            let seconds = (session.end_time.min(end) - session.start_time.max(start))
+                .num_milliseconds()
+                .max(0) as f64
+                / 1000.0;
+            total_seconds += seconds;
+
+            let key = match group_by {
+                ActivityGroupBy::App => (session.app_name.clone(), None),
+                ActivityGroupBy::Domain => match &session.domain {
+                    Some(domain) => (domain.clone(), None),
+                    None => continue,
+                },
+                ActivityGroupBy::Window => {
+                    (session.window_name.clone(), Some(session.app_name.clone()))
+                }
+            };
+            let group = groups.entry(key.clone()).or_insert_with(|| ActivityGroup {
+                name: key.0,
+                app_name: key.1,
+                seconds: 0.0,
+                sessions: 0,
+            });
+            group.seconds += seconds;
+            group.sessions += 1;
+        }
+
+        let mut groups: Vec<ActivityGroup> = groups.into_values().collect();
+        groups.sort_by(|a, b| {
+            b.seconds
+                .total_cmp(&a.seconds)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(ActivityReport {
+            start,
+            end,
+            total_seconds,
+            groups,
+            sessions: if include_sessions {
+                sessions
+            } else {
+                Vec::new()
+            },
+        })
+    }
+}
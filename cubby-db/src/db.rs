@@ -3,12 +3,10 @@ use image::DynamicImage;
 use libsqlite3_sys::sqlite3_auto_extension;
 use sqlite_vec::sqlite3_vec_init;
 use sqlx::migrate::MigrateDatabase;
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
-use sqlx::Column;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
 use sqlx::Error as SqlxError;
 use sqlx::Row;
-use sqlx::TypeInfo;
-use sqlx::ValueRef;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, warn};
@@ -19,6 +17,8 @@ use zerocopy::AsBytes;
 
 use futures::future::try_join_all;
 
+use crate::raw_sql::row_to_json;
+use crate::search_cursor::{SearchWindow, AUDIO_KIND, OCR_KIND, UI_KIND};
 use crate::{
     AudioChunksResponse, AudioDevice, AudioEntry, AudioResult, AudioResultRaw, ContentType,
     DeviceType, FrameData, FrameRow, OCREntry, OCRResult, OCRResultRaw, OcrEngine, OcrTextBlock,
@@ -28,6 +28,21 @@ use crate::{
 
 pub struct DatabaseManager {
     pub pool: SqlitePool,
+    /// Read-only connections for user supplied queries, `None` for in-memory databases
+    pub(crate) read_only_pool: Option<SqlitePool>,
+    /// Held while frames are rolled up into activity sessions
+    pub(crate) activity_sessionizer: tokio::sync::Mutex<()>,
+    /// Opened with `new_read_only`, every write fails
+    read_only: bool,
+}
+
+/// Registers sqlite-vec on every connection opened afterwards
+fn register_sqlite_vec() {
+    unsafe {
+        sqlite3_auto_extension(Some(
+            std::mem::transmute::<*const (), unsafe extern "C" fn()>(sqlite3_vec_init as *const ()),
+        ));
+    }
 }
 
 impl DatabaseManager {
@@ -38,13 +53,7 @@ impl DatabaseManager {
         );
         let connection_string = format!("sqlite:{}", database_path);
 
-        unsafe {
-            sqlite3_auto_extension(Some(
-                std::mem::transmute::<*const (), unsafe extern "C" fn()>(
-                    sqlite3_vec_init as *const (),
-                ),
-            ));
-        }
+        register_sqlite_vec();
 
         // Create the database if it doesn't exist
         if !sqlx::Sqlite::database_exists(&connection_string).await? {
@@ -73,7 +82,26 @@ impl DatabaseManager {
             .execute(&pool)
             .await?;
 
-        let db_manager = DatabaseManager { pool };
+        let read_only_pool = if connection_string.contains(":memory:") {
+            None
+        } else {
+            let options = SqliteConnectOptions::from_str(&connection_string)?
+                .read_only(true)
+                .pragma("query_only", "ON");
+            Some(
+                SqlitePoolOptions::new()
+                    .max_connections(4)
+                    .acquire_timeout(Duration::from_secs(10))
+                    .connect_lazy_with(options),
+            )
+        };
+
+        let db_manager = DatabaseManager {
+            pool,
+            read_only_pool,
+            activity_sessionizer: tokio::sync::Mutex::new(()),
+            read_only: false,
+        };
 
         // Run migrations after establishing the connection
         Self::run_migrations(&db_manager.pool).await?;
@@ -81,6 +109,34 @@ impl DatabaseManager {
         Ok(db_manager)
     }
 
+    /// Opens an existing database without writing to it, for processes reading alongside
+    /// the one recording. Migrations are not run, the recording process keeps the schema
+    /// up to date.
+    pub async fn new_read_only(database_path: &str) -> Result<Self, sqlx::Error> {
+        debug!("Opening database read-only: {}", database_path);
+        register_sqlite_vec();
+
+        let options = SqliteConnectOptions::from_str(&format!("sqlite:{}", database_path))?
+            .read_only(true)
+            .pragma("query_only", "ON");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(8)
+            .acquire_timeout(Duration::from_secs(10))
+            .connect_with(options)
+            .await?;
+
+        Ok(DatabaseManager {
+            pool: pool.clone(),
+            read_only_pool: Some(pool),
+            activity_sessionizer: tokio::sync::Mutex::new(()),
+            read_only: true,
+        })
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
         let mut migrator = sqlx::migrate!("./src/migrations");
         migrator.set_ignore_missing(true);
@@ -91,10 +147,19 @@ impl DatabaseManager {
     }
 
     pub async fn insert_audio_chunk(&self, file_path: &str) -> Result<i64, sqlx::Error> {
+        self.insert_audio_chunk_at(file_path, Utc::now()).await
+    }
+
+    /// Inserts an audio chunk recorded at `timestamp`, for audio imported after the fact
+    pub async fn insert_audio_chunk_at(
+        &self,
+        file_path: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
         let mut tx = self.pool.begin().await?;
         let id = sqlx::query("INSERT INTO audio_chunks (file_path, timestamp) VALUES (?1, ?2)")
             .bind(file_path)
-            .bind(Utc::now())
+            .bind(timestamp)
             .execute(&mut *tx)
             .await?
             .last_insert_rowid();
@@ -102,12 +167,23 @@ impl DatabaseManager {
         Ok(id)
     }
 
-    async fn get_audio_chunk_id(&self, file_path: &str) -> Result<i64, sqlx::Error> {
-        let id = sqlx::query_scalar::<_, i64>("SELECT id FROM audio_chunks WHERE file_path = ?1")
+    pub async fn find_audio_chunk(&self, file_path: &str) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>("SELECT id FROM audio_chunks WHERE file_path = ?1")
             .bind(file_path)
             .fetch_optional(&self.pool)
-            .await?;
-        Ok(id.unwrap_or(0))
+            .await
+    }
+
+    /// File of the audio chunk `id`
+    pub async fn get_audio_chunk_path(&self, id: i64) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar::<_, String>("SELECT file_path FROM audio_chunks WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn get_audio_chunk_id(&self, file_path: &str) -> Result<i64, sqlx::Error> {
+        Ok(self.find_audio_chunk(file_path).await?.unwrap_or(0))
     }
 
     pub async fn get_or_insert_audio_chunk(&self, file_path: &str) -> Result<i64, sqlx::Error> {
@@ -142,6 +218,34 @@ impl DatabaseManager {
         speaker_id: Option<i64>,
         start_time: Option<f64>,
         end_time: Option<f64>,
+    ) -> Result<i64, sqlx::Error> {
+        self.insert_audio_transcription_at(
+            audio_chunk_id,
+            transcription,
+            offset_index,
+            transcription_engine,
+            device,
+            speaker_id,
+            start_time,
+            end_time,
+            Utc::now(),
+        )
+        .await
+    }
+
+    /// Inserts a transcription heard at `timestamp`, for audio imported after the fact
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_audio_transcription_at(
+        &self,
+        audio_chunk_id: i64,
+        transcription: &str,
+        offset_index: i64,
+        transcription_engine: &str,
+        device: &AudioDevice,
+        speaker_id: Option<i64>,
+        start_time: Option<f64>,
+        end_time: Option<f64>,
+        timestamp: DateTime<Utc>,
     ) -> Result<i64, sqlx::Error> {
         let text_length = transcription.len() as i64;
         let mut tx = self.pool.begin().await?;
@@ -153,7 +257,7 @@ impl DatabaseManager {
         .bind(audio_chunk_id)
         .bind(transcription)
         .bind(offset_index)
-        .bind(Utc::now())
+        .bind(timestamp)
         .bind(transcription_engine)
         .bind(&device.name)
         .bind(device.device_type == DeviceType::Input)
@@ -328,9 +432,16 @@ impl DatabaseManager {
             }
         };
 
-        // Calculate the offset_index
+        // Calculate the offset_index, frames forgotten while recording still hold their place
+        // in the file
         let offset_index: i64 = sqlx::query_scalar(
-            "SELECT COALESCE(MAX(offset_index), -1) + 1 FROM frames WHERE video_chunk_id = ?1",
+            r#"
+            SELECT COALESCE(MAX(offset_index), -1) + 1 FROM (
+                SELECT offset_index FROM frames WHERE video_chunk_id = ?1
+                UNION ALL
+                SELECT offset_index FROM video_chunk_pending_scrubs WHERE video_chunk_id = ?1
+            )
+            "#,
         )
         .bind(video_chunk_id)
         .fetch_one(&mut *tx)
@@ -390,7 +501,7 @@ impl DatabaseManager {
     pub async fn search(
         &self,
         query: &str,
-        mut content_type: ContentType,
+        content_type: ContentType,
         limit: u32,
         offset: u32,
         start_time: Option<DateTime<Utc>>,
@@ -404,257 +515,127 @@ impl DatabaseManager {
         browser_url: Option<&str>,
         focused: Option<bool>,
     ) -> Result<Vec<SearchResult>, sqlx::Error> {
-        let mut results = Vec::new();
+        // every query returns its first `offset + limit` rows, the page is cut from the
+        // merged rows
+        let results = self
+            .search_merged(
+                query,
+                content_type,
+                offset.saturating_add(limit),
+                &SearchWindow::NEWEST_FIRST,
+                start_time,
+                end_time,
+                app_name,
+                window_name,
+                min_length,
+                max_length,
+                speaker_ids,
+                frame_name,
+                browser_url,
+                focused,
+            )
+            .await?;
 
-        // if focused or browser_url is present, we run only on OCR
-        if focused.is_some() || browser_url.is_some() {
-            content_type = ContentType::OCR;
-        }
+        Ok(results
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
 
-        match content_type {
-            ContentType::All => {
-                let (ocr_results, audio_results, ui_results) =
-                    if app_name.is_none() && window_name.is_none() && frame_name.is_none() {
-                        // Run all three queries in parallel
-                        let (ocr, audio, ui) = tokio::try_join!(
-                            self.search_ocr(
-                                query,
-                                limit,
-                                offset,
-                                start_time,
-                                end_time,
-                                app_name,
-                                window_name,
-                                min_length,
-                                max_length,
-                                frame_name,
-                                browser_url,
-                                focused,
-                            ),
-                            self.search_audio(
-                                query,
-                                limit,
-                                offset,
-                                start_time,
-                                end_time,
-                                min_length,
-                                max_length,
-                                speaker_ids
-                            ),
-                            self.search_ui_monitoring(
-                                query,
-                                app_name,
-                                window_name,
-                                start_time,
-                                end_time,
-                                limit,
-                                offset,
-                            )
-                        )?;
-                        (ocr, Some(audio), ui)
-                    } else {
-                        // Run only OCR and UI queries in parallel when app/window filters are present
-                        let (ocr, ui) = tokio::try_join!(
-                            self.search_ocr(
-                                query,
-                                limit,
-                                offset,
-                                start_time,
-                                end_time,
-                                app_name,
-                                window_name,
-                                min_length,
-                                max_length,
-                                frame_name,
-                                browser_url,
-                                focused,
-                            ),
-                            self.search_ui_monitoring(
-                                query,
-                                app_name,
-                                window_name,
-                                start_time,
-                                end_time,
-                                limit,
-                                offset,
-                            )
-                        )?;
-                        (ocr, None, ui)
-                    };
+    /// Runs the OCR, audio and UI queries the content type covers, each returning its
+    /// first `limit` rows in `window`, and merges them into the first `limit` results
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn search_merged(
+        &self,
+        query: &str,
+        content_type: ContentType,
+        limit: u32,
+        window: &SearchWindow<'_>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        frame_name: Option<&str>,
+        browser_url: Option<&str>,
+        focused: Option<bool>,
+    ) -> Result<Vec<SearchResult>, sqlx::Error> {
+        let (ocr, audio, ui) = searched_content(
+            &content_type,
+            app_name,
+            window_name,
+            frame_name,
+            browser_url,
+            focused,
+        );
 
-                results.extend(ocr_results.into_iter().map(SearchResult::OCR));
-                if let Some(audio) = audio_results {
-                    results.extend(audio.into_iter().map(SearchResult::Audio));
+        let (ocr_results, audio_results, ui_results) = tokio::try_join!(
+            async {
+                if !ocr {
+                    return Ok(Vec::new());
                 }
-                results.extend(ui_results.into_iter().map(SearchResult::UI));
-            }
-            ContentType::OCR => {
-                let ocr_results = self
-                    .search_ocr(
-                        query,
-                        limit,
-                        offset,
-                        start_time,
-                        end_time,
-                        app_name,
-                        window_name,
-                        min_length,
-                        max_length,
-                        frame_name,
-                        browser_url,
-                        focused,
-                    )
-                    .await?;
-                results.extend(ocr_results.into_iter().map(SearchResult::OCR));
-            }
-            ContentType::Audio => {
-                if app_name.is_none() && window_name.is_none() {
-                    let audio_results = self
-                        .search_audio(
-                            query,
-                            limit,
-                            offset,
-                            start_time,
-                            end_time,
-                            min_length,
-                            max_length,
-                            speaker_ids,
-                        )
-                        .await?;
-                    results.extend(audio_results.into_iter().map(SearchResult::Audio));
+                self.search_ocr(
+                    query,
+                    limit,
+                    start_time,
+                    end_time,
+                    app_name,
+                    window_name,
+                    min_length,
+                    max_length,
+                    frame_name,
+                    browser_url,
+                    focused,
+                    window,
+                )
+                .await
+            },
+            async {
+                if !audio {
+                    return Ok(Vec::new());
                 }
-            }
-            ContentType::UI => {
-                let ui_results = self
-                    .search_ui_monitoring(
-                        query,
-                        app_name,
-                        window_name,
-                        start_time,
-                        end_time,
-                        limit,
-                        offset,
-                    )
-                    .await?;
-                results.extend(ui_results.into_iter().map(SearchResult::UI));
-            }
-            ContentType::AudioAndUi => {
-                let audio_results = self
-                    .search_audio(
-                        query,
-                        limit / 2,
-                        offset,
-                        start_time,
-                        end_time,
-                        min_length,
-                        max_length,
-                        speaker_ids,
-                    )
-                    .await?;
-                let ui_results = self
-                    .search_ui_monitoring(
-                        query,
-                        app_name,
-                        window_name,
-                        start_time,
-                        end_time,
-                        limit / 2,
-                        offset,
-                    )
-                    .await?;
-
-                results.extend(audio_results.into_iter().map(SearchResult::Audio));
-                results.extend(ui_results.into_iter().map(SearchResult::UI));
-            }
-            ContentType::OcrAndUi => {
-                let ocr_results = self
-                    .search_ocr(
-                        query,
-                        limit / 2,
-                        offset,
-                        start_time,
-                        end_time,
-                        app_name,
-                        window_name,
-                        min_length,
-                        max_length,
-                        frame_name,
-                        browser_url,
-                        focused,
-                    )
-                    .await?;
-                let ui_results = self
-                    .search_ui_monitoring(
-                        query,
-                        app_name,
-                        window_name,
-                        start_time,
-                        end_time,
-                        limit / 2,
-                        offset,
-                    )
-                    .await?;
-
-                results.extend(ocr_results.into_iter().map(SearchResult::OCR));
-                results.extend(ui_results.into_iter().map(SearchResult::UI));
-            }
-            ContentType::AudioAndOcr => {
-                let audio_results = self
-                    .search_audio(
-                        query,
-                        limit / 2,
-                        offset,
-                        start_time,
-                        end_time,
-                        min_length,
-                        max_length,
-                        speaker_ids,
-                    )
-                    .await?;
-                let ocr_results = self
-                    .search_ocr(
-                        query,
-                        limit / 2,
-                        offset,
-                        start_time,
-                        end_time,
-                        app_name,
-                        window_name,
-                        min_length,
-                        max_length,
-                        frame_name,
-                        browser_url,
-                        focused,
-                    )
-                    .await?;
-
-                results.extend(audio_results.into_iter().map(SearchResult::Audio));
-                results.extend(ocr_results.into_iter().map(SearchResult::OCR));
-            }
-        }
-
-        // Sort results by timestamp in descending order
-        results.sort_by(|a, b| {
-            let timestamp_a = match a {
-                SearchResult::OCR(ocr) => ocr.timestamp,
-                SearchResult::Audio(audio) => audio.timestamp,
-                SearchResult::UI(ui) => ui.timestamp,
-            };
-            let timestamp_b = match b {
-                SearchResult::OCR(ocr) => ocr.timestamp,
-                SearchResult::Audio(audio) => audio.timestamp,
-                SearchResult::UI(ui) => ui.timestamp,
-            };
-            timestamp_b.cmp(&timestamp_a)
-        });
+                self.search_audio_in_window(
+                    query,
+                    limit,
+                    0,
+                    start_time,
+                    end_time,
+                    min_length,
+                    max_length,
+                    speaker_ids,
+                    window,
+                )
+                .await
+            },
+            async {
+                if !ui {
+                    return Ok(Vec::new());
+                }
+                self.search_ui_monitoring_in_window(
+                    query,
+                    app_name,
+                    window_name,
+                    start_time,
+                    end_time,
+                    limit,
+                    0,
+                    window,
+                )
+                .await
+            },
+        )?;
 
-        // Apply offset and limit after sorting
-        results = results
+        let mut results: Vec<SearchResult> = ocr_results
             .into_iter()
-            .skip(offset as usize)
-            .take(limit as usize)
+            .map(SearchResult::OCR)
+            .chain(audio_results.into_iter().map(SearchResult::Audio))
+            .chain(ui_results.into_iter().map(SearchResult::UI))
             .collect();
-
+        window.merge(&mut results);
+        results.truncate(limit as usize);
         Ok(results)
     }
 
@@ -663,7 +644,6 @@ impl DatabaseManager {
         &self,
         query: &str,
         limit: u32,
-        offset: u32,
         start_time: Option<DateTime<Utc>>,
         end_time: Option<DateTime<Utc>>,
         app_name: Option<&str>,
@@ -673,34 +653,9 @@ impl DatabaseManager {
         frame_name: Option<&str>,
         browser_url: Option<&str>,
         focused: Option<bool>,
+        window: &SearchWindow<'_>,
     ) -> Result<Vec<OCRResult>, sqlx::Error> {
-        let mut frame_fts_parts = Vec::new();
-
-        if let Some(app) = app_name {
-            if !app.is_empty() {
-                frame_fts_parts.push(format!("app_name:{}", app));
-            }
-        }
-        if let Some(window) = window_name {
-            if !window.is_empty() {
-                frame_fts_parts.push(format!("window_name:{}", window));
-            }
-        }
-        if let Some(browser) = browser_url {
-            if !browser.is_empty() {
-                frame_fts_parts.push(format!("browser_url:{}", browser));
-            }
-        }
-        if let Some(is_focused) = focused {
-            frame_fts_parts.push(format!("focused:{}", if is_focused { "1" } else { "0" }));
-        }
-        if let Some(frame_name) = frame_name {
-            if !frame_name.is_empty() {
-                frame_fts_parts.push(format!("name:{}", frame_name));
-            }
-        }
-
-        let frame_query = frame_fts_parts.join(" ");
+        let frame_query = frame_fts_query(app_name, window_name, browser_url, focused, frame_name);
 
         let sql = format!(
             r#"
@@ -733,10 +688,14 @@ impl DatabaseManager {
             AND (?3 IS NULL OR frames.timestamp <= ?3)
             AND (?4 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) >= ?4)
             AND (?5 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) <= ?5)
+            {window_condition}
         GROUP BY frames.id
-        ORDER BY frames.timestamp DESC
-        LIMIT ?7 OFFSET ?8
+        ORDER BY frames.timestamp {direction}, frames.id {direction}
+        LIMIT ?7
         "#,
+            window_condition =
+                window.numbered_condition(OCR_KIND, "frames.timestamp", "frames.id", 8),
+            direction = window.direction(),
             frame_fts_join = if frame_query.trim().is_empty() {
                 ""
             } else {
@@ -777,32 +736,13 @@ impl DatabaseManager {
                 Some(query)
             })
             .bind(limit)
-            .bind(offset)
+            .bind(window.after_timestamp())
+            .bind(window.after_kind())
+            .bind(window.after_id())
             .fetch_all(&self.pool)
             .await?;
 
-        Ok(raw_results
-            .into_iter()
-            .map(|raw| OCRResult {
-                frame_id: raw.frame_id,
-                ocr_text: raw.ocr_text,
-                text_json: raw.text_json,
-                timestamp: raw.timestamp,
-                frame_name: raw.frame_name,
-                file_path: raw.file_path,
-                offset_index: raw.offset_index,
-                app_name: raw.app_name,
-                ocr_engine: raw.ocr_engine,
-                window_name: raw.window_name,
-                device_name: raw.device_name,
-                tags: raw
-                    .tags
-                    .map(|t| t.split(',').map(String::from).collect())
-                    .unwrap_or_default(),
-                browser_url: raw.browser_url,
-                focused: raw.focused,
-            })
-            .collect())
+        Ok(raw_results.into_iter().map(OCRResult::from).collect())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -816,11 +756,39 @@ impl DatabaseManager {
         min_length: Option<usize>,
         max_length: Option<usize>,
         speaker_ids: Option<Vec<i64>>,
+    ) -> Result<Vec<AudioResult>, sqlx::Error> {
+        self.search_audio_in_window(
+            query,
+            limit,
+            offset,
+            start_time,
+            end_time,
+            min_length,
+            max_length,
+            speaker_ids,
+            &SearchWindow::NEWEST_FIRST,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn search_audio_in_window(
+        &self,
+        query: &str,
+        limit: u32,
+        offset: u32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        window: &SearchWindow<'_>,
     ) -> Result<Vec<AudioResult>, sqlx::Error> {
         // base query for audio search
         let mut base_sql = String::from(
             "SELECT
                 audio_transcriptions.audio_chunk_id,
+                MAX(audio_transcriptions.id) as transcription_id,
                 audio_transcriptions.transcription,
                 audio_transcriptions.timestamp,
                 audio_chunks.file_path,
@@ -831,7 +799,9 @@ impl DatabaseManager {
                 audio_transcriptions.is_input_device,
                 audio_transcriptions.speaker_id,
                 audio_transcriptions.start_time,
-                audio_transcriptions.end_time
+                audio_transcriptions.end_time,
+                audio_transcriptions.language,
+                audio_transcriptions.avg_logprob
              FROM audio_transcriptions
              JOIN audio_chunks ON audio_transcriptions.audio_chunk_id = audio_chunks.id
              LEFT JOIN speakers ON audio_transcriptions.speaker_id = speakers.id
@@ -864,6 +834,18 @@ impl DatabaseManager {
         if speaker_ids.is_some() {
             conditions.push("(json_array_length(?) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?)))");
         }
+        // one row per chunk offset, the one of its latest transcription (`MAX` picks the row
+        // the other columns come from). The cursor applies to that row after grouping, so a
+        // page never brings back an offset through an earlier transcription of it.
+        let window_condition = window.anonymous_condition(
+            AUDIO_KIND,
+            "audio_transcriptions.timestamp",
+            "audio_transcriptions.id",
+        );
+        let having_clause = window_condition
+            .as_ref()
+            .map(|condition| format!("HAVING {}", condition))
+            .unwrap_or_default();
 
         let where_clause = if conditions.is_empty() {
             "WHERE 1=1".to_owned()
@@ -873,8 +855,11 @@ impl DatabaseManager {
 
         // complete sql with group, order, limit and offset
         let sql = format!(
-            "{} {} GROUP BY audio_transcriptions.audio_chunk_id, audio_transcriptions.offset_index ORDER BY audio_transcriptions.timestamp DESC LIMIT ? OFFSET ?",
-            base_sql, where_clause
+            "{} {} GROUP BY audio_transcriptions.audio_chunk_id, audio_transcriptions.offset_index {} ORDER BY audio_transcriptions.timestamp {direction}, audio_transcriptions.id {direction} LIMIT ? OFFSET ?",
+            base_sql,
+            where_clause,
+            having_clause,
+            direction = window.direction()
         );
 
         // prepare binding for speaker_ids (if any)
@@ -906,11 +891,25 @@ impl DatabaseManager {
                 .bind(&speaker_ids_json)
                 .bind(&speaker_ids_json);
         }
+        if window_condition.is_some() {
+            query_builder = query_builder
+                .bind(window.after_timestamp())
+                .bind(window.after_timestamp())
+                .bind(window.after_kind())
+                .bind(window.after_kind())
+                .bind(window.after_id());
+        }
         query_builder = query_builder.bind(limit as i64).bind(offset as i64);
 
         let results_raw: Vec<AudioResultRaw> = query_builder.fetch_all(&self.pool).await?;
+        self.audio_results_from_raw(results_raw).await
+    }
 
-        // map raw results into audio result type
+    /// Maps raw audio rows to results, looking up their speakers
+    pub(crate) async fn audio_results_from_raw(
+        &self,
+        results_raw: Vec<AudioResultRaw>,
+    ) -> Result<Vec<AudioResult>, sqlx::Error> {
         let futures: Vec<_> = results_raw
             .into_iter()
             .map(|raw| async move {
@@ -924,6 +923,7 @@ impl DatabaseManager {
 
                 Ok::<AudioResult, sqlx::Error>(AudioResult {
                     audio_chunk_id: raw.audio_chunk_id,
+                    transcription_id: raw.transcription_id,
                     transcription: raw.transcription,
                     timestamp: raw.timestamp,
                     file_path: raw.file_path,
@@ -942,6 +942,8 @@ impl DatabaseManager {
                     speaker,
                     start_time: raw.start_time,
                     end_time: raw.end_time,
+                    language: raw.language,
+                    avg_logprob: raw.avg_logprob,
                 })
             })
             .collect();
@@ -1423,41 +1425,13 @@ impl DatabaseManager {
         tx.commit().await?;
         Ok(())
     }
+    /// Runs trusted SQL on the main pool, user supplied queries go through
+    /// `execute_read_only_sql` instead
     pub async fn execute_raw_sql(&self, query: &str) -> Result<serde_json::Value, sqlx::Error> {
         let rows = sqlx::query(query).fetch_all(&self.pool).await?;
 
-        let result: Vec<serde_json::Map<String, serde_json::Value>> = rows
-            .iter()
-            .map(|row| {
-                let mut map = serde_json::Map::new();
-                for (i, column) in row.columns().iter().enumerate() {
-                    if let Ok(value) = row.try_get_raw(i) {
-                        let json_value = match value.type_info().name() {
-                            "TEXT" => {
-                                let s: String = row.try_get(i).unwrap_or_default();
-                                serde_json::Value::String(s)
-                            }
-                            "INTEGER" => {
-                                let i: i64 = row.try_get(i).unwrap_or_default();
-                                serde_json::Value::Number(i.into())
-                            }
-                            "REAL" => {
-                                let f: f64 = row.try_get(i).unwrap_or_default();
-                                serde_json::Value::Number(
-                                    serde_json::Number::from_f64(f).unwrap_or(0.into()),
-                                )
-                            }
-                            _ => serde_json::Value::Null,
-                        };
-                        map.insert(column.name().to_string(), json_value);
-                    }
-                }
-                map
-            })
-            .collect();
-
         Ok(serde_json::Value::Array(
-            result.into_iter().map(serde_json::Value::Object).collect(),
+            rows.iter().map(row_to_json).collect(),
         ))
     }
 
@@ -1582,6 +1556,31 @@ impl DatabaseManager {
         end_time: Option<DateTime<Utc>>,
         limit: u32,
         offset: u32,
+    ) -> Result<Vec<UiContent>, sqlx::Error> {
+        self.search_ui_monitoring_in_window(
+            query,
+            app_name,
+            window_name,
+            start_time,
+            end_time,
+            limit,
+            offset,
+            &SearchWindow::NEWEST_FIRST,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn search_ui_monitoring_in_window(
+        &self,
+        query: &str,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+        window: &SearchWindow<'_>,
     ) -> Result<Vec<UiContent>, sqlx::Error> {
         // combine search aspects into single fts query
         let mut fts_parts = Vec::new();
@@ -1630,11 +1629,15 @@ impl DatabaseManager {
             {}
                 AND (?2 IS NULL OR ui_monitoring.timestamp >= ?2)
                 AND (?3 IS NULL OR ui_monitoring.timestamp <= ?3)
+                {}
             GROUP BY ui_monitoring.id
-            ORDER BY ui_monitoring.timestamp DESC
+            ORDER BY ui_monitoring.timestamp {direction}, ui_monitoring.id {direction}
             LIMIT ?4 OFFSET ?5
             "#,
-            base_sql, where_clause
+            base_sql,
+            where_clause,
+            window.numbered_condition(UI_KIND, "ui_monitoring.timestamp", "ui_monitoring.id", 6),
+            direction = window.direction()
         );
 
         sqlx::query_as(&sql)
@@ -1647,6 +1650,9 @@ impl DatabaseManager {
             .bind(end_time)
             .bind(limit)
             .bind(offset)
+            .bind(window.after_timestamp())
+            .bind(window.after_kind())
+            .bind(window.after_id())
             .fetch_all(&self.pool)
             .await
     }
@@ -1961,12 +1967,13 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Creates a video chunk with one frame per image, returns the chunk id and the frame ids
     pub async fn create_video_with_frames(
         &self,
         file_path: &str,
         frames: Vec<DynamicImage>,
         metadata: VideoMetadata,
-    ) -> Result<Vec<i64>, sqlx::Error> {
+    ) -> Result<(i64, Vec<i64>), sqlx::Error> {
         let mut tx = self.pool.begin().await?;
         debug!(
             "creating video chunk {}, metadata: {:?}",
@@ -2016,94 +2023,7 @@ impl DatabaseManager {
             video_chunk_id
         );
 
-        Ok(frame_ids)
-    }
-
-    pub async fn insert_embeddings(
-        &self,
-        frame_id: i64,
-        embedding: String,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query("INSERT INTO ocr_text_embeddings (frame_id, embedding) VALUES (?1, ?2)")
-            .bind(frame_id)
-            .bind(embedding)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
-    }
-
-    pub async fn search_similar_embeddings(
-        &self,
-        embedding: Vec<f32>,
-        limit: u32,
-        threshold: f32,
-    ) -> Result<Vec<OCRResult>, sqlx::Error> {
-        debug!("searching similar embeddings with threshold {}", threshold);
-
-        let sql = r#"
-            WITH embedding_matches AS (
-                SELECT
-                    frame_id,
-                    vec_distance_cosine(embedding, vec_f32(?1)) as similarity
-                FROM ocr_text_embeddings
-                WHERE vec_distance_cosine(embedding, vec_f32(?1)) < ?2
-                ORDER BY similarity ASC
-                LIMIT ?3
-            )
-            SELECT
-                ocr_text.frame_id,
-                ocr_text.text as ocr_text,
-                ocr_text.text_json,
-                frames.timestamp,
-                video_chunks.file_path,
-                frames.offset_index,
-                frames.name as frame_name,
-                frames.app_name,
-                ocr_text.ocr_engine,
-                frames.window_name,
-                GROUP_CONCAT(tags.name, ',') as tags,
-                frames.browser_url
-            FROM embedding_matches
-            JOIN ocr_text ON embedding_matches.frame_id = ocr_text.frame_id
-            JOIN frames ON ocr_text.frame_id = frames.id
-            JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
-            LEFT JOIN vision_tags ON frames.id = vision_tags.vision_id
-            LEFT JOIN tags ON vision_tags.tag_id = tags.id
-            GROUP BY ocr_text.frame_id
-            ORDER BY embedding_matches.similarity ASC
-        "#;
-
-        let bytes = embedding.as_bytes();
-
-        let raw_results: Vec<OCRResultRaw> = sqlx::query_as(sql)
-            .bind(bytes)
-            .bind(threshold)
-            .bind(limit)
-            .fetch_all(&self.pool)
-            .await?;
-
-        Ok(raw_results
-            .into_iter()
-            .map(|raw| OCRResult {
-                frame_id: raw.frame_id,
-                ocr_text: raw.ocr_text,
-                text_json: raw.text_json,
-                timestamp: raw.timestamp,
-                file_path: raw.file_path,
-                offset_index: raw.offset_index,
-                app_name: raw.app_name,
-                ocr_engine: raw.ocr_engine,
-                window_name: raw.window_name,
-                frame_name: raw.frame_name,
-                device_name: raw.device_name,
-                tags: raw
-                    .tags
-                    .map(|t| t.split(',').map(String::from).collect())
-                    .unwrap_or_default(),
-                browser_url: raw.browser_url,
-                focused: raw.focused,
-            })
-            .collect())
+        Ok((video_chunk_id, frame_ids))
     }
 
     // Add method to update frame names
@@ -2350,6 +2270,70 @@ LIMIT ? OFFSET ?
     }
 }
 
+/// `frames_fts` query matching the frame filters of a search, empty without any
+pub(crate) fn frame_fts_query(
+    app_name: Option<&str>,
+    window_name: Option<&str>,
+    browser_url: Option<&str>,
+    focused: Option<bool>,
+    frame_name: Option<&str>,
+) -> String {
+    let mut frame_fts_parts = Vec::new();
+
+    if let Some(app) = app_name {
+        if !app.is_empty() {
+            frame_fts_parts.push(format!("app_name:{}", app));
+        }
+    }
+    if let Some(window) = window_name {
+        if !window.is_empty() {
+            frame_fts_parts.push(format!("window_name:{}", window));
+        }
+    }
+    if let Some(browser) = browser_url {
+        if !browser.is_empty() {
+            frame_fts_parts.push(format!("browser_url:{}", browser));
+        }
+    }
+    if let Some(is_focused) = focused {
+        frame_fts_parts.push(format!("focused:{}", if is_focused { "1" } else { "0" }));
+    }
+    if let Some(frame_name) = frame_name {
+        if !frame_name.is_empty() {
+            frame_fts_parts.push(format!("name:{}", frame_name));
+        }
+    }
+
+    frame_fts_parts.join(" ")
+}
+
+/// Which of the OCR, audio and UI queries a search with these filters runs, as
+/// (ocr, audio, ui)
+pub(crate) fn searched_content(
+    content_type: &ContentType,
+    app_name: Option<&str>,
+    window_name: Option<&str>,
+    frame_name: Option<&str>,
+    browser_url: Option<&str>,
+    focused: Option<bool>,
+) -> (bool, bool, bool) {
+    // if focused or browser_url is present, we run only on OCR
+    if focused.is_some() || browser_url.is_some() {
+        return (true, false, false);
+    }
+
+    let no_app_filter = app_name.is_none() && window_name.is_none();
+    match content_type {
+        ContentType::All => (true, no_app_filter && frame_name.is_none(), true),
+        ContentType::OCR => (true, false, false),
+        ContentType::Audio => (false, no_app_filter, false),
+        ContentType::UI => (false, false, true),
+        ContentType::AudioAndUi => (false, true, true),
+        ContentType::OcrAndUi => (true, false, true),
+        ContentType::AudioAndOcr => (true, true, false),
+    }
+}
+
 pub fn find_matching_positions(blocks: &[OcrTextBlock], query: &str) -> Vec<TextPosition> {
     let query_lower = query.to_lowercase();
     let query_words: Vec<&str> = query_lower.split_whitespace().collect();
@@ -0,0 +1,625 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use zerocopy::AsBytes;
+
+use crate::{
+    AudioResult, AudioResultRaw, ContentType, DatabaseManager, OCRResult, OCRResultRaw,
+    SearchResult, UiContent,
+};
+
+/// Text that gets embedded for semantic search
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingSource {
+    /// `ocr_text` rows, embedded per frame
+    Ocr,
+    /// `audio_transcriptions` rows
+    Audio,
+    /// `ui_monitoring` rows, the text of a window
+    Ui,
+}
+
+impl EmbeddingSource {
+    pub const ALL: [EmbeddingSource; 3] = [
+        EmbeddingSource::Ocr,
+        EmbeddingSource::Audio,
+        EmbeddingSource::Ui,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbeddingSource::Ocr => "ocr",
+            EmbeddingSource::Audio => "audio",
+            EmbeddingSource::Ui => "ui",
+        }
+    }
+
+    /// Whether rows are only ever appended, so a cursor can track how far the embedding
+    /// went. UI rows are rewritten in place as the text of their window changes, which
+    /// drops their embeddings, so they are found by the missing embeddings alone.
+    pub fn is_append_only(&self) -> bool {
+        !matches!(self, EmbeddingSource::Ui)
+    }
+
+    /// Rows after `?1` not embedded with model `?2` yet, as (cursor, row id, text), at
+    /// most `?3` of them. Sources that aren't append only take no cursor, the model is
+    /// `?1` and the limit `?2`.
+    fn pending_sql(&self) -> &'static str {
+        match self {
+            EmbeddingSource::Ocr => {
+                r#"
+                SELECT o.rowid, o.frame_id, o.text
+                FROM ocr_text o
+                WHERE o.rowid > ?1
+                    AND TRIM(o.text) != ''
+                    AND NOT EXISTS (
+                        SELECT 1 FROM ocr_text_embeddings e
+                        WHERE e.frame_id = o.frame_id AND e.model = ?2
+                    )
+                ORDER BY o.rowid
+                LIMIT ?3
+                "#
+            }
+            EmbeddingSource::Audio => {
+                r#"
+                SELECT a.id, a.id, a.transcription
+                FROM audio_transcriptions a
+                WHERE a.id > ?1
+                    AND TRIM(a.transcription) != ''
+                    AND NOT EXISTS (
+                        SELECT 1 FROM audio_transcription_embeddings e
+                        WHERE e.audio_transcription_id = a.id AND e.model = ?2
+                    )
+                ORDER BY a.id
+                LIMIT ?3
+                "#
+            }
+            EmbeddingSource::Ui => {
+                r#"
+                -- without a cursor a row that chunks to nothing would come back forever, the
+                -- worker records those it finds in text_embedding_skips
+                SELECT u.id, u.id, u.text_output
+                FROM ui_monitoring u
+                WHERE TRIM(u.text_output, ' ' || char(9, 10, 13)) != ''
+                    AND NOT EXISTS (
+                        SELECT 1 FROM ui_monitoring_embeddings e
+                        WHERE e.ui_monitoring_id = u.id AND e.model = ?1
+                    )
+                    AND NOT EXISTS (
+                        SELECT 1 FROM text_embedding_skips k
+                        WHERE k.source = 'ui' AND k.row_id = u.id AND k.model = ?1
+                    )
+                ORDER BY u.id
+                LIMIT ?2
+                "#
+            }
+        }
+    }
+
+    /// (rows up to cursor `?1`, all rows) with text to embed. Sources that aren't append
+    /// only count the rows that have embeddings from model `?1`, or were skipped by it,
+    /// instead.
+    fn progress_sql(&self) -> &'static str {
+        match self {
+            EmbeddingSource::Ocr => {
+                r#"
+                SELECT COUNT(*) FILTER (WHERE rowid <= ?1), COUNT(*)
+                FROM ocr_text
+                WHERE TRIM(text) != ''
+                "#
+            }
+            EmbeddingSource::Audio => {
+                r#"
+                SELECT COUNT(*) FILTER (WHERE id <= ?1), COUNT(*)
+                FROM audio_transcriptions
+                WHERE TRIM(transcription) != ''
+                "#
+            }
+            EmbeddingSource::Ui => {
+                r#"
+                SELECT
+                    COUNT(*) FILTER (WHERE EXISTS (
+                        SELECT 1 FROM ui_monitoring_embeddings e
+                        WHERE e.ui_monitoring_id = u.id AND e.model = ?1
+                    ) OR EXISTS (
+                        SELECT 1 FROM text_embedding_skips k
+                        WHERE k.source = 'ui' AND k.row_id = u.id AND k.model = ?1
+                    )),
+                    COUNT(*)
+                FROM ui_monitoring u
+                WHERE TRIM(u.text_output, ' ' || char(9, 10, 13)) != ''
+                "#
+            }
+        }
+    }
+
+    fn insert_sql(&self) -> &'static str {
+        match self {
+            EmbeddingSource::Ocr => {
+                "INSERT INTO ocr_text_embeddings (frame_id, model, embedding) VALUES (?1, ?2, vec_f32(?3))"
+            }
+            EmbeddingSource::Audio => {
+                "INSERT INTO audio_transcription_embeddings (audio_transcription_id, model, embedding) VALUES (?1, ?2, vec_f32(?3))"
+            }
+            EmbeddingSource::Ui => {
+                "INSERT INTO ui_monitoring_embeddings (ui_monitoring_id, model, embedding) VALUES (?1, ?2, vec_f32(?3))"
+            }
+        }
+    }
+
+    /// Drops the embeddings of row `?1` from model `?2`, only for sources that aren't
+    /// append only
+    fn delete_sql(&self) -> Option<&'static str> {
+        match self {
+            EmbeddingSource::Ui => Some(
+                "DELETE FROM ui_monitoring_embeddings WHERE ui_monitoring_id = ?1 AND model = ?2",
+            ),
+            _ => None,
+        }
+    }
+
+    /// Rows of the source with embeddings from model `?2` closest to vector `?1`, as
+    /// (row id, distance of the closest chunk), closer than `?3`, at most `?4` of them.
+    /// The time range is `?5` to `?6`, then OCR and UI take the app `?7` and window `?8`,
+    /// audio takes the speaker ids `?7` as a json array.
+    fn match_sql(&self) -> &'static str {
+        match self {
+            EmbeddingSource::Ocr => {
+                r#"
+                SELECT e.frame_id, MIN(vec_distance_cosine(e.embedding, vec_f32(?1))) AS distance
+                FROM ocr_text_embeddings e
+                JOIN frames f ON f.id = e.frame_id
+                WHERE e.model = ?2
+                    AND (?5 IS NULL OR f.timestamp >= ?5)
+                    AND (?6 IS NULL OR f.timestamp <= ?6)
+                    AND (?7 IS NULL OR f.app_name LIKE '%' || ?7 || '%')
+                    AND (?8 IS NULL OR f.window_name LIKE '%' || ?8 || '%')
+                GROUP BY e.frame_id
+                HAVING distance < ?3
+                ORDER BY distance
+                LIMIT ?4
+                "#
+            }
+            EmbeddingSource::Audio => {
+                r#"
+                SELECT e.audio_transcription_id, MIN(vec_distance_cosine(e.embedding, vec_f32(?1))) AS distance
+                FROM audio_transcription_embeddings e
+                JOIN audio_transcriptions a ON a.id = e.audio_transcription_id
+                LEFT JOIN speakers s ON s.id = a.speaker_id
+                WHERE e.model = ?2
+                    AND (?5 IS NULL OR a.timestamp >= ?5)
+                    AND (?6 IS NULL OR a.timestamp <= ?6)
+                    AND COALESCE(s.hallucination, 0) = 0
+                    AND (json_array_length(?7) = 0 OR a.speaker_id IN (SELECT value FROM json_each(?7)))
+                GROUP BY e.audio_transcription_id
+                HAVING distance < ?3
+                ORDER BY distance
+                LIMIT ?4
+                "#
+            }
+            EmbeddingSource::Ui => {
+                r#"
+                SELECT e.ui_monitoring_id, MIN(vec_distance_cosine(e.embedding, vec_f32(?1))) AS distance
+                FROM ui_monitoring_embeddings e
+                JOIN ui_monitoring u ON u.id = e.ui_monitoring_id
+                WHERE e.model = ?2
+                    AND (?5 IS NULL OR u.timestamp >= ?5)
+                    AND (?6 IS NULL OR u.timestamp <= ?6)
+                    AND (?7 IS NULL OR u.app LIKE '%' || ?7 || '%')
+                    AND (?8 IS NULL OR u.window LIKE '%' || ?8 || '%')
+                GROUP BY e.ui_monitoring_id
+                HAVING distance < ?3
+                ORDER BY distance
+                LIMIT ?4
+                "#
+            }
+        }
+    }
+}
+
+impl Display for EmbeddingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A row waiting for its embeddings
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEmbedding {
+    /// Position of the row in the source, what the cursor moves to once it is embedded
+    pub cursor: i64,
+    /// Frame id for OCR text, transcription id for audio, `ui_monitoring` id for UI text
+    pub id: i64,
+    pub text: String,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingProgress {
+    pub source: EmbeddingSource,
+    pub model: String,
+    /// Rows with text the model went through so far
+    pub embedded: i64,
+    /// Rows with text, including those still to embed
+    pub total: i64,
+}
+
+/// What semantic search looks through, the filters match those of `search`
+#[derive(Debug, Clone, Default)]
+pub struct SemanticSearchFilter {
+    pub content_type: ContentType,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Leaves out audio, which has no app
+    pub app_name: Option<String>,
+    /// Leaves out audio, which has no window
+    pub window_name: Option<String>,
+    /// Only audio of these speakers, all of them when empty
+    pub speaker_ids: Option<Vec<i64>>,
+}
+
+impl SemanticSearchFilter {
+    /// Sources the content type covers, audio is left out when filtering by app or window
+    pub(crate) fn sources(&self) -> Vec<EmbeddingSource> {
+        let sources: &[EmbeddingSource] = match self.content_type {
+            ContentType::All => &EmbeddingSource::ALL,
+            ContentType::OCR => &[EmbeddingSource::Ocr],
+            ContentType::Audio => &[EmbeddingSource::Audio],
+            ContentType::UI => &[EmbeddingSource::Ui],
+            ContentType::AudioAndUi => &[EmbeddingSource::Audio, EmbeddingSource::Ui],
+            ContentType::OcrAndUi => &[EmbeddingSource::Ocr, EmbeddingSource::Ui],
+            ContentType::AudioAndOcr => &[EmbeddingSource::Ocr, EmbeddingSource::Audio],
+        };
+        let app_or_window = self.app_name.is_some() || self.window_name.is_some();
+        sources
+            .iter()
+            .copied()
+            .filter(|source| !(app_or_window && *source == EmbeddingSource::Audio))
+            .collect()
+    }
+
+    /// Speaker ids as a json array, empty when not filtering by speaker
+    pub(crate) fn speaker_ids_json(&self) -> String {
+        serde_json::to_string(self.speaker_ids.as_deref().unwrap_or(&[]))
+            .unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+/// A frame, transcription or window whose text is close to the searched text
+#[derive(Debug)]
+pub struct SemanticMatch {
+    pub result: SearchResult,
+    /// Cosine distance of the closest chunk of text, lower is closer
+    pub distance: f32,
+}
+
+impl DatabaseManager {
+    /// The next rows of `source` that have no embedding from `model`, oldest first
+    pub async fn pending_embeddings(
+        &self,
+        source: EmbeddingSource,
+        model: &str,
+        limit: u32,
+    ) -> Result<Vec<PendingEmbedding>, sqlx::Error> {
+        let mut query = sqlx::query_as::<_, (i64, i64, String)>(source.pending_sql());
+        if source.is_append_only() {
+            query = query.bind(self.embedding_cursor(source, model).await?);
+        }
+        let rows = query.bind(model).bind(limit).fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|(cursor, id, text)| PendingEmbedding { cursor, id, text })
+            .collect())
+    }
+
+    /// Stores the embeddings of text chunks, as (row id, vector), and moves the cursor of
+    /// the model past `cursor` when given. Rows of sources that aren't append only have
+    /// their previous embeddings from the model replaced, and take no cursor.
+    pub async fn insert_text_embeddings(
+        &self,
+        source: EmbeddingSource,
+        model: &str,
+        embeddings: &[(i64, Vec<f32>)],
+        cursor: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        if let Some(delete_sql) = source.delete_sql() {
+            let mut ids: Vec<i64> = embeddings.iter().map(|(id, _)| *id).collect();
+            ids.sort_unstable();
+            ids.dedup();
+            for id in ids {
+                sqlx::query(delete_sql)
+                    .bind(id)
+                    .bind(model)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        for (id, embedding) in embeddings {
+            let bytes: &[u8] = embedding.as_bytes();
+            sqlx::query(source.insert_sql())
+                .bind(id)
+                .bind(model)
+                .bind(bytes)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        if let Some(cursor) = cursor.filter(|_| source.is_append_only()) {
+            sqlx::query(
+                r#"
+                INSERT INTO embedding_cursors (source, model, last_id, updated_at)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (source, model) DO UPDATE
+                SET last_id = MAX(last_id, excluded.last_id), updated_at = excluded.updated_at
+                "#,
+            )
+            .bind(source.as_str())
+            .bind(model)
+            .bind(cursor)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Records rows of `source` that `model` can't embed, as (row id, reason). Rows of
+    /// sources that aren't append only stop being pending until their text changes, the
+    /// cursor of the others moves past them with the rest of their batch.
+    pub async fn skip_text_embeddings(
+        &self,
+        source: EmbeddingSource,
+        model: &str,
+        rows: &[(i64, String)],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for (id, reason) in rows {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO text_embedding_skips (source, row_id, model, reason, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                "#,
+            )
+            .bind(source.as_str())
+            .bind(id)
+            .bind(model)
+            .bind(reason)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// How far `model` went through the rows of `source`
+    pub async fn embedding_progress(
+        &self,
+        source: EmbeddingSource,
+        model: &str,
+    ) -> Result<EmbeddingProgress, sqlx::Error> {
+        let query = sqlx::query_as::<_, (i64, i64)>(source.progress_sql());
+        let query = if source.is_append_only() {
+            query.bind(self.embedding_cursor(source, model).await?)
+        } else {
+            query.bind(model)
+        };
+        let (embedded, total) = query.fetch_one(&self.pool).await?;
+        Ok(EmbeddingProgress {
+            source,
+            model: model.to_string(),
+            embedded,
+            total,
+        })
+    }
+
+    /// Frames, transcriptions and windows whose text is closest to `embedding`, among the
+    /// embeddings `model` produced, closest first. A row matches through its closest
+    /// chunk of text.
+    pub async fn semantic_search(
+        &self,
+        embedding: &[f32],
+        model: &str,
+        filter: &SemanticSearchFilter,
+        limit: u32,
+        threshold: f32,
+    ) -> Result<Vec<SemanticMatch>, sqlx::Error> {
+        let mut matches = Vec::new();
+        for source in filter.sources() {
+            let distances: HashMap<i64, f32> = self
+                .closest_rows(source, embedding, model, filter, limit, threshold)
+                .await?
+                .into_iter()
+                .collect();
+            let ids: Vec<i64> = distances.keys().copied().collect();
+            let results = self.results_by_ids(source, &ids).await?;
+            matches.extend(results.into_iter().map(|(id, result)| SemanticMatch {
+                result,
+                distance: distances[&id],
+            }));
+        }
+
+        matches.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        matches.truncate(limit as usize);
+        Ok(matches)
+    }
+
+    /// Rows of `source` whose embeddings from `model` are closest to `embedding`, as (row
+    /// id, distance of the closest chunk), closest first
+    pub(crate) async fn closest_rows(
+        &self,
+        source: EmbeddingSource,
+        embedding: &[f32],
+        model: &str,
+        filter: &SemanticSearchFilter,
+        limit: u32,
+        threshold: f32,
+    ) -> Result<Vec<(i64, f32)>, sqlx::Error> {
+        let query = sqlx::query_as::<_, (i64, f32)>(source.match_sql())
+            .bind(embedding.as_bytes())
+            .bind(model)
+            .bind(threshold)
+            .bind(limit)
+            .bind(filter.start_time)
+            .bind(filter.end_time);
+        let query = match source {
+            EmbeddingSource::Audio => query.bind(filter.speaker_ids_json()),
+            _ => query.bind(&filter.app_name).bind(&filter.window_name),
+        };
+        query.fetch_all(&self.pool).await
+    }
+
+    /// The frames, transcriptions or windows of `source` with these ids, as (row id,
+    /// result), in no particular order
+    pub(crate) async fn results_by_ids(
+        &self,
+        source: EmbeddingSource,
+        ids: &[i64],
+    ) -> Result<Vec<(i64, SearchResult)>, sqlx::Error> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids = serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string());
+        Ok(match source {
+            EmbeddingSource::Ocr => self
+                .ocr_results_by_ids(&ids)
+                .await?
+                .into_iter()
+                .map(|r| (r.frame_id, SearchResult::OCR(r)))
+                .collect(),
+            EmbeddingSource::Audio => self
+                .audio_results_by_ids(&ids)
+                .await?
+                .into_iter()
+                .map(|r| (r.transcription_id, SearchResult::Audio(r)))
+                .collect(),
+            EmbeddingSource::Ui => self
+                .ui_results_by_ids(&ids)
+                .await?
+                .into_iter()
+                .map(|r| (r.id, SearchResult::UI(r)))
+                .collect(),
+        })
+    }
+
+    /// `ids` is a json array of frame ids
+    async fn ocr_results_by_ids(&self, ids: &str) -> Result<Vec<OCRResult>, sqlx::Error> {
+        let raw_results: Vec<OCRResultRaw> = sqlx::query_as(
+            r#"
+            SELECT
+                ocr_text.frame_id,
+                ocr_text.text as ocr_text,
+                ocr_text.text_json,
+                frames.timestamp,
+                frames.name as frame_name,
+                video_chunks.file_path,
+                frames.offset_index,
+                frames.app_name,
+                ocr_text.ocr_engine,
+                frames.window_name,
+                video_chunks.device_name,
+                GROUP_CONCAT(tags.name, ',') as tags,
+                frames.browser_url,
+                frames.focused
+            FROM frames
+            JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+            JOIN ocr_text ON frames.id = ocr_text.frame_id
+            LEFT JOIN vision_tags ON frames.id = vision_tags.vision_id
+            LEFT JOIN tags ON vision_tags.tag_id = tags.id
+            WHERE frames.id IN (SELECT value FROM json_each(?1))
+            GROUP BY frames.id
+            "#,
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raw_results.into_iter().map(OCRResult::from).collect())
+    }
+
+    /// `ids` is a json array of transcription ids
+    async fn audio_results_by_ids(&self, ids: &str) -> Result<Vec<AudioResult>, sqlx::Error> {
+        let raw_results: Vec<AudioResultRaw> = sqlx::query_as(
+            r#"
+            SELECT
+                audio_transcriptions.audio_chunk_id,
+                audio_transcriptions.id as transcription_id,
+                audio_transcriptions.transcription,
+                audio_transcriptions.timestamp,
+                audio_chunks.file_path,
+                audio_transcriptions.offset_index,
+                audio_transcriptions.transcription_engine,
+                GROUP_CONCAT(tags.name, ',') as tags,
+                audio_transcriptions.device as device_name,
+                audio_transcriptions.is_input_device,
+                audio_transcriptions.speaker_id,
+                audio_transcriptions.start_time,
+                audio_transcriptions.end_time,
+                audio_transcriptions.language,
+                audio_transcriptions.avg_logprob
+            FROM audio_transcriptions
+            JOIN audio_chunks ON audio_transcriptions.audio_chunk_id = audio_chunks.id
+            LEFT JOIN audio_tags ON audio_chunks.id = audio_tags.audio_chunk_id
+            LEFT JOIN tags ON audio_tags.tag_id = tags.id
+            WHERE audio_transcriptions.id IN (SELECT value FROM json_each(?1))
+            GROUP BY audio_transcriptions.id
+            "#,
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        self.audio_results_from_raw(raw_results).await
+    }
+
+    /// `ids` is a json array of `ui_monitoring` ids, the frame is the one recorded within
+    /// a second of the text when there is one
+    async fn ui_results_by_ids(&self, ids: &str) -> Result<Vec<UiContent>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                ui_monitoring.id,
+                ui_monitoring.text_output,
+                ui_monitoring.timestamp,
+                ui_monitoring.app as app_name,
+                ui_monitoring.window as window_name,
+                ui_monitoring.initial_traversal_at,
+                COALESCE(video_chunks.file_path, '') as file_path,
+                COALESCE(frames.offset_index, 0) as offset_index,
+                frames.name as frame_name,
+                frames.browser_url
+            FROM ui_monitoring
+            LEFT JOIN frames ON
+                frames.timestamp BETWEEN
+                    datetime(ui_monitoring.timestamp, '-1 seconds')
+                    AND datetime(ui_monitoring.timestamp, '+1 seconds')
+            LEFT JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+            WHERE ui_monitoring.id IN (SELECT value FROM json_each(?1))
+            GROUP BY ui_monitoring.id
+            "#,
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn embedding_cursor(
+        &self,
+        source: EmbeddingSource,
+        model: &str,
+    ) -> Result<i64, sqlx::Error> {
+        let cursor = sqlx::query_scalar::<_, i64>(
+            "SELECT last_id FROM embedding_cursors WHERE source = ?1 AND model = ?2",
+        )
+        .bind(source.as_str())
+        .bind(model)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(cursor.unwrap_or(0))
+    }
+}
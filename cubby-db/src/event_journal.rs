@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{Sqlite, Transaction};
+use std::str::FromStr;
+
+use crate::{DatabaseManager, ForgetFilter};
+
+/// An event of the bus as recorded in the journal
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEvent {
+    /// Grows with every event, also across restarts
+    pub seq: i64,
+    pub name: String,
+    pub data: serde_json::Value,
+    /// When the event was journaled
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Where a replay of the journal starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalPosition {
+    /// Events after this sequence number
+    After(i64),
+    /// Events journaled at or after this time
+    Since(DateTime<Utc>),
+}
+
+impl FromStr for JournalPosition {
+    type Err = String;
+
+    /// A sequence number, or an RFC 3339 timestamp
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(seq) = s.parse::<i64>() {
+            return Ok(JournalPosition::After(seq));
+        }
+        DateTime::parse_from_rfc3339(s)
+            .map(|timestamp| JournalPosition::Since(timestamp.with_timezone(&Utc)))
+            .map_err(|_| format!("'{}' is neither a sequence number nor a timestamp", s))
+    }
+}
+
+type JournalEventRow = (i64, String, String, DateTime<Utc>);
+
+/// Journaled events carrying captured text, deleted along with the content they were sent
+/// for so that `/ws/events?since=` can't replay it
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournaledContent {
+    /// Matched the way `plan_forget` matches rows, on the time the events carry and their
+    /// app, window, url and device fields. `ocr_result` names no monitor, so a device only
+    /// narrows down transcriptions.
+    pub filter: ForgetFilter,
+    /// `ocr_result` and `ui_frame` events
+    pub screen: bool,
+    /// `transcription` events
+    pub audio: bool,
+}
+
+pub(crate) async fn delete_journaled_content_in_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    content: &JournaledContent,
+) -> Result<u64, sqlx::Error> {
+    let filter = &content.filter;
+    let result = sqlx::query(
+        r#"
+        DELETE FROM event_journal
+        WHERE CASE name
+            WHEN 'transcription' THEN ?7
+                AND COALESCE(julianday(json_extract(data, '$.timestamp')), julianday(timestamp))
+                    BETWEEN julianday(?1) AND julianday(?2)
+                AND (?6 IS NULL OR json_extract(data, '$.device') = ?6)
+            WHEN 'ocr_result' THEN ?9
+                AND COALESCE(
+                    julianday(json_extract(data, '$.timestamp') / 1000.0, 'unixepoch'),
+                    julianday(timestamp)
+                ) BETWEEN julianday(?1) AND julianday(?2)
+                AND (?3 IS NULL OR json_extract(data, '$.app_name') LIKE '%' || ?3 || '%')
+                AND (?4 IS NULL OR json_extract(data, '$.window_name') LIKE '%' || ?4 || '%')
+                AND (?5 IS NULL OR json_extract(data, '$.browser_url') LIKE '%' || ?5 || '%')
+            WHEN 'ui_frame' THEN ?8
+                AND julianday(timestamp) BETWEEN julianday(?1) AND julianday(?2)
+                AND (?3 IS NULL OR json_extract(data, '$.app') LIKE '%' || ?3 || '%')
+                AND (?4 IS NULL OR json_extract(data, '$.window') LIKE '%' || ?4 || '%')
+            ELSE 0
+        END
+        "#,
+    )
+    .bind(filter.start_time)
+    .bind(filter.end_time)
+    .bind(&filter.app_name)
+    .bind(&filter.window_name)
+    .bind(&filter.browser_url)
+    .bind(&filter.device_name)
+    .bind(content.audio && filter.includes_audio())
+    .bind(content.screen && filter.includes_ui())
+    .bind(content.screen)
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+impl DatabaseManager {
+    /// Journals an event and returns its sequence number
+    pub async fn insert_journal_event(
+        &self,
+        name: &str,
+        data: &serde_json::Value,
+        timestamp: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        let seq = sqlx::query(
+            r#"
+            INSERT INTO event_journal (name, data, timestamp)
+            VALUES (?1, ?2, ?3)
+            "#,
+        )
+        .bind(name)
+        .bind(data.to_string())
+        .bind(timestamp)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(seq)
+    }
+
+    /// Sequence number of the last journaled event, 0 when there is none
+    pub async fn latest_journal_seq(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COALESCE(MAX(seq), 0) FROM event_journal")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Up to `limit` journaled events from `position`, oldest first
+    pub async fn get_journal_events(
+        &self,
+        position: JournalPosition,
+        limit: u32,
+    ) -> Result<Vec<JournalEvent>, sqlx::Error> {
+        let query = match position {
+            JournalPosition::After(seq) => sqlx::query_as::<_, JournalEventRow>(
+                r#"
+                SELECT seq, name, data, timestamp FROM event_journal
+                WHERE seq > ?1 ORDER BY seq LIMIT ?2
+                "#,
+            )
+            .bind(seq),
+            JournalPosition::Since(timestamp) => sqlx::query_as::<_, JournalEventRow>(
+                r#"
+                SELECT seq, name, data, timestamp FROM event_journal
+                WHERE timestamp >= ?1 ORDER BY seq LIMIT ?2
+                "#,
+            )
+            .bind(timestamp),
+        };
+        let rows = query.bind(limit).fetch_all(&self.pool).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(seq, name, data, timestamp)| JournalEvent {
+                seq,
+                name,
+                data: serde_json::from_str(&data).unwrap_or_default(),
+                timestamp,
+            })
+            .collect())
+    }
+
+    /// Deletes the events journaled before `timestamp` and returns how many were deleted
+    pub async fn delete_journal_events_before(
+        &self,
+        timestamp: DateTime<Utc>,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM event_journal WHERE timestamp < ?1")
+            .bind(timestamp)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes the journaled events of `content` and returns how many were deleted
+    pub async fn delete_journaled_content(
+        &self,
+        content: &JournaledContent,
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let deleted = delete_journaled_content_in_tx(&mut tx, content).await?;
+        tx.commit().await?;
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,522 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Sqlite, Transaction};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::event_journal::{delete_journaled_content_in_tx, JournaledContent};
+use crate::retention::{delete_frames_in_tx, json_ids};
+use crate::DatabaseManager;
+
+/// Start and end of a transcription within its audio chunk, in seconds
+type Span = (Option<f64>, Option<f64>);
+
+/// Time window and scope of data to forget
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForgetFilter {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// Substring of the app name, matched with LIKE
+    pub app_name: Option<String>,
+    /// Substring of the window name, matched with LIKE
+    pub window_name: Option<String>,
+    /// Substring of the browser url, matched with LIKE
+    pub browser_url: Option<String>,
+    /// Exact monitor or audio device name
+    pub device_name: Option<String>,
+}
+
+impl ForgetFilter {
+    /// Audio has no app, window or url, so it is only forgotten for whole time ranges
+    pub(crate) fn includes_audio(&self) -> bool {
+        self.app_name.is_none() && self.window_name.is_none() && self.browser_url.is_none()
+    }
+
+    /// UI monitoring rows have no url or device
+    pub(crate) fn includes_ui(&self) -> bool {
+        self.browser_url.is_none() && self.device_name.is_none()
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ForgottenFrame {
+    pub id: i64,
+    pub video_chunk_id: i64,
+    pub offset_index: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+}
+
+/// Frames to cut out of a video chunk
+#[derive(Debug, Clone)]
+pub struct VideoChunkEdit {
+    pub id: i64,
+    pub file_path: String,
+    pub removed_offsets: Vec<i64>,
+    /// Every frame of the chunk is forgotten, the whole file goes away
+    pub fully_covered: bool,
+    /// Latest chunk of its device, still being written by ffmpeg. Its frames are cut out
+    /// once it is finished, see `pending_video_scrubs`
+    pub recording: bool,
+}
+
+/// Seconds of an audio chunk to silence
+#[derive(Debug, Clone)]
+pub struct AudioChunkEdit {
+    pub id: i64,
+    pub file_path: String,
+    pub muted_ranges: Vec<(f64, f64)>,
+    /// Some forgotten transcription has no start/end time, so the whole chunk is silenced
+    pub mute_all: bool,
+    /// Every transcription of the chunk is forgotten, the whole file goes away
+    pub fully_covered: bool,
+}
+
+/// Rows and media edits selected by a `ForgetFilter`, computed before anything is deleted
+#[derive(Debug, Clone, Default)]
+pub struct ForgetPlan {
+    pub frames: Vec<ForgottenFrame>,
+    pub ocr_text_count: i64,
+    pub embedding_count: i64,
+    pub audio_transcription_ids: Vec<i64>,
+    pub ui_monitoring_ids: Vec<i64>,
+    pub video_chunks: Vec<VideoChunkEdit>,
+    pub audio_chunks: Vec<AudioChunkEdit>,
+    /// Filter the plan was made from, the journaled events it covers go with the rows
+    pub filter: Option<ForgetFilter>,
+}
+
+impl ForgetPlan {
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+            && self.audio_transcription_ids.is_empty()
+            && self.ui_monitoring_ids.is_empty()
+    }
+
+    /// What applying this plan will remove
+    pub fn report(&self) -> ForgetReport {
+        let mut report = ForgetReport {
+            frames: self.frames.len() as i64,
+            ocr_text: self.ocr_text_count,
+            ocr_text_embeddings: self.embedding_count,
+            audio_transcriptions: self.audio_transcription_ids.len() as i64,
+            ui_monitoring: self.ui_monitoring_ids.len() as i64,
+            ..Default::default()
+        };
+
+        for chunk in &self.video_chunks {
+            if chunk.recording {
+                report.pending_files.push(chunk.file_path.clone());
+            } else if chunk.fully_covered {
+                report.deleted_files.push(chunk.file_path.clone());
+            } else {
+                report.reencoded_files.push(chunk.file_path.clone());
+            }
+        }
+        for chunk in &self.audio_chunks {
+            if chunk.fully_covered {
+                report.deleted_files.push(chunk.file_path.clone());
+            } else {
+                report.reencoded_files.push(chunk.file_path.clone());
+            }
+        }
+
+        report
+    }
+}
+
+/// Everything removed by a forget request
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ForgetReport {
+    pub frames: i64,
+    pub ocr_text: i64,
+    pub ocr_text_embeddings: i64,
+    pub audio_transcriptions: i64,
+    pub ui_monitoring: i64,
+    /// Media chunks that were entirely covered and deleted
+    pub deleted_files: Vec<String>,
+    /// Media chunks re-encoded without the forgotten frames or speech
+    pub reencoded_files: Vec<String>,
+    /// Chunks still being recorded. Their frames are gone from the database and are cut out
+    /// of the file once the chunk is finished
+    pub pending_files: Vec<String>,
+}
+
+impl DatabaseManager {
+    /// Selects the rows matching `filter` and works out which media chunks need to be
+    /// re-encoded or deleted, without changing anything.
+    pub async fn plan_forget(&self, filter: &ForgetFilter) -> Result<ForgetPlan, sqlx::Error> {
+        let frames = sqlx::query_as::<_, ForgottenFrame>(
+            r#"
+            SELECT id, video_chunk_id, offset_index, timestamp, device_name
+            FROM frames
+            WHERE timestamp >= ?1 AND timestamp <= ?2
+              AND (?3 IS NULL OR app_name LIKE '%' || ?3 || '%')
+              AND (?4 IS NULL OR window_name LIKE '%' || ?4 || '%')
+              AND (?5 IS NULL OR browser_url LIKE '%' || ?5 || '%')
+              AND (?6 IS NULL OR device_name = ?6)
+            ORDER BY id
+            "#,
+        )
+        .bind(filter.start_time)
+        .bind(filter.end_time)
+        .bind(&filter.app_name)
+        .bind(&filter.window_name)
+        .bind(&filter.browser_url)
+        .bind(&filter.device_name)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let transcriptions = if filter.includes_audio() {
+            sqlx::query_as::<_, (i64, i64, Option<f64>, Option<f64>)>(
+                r#"
+                SELECT id, audio_chunk_id, start_time, end_time
+                FROM audio_transcriptions
+                WHERE timestamp >= ?1 AND timestamp <= ?2
+                  AND (?3 IS NULL OR device = ?3)
+                ORDER BY id
+                "#,
+            )
+            .bind(filter.start_time)
+            .bind(filter.end_time)
+            .bind(&filter.device_name)
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            Vec::new()
+        };
+
+        let ui_monitoring_ids = if filter.includes_ui() {
+            sqlx::query_scalar::<_, i64>(
+                r#"
+                SELECT id FROM ui_monitoring
+                WHERE timestamp >= ?1 AND timestamp <= ?2
+                  AND (?3 IS NULL OR app LIKE '%' || ?3 || '%')
+                  AND (?4 IS NULL OR window LIKE '%' || ?4 || '%')
+                ORDER BY id
+                "#,
+            )
+            .bind(filter.start_time)
+            .bind(filter.end_time)
+            .bind(&filter.app_name)
+            .bind(&filter.window_name)
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            Vec::new()
+        };
+
+        let frame_ids: Vec<i64> = frames.iter().map(|f| f.id).collect();
+        let (ocr_text_count, embedding_count): (i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM ocr_text WHERE frame_id IN (SELECT value FROM json_each(?1))),
+                (SELECT COUNT(*) FROM ocr_text_embeddings WHERE frame_id IN (SELECT value FROM json_each(?1)))
+            "#,
+        )
+        .bind(json_ids(&frame_ids))
+        .fetch_one(&self.pool)
+        .await?;
+
+        let recording = self.active_video_chunk_ids().await?;
+        let mut removed_offsets: BTreeMap<i64, Vec<i64>> = BTreeMap::new();
+        for frame in &frames {
+            removed_offsets
+                .entry(frame.video_chunk_id)
+                .or_default()
+                .push(frame.offset_index);
+        }
+        let chunk_ids: Vec<i64> = removed_offsets.keys().copied().collect();
+        // frames forgotten while a chunk was recording are still in its file
+        let mut pending: HashMap<i64, Vec<i64>> = HashMap::new();
+        for (chunk_id, offset) in sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT video_chunk_id, offset_index FROM video_chunk_pending_scrubs
+            WHERE video_chunk_id IN (SELECT value FROM json_each(?1))
+            "#,
+        )
+        .bind(json_ids(&chunk_ids))
+        .fetch_all(&self.pool)
+        .await?
+        {
+            pending.entry(chunk_id).or_default().push(offset);
+        }
+        let chunks: HashMap<i64, (String, i64)> = sqlx::query_as::<_, (i64, String, i64)>(
+            r#"
+            SELECT video_chunks.id, video_chunks.file_path, COUNT(frames.id)
+            FROM video_chunks
+            JOIN frames ON frames.video_chunk_id = video_chunks.id
+            WHERE video_chunks.id IN (SELECT value FROM json_each(?1))
+            GROUP BY video_chunks.id
+            "#,
+        )
+        .bind(json_ids(&chunk_ids))
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(id, file_path, total)| (id, (file_path, total)))
+        .collect();
+
+        let mut video_chunks = Vec::new();
+        for (id, mut offsets) in removed_offsets {
+            let Some((file_path, total)) = chunks.get(&id) else {
+                continue;
+            };
+            let fully_covered = offsets.len() as i64 == *total;
+            let recording = recording.contains(&id);
+            if !recording {
+                offsets.extend(pending.remove(&id).unwrap_or_default());
+            }
+            offsets.sort_unstable();
+            video_chunks.push(VideoChunkEdit {
+                id,
+                file_path: file_path.clone(),
+                fully_covered,
+                removed_offsets: offsets,
+                recording,
+            });
+        }
+
+        let mut muted: BTreeMap<i64, Vec<Span>> = BTreeMap::new();
+        for (_, chunk_id, start, end) in &transcriptions {
+            muted.entry(*chunk_id).or_default().push((*start, *end));
+        }
+        let chunk_ids: Vec<i64> = muted.keys().copied().collect();
+        let chunks: HashMap<i64, (String, i64)> = sqlx::query_as::<_, (i64, String, i64)>(
+            r#"
+            SELECT audio_chunks.id, audio_chunks.file_path, COUNT(audio_transcriptions.id)
+            FROM audio_chunks
+            JOIN audio_transcriptions ON audio_transcriptions.audio_chunk_id = audio_chunks.id
+            WHERE audio_chunks.id IN (SELECT value FROM json_each(?1))
+            GROUP BY audio_chunks.id
+            "#,
+        )
+        .bind(json_ids(&chunk_ids))
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(id, file_path, total)| (id, (file_path, total)))
+        .collect();
+
+        let mut audio_chunks = Vec::new();
+        for (id, ranges) in muted {
+            let Some((file_path, total)) = chunks.get(&id) else {
+                continue;
+            };
+            audio_chunks.push(AudioChunkEdit {
+                id,
+                file_path: file_path.clone(),
+                fully_covered: ranges.len() as i64 == *total,
+                mute_all: ranges.iter().any(|(s, e)| s.is_none() || e.is_none()),
+                muted_ranges: ranges
+                    .into_iter()
+                    .filter_map(|(s, e)| Some((s?, e?)))
+                    .collect(),
+            });
+        }
+
+        Ok(ForgetPlan {
+            frames,
+            ocr_text_count,
+            embedding_count,
+            audio_transcription_ids: transcriptions.into_iter().map(|(id, ..)| id).collect(),
+            ui_monitoring_ids,
+            video_chunks,
+            audio_chunks,
+            filter: Some(filter.clone()),
+        })
+    }
+
+    /// Deletes everything in `plan` in a single transaction. Fully covered chunks lose their
+    /// rows and the remaining frames of re-encoded chunks get their `offset_index` compacted,
+    /// so the caller must have the edited media ready before calling this and swap it in
+    /// (or delete the files in `deleted_files`) right after.
+    pub async fn apply_forget(&self, plan: &ForgetPlan) -> Result<ForgetReport, sqlx::Error> {
+        let mut report = plan.report();
+        let mut tx = self.pool.begin().await?;
+
+        let frame_ids: Vec<i64> = plan.frames.iter().map(|f| f.id).collect();
+        let frame_ids = json_ids(&frame_ids);
+        report.ocr_text_embeddings = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM ocr_text_embeddings WHERE frame_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&frame_ids)
+        .fetch_one(&mut *tx)
+        .await?;
+        // sessions rolled up from the frames would still tell what was on screen
+        sqlx::query(
+            r#"
+            DELETE FROM activity_sessions WHERE EXISTS (
+                SELECT 1 FROM frames
+                WHERE frames.id IN (SELECT value FROM json_each(?1))
+                    AND frames.focused = 1
+                    AND COALESCE(frames.app_name, '') = activity_sessions.app_name
+                    AND COALESCE(frames.window_name, '') = activity_sessions.window_name
+                    AND frames.timestamp BETWEEN activity_sessions.start_time
+                        AND activity_sessions.end_time
+            )
+            "#,
+        )
+        .bind(&frame_ids)
+        .execute(&mut *tx)
+        .await?;
+        let deleted = delete_frames_in_tx(&mut tx, &frame_ids).await?;
+        report.frames = deleted.frames;
+        report.ocr_text = deleted.ocr_text;
+
+        report.audio_transcriptions = sqlx::query(
+            "DELETE FROM audio_transcriptions WHERE id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(json_ids(&plan.audio_transcription_ids))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as i64;
+
+        let ui_ids = json_ids(&plan.ui_monitoring_ids);
+        sqlx::query(
+            "DELETE FROM ui_monitoring_tags WHERE ui_monitoring_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ui_ids)
+        .execute(&mut *tx)
+        .await?;
+        report.ui_monitoring =
+            sqlx::query("DELETE FROM ui_monitoring WHERE id IN (SELECT value FROM json_each(?1))")
+                .bind(&ui_ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected() as i64;
+
+        for chunk in &plan.video_chunks {
+            if chunk.recording {
+                sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO video_chunk_pending_scrubs (video_chunk_id, offset_index)
+                    SELECT ?1, value FROM json_each(?2)
+                    "#,
+                )
+                .bind(chunk.id)
+                .bind(json_ids(&chunk.removed_offsets))
+                .execute(&mut *tx)
+                .await?;
+            } else {
+                finish_video_chunk_edit(&mut tx, chunk).await?;
+            }
+        }
+
+        // `/ws/events?since=` would otherwise replay the forgotten text
+        if let Some(filter) = &plan.filter {
+            let content = JournaledContent {
+                filter: filter.clone(),
+                screen: true,
+                audio: true,
+            };
+            delete_journaled_content_in_tx(&mut tx, &content).await?;
+        }
+
+        for chunk in plan.audio_chunks.iter().filter(|c| c.fully_covered) {
+            let operations = [
+                "DELETE FROM audio_tags WHERE audio_chunk_id = ?1",
+                "DELETE FROM chunked_text_entries WHERE audio_chunk_id = ?1",
+                "DELETE FROM audio_chunks WHERE id = ?1",
+            ];
+            for query in operations {
+                sqlx::query(query).bind(chunk.id).execute(&mut *tx).await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    /// Finished chunks that still hold frames forgotten while they were recording, or purged
+    /// by retention. The caller cuts `removed_offsets` out of each file (or deletes it when
+    /// `fully_covered`) and then calls `finish_video_scrub`.
+    pub async fn pending_video_scrubs(&self) -> Result<Vec<VideoChunkEdit>, sqlx::Error> {
+        // chunks purged in the meantime took their frames with them
+        sqlx::query(
+            r#"
+            DELETE FROM video_chunk_pending_scrubs
+            WHERE video_chunk_id NOT IN (SELECT id FROM video_chunks)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let recording = self.active_video_chunk_ids().await?;
+        let rows = sqlx::query_as::<_, (i64, String, i64, String)>(
+            r#"
+            SELECT
+                video_chunks.id,
+                video_chunks.file_path,
+                (SELECT COUNT(*) FROM frames WHERE frames.video_chunk_id = video_chunks.id),
+                json_group_array(video_chunk_pending_scrubs.offset_index)
+            FROM video_chunk_pending_scrubs
+            JOIN video_chunks ON video_chunks.id = video_chunk_pending_scrubs.video_chunk_id
+            GROUP BY video_chunks.id
+            ORDER BY video_chunks.id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter(|(id, ..)| !recording.contains(id))
+            .map(|(id, file_path, remaining, offsets)| {
+                let mut removed_offsets: Vec<i64> =
+                    serde_json::from_str(&offsets).unwrap_or_default();
+                removed_offsets.sort_unstable();
+                VideoChunkEdit {
+                    id,
+                    file_path,
+                    removed_offsets,
+                    fully_covered: remaining == 0,
+                    recording: false,
+                }
+            })
+            .collect())
+    }
+
+    /// Records that the forgotten frames of `chunk` were cut out of its file: compacts the
+    /// offsets of the remaining frames, or deletes the chunk when none are left.
+    pub async fn finish_video_scrub(&self, chunk: &VideoChunkEdit) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        finish_video_chunk_edit(&mut tx, chunk).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+async fn finish_video_chunk_edit(
+    tx: &mut Transaction<'_, Sqlite>,
+    chunk: &VideoChunkEdit,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM video_chunk_pending_scrubs WHERE video_chunk_id = ?1")
+        .bind(chunk.id)
+        .execute(&mut **tx)
+        .await?;
+    if chunk.fully_covered {
+        sqlx::query("DELETE FROM video_chunks WHERE id = ?1")
+            .bind(chunk.id)
+            .execute(&mut **tx)
+            .await?;
+    } else {
+        // the re-encoded file only holds the remaining frames, in the same order
+        sqlx::query(
+            r#"
+            UPDATE frames
+            SET offset_index = ranked.new_offset
+            FROM (
+                SELECT id, ROW_NUMBER() OVER (ORDER BY offset_index) - 1 AS new_offset
+                FROM frames
+                WHERE video_chunk_id = ?1
+            ) AS ranked
+            WHERE frames.id = ranked.id
+            "#,
+        )
+        .bind(chunk.id)
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,313 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{DatabaseManager, EmbeddingSource, SearchResult, SemanticSearchFilter};
+
+/// How search ranks results
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Full text search, newest first
+    #[default]
+    Fts,
+    /// Keyword and vector rankings fused, most relevant first
+    Hybrid,
+}
+
+/// Tuning of hybrid search, every field can be left out
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HybridSearchOptions {
+    /// Constant of reciprocal rank fusion, a result scores `1 / (rrf_k + rank)` in each
+    /// ranking it appears in. Higher values narrow the gap between top and lower ranks.
+    pub rrf_k: f32,
+    pub ocr_weight: f32,
+    pub audio_weight: f32,
+    pub ui_weight: f32,
+    /// Boost of a result recorded just now, the score is multiplied by up to
+    /// `1 + recency_weight`. 0 turns the boost off.
+    pub recency_weight: f32,
+    /// Age at which the recency boost is halved
+    pub recency_half_life_hours: f32,
+    /// Nearest neighbours farther than this cosine distance are left out
+    pub max_distance: f32,
+    /// Results taken from the keyword and the vector ranking of each content type, raised
+    /// to cover the requested page
+    pub candidates: u32,
+}
+
+impl Default for HybridSearchOptions {
+    fn default() -> Self {
+        Self {
+            rrf_k: 60.0,
+            ocr_weight: 1.0,
+            audio_weight: 1.0,
+            ui_weight: 1.0,
+            recency_weight: 0.2,
+            recency_half_life_hours: 168.0,
+            max_distance: 0.5,
+            candidates: 50,
+        }
+    }
+}
+
+impl HybridSearchOptions {
+    fn weight(&self, source: EmbeddingSource) -> f32 {
+        match source {
+            EmbeddingSource::Ocr => self.ocr_weight,
+            EmbeddingSource::Audio => self.audio_weight,
+            EmbeddingSource::Ui => self.ui_weight,
+        }
+    }
+
+    fn recency_boost(&self, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> f32 {
+        if self.recency_weight == 0.0 || self.recency_half_life_hours <= 0.0 {
+            return 1.0;
+        }
+        let age_hours = (now - timestamp).num_seconds().max(0) as f32 / 3600.0;
+        1.0 + self.recency_weight * 0.5f32.powf(age_hours / self.recency_half_life_hours)
+    }
+}
+
+/// Why a result came up in hybrid search
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Relevance {
+    /// Fused score, higher is more relevant. Only comparable within one search.
+    pub score: f32,
+    /// Position in the bm25 ranking of its content type, from 1
+    pub keyword_rank: Option<u32>,
+    /// Position in the vector ranking of its content type, from 1
+    pub vector_rank: Option<u32>,
+    /// Text around the keyword matches, matched terms are wrapped in `<b>` and `</b>`
+    pub snippets: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HybridMatch {
+    pub result: SearchResult,
+    pub relevance: Relevance,
+}
+
+impl EmbeddingSource {
+    /// Rows matching fts query `?1`, as (row id, bm25, snippet), best first, at most `?2`
+    /// of them. The time range is `?3` to `?4`, then OCR and UI take the app `?5` and
+    /// window `?6`, audio takes the speaker ids `?5` as a json array.
+    fn keyword_sql(&self) -> &'static str {
+        match self {
+            EmbeddingSource::Ocr => {
+                r#"
+                SELECT
+                    ocr_text_fts.frame_id,
+                    bm25(ocr_text_fts) AS relevance,
+                    snippet(ocr_text_fts, 0, '<b>', '</b>', '...', 16)
+                FROM ocr_text_fts
+                JOIN frames ON frames.id = ocr_text_fts.frame_id
+                WHERE ocr_text_fts MATCH ?1
+                    AND (?3 IS NULL OR frames.timestamp >= ?3)
+                    AND (?4 IS NULL OR frames.timestamp <= ?4)
+                    AND (?5 IS NULL OR frames.app_name LIKE '%' || ?5 || '%')
+                    AND (?6 IS NULL OR frames.window_name LIKE '%' || ?6 || '%')
+                ORDER BY relevance
+                LIMIT ?2
+                "#
+            }
+            EmbeddingSource::Audio => {
+                // fts rows only know their chunk, a transcription is told apart by its
+                // text and start time like the delete trigger does
+                r#"
+                SELECT
+                    audio_transcriptions.id,
+                    bm25(audio_transcriptions_fts) AS relevance,
+                    snippet(audio_transcriptions_fts, 0, '<b>', '</b>', '...', 16)
+                FROM audio_transcriptions_fts
+                JOIN audio_transcriptions ON
+                    audio_transcriptions.audio_chunk_id = audio_transcriptions_fts.audio_chunk_id
+                    AND audio_transcriptions.transcription = audio_transcriptions_fts.transcription
+                    AND audio_transcriptions.start_time IS audio_transcriptions_fts.start_time
+                LEFT JOIN speakers ON speakers.id = audio_transcriptions.speaker_id
+                WHERE audio_transcriptions_fts MATCH ?1
+                    AND (?3 IS NULL OR audio_transcriptions.timestamp >= ?3)
+                    AND (?4 IS NULL OR audio_transcriptions.timestamp <= ?4)
+                    AND COALESCE(speakers.hallucination, 0) = 0
+                    AND (json_array_length(?5) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?5)))
+                ORDER BY relevance
+                LIMIT ?2
+                "#
+            }
+            EmbeddingSource::Ui => {
+                r#"
+                SELECT
+                    ui_monitoring.id,
+                    bm25(ui_monitoring_fts) AS relevance,
+                    snippet(ui_monitoring_fts, 0, '<b>', '</b>', '...', 16)
+                FROM ui_monitoring_fts
+                JOIN ui_monitoring ON ui_monitoring.id = ui_monitoring_fts.ui_id
+                WHERE ui_monitoring_fts MATCH ?1
+                    AND (?3 IS NULL OR ui_monitoring.timestamp >= ?3)
+                    AND (?4 IS NULL OR ui_monitoring.timestamp <= ?4)
+                    AND (?5 IS NULL OR ui_monitoring.app LIKE '%' || ?5 || '%')
+                    AND (?6 IS NULL OR ui_monitoring.window LIKE '%' || ?6 || '%')
+                ORDER BY relevance
+                LIMIT ?2
+                "#
+            }
+        }
+    }
+
+    /// The fts column holding the text
+    fn keyword_column(&self) -> &'static str {
+        match self {
+            EmbeddingSource::Ocr => "text",
+            EmbeddingSource::Audio => "transcription",
+            EmbeddingSource::Ui => "text_output",
+        }
+    }
+
+    /// Fts query matching any word of `query` in the text, with every word quoted so user
+    /// input can't break the fts syntax
+    fn keyword_query(&self, query: &str) -> Option<String> {
+        let words: Vec<String> = query
+            .split_whitespace()
+            .map(|word| word.replace('"', ""))
+            .filter(|word| !word.is_empty())
+            .map(|word| format!("\"{}\"", word))
+            .collect();
+        if words.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{} : ({})",
+            self.keyword_column(),
+            words.join(" OR ")
+        ))
+    }
+}
+
+fn result_timestamp(result: &SearchResult) -> DateTime<Utc> {
+    match result {
+        SearchResult::OCR(ocr) => ocr.timestamp,
+        SearchResult::Audio(audio) => audio.timestamp,
+        SearchResult::UI(ui) => ui.timestamp,
+    }
+}
+
+impl DatabaseManager {
+    /// Ranks frames, transcriptions and windows by both the bm25 ranking of `query` and the
+    /// vector ranking of its `embedding`, as (vector, model), fused with reciprocal rank
+    /// fusion. Without an embedding only the keyword ranking counts.
+    ///
+    /// Returns the page of results, most relevant first, and how many results there are in
+    /// all. The total is only known when no ranking filled its `candidates`, otherwise
+    /// there may be more matches than were ranked and it is `None`.
+    pub async fn search_hybrid(
+        &self,
+        query: &str,
+        embedding: Option<(&[f32], &str)>,
+        filter: &SemanticSearchFilter,
+        options: &HybridSearchOptions,
+        limit: u32,
+        offset: u32,
+    ) -> Result<(Vec<HybridMatch>, Option<usize>), sqlx::Error> {
+        let candidates = options.candidates.max(limit.saturating_add(offset));
+        let now = Utc::now();
+        let mut capped = false;
+
+        let mut matches = Vec::new();
+        for source in filter.sources() {
+            let mut ranked: HashMap<i64, Relevance> = HashMap::new();
+
+            if let Some(keyword_query) = source.keyword_query(query) {
+                let rows = self
+                    .keyword_rows(source, &keyword_query, filter, candidates)
+                    .await?;
+                capped |= rows.len() >= candidates as usize;
+                let mut rank = 0;
+                for (id, snippet) in rows {
+                    let relevance = ranked.entry(id).or_default();
+                    if relevance.keyword_rank.is_none() {
+                        rank += 1;
+                        relevance.keyword_rank = Some(rank);
+                    }
+                    if !relevance.snippets.contains(&snippet) {
+                        relevance.snippets.push(snippet);
+                    }
+                }
+            }
+
+            if let Some((vector, model)) = embedding {
+                let rows = self
+                    .closest_rows(
+                        source,
+                        vector,
+                        model,
+                        filter,
+                        candidates,
+                        options.max_distance,
+                    )
+                    .await?;
+                capped |= rows.len() >= candidates as usize;
+                for (rank, (id, _)) in rows.into_iter().enumerate() {
+                    ranked.entry(id).or_default().vector_rank = Some(rank as u32 + 1);
+                }
+            }
+
+            let ids: Vec<i64> = ranked.keys().copied().collect();
+            for (id, result) in self.results_by_ids(source, &ids).await? {
+                let Some(mut relevance) = ranked.remove(&id) else {
+                    continue;
+                };
+                let fused: f32 = [relevance.keyword_rank, relevance.vector_rank]
+                    .into_iter()
+                    .flatten()
+                    .map(|rank| 1.0 / (options.rrf_k + rank as f32))
+                    .sum();
+                relevance.score = fused
+                    * options.weight(source)
+                    * options.recency_boost(result_timestamp(&result), now);
+                matches.push(HybridMatch { result, relevance });
+            }
+        }
+
+        matches.sort_by(|a, b| {
+            b.relevance
+                .score
+                .total_cmp(&a.relevance.score)
+                .then_with(|| result_timestamp(&b.result).cmp(&result_timestamp(&a.result)))
+        });
+        let total = (!capped).then_some(matches.len());
+        let page = matches
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        Ok((page, total))
+    }
+
+    /// Rows of `source` matching `keyword_query`, as (row id, snippet), best first. A row
+    /// comes up once per fts row that matched.
+    async fn keyword_rows(
+        &self,
+        source: EmbeddingSource,
+        keyword_query: &str,
+        filter: &SemanticSearchFilter,
+        limit: u32,
+    ) -> Result<Vec<(i64, String)>, sqlx::Error> {
+        let query = sqlx::query_as::<_, (i64, f64, String)>(source.keyword_sql())
+            .bind(keyword_query)
+            .bind(limit)
+            .bind(filter.start_time)
+            .bind(filter.end_time);
+        let query = match source {
+            EmbeddingSource::Audio => query.bind(filter.speaker_ids_json()),
+            _ => query.bind(&filter.app_name).bind(&filter.window_name),
+        };
+        Ok(query
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|(id, _, snippet)| (id, snippet))
+            .collect())
+    }
+}
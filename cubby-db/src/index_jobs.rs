@@ -0,0 +1,383 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::DatabaseManager;
+
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexJobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl IndexJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexJobStatus::Queued => "queued",
+            IndexJobStatus::Running => "running",
+            IndexJobStatus::Completed => "completed",
+            IndexJobStatus::Failed => "failed",
+            IndexJobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// Whether the job will not make any more progress
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            IndexJobStatus::Completed | IndexJobStatus::Failed | IndexJobStatus::Cancelled
+        )
+    }
+}
+
+impl Display for IndexJobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for IndexJobStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queued" => Ok(IndexJobStatus::Queued),
+            "running" => Ok(IndexJobStatus::Running),
+            "completed" => Ok(IndexJobStatus::Completed),
+            "failed" => Ok(IndexJobStatus::Failed),
+            "cancelled" => Ok(IndexJobStatus::Cancelled),
+            _ => Err(format!("unknown index job status '{}'", s)),
+        }
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexJob {
+    pub id: i64,
+    /// File or directory being indexed
+    pub path: String,
+    /// Only files whose path matches this regex are indexed
+    pub pattern: Option<String>,
+    /// Indexing options the job was created with
+    pub options: serde_json::Value,
+    pub status: IndexJobStatus,
+    /// `None` until the files to index have been listed
+    pub total_files: Option<i64>,
+    /// Files indexed or failed so far
+    pub processed_files: i64,
+    pub failed_files: i64,
+    /// Frames indexed so far
+    pub frames: i64,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexJobFile {
+    pub file_path: String,
+    /// Video chunk the file was indexed into, `None` until its frames are created
+    pub video_chunk_id: Option<i64>,
+    pub frames: i64,
+    pub error: Option<String>,
+    /// `false` when the job was interrupted while indexing the file
+    pub finished: bool,
+}
+
+type IndexJobRow = (
+    i64,
+    String,
+    Option<String>,
+    String,
+    String,
+    Option<i64>,
+    i64,
+    i64,
+    i64,
+    Option<String>,
+    DateTime<Utc>,
+    DateTime<Utc>,
+    Option<DateTime<Utc>>,
+);
+
+const INDEX_JOB_COLUMNS: &str = r#"
+    id, path, pattern, options, status, total_files, processed_files, failed_files, frames, error,
+    created_at, updated_at, finished_at
+"#;
+
+impl DatabaseManager {
+    /// Queues a job indexing the videos under `path` and returns its id
+    pub async fn create_index_job(
+        &self,
+        path: &str,
+        pattern: Option<&str>,
+        options: &serde_json::Value,
+    ) -> Result<i64, sqlx::Error> {
+        let now = Utc::now();
+        let id = sqlx::query(
+            r#"
+            INSERT INTO index_jobs (path, pattern, options, status, created_at, updated_at)
+            VALUES (?1, ?2, ?3, 'queued', ?4, ?4)
+            "#,
+        )
+        .bind(path)
+        .bind(pattern)
+        .bind(options.to_string())
+        .bind(now)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    pub async fn get_index_job(&self, id: i64) -> Result<Option<IndexJob>, sqlx::Error> {
+        let row = sqlx::query_as::<_, IndexJobRow>(&format!(
+            "SELECT {} FROM index_jobs WHERE id = ?1",
+            INDEX_JOB_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(index_job_from_row))
+    }
+
+    /// Most recent jobs first
+    pub async fn list_index_jobs(&self, limit: u32) -> Result<Vec<IndexJob>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, IndexJobRow>(&format!(
+            "SELECT {} FROM index_jobs ORDER BY id DESC LIMIT ?1",
+            INDEX_JOB_COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(index_job_from_row).collect())
+    }
+
+    /// Jobs a previous run left queued or running, oldest first
+    pub async fn unfinished_index_jobs(&self) -> Result<Vec<IndexJob>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, IndexJobRow>(&format!(
+            "SELECT {} FROM index_jobs WHERE status IN ('queued', 'running') ORDER BY id",
+            INDEX_JOB_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(index_job_from_row).collect())
+    }
+
+    /// Marks a queued job, or a running one being resumed, as running over `total_files`.
+    /// Returns false when the job was cancelled or has finished.
+    pub async fn start_index_job(&self, id: i64, total_files: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE index_jobs
+            SET status = 'running', total_files = ?2, updated_at = ?3
+            WHERE id = ?1 AND status IN ('queued', 'running')
+            "#,
+        )
+        .bind(id)
+        .bind(total_files)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Records that a job created the video chunk of a file, so the chunk can be deleted
+    /// if the job is interrupted before it is done with the file
+    pub async fn start_index_job_file(
+        &self,
+        id: i64,
+        file_path: &str,
+        video_chunk_id: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO index_job_files (job_id, file_path, video_chunk_id)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (job_id, file_path) DO UPDATE SET video_chunk_id = excluded.video_chunk_id
+            WHERE finished = FALSE
+            "#,
+        )
+        .bind(id)
+        .bind(file_path)
+        .bind(video_chunk_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Records that a job is done with a file, failed when `error` is set. A file is only
+    /// counted once in the job progress.
+    pub async fn finish_index_job_file(
+        &self,
+        id: i64,
+        file_path: &str,
+        frames: i64,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut finished = sqlx::query(
+            r#"
+            UPDATE index_job_files SET frames = ?3, error = ?4, finished = TRUE
+            WHERE job_id = ?1 AND file_path = ?2 AND finished = FALSE
+            "#,
+        )
+        .bind(id)
+        .bind(file_path)
+        .bind(frames)
+        .bind(error)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if finished == 0 {
+            finished = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO index_job_files (job_id, file_path, frames, error, finished)
+                VALUES (?1, ?2, ?3, ?4, TRUE)
+                "#,
+            )
+            .bind(id)
+            .bind(file_path)
+            .bind(frames)
+            .bind(error)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        if finished > 0 {
+            sqlx::query(
+                r#"
+                UPDATE index_jobs
+                SET processed_files = processed_files + 1,
+                    failed_files = failed_files + ?2,
+                    frames = frames + ?3,
+                    updated_at = ?4
+                WHERE id = ?1
+                "#,
+            )
+            .bind(id)
+            .bind(error.is_some() as i64)
+            .bind(frames)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Files a job has started on, in the order they were started
+    pub async fn index_job_files(&self, id: i64) -> Result<Vec<IndexJobFile>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (String, Option<i64>, i64, Option<String>, bool)>(
+            r#"
+            SELECT file_path, video_chunk_id, frames, error, finished
+            FROM index_job_files
+            WHERE job_id = ?1
+            ORDER BY rowid
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(file_path, video_chunk_id, frames, error, finished)| IndexJobFile {
+                    file_path,
+                    video_chunk_id,
+                    frames,
+                    error,
+                    finished,
+                },
+            )
+            .collect())
+    }
+
+    /// Ends a queued or running job. Returns false when it has already ended, e.g. it was
+    /// cancelled meanwhile.
+    pub async fn finish_index_job(
+        &self,
+        id: i64,
+        status: IndexJobStatus,
+        error: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+        let result = sqlx::query(
+            r#"
+            UPDATE index_jobs
+            SET status = ?2, error = ?3, updated_at = ?4, finished_at = ?4
+            WHERE id = ?1 AND status IN ('queued', 'running')
+            "#,
+        )
+        .bind(id)
+        .bind(status.as_str())
+        .bind(error)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Cancels a queued or running job. Returns false when it has already finished or
+    /// does not exist.
+    pub async fn cancel_index_job(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+        let result = sqlx::query(
+            r#"
+            UPDATE index_jobs
+            SET status = 'cancelled', updated_at = ?2, finished_at = ?2
+            WHERE id = ?1 AND status IN ('queued', 'running')
+            "#,
+        )
+        .bind(id)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn index_job_from_row(
+    (
+        id,
+        path,
+        pattern,
+        options,
+        status,
+        total_files,
+        processed_files,
+        failed_files,
+        frames,
+        error,
+        created_at,
+        updated_at,
+        finished_at,
+    ): IndexJobRow,
+) -> IndexJob {
+    IndexJob {
+        id,
+        path,
+        pattern,
+        options: serde_json::from_str(&options).unwrap_or_default(),
+        // only this module writes the status
+        status: status.parse().unwrap_or(IndexJobStatus::Failed),
+        total_files,
+        processed_files,
+        failed_files,
+        frames,
+        error,
+        created_at,
+        updated_at,
+        finished_at,
+    }
+}
@@ -1,11 +1,58 @@
+mod activity;
 mod db;
+mod embeddings;
+mod event_journal;
+mod forget;
+mod hybrid_search;
+mod index_jobs;
+mod meetings;
 mod migration_worker;
+mod raw_sql;
+mod retention;
+mod retention_worker;
+mod search_aggregate;
+mod search_cursor;
+mod speaker_clustering;
+mod tokens;
+mod transcription_segments;
 mod types;
 mod video_db;
+mod webhooks;
 
+pub use activity::{
+    ActivityGroup, ActivityGroupBy, ActivityReport, ActivitySession, ACTIVITY_IDLE_SECONDS,
+};
 pub use db::DatabaseManager;
+pub use embeddings::{
+    EmbeddingProgress, EmbeddingSource, PendingEmbedding, SemanticMatch, SemanticSearchFilter,
+};
+pub use event_journal::{JournalEvent, JournalPosition, JournaledContent};
+pub use forget::{
+    AudioChunkEdit, ForgetFilter, ForgetPlan, ForgetReport, ForgottenFrame, VideoChunkEdit,
+};
+pub use hybrid_search::{HybridMatch, HybridSearchOptions, Relevance, SearchMode};
+pub use index_jobs::{IndexJob, IndexJobFile, IndexJobStatus};
+pub use meetings::{Meeting, MeetingSpeaker, MeetingTranscript, MeetingTranscriptEntry};
 pub use migration_worker::{
     create_migration_worker, MigrationCommand, MigrationConfig, MigrationResponse, MigrationStatus,
     MigrationWorker,
 };
+pub use raw_sql::{
+    ensure_read_only_statement, RawSqlColumn, RawSqlError, RawSqlOptions, RawSqlResult,
+};
+pub use retention::{MediaChunk, RetentionPlan, RetentionPolicy, RetentionReport, RetentionRule};
+pub use retention_worker::{
+    create_retention_worker, remove_media_files, RetentionCommand, RetentionConfig,
+    RetentionResponse, RetentionStatus, RetentionWorker,
+};
+pub use search_aggregate::{
+    AggregateOptions, Facet, HistogramBucket, SearchAggregate, SpeakerFacet,
+};
+pub use search_cursor::{SearchCursor, SearchPage};
+pub use speaker_clustering::{
+    ReclusterOptions, ReclusterReport, SpeakerMerge, MAX_SPEAKER_EMBEDDINGS,
+};
+pub use tokens::{ApiToken, TokenScope};
+pub use transcription_segments::{TranscriptionDetails, TranscriptionSegment, TranscriptionWord};
 pub use types::*;
+pub use webhooks::{Webhook, WebhookDeadLetter};
@@ -0,0 +1,319 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::retention::json_ids;
+use crate::DatabaseManager;
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeetingSpeaker {
+    pub id: i64,
+    pub name: Option<String>,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Meeting {
+    pub id: i64,
+    /// App the meeting was detected in
+    pub app: String,
+    /// Window title when the meeting started, if known
+    pub title: Option<String>,
+    pub start_time: DateTime<Utc>,
+    /// `None` while the meeting is in progress
+    pub end_time: Option<DateTime<Utc>>,
+    /// Speakers heard during the meeting, hallucinated speakers excluded
+    pub speakers: Vec<MeetingSpeaker>,
+    pub transcription_count: i64,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeetingTranscriptEntry {
+    pub audio_transcription_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub transcription: String,
+    pub device_name: String,
+    pub is_input_device: bool,
+    pub speaker: Option<MeetingSpeaker>,
+    /// Offsets of the segment within its audio chunk, in seconds
+    pub start_offset: Option<f64>,
+    pub end_offset: Option<f64>,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeetingTranscript {
+    pub meeting: Meeting,
+    /// Transcriptions in the order they were heard
+    pub transcript: Vec<MeetingTranscriptEntry>,
+}
+
+type MeetingRow = (
+    i64,
+    String,
+    Option<String>,
+    DateTime<Utc>,
+    Option<DateTime<Utc>>,
+    i64,
+);
+
+type TranscriptRow = (
+    i64,
+    DateTime<Utc>,
+    String,
+    String,
+    bool,
+    Option<i64>,
+    Option<String>,
+    Option<f64>,
+    Option<f64>,
+);
+
+const MEETING_COLUMNS: &str = r#"
+    m.id, m.app, m.title, m.start_time, m.end_time,
+    (SELECT COUNT(*) FROM meeting_transcriptions mt WHERE mt.meeting_id = m.id)
+"#;
+
+impl DatabaseManager {
+    /// Records the start of a meeting and returns its id
+    pub async fn start_meeting(
+        &self,
+        app: &str,
+        title: Option<&str>,
+        start_time: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query("INSERT INTO meetings (app, title, start_time) VALUES (?1, ?2, ?3)")
+            .bind(app)
+            .bind(title)
+            .bind(start_time)
+            .execute(&self.pool)
+            .await?
+            .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Closes a meeting and links the transcriptions heard until `end_time`. Returns false
+    /// when there is no meeting in progress with this id.
+    pub async fn end_meeting(&self, id: i64, end_time: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let result =
+            sqlx::query("UPDATE meetings SET end_time = ?2 WHERE id = ?1 AND end_time IS NULL")
+                .bind(id)
+                .bind(end_time)
+                .execute(&self.pool)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        self.link_meeting_transcriptions(id).await?;
+        Ok(true)
+    }
+
+    /// Links the transcriptions recorded during a meeting, up to now for meetings still in
+    /// progress. Safe to call repeatedly, returns the number of newly linked transcriptions.
+    pub async fn link_meeting_transcriptions(&self, id: i64) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // links made while the meeting was in progress may fall after its final end time
+        sqlx::query(
+            r#"
+            DELETE FROM meeting_transcriptions
+            WHERE meeting_id = ?1 AND audio_transcription_id IN (
+                SELECT at.id
+                FROM meetings m
+                JOIN audio_transcriptions at ON at.timestamp > m.end_time
+                WHERE m.id = ?1
+            )
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO meeting_transcriptions (meeting_id, audio_transcription_id)
+            SELECT m.id, at.id
+            FROM meetings m
+            JOIN audio_transcriptions at
+                ON at.timestamp >= m.start_time
+                AND (m.end_time IS NULL OR at.timestamp <= m.end_time)
+            WHERE m.id = ?1
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Closes meetings left open by a previous run at their last linked transcription, or
+    /// at their start when nothing was heard. Returns the number of meetings closed.
+    pub async fn close_open_meetings(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE meetings
+            SET end_time = COALESCE(
+                (
+                    SELECT MAX(at.timestamp)
+                    FROM meeting_transcriptions mt
+                    JOIN audio_transcriptions at ON at.id = mt.audio_transcription_id
+                    WHERE mt.meeting_id = meetings.id
+                ),
+                start_time
+            )
+            WHERE end_time IS NULL
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Meetings overlapping the given time range, most recent first
+    pub async fn list_meetings(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Meeting>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, MeetingRow>(&format!(
+            r#"
+            SELECT {}
+            FROM meetings m
+            WHERE (?1 IS NULL OR m.end_time IS NULL OR m.end_time >= ?1)
+                AND (?2 IS NULL OR m.start_time <= ?2)
+            ORDER BY m.start_time DESC
+            LIMIT ?3 OFFSET ?4
+            "#,
+            MEETING_COLUMNS
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let ids: Vec<i64> = rows.iter().map(|row| row.0).collect();
+        let mut speakers = self.meeting_speakers(&ids).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let speakers = speakers.remove(&row.0).unwrap_or_default();
+                meeting_from_row(row, speakers)
+            })
+            .collect())
+    }
+
+    /// A meeting with its speaker attributed transcript
+    pub async fn get_meeting(&self, id: i64) -> Result<Option<MeetingTranscript>, sqlx::Error> {
+        let Some(row) = sqlx::query_as::<_, MeetingRow>(&format!(
+            "SELECT {} FROM meetings m WHERE m.id = ?1",
+            MEETING_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let transcript = sqlx::query_as::<_, TranscriptRow>(
+            r#"
+            SELECT at.id, at.timestamp, at.transcription, at.device, at.is_input_device,
+                s.id, s.name, at.start_time, at.end_time
+            FROM meeting_transcriptions mt
+            JOIN audio_transcriptions at ON at.id = mt.audio_transcription_id
+            LEFT JOIN speakers s ON s.id = at.speaker_id
+            WHERE mt.meeting_id = ?1
+            ORDER BY at.timestamp, at.start_time, at.id
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(
+            |(
+                audio_transcription_id,
+                timestamp,
+                transcription,
+                device_name,
+                is_input_device,
+                speaker_id,
+                speaker_name,
+                start_offset,
+                end_offset,
+            )| MeetingTranscriptEntry {
+                audio_transcription_id,
+                timestamp,
+                transcription,
+                device_name,
+                is_input_device,
+                speaker: speaker_id.map(|id| MeetingSpeaker {
+                    id,
+                    name: speaker_name,
+                }),
+                start_offset,
+                end_offset,
+            },
+        )
+        .collect();
+
+        let speakers = self
+            .meeting_speakers(&[id])
+            .await?
+            .remove(&id)
+            .unwrap_or_default();
+        Ok(Some(MeetingTranscript {
+            meeting: meeting_from_row(row, speakers),
+            transcript,
+        }))
+    }
+
+    async fn meeting_speakers(
+        &self,
+        meeting_ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<MeetingSpeaker>>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (i64, i64, Option<String>)>(
+            r#"
+            SELECT DISTINCT mt.meeting_id, s.id, s.name
+            FROM meeting_transcriptions mt
+            JOIN audio_transcriptions at ON at.id = mt.audio_transcription_id
+            JOIN speakers s ON s.id = at.speaker_id
+            WHERE mt.meeting_id IN (SELECT value FROM json_each(?1))
+                AND COALESCE(s.hallucination, FALSE) = FALSE
+            ORDER BY mt.meeting_id, s.id
+            "#,
+        )
+        .bind(json_ids(meeting_ids))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut speakers: HashMap<i64, Vec<MeetingSpeaker>> = HashMap::new();
+        for (meeting_id, id, name) in rows {
+            speakers
+                .entry(meeting_id)
+                .or_default()
+                .push(MeetingSpeaker { id, name });
+        }
+        Ok(speakers)
+    }
+}
+
+fn meeting_from_row(
+    (id, app, title, start_time, end_time, transcription_count): MeetingRow,
+    speakers: Vec<MeetingSpeaker>,
+) -> Meeting {
+    Meeting {
+        id,
+        app,
+        title,
+        start_time,
+        end_time,
+        speakers,
+        transcription_count,
+    }
+}
@@ -0,0 +1,11 @@
+-- The delete trigger used to wipe every FTS row of the audio chunk, so removing a single
+-- transcription dropped its siblings from search. Scope it to the deleted row instead.
+DROP TRIGGER IF EXISTS audio_transcriptions_delete;
+
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_delete AFTER DELETE ON audio_transcriptions
+BEGIN
+    DELETE FROM audio_transcriptions_fts
+    WHERE audio_chunk_id = OLD.audio_chunk_id
+      AND transcription = OLD.transcription
+      AND start_time IS OLD.start_time;
+END;
@@ -0,0 +1,24 @@
+-- Meetings detected from meeting_started / meeting_ended events
+CREATE TABLE IF NOT EXISTS meetings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    app TEXT NOT NULL,
+    title TEXT,
+    start_time TIMESTAMP NOT NULL,
+    -- NULL while the meeting is in progress
+    end_time TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_meetings_start_time ON meetings(start_time);
+
+-- Transcriptions heard during a meeting. Speakers are linked through the transcriptions
+-- so merging or deleting a speaker is reflected in the meetings they took part in.
+CREATE TABLE IF NOT EXISTS meeting_transcriptions (
+    meeting_id INTEGER NOT NULL,
+    audio_transcription_id INTEGER NOT NULL,
+    PRIMARY KEY (meeting_id, audio_transcription_id),
+    FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE,
+    FOREIGN KEY (audio_transcription_id) REFERENCES audio_transcriptions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_meeting_transcriptions_audio_transcription_id
+    ON meeting_transcriptions(audio_transcription_id);
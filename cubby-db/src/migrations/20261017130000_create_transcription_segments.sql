@@ -0,0 +1,36 @@
+-- Language and confidence reported by the transcription engine
+ALTER TABLE audio_transcriptions ADD COLUMN language TEXT;
+ALTER TABLE audio_transcriptions ADD COLUMN avg_logprob REAL;
+
+-- Segments of a transcription with their offsets in the audio chunk, in seconds.
+-- `words` is a JSON array of {text, start_time, end_time, probability}.
+CREATE TABLE IF NOT EXISTS transcription_segments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audio_transcription_id INTEGER NOT NULL,
+    start_time REAL NOT NULL,
+    end_time REAL NOT NULL,
+    text TEXT NOT NULL,
+    avg_logprob REAL,
+    words JSON NOT NULL DEFAULT '[]',
+    FOREIGN KEY (audio_transcription_id) REFERENCES audio_transcriptions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcription_segments_audio_transcription_id
+    ON transcription_segments(audio_transcription_id);
+
+-- The update trigger fired on any column and rewrote every FTS row of the chunk, so
+-- storing the language of a transcription would clobber its siblings. Only react to
+-- changes of the indexed columns.
+DROP TRIGGER IF EXISTS audio_transcriptions_update;
+
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_update
+AFTER UPDATE OF transcription, device, start_time, end_time ON audio_transcriptions
+WHEN NEW.transcription IS NOT NULL AND NEW.transcription != '' AND OLD.audio_chunk_id IS NOT NULL
+BEGIN
+    UPDATE audio_transcriptions_fts
+    SET transcription = NEW.transcription,
+        device = COALESCE(NEW.device, ''),
+        start_time = NEW.start_time,
+        end_time = NEW.end_time
+    WHERE audio_chunk_id = OLD.audio_chunk_id;
+END;
@@ -0,0 +1,36 @@
+-- Video indexing jobs started from the api. Jobs left queued or running by a crash are
+-- picked up again when the server starts.
+CREATE TABLE IF NOT EXISTS index_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL,
+    pattern TEXT,
+    -- json, the indexing options (ocr engine, copy_videos, ...)
+    options TEXT NOT NULL DEFAULT '{}',
+    -- queued, running, completed, failed or cancelled
+    status TEXT NOT NULL DEFAULT 'queued',
+    -- NULL until the files to index have been listed
+    total_files INTEGER,
+    processed_files INTEGER NOT NULL DEFAULT 0,
+    failed_files INTEGER NOT NULL DEFAULT 0,
+    frames INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_index_jobs_status ON index_jobs(status);
+
+-- Files a job has started on. A resumed job skips the finished ones and deletes the video
+-- chunk of the one it was interrupted in before indexing it again.
+CREATE TABLE IF NOT EXISTS index_job_files (
+    job_id INTEGER NOT NULL,
+    file_path TEXT NOT NULL,
+    -- not a foreign key, the chunk may be deleted by retention while the job is kept
+    video_chunk_id INTEGER,
+    frames INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    finished BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (job_id, file_path),
+    FOREIGN KEY (job_id) REFERENCES index_jobs(id) ON DELETE CASCADE
+);
@@ -0,0 +1,28 @@
+-- Embeddings of one model can't be compared with another's, so every vector records the
+-- model that produced it. The existing ones all came from Ollama.
+ALTER TABLE ocr_text_embeddings ADD COLUMN model TEXT NOT NULL DEFAULT 'ollama:nomic-embed-text';
+
+CREATE INDEX IF NOT EXISTS idx_ocr_text_embeddings_frame_id_model
+    ON ocr_text_embeddings(frame_id, model);
+
+-- One row per chunk of a transcription
+CREATE TABLE IF NOT EXISTS audio_transcription_embeddings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audio_transcription_id INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (audio_transcription_id) REFERENCES audio_transcriptions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_audio_transcription_embeddings_transcription_id_model
+    ON audio_transcription_embeddings(audio_transcription_id, model);
+
+-- Last row of each source embedded with each model, rows are embedded in id order
+CREATE TABLE IF NOT EXISTS embedding_cursors (
+    source TEXT NOT NULL,
+    model TEXT NOT NULL,
+    last_id INTEGER NOT NULL DEFAULT 0,
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (source, model)
+);
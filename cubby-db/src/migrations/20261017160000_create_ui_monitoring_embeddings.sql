@@ -0,0 +1,20 @@
+-- One row per chunk of the text of a window
+CREATE TABLE IF NOT EXISTS ui_monitoring_embeddings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ui_monitoring_id INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (ui_monitoring_id) REFERENCES ui_monitoring(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_ui_monitoring_embeddings_ui_monitoring_id_model
+    ON ui_monitoring_embeddings(ui_monitoring_id, model);
+
+-- The text of a window is rewritten in place as it changes, its embeddings go stale with it
+CREATE TRIGGER IF NOT EXISTS ui_monitoring_embeddings_stale
+AFTER UPDATE OF text_output ON ui_monitoring
+WHEN OLD.text_output IS NOT NEW.text_output
+BEGIN
+    DELETE FROM ui_monitoring_embeddings WHERE ui_monitoring_id = NEW.id;
+END;
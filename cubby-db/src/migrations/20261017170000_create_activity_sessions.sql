@@ -0,0 +1,21 @@
+-- Stretches of time spent in one focused app and window, rolled up from focused frames
+CREATE TABLE IF NOT EXISTS activity_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    app_name TEXT NOT NULL,
+    window_name TEXT NOT NULL,
+    -- host of the browser url, NULL outside browsers
+    domain TEXT,
+    start_time TIMESTAMP NOT NULL,
+    -- the next frame of another window, or the last frame when the screen went idle
+    end_time TIMESTAMP NOT NULL,
+    frame_count INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_activity_sessions_end_time ON activity_sessions(end_time);
+
+-- Last frame rolled up into sessions, frames are processed in id order
+CREATE TABLE IF NOT EXISTS activity_session_cursor (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    last_frame_id INTEGER NOT NULL,
+    updated_at DATETIME NOT NULL
+);
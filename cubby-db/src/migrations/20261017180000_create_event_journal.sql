@@ -0,0 +1,12 @@
+-- Events of the in-process bus, kept when the event journal is enabled so that clients
+-- can replay what they missed. AUTOINCREMENT keeps sequence numbers growing even after
+-- old events are deleted.
+CREATE TABLE IF NOT EXISTS event_journal (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    -- json, without the screenshots of ocr_result and ui_frame events
+    data TEXT NOT NULL,
+    timestamp TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_event_journal_timestamp ON event_journal(timestamp);
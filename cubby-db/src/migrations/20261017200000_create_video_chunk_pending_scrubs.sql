@@ -0,0 +1,7 @@
+-- Frames forgotten while their video chunk was still being recorded. Their rows are gone
+-- but their pixels are cut out of the file only once the chunk is finished.
+CREATE TABLE IF NOT EXISTS video_chunk_pending_scrubs (
+    video_chunk_id INTEGER NOT NULL,
+    offset_index INTEGER NOT NULL,
+    PRIMARY KEY (video_chunk_id, offset_index)
+);
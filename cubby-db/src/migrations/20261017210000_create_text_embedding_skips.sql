@@ -0,0 +1,17 @@
+-- Rows a model could not embed, e.g. text its backend always rejects, so the embedding
+-- worker stops retrying them. Windows are tried again once their text changes.
+CREATE TABLE IF NOT EXISTS text_embedding_skips (
+    source TEXT NOT NULL,
+    row_id INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at DATETIME NOT NULL,
+    PRIMARY KEY (source, row_id, model)
+);
+
+CREATE TRIGGER IF NOT EXISTS text_embedding_skips_ui_stale
+AFTER UPDATE OF text_output ON ui_monitoring
+WHEN OLD.text_output IS NOT NEW.text_output
+BEGIN
+    DELETE FROM text_embedding_skips WHERE source = 'ui' AND row_id = NEW.id;
+END;
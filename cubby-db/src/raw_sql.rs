@@ -0,0 +1,381 @@
+use base64::{engine::general_purpose, Engine as _};
+use futures::TryStreamExt;
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::{SqliteConnection, SqliteRow};
+use sqlx::{Column, Executor, Row, Sqlite, Statement, TypeInfo, ValueRef};
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::DatabaseManager;
+
+/// Limits applied to user supplied SQL
+#[derive(Debug, Clone)]
+pub struct RawSqlOptions {
+    /// Rows returned at most, the rest of the result set is dropped
+    pub max_rows: usize,
+    /// Queries running longer than this are interrupted
+    pub timeout: Duration,
+}
+
+impl Default for RawSqlOptions {
+    fn default() -> Self {
+        Self {
+            max_rows: 10_000,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawSqlColumn {
+    pub name: String,
+    /// Declared type of the column, or the type of the first value for expressions
+    pub decl_type: String,
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct RawSqlResult {
+    pub columns: Vec<RawSqlColumn>,
+    /// One object per row, BLOBs are base64 encoded
+    pub rows: Vec<serde_json::Value>,
+    /// More rows matched than `max_rows`
+    pub truncated: bool,
+}
+
+#[derive(Debug)]
+pub enum RawSqlError {
+    /// The query is not a single read-only statement
+    Rejected(String),
+    /// The query ran longer than the configured timeout
+    Timeout(Duration),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for RawSqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RawSqlError::Rejected(reason) => write!(f, "query rejected: {}", reason),
+            RawSqlError::Timeout(timeout) => {
+                write!(f, "query interrupted after {}ms", timeout.as_millis())
+            }
+            RawSqlError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RawSqlError {}
+
+impl From<sqlx::Error> for RawSqlError {
+    fn from(e: sqlx::Error) -> Self {
+        RawSqlError::Database(e)
+    }
+}
+
+/// Accepts a single SELECT or WITH statement, optionally followed by semicolons and
+/// comments. Everything else, including stacked statements, is rejected.
+pub fn ensure_read_only_statement(query: &str) -> Result<(), RawSqlError> {
+    let mut statements = 0;
+    let mut first_keyword: Option<String> = None;
+    let mut in_statement = false;
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        closed = true;
+                        break;
+                    }
+                    prev = c;
+                }
+                if !closed {
+                    return Err(RawSqlError::Rejected("unterminated comment".to_string()));
+                }
+                continue;
+            }
+            ';' => {
+                in_statement = false;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            _ => {}
+        }
+
+        if !in_statement {
+            in_statement = true;
+            statements += 1;
+            if statements > 1 {
+                return Err(RawSqlError::Rejected(
+                    "only a single statement is allowed".to_string(),
+                ));
+            }
+            let mut keyword = String::from(c);
+            while let Some(c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                keyword.push(*c);
+                chars.next();
+            }
+            first_keyword = Some(keyword.to_ascii_uppercase());
+            continue;
+        }
+
+        // skip over quoted strings and identifiers so their contents are not parsed
+        let close = match c {
+            '\'' => '\'',
+            '"' => '"',
+            '`' => '`',
+            '[' => ']',
+            _ => continue,
+        };
+        let mut closed = false;
+        while let Some(c) = chars.next() {
+            if c == close {
+                // doubled quotes are escapes
+                if close != ']' && chars.peek() == Some(&close) {
+                    chars.next();
+                    continue;
+                }
+                closed = true;
+                break;
+            }
+        }
+        if !closed {
+            return Err(RawSqlError::Rejected("unterminated quote".to_string()));
+        }
+    }
+
+    match first_keyword.as_deref() {
+        Some("SELECT") | Some("WITH") => Ok(()),
+        Some(keyword) => Err(RawSqlError::Rejected(format!(
+            "only SELECT and WITH statements are allowed, got {}",
+            keyword
+        ))),
+        None => Err(RawSqlError::Rejected("empty query".to_string())),
+    }
+}
+
+/// Converts a row to a JSON object keyed by column name
+pub(crate) fn row_to_json(row: &SqliteRow) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+    for (i, column) in row.columns().iter().enumerate() {
+        let Ok(value) = row.try_get_raw(i) else {
+            continue;
+        };
+        let json_value = if value.is_null() {
+            serde_json::Value::Null
+        } else {
+            match value.type_info().name() {
+                "INTEGER" | "BOOLEAN" => row
+                    .try_get_unchecked::<i64, _>(i)
+                    .map(Into::into)
+                    .unwrap_or_default(),
+                "REAL" | "NUMERIC" => row
+                    .try_get_unchecked::<f64, _>(i)
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(serde_json::Value::Number)
+                    .unwrap_or_default(),
+                "BLOB" => row
+                    .try_get_unchecked::<Vec<u8>, _>(i)
+                    .map(|bytes| general_purpose::STANDARD.encode(bytes).into())
+                    .unwrap_or_default(),
+                _ => row
+                    .try_get_unchecked::<String, _>(i)
+                    .map(Into::into)
+                    .unwrap_or_default(),
+            }
+        };
+        map.insert(column.name().to_string(), json_value);
+    }
+    serde_json::Value::Object(map)
+}
+
+impl DatabaseManager {
+    /// Runs a user supplied query on a read-only connection. The query must be a single
+    /// SELECT or WITH statement; it is interrupted once `options.timeout` has passed and at
+    /// most `options.max_rows` rows are returned.
+    pub async fn execute_read_only_sql(
+        &self,
+        query: &str,
+        options: &RawSqlOptions,
+    ) -> Result<RawSqlResult, RawSqlError> {
+        ensure_read_only_statement(query)?;
+
+        // in-memory databases can't be opened a second time, so they share the main pool
+        // and rely on query_only for the duration of the query
+        let shared = self.read_only_pool.is_none();
+        let conn = match &self.read_only_pool {
+            Some(pool) => pool.acquire().await?,
+            None => self.pool.acquire().await?,
+        };
+        let mut conn = RawSqlConnection {
+            conn: Some(conn),
+            shared,
+        };
+        if shared {
+            sqlx::query("PRAGMA query_only = ON")
+                .execute(conn.get())
+                .await?;
+        }
+
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let result = fetch_with_timeout(conn.get(), query, options, timed_out.clone()).await;
+        conn.release().await?;
+
+        match result {
+            Err(_) if timed_out.load(Ordering::SeqCst) => {
+                Err(RawSqlError::Timeout(options.timeout))
+            }
+            Err(e) => Err(e.into()),
+            Ok(result) => Ok(result),
+        }
+    }
+}
+
+/// Pooled connection running a user supplied query. Its progress handler (and query_only
+/// on the shared pool) must be reset before it goes back to the pool, also when the query
+/// future is dropped half way, e.g. because the client disconnected.
+struct RawSqlConnection {
+    conn: Option<PoolConnection<Sqlite>>,
+    shared: bool,
+}
+
+impl RawSqlConnection {
+    fn get(&mut self) -> &mut SqliteConnection {
+        self.conn
+            .as_mut()
+            .expect("connection is only taken on release")
+    }
+
+    async fn release(mut self) -> Result<(), sqlx::Error> {
+        let conn = self
+            .conn
+            .take()
+            .expect("connection is only taken on release");
+        reset_connection(conn, self.shared).await
+    }
+}
+
+impl Drop for RawSqlConnection {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else {
+            return;
+        };
+        let shared = self.shared;
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = reset_connection(conn, shared).await {
+                        warn!("failed to reset raw sql connection: {}", e);
+                    }
+                });
+            }
+            Err(_) => drop(conn.detach()),
+        }
+    }
+}
+
+/// Removes the progress handler and turns query_only back off on the shared pool. A
+/// connection that can't be reset is closed instead of going back to the pool.
+async fn reset_connection(
+    mut conn: PoolConnection<Sqlite>,
+    shared: bool,
+) -> Result<(), sqlx::Error> {
+    let result = async {
+        conn.lock_handle().await?.remove_progress_handler();
+        if shared {
+            sqlx::query("PRAGMA query_only = OFF")
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(())
+    }
+    .await;
+    if result.is_err() {
+        drop(conn.detach());
+    }
+    result
+}
+
+/// Runs `query` with a progress handler that interrupts it once `options.timeout` has passed.
+/// The handler is left in place, `reset_connection` removes it.
+async fn fetch_with_timeout(
+    conn: &mut SqliteConnection,
+    query: &str,
+    options: &RawSqlOptions,
+    timed_out: Arc<AtomicBool>,
+) -> Result<RawSqlResult, sqlx::Error> {
+    let deadline = Instant::now() + options.timeout;
+    conn.lock_handle()
+        .await?
+        .set_progress_handler(1000, move || {
+            if Instant::now() < deadline {
+                return true;
+            }
+            timed_out.store(true, Ordering::SeqCst);
+            false
+        });
+
+    fetch_limited(conn, query, options.max_rows).await
+}
+
+async fn fetch_limited(
+    conn: &mut SqliteConnection,
+    query: &str,
+    max_rows: usize,
+) -> Result<RawSqlResult, sqlx::Error> {
+    let statement = (&mut *conn).prepare(query).await?;
+    let mut columns: Vec<RawSqlColumn> = statement
+        .columns()
+        .iter()
+        .map(|column| RawSqlColumn {
+            name: column.name().to_string(),
+            decl_type: column.type_info().name().to_string(),
+        })
+        .collect();
+
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    let mut stream = statement.query().fetch(&mut *conn);
+    while let Some(row) = stream.try_next().await? {
+        if rows.len() == max_rows {
+            truncated = true;
+            break;
+        }
+        if rows.is_empty() {
+            // expressions have no declared type, fall back to the type of their first value
+            for (i, column) in columns.iter_mut().enumerate() {
+                if column.decl_type == "NULL" {
+                    if let Ok(value) = row.try_get_raw(i) {
+                        column.decl_type = value.type_info().name().to_string();
+                    }
+                }
+            }
+        }
+        rows.push(row_to_json(&row));
+    }
+
+    Ok(RawSqlResult {
+        columns,
+        rows,
+        truncated,
+    })
+}
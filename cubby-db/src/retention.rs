@@ -0,0 +1,682 @@
+use chrono::{DateTime, Duration, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{Sqlite, Transaction};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use tracing::debug;
+
+use crate::{DatabaseManager, ForgetFilter, JournaledContent};
+
+/// Rules deciding which recorded content gets purged
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Purge all content older than this many days
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// Per app/window rules, usually stricter than `max_age_days`
+    #[serde(default)]
+    pub rules: Vec<RetentionRule>,
+    /// Purge the oldest video/audio chunks until their files fit in this many bytes
+    #[serde(default)]
+    pub max_disk_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.max_age_days.is_none() && self.rules.is_empty() && self.max_disk_bytes.is_none()
+    }
+}
+
+/// Purge screen and UI content of matching apps/windows after `max_age_days`
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionRule {
+    /// Substring of the app name, matched with LIKE
+    #[serde(default)]
+    pub app_name: Option<String>,
+    /// Substring of the window name, matched with LIKE
+    #[serde(default)]
+    pub window_name: Option<String>,
+    /// Days to keep matching content, 0 purges it on the next run
+    #[serde(default)]
+    pub max_age_days: u32,
+}
+
+/// A video or audio chunk selected for deletion
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaChunk {
+    pub id: i64,
+    pub file_path: String,
+    pub size_bytes: u64,
+}
+
+/// Rows and files selected by a retention policy, computed before anything is deleted
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPlan {
+    pub frame_ids: Vec<i64>,
+    pub ocr_text_count: i64,
+    pub audio_transcription_ids: Vec<i64>,
+    pub ui_monitoring_ids: Vec<i64>,
+    pub video_chunks: Vec<MediaChunk>,
+    pub audio_chunks: Vec<MediaChunk>,
+    /// Journaled events carrying the text of what is purged
+    pub journal_content: Vec<JournaledContent>,
+}
+
+impl RetentionPlan {
+    pub fn total_items(&self) -> usize {
+        self.frame_ids.len()
+            + self.audio_transcription_ids.len()
+            + self.ui_monitoring_ids.len()
+            + self.video_chunks.len()
+            + self.audio_chunks.len()
+    }
+
+    /// Summary of what purging this plan would remove
+    pub fn report(&self) -> RetentionReport {
+        let chunks = self.video_chunks.iter().chain(self.audio_chunks.iter());
+        RetentionReport {
+            frames: self.frame_ids.len() as i64,
+            ocr_text: self.ocr_text_count,
+            audio_transcriptions: self.audio_transcription_ids.len() as i64,
+            ui_monitoring: self.ui_monitoring_ids.len() as i64,
+            video_chunks: self.video_chunks.len() as i64,
+            audio_chunks: self.audio_chunks.len() as i64,
+            bytes: chunks.clone().map(|c| c.size_bytes).sum(),
+            files: chunks.map(|c| c.file_path.clone()).collect(),
+        }
+    }
+}
+
+/// Counts of removed (or, for a preview, removable) rows and media files
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub frames: i64,
+    pub ocr_text: i64,
+    pub audio_transcriptions: i64,
+    pub ui_monitoring: i64,
+    pub video_chunks: i64,
+    pub audio_chunks: i64,
+    pub bytes: u64,
+    pub files: Vec<String>,
+}
+
+impl RetentionReport {
+    pub fn merge(&mut self, other: RetentionReport) {
+        self.frames += other.frames;
+        self.ocr_text += other.ocr_text;
+        self.audio_transcriptions += other.audio_transcriptions;
+        self.ui_monitoring += other.ui_monitoring;
+        self.video_chunks += other.video_chunks;
+        self.audio_chunks += other.audio_chunks;
+        self.bytes += other.bytes;
+        self.files.extend(other.files);
+    }
+}
+
+/// Everything up to `cutoff`, of the apps and windows matching if given
+fn journal_filter(
+    cutoff: DateTime<Utc>,
+    app_name: Option<String>,
+    window_name: Option<String>,
+) -> ForgetFilter {
+    ForgetFilter {
+        start_time: DateTime::UNIX_EPOCH,
+        end_time: cutoff,
+        app_name,
+        window_name,
+        browser_url: None,
+        device_name: None,
+    }
+}
+
+pub(crate) fn json_ids(ids: &[i64]) -> String {
+    serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string())
+}
+
+async fn file_size(path: &str) -> u64 {
+    tokio::fs::metadata(path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0)
+}
+
+impl DatabaseManager {
+    /// Latest video chunk of every device, which ffmpeg may still be writing to
+    pub(crate) async fn active_video_chunk_ids(&self) -> Result<HashSet<i64>, sqlx::Error> {
+        Ok(
+            sqlx::query_scalar::<_, i64>("SELECT MAX(id) FROM video_chunks GROUP BY device_name")
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    /// Computes what `policy` would purge at `now` without deleting anything.
+    pub async fn plan_retention(
+        &self,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<RetentionPlan, sqlx::Error> {
+        // frame id -> video chunk id, transcription id -> audio chunk id
+        let mut frames: BTreeMap<i64, i64> = BTreeMap::new();
+        let mut transcriptions: BTreeMap<i64, i64> = BTreeMap::new();
+        let mut ui_ids: BTreeSet<i64> = BTreeSet::new();
+        let mut video_chunk_ids: BTreeSet<i64> = BTreeSet::new();
+        let mut audio_chunk_ids: BTreeSet<i64> = BTreeSet::new();
+        let mut journal_content = Vec::new();
+
+        if let Some(days) = policy.max_age_days {
+            let cutoff = now - Duration::days(days as i64);
+            journal_content.push(JournaledContent {
+                filter: journal_filter(cutoff, None, None),
+                screen: true,
+                audio: true,
+            });
+
+            frames.extend(
+                sqlx::query_as::<_, (i64, i64)>(
+                    "SELECT id, video_chunk_id FROM frames WHERE timestamp < ?1",
+                )
+                .bind(cutoff)
+                .fetch_all(&self.pool)
+                .await?,
+            );
+            transcriptions.extend(
+                sqlx::query_as::<_, (i64, i64)>(
+                    "SELECT id, audio_chunk_id FROM audio_transcriptions WHERE timestamp < ?1",
+                )
+                .bind(cutoff)
+                .fetch_all(&self.pool)
+                .await?,
+            );
+            ui_ids.extend(
+                sqlx::query_scalar::<_, i64>("SELECT id FROM ui_monitoring WHERE timestamp < ?1")
+                    .bind(cutoff)
+                    .fetch_all(&self.pool)
+                    .await?,
+            );
+            // audio chunks where nothing was transcribed (silence) have no rows pointing at them
+            audio_chunk_ids.extend(
+                sqlx::query_scalar::<_, i64>(
+                    r#"
+                    SELECT id FROM audio_chunks
+                    WHERE timestamp < ?1
+                      AND NOT EXISTS (
+                          SELECT 1 FROM audio_transcriptions
+                          WHERE audio_transcriptions.audio_chunk_id = audio_chunks.id
+                      )
+                    "#,
+                )
+                .bind(cutoff)
+                .fetch_all(&self.pool)
+                .await?,
+            );
+        }
+
+        for rule in &policy.rules {
+            let cutoff = now - Duration::days(rule.max_age_days as i64);
+            journal_content.push(JournaledContent {
+                filter: journal_filter(cutoff, rule.app_name.clone(), rule.window_name.clone()),
+                screen: true,
+                audio: false,
+            });
+
+            frames.extend(
+                sqlx::query_as::<_, (i64, i64)>(
+                    r#"
+                    SELECT id, video_chunk_id FROM frames
+                    WHERE timestamp < ?1
+                      AND (?2 IS NULL OR app_name LIKE '%' || ?2 || '%')
+                      AND (?3 IS NULL OR window_name LIKE '%' || ?3 || '%')
+                    "#,
+                )
+                .bind(cutoff)
+                .bind(&rule.app_name)
+                .bind(&rule.window_name)
+                .fetch_all(&self.pool)
+                .await?,
+            );
+            ui_ids.extend(
+                sqlx::query_scalar::<_, i64>(
+                    r#"
+                    SELECT id FROM ui_monitoring
+                    WHERE timestamp < ?1
+                      AND (?2 IS NULL OR app LIKE '%' || ?2 || '%')
+                      AND (?3 IS NULL OR window LIKE '%' || ?3 || '%')
+                    "#,
+                )
+                .bind(cutoff)
+                .bind(&rule.app_name)
+                .bind(&rule.window_name)
+                .fetch_all(&self.pool)
+                .await?,
+            );
+        }
+
+        // The newest chunk of each device may still be written to, never delete its file
+        let active_video_chunks = self.active_video_chunk_ids().await?;
+
+        // A chunk only goes away once every row pointing at it is purged
+        let mut purged_per_video_chunk: HashMap<i64, i64> = HashMap::new();
+        for chunk_id in frames.values() {
+            *purged_per_video_chunk.entry(*chunk_id).or_default() += 1;
+        }
+        let touched: Vec<i64> = purged_per_video_chunk.keys().copied().collect();
+        let totals = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT video_chunk_id, COUNT(*) FROM frames
+            WHERE video_chunk_id IN (SELECT value FROM json_each(?1))
+            GROUP BY video_chunk_id
+            "#,
+        )
+        .bind(json_ids(&touched))
+        .fetch_all(&self.pool)
+        .await?;
+        for (chunk_id, total) in totals {
+            if purged_per_video_chunk.get(&chunk_id) == Some(&total)
+                && !active_video_chunks.contains(&chunk_id)
+            {
+                video_chunk_ids.insert(chunk_id);
+            }
+        }
+
+        let mut purged_per_audio_chunk: HashMap<i64, i64> = HashMap::new();
+        for chunk_id in transcriptions.values() {
+            *purged_per_audio_chunk.entry(*chunk_id).or_default() += 1;
+        }
+        let touched: Vec<i64> = purged_per_audio_chunk.keys().copied().collect();
+        let totals = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT audio_chunk_id, COUNT(*) FROM audio_transcriptions
+            WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))
+            GROUP BY audio_chunk_id
+            "#,
+        )
+        .bind(json_ids(&touched))
+        .fetch_all(&self.pool)
+        .await?;
+        for (chunk_id, total) in totals {
+            if purged_per_audio_chunk.get(&chunk_id) == Some(&total) {
+                audio_chunk_ids.insert(chunk_id);
+            }
+        }
+
+        if let Some(max_disk_bytes) = policy.max_disk_bytes {
+            let video = sqlx::query_as::<_, (i64, String, Option<DateTime<Utc>>)>(
+                r#"
+                SELECT id, file_path,
+                       (SELECT MIN(timestamp) FROM frames WHERE frames.video_chunk_id = video_chunks.id)
+                FROM video_chunks
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            let audio = sqlx::query_as::<_, (i64, String, Option<DateTime<Utc>>)>(
+                "SELECT id, file_path, timestamp FROM audio_chunks",
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            let mut used_bytes = 0;
+            // (timestamp, is_audio, id, size) of chunks that could still be purged
+            let mut candidates = Vec::new();
+            for (is_audio, (id, file_path, timestamp)) in video
+                .into_iter()
+                .map(|c| (false, c))
+                .chain(audio.into_iter().map(|c| (true, c)))
+            {
+                let size = file_size(&file_path).await;
+                let planned = if is_audio {
+                    audio_chunk_ids.contains(&id)
+                } else {
+                    video_chunk_ids.contains(&id)
+                };
+                if planned {
+                    continue;
+                }
+                used_bytes += size;
+                if is_audio || !active_video_chunks.contains(&id) {
+                    candidates.push((timestamp, is_audio, id, size));
+                }
+            }
+
+            // chunks without a timestamp have no frames left and go first
+            candidates.sort();
+            let mut budget_video = Vec::new();
+            let mut budget_audio = Vec::new();
+            for (_, is_audio, id, size) in candidates {
+                if used_bytes <= max_disk_bytes {
+                    break;
+                }
+                used_bytes -= size;
+                if is_audio {
+                    audio_chunk_ids.insert(id);
+                    budget_audio.push(id);
+                } else {
+                    video_chunk_ids.insert(id);
+                    budget_video.push(id);
+                }
+            }
+
+            frames.extend(
+                sqlx::query_as::<_, (i64, i64)>(
+                    r#"
+                    SELECT id, video_chunk_id FROM frames
+                    WHERE video_chunk_id IN (SELECT value FROM json_each(?1))
+                    "#,
+                )
+                .bind(json_ids(&budget_video))
+                .fetch_all(&self.pool)
+                .await?,
+            );
+            transcriptions.extend(
+                sqlx::query_as::<_, (i64, i64)>(
+                    r#"
+                    SELECT id, audio_chunk_id FROM audio_transcriptions
+                    WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))
+                    "#,
+                )
+                .bind(json_ids(&budget_audio))
+                .fetch_all(&self.pool)
+                .await?,
+            );
+
+            // what the chunks held was journaled while they were recorded
+            let recorded = [
+                (
+                    r#"
+                    SELECT device_name, MIN(timestamp), MAX(timestamp) FROM frames
+                    WHERE video_chunk_id IN (SELECT value FROM json_each(?1))
+                    GROUP BY video_chunk_id
+                    "#,
+                    &budget_video,
+                    false,
+                ),
+                (
+                    r#"
+                    SELECT device, MIN(timestamp), MAX(timestamp) FROM audio_transcriptions
+                    WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))
+                    GROUP BY audio_chunk_id
+                    "#,
+                    &budget_audio,
+                    true,
+                ),
+            ];
+            for (query, ids, audio) in recorded {
+                for (device_name, start_time, end_time) in
+                    sqlx::query_as::<_, (String, DateTime<Utc>, DateTime<Utc>)>(query)
+                        .bind(json_ids(ids))
+                        .fetch_all(&self.pool)
+                        .await?
+                {
+                    journal_content.push(JournaledContent {
+                        filter: ForgetFilter {
+                            start_time,
+                            end_time,
+                            app_name: None,
+                            window_name: None,
+                            browser_url: None,
+                            device_name: Some(device_name),
+                        },
+                        screen: !audio,
+                        audio,
+                    });
+                }
+            }
+        }
+
+        let frame_ids: Vec<i64> = frames.into_keys().collect();
+        let ocr_text_count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM ocr_text WHERE frame_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(json_ids(&frame_ids))
+        .fetch_one(&self.pool)
+        .await?;
+
+        let mut plan = RetentionPlan {
+            frame_ids,
+            ocr_text_count,
+            audio_transcription_ids: transcriptions.into_keys().collect(),
+            ui_monitoring_ids: ui_ids.into_iter().collect(),
+            journal_content,
+            ..Default::default()
+        };
+
+        let video_chunk_ids: Vec<i64> = video_chunk_ids.into_iter().collect();
+        for (id, file_path) in sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, file_path FROM video_chunks WHERE id IN (SELECT value FROM json_each(?1)) ORDER BY id",
+        )
+        .bind(json_ids(&video_chunk_ids))
+        .fetch_all(&self.pool)
+        .await?
+        {
+            let size_bytes = file_size(&file_path).await;
+            plan.video_chunks.push(MediaChunk {
+                id,
+                file_path,
+                size_bytes,
+            });
+        }
+
+        let audio_chunk_ids: Vec<i64> = audio_chunk_ids.into_iter().collect();
+        for (id, file_path) in sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, file_path FROM audio_chunks WHERE id IN (SELECT value FROM json_each(?1)) ORDER BY id",
+        )
+        .bind(json_ids(&audio_chunk_ids))
+        .fetch_all(&self.pool)
+        .await?
+        {
+            let size_bytes = file_size(&file_path).await;
+            plan.audio_chunks.push(MediaChunk {
+                id,
+                file_path,
+                size_bytes,
+            });
+        }
+
+        debug!(
+            "retention plan: {} frames, {} transcriptions, {} ui rows, {} video chunks, {} audio chunks",
+            plan.frame_ids.len(),
+            plan.audio_transcription_ids.len(),
+            plan.ui_monitoring_ids.len(),
+            plan.video_chunks.len(),
+            plan.audio_chunks.len()
+        );
+
+        Ok(plan)
+    }
+
+    /// Deletes frames with their OCR text, embeddings and tags. FTS rows go through triggers.
+    /// Their pixels stay in the chunk files, so their offsets are queued to be cut out once
+    /// the chunk is finished, see `pending_video_scrubs`. Chunks deleted as a whole drop
+    /// their queued offsets in `delete_video_chunks`.
+    pub async fn delete_frames(&self, ids: &[i64]) -> Result<RetentionReport, sqlx::Error> {
+        let ids = json_ids(ids);
+        let mut tx = self.pool.begin().await?;
+        // also keeps new frames of a chunk still recording from taking these offsets
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO video_chunk_pending_scrubs (video_chunk_id, offset_index)
+            SELECT video_chunk_id, offset_index FROM frames
+            WHERE id IN (SELECT value FROM json_each(?1))
+            "#,
+        )
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?;
+        let report = delete_frames_in_tx(&mut tx, &ids).await?;
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    pub async fn delete_audio_transcriptions(
+        &self,
+        ids: &[i64],
+    ) -> Result<RetentionReport, sqlx::Error> {
+        let audio_transcriptions = sqlx::query(
+            "DELETE FROM audio_transcriptions WHERE id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(json_ids(ids))
+        .execute(&self.pool)
+        .await?
+        .rows_affected() as i64;
+
+        Ok(RetentionReport {
+            audio_transcriptions,
+            ..Default::default()
+        })
+    }
+
+    pub async fn delete_ui_monitoring(&self, ids: &[i64]) -> Result<RetentionReport, sqlx::Error> {
+        let ids = json_ids(ids);
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "DELETE FROM ui_monitoring_tags WHERE ui_monitoring_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?;
+        let ui_monitoring =
+            sqlx::query("DELETE FROM ui_monitoring WHERE id IN (SELECT value FROM json_each(?1))")
+                .bind(&ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected() as i64;
+
+        tx.commit().await?;
+        Ok(RetentionReport {
+            ui_monitoring,
+            ..Default::default()
+        })
+    }
+
+    /// Deletes video chunks and any frames still pointing at them. Returns the chunk file
+    /// paths in `files`, removing them from disk is up to the caller.
+    pub async fn delete_video_chunks(&self, ids: &[i64]) -> Result<RetentionReport, sqlx::Error> {
+        let ids = json_ids(ids);
+        let mut tx = self.pool.begin().await?;
+
+        let frame_ids: Vec<i64> = sqlx::query_scalar(
+            "SELECT id FROM frames WHERE video_chunk_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ids)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut report = delete_frames_in_tx(&mut tx, &json_ids(&frame_ids)).await?;
+
+        sqlx::query(
+            "DELETE FROM video_chunk_pending_scrubs WHERE video_chunk_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?;
+        report.files = sqlx::query_scalar(
+            "SELECT file_path FROM video_chunks WHERE id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ids)
+        .fetch_all(&mut *tx)
+        .await?;
+        report.video_chunks =
+            sqlx::query("DELETE FROM video_chunks WHERE id IN (SELECT value FROM json_each(?1))")
+                .bind(&ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected() as i64;
+
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    /// Deletes audio chunks with their transcriptions and tags. Returns the chunk file
+    /// paths in `files`, removing them from disk is up to the caller.
+    pub async fn delete_audio_chunks(&self, ids: &[i64]) -> Result<RetentionReport, sqlx::Error> {
+        let ids = json_ids(ids);
+        let mut tx = self.pool.begin().await?;
+        let audio_transcriptions = sqlx::query(
+            "DELETE FROM audio_transcriptions WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as i64;
+        sqlx::query(
+            "DELETE FROM audio_tags WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM chunked_text_entries WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?;
+
+        let files = sqlx::query_scalar(
+            "SELECT file_path FROM audio_chunks WHERE id IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(&ids)
+        .fetch_all(&mut *tx)
+        .await?;
+        let audio_chunks =
+            sqlx::query("DELETE FROM audio_chunks WHERE id IN (SELECT value FROM json_each(?1))")
+                .bind(&ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected() as i64;
+
+        tx.commit().await?;
+        Ok(RetentionReport {
+            audio_transcriptions,
+            audio_chunks,
+            files,
+            ..Default::default()
+        })
+    }
+}
+
+pub(crate) async fn delete_frames_in_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    ids: &str,
+) -> Result<RetentionReport, sqlx::Error> {
+    let ocr_text =
+        sqlx::query("DELETE FROM ocr_text WHERE frame_id IN (SELECT value FROM json_each(?1))")
+            .bind(ids)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected() as i64;
+
+    // Array of (query, operation description) tuples
+    let operations = [
+        (
+            "DELETE FROM ocr_text_embeddings WHERE frame_id IN (SELECT value FROM json_each(?1))",
+            "ocr text embeddings",
+        ),
+        (
+            "DELETE FROM vision_tags WHERE vision_id IN (SELECT value FROM json_each(?1))",
+            "vision tags",
+        ),
+        (
+            "DELETE FROM chunked_text_entries WHERE frame_id IN (SELECT value FROM json_each(?1))",
+            "chunked text entries",
+        ),
+    ];
+    for (query, operation) in operations {
+        sqlx::query(query).bind(ids).execute(&mut **tx).await?;
+        debug!("deleted {} of purged frames", operation);
+    }
+
+    let frames = sqlx::query("DELETE FROM frames WHERE id IN (SELECT value FROM json_each(?1))")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected() as i64;
+
+    Ok(RetentionReport {
+        frames,
+        ocr_text,
+        ..Default::default()
+    })
+}
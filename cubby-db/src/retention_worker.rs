@@ -0,0 +1,399 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tokio::{sync::mpsc, task::JoinHandle, time};
+use tracing::{debug, error, info, warn};
+
+use crate::{DatabaseManager, RetentionPolicy, RetentionReport};
+
+/// Status of a retention run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum RetentionStatus {
+    /// Purge is currently running
+    Running {
+        total_items: i64,
+        processed_items: i64,
+    },
+    /// Purge completed successfully
+    Completed {
+        report: RetentionReport,
+        duration_secs: u64,
+    },
+    /// Purge is paused and can be resumed
+    Paused {
+        total_items: i64,
+        processed_items: i64,
+    },
+    /// Purge failed with an error
+    Failed { error: String },
+    /// No purge has run yet
+    NotStarted,
+}
+
+/// Commands that can be sent to control the retention worker
+#[derive(Debug, Clone)]
+pub enum RetentionCommand {
+    /// Start a purge, optionally with a one-off policy instead of the configured one,
+    /// or resume a paused purge
+    Start(Option<RetentionPolicy>),
+    /// Pause the purge (can be resumed later)
+    Pause,
+    /// Stop the worker
+    Stop,
+    /// Request current retention status
+    Status,
+}
+
+/// Response from the retention worker, including current status
+#[derive(Serialize)]
+pub struct RetentionResponse {
+    pub status: RetentionStatus,
+}
+
+/// Configuration for the retention worker
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    /// Policy applied by scheduled runs and by `Start(None)`
+    pub policy: RetentionPolicy,
+    /// Number of rows or chunks deleted in a single transaction
+    pub batch_size: usize,
+    /// Delay between batches to reduce database load
+    pub batch_delay_ms: u64,
+    /// Run the configured policy on this interval, `None` only runs on demand
+    pub interval_secs: Option<u64>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            policy: RetentionPolicy::default(),
+            batch_size: 500,
+            batch_delay_ms: 50,
+            interval_secs: Some(3600),
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub fn new(policy: RetentionPolicy, interval_secs: Option<u64>) -> Self {
+        Self {
+            policy,
+            interval_secs,
+            ..Default::default()
+        }
+    }
+}
+
+/// Worker that purges recorded content according to a retention policy
+pub struct RetentionWorker {
+    db: Arc<DatabaseManager>,
+    status: RetentionStatus,
+    is_running: Arc<AtomicBool>,
+    is_paused: Arc<AtomicBool>,
+    config: RetentionConfig,
+    cmd_rx: mpsc::Receiver<RetentionCommand>,
+    status_tx: mpsc::Sender<RetentionResponse>,
+    worker_handle: Option<JoinHandle<()>>,
+}
+
+impl RetentionWorker {
+    /// Create a new retention worker
+    pub fn new(
+        db: Arc<DatabaseManager>,
+        cmd_rx: mpsc::Receiver<RetentionCommand>,
+        status_tx: mpsc::Sender<RetentionResponse>,
+        config: RetentionConfig,
+    ) -> Self {
+        Self {
+            db,
+            status: RetentionStatus::NotStarted,
+            is_running: Arc::new(AtomicBool::new(false)),
+            is_paused: Arc::new(AtomicBool::new(false)),
+            config,
+            cmd_rx,
+            status_tx,
+            worker_handle: None,
+        }
+    }
+
+    /// Start the retention worker to process commands and scheduled runs
+    pub fn start(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("Retention worker started");
+            let mut schedule = self
+                .config
+                .interval_secs
+                .map(|secs| time::interval(Duration::from_secs(secs)));
+
+            loop {
+                let cmd = match schedule.as_mut() {
+                    Some(schedule) => tokio::select! {
+                        cmd = self.cmd_rx.recv() => cmd,
+                        _ = schedule.tick() => {
+                            if self.is_running.load(Ordering::SeqCst) {
+                                continue;
+                            }
+                            Some(RetentionCommand::Start(None))
+                        }
+                    },
+                    None => self.cmd_rx.recv().await,
+                };
+                let Some(cmd) = cmd else {
+                    break;
+                };
+
+                match cmd {
+                    RetentionCommand::Start(policy) => {
+                        self.start_purge(policy).await;
+                    }
+                    RetentionCommand::Pause => {
+                        self.pause_purge();
+                    }
+                    RetentionCommand::Stop => {
+                        self.stop_purge();
+                        break;
+                    }
+                    RetentionCommand::Status => {
+                        let _ = self
+                            .status_tx
+                            .send(RetentionResponse {
+                                status: self.status.clone(),
+                            })
+                            .await;
+                    }
+                }
+            }
+            info!("Retention worker stopped");
+        })
+    }
+
+    async fn start_purge(&mut self, policy: Option<RetentionPolicy>) {
+        if self.is_running.load(Ordering::SeqCst) {
+            if self.is_paused.load(Ordering::SeqCst) {
+                info!("Resuming retention purge");
+                self.is_paused.store(false, Ordering::SeqCst);
+                return;
+            }
+            warn!("Retention purge is already running");
+            return;
+        }
+
+        let policy = policy.unwrap_or_else(|| self.config.policy.clone());
+        if policy.is_empty() {
+            debug!("No retention policy configured, skipping purge");
+            return;
+        }
+
+        self.is_running.store(true, Ordering::SeqCst);
+
+        // Create clones of shared resources for the worker task
+        let db = self.db.clone();
+        let config = self.config.clone();
+        let is_running = self.is_running.clone();
+        let is_paused = self.is_paused.clone();
+        let status_tx = self.status_tx.clone();
+
+        let handle = tokio::spawn(async move {
+            let started = std::time::Instant::now();
+            let result = purge(
+                &db,
+                &policy,
+                &config,
+                is_running.clone(),
+                is_paused.clone(),
+                status_tx.clone(),
+            )
+            .await;
+
+            let status = match result {
+                Ok(report) => {
+                    info!(
+                        "Retention purge completed: {} frames, {} transcriptions, {} ui rows, {} files ({} bytes)",
+                        report.frames,
+                        report.audio_transcriptions,
+                        report.ui_monitoring,
+                        report.files.len(),
+                        report.bytes
+                    );
+                    RetentionStatus::Completed {
+                        report,
+                        duration_secs: started.elapsed().as_secs(),
+                    }
+                }
+                Err(e) => {
+                    error!("Retention purge failed: {}", e);
+                    RetentionStatus::Failed {
+                        error: e.to_string(),
+                    }
+                }
+            };
+            let _ = status_tx.send(RetentionResponse { status }).await;
+
+            is_running.store(false, Ordering::SeqCst);
+            is_paused.store(false, Ordering::SeqCst);
+        });
+
+        self.worker_handle = Some(handle);
+    }
+
+    fn pause_purge(&self) {
+        if self.is_running.load(Ordering::SeqCst) {
+            info!("Pausing retention purge");
+            self.is_paused.store(true, Ordering::SeqCst);
+        } else {
+            warn!("Cannot pause retention purge: not running");
+        }
+    }
+
+    fn stop_purge(&self) {
+        if self.is_running.load(Ordering::SeqCst) {
+            info!("Stopping retention purge");
+            self.is_running.store(false, Ordering::SeqCst);
+
+            if let Some(handle) = &self.worker_handle {
+                handle.abort();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PurgeTarget {
+    Frames,
+    AudioTranscriptions,
+    UiMonitoring,
+    VideoChunks,
+    AudioChunks,
+}
+
+/// Plan and delete everything selected by `policy`, in batches
+async fn purge(
+    db: &DatabaseManager,
+    policy: &RetentionPolicy,
+    config: &RetentionConfig,
+    is_running: Arc<AtomicBool>,
+    is_paused: Arc<AtomicBool>,
+    status_tx: mpsc::Sender<RetentionResponse>,
+) -> Result<RetentionReport> {
+    let plan = db.plan_retention(policy, Utc::now()).await?;
+    let total_items = plan.total_items() as i64;
+    let mut processed_items = 0;
+    let mut report = RetentionReport::default();
+
+    info!("Starting retention purge: total_items={}", total_items);
+
+    let _ = status_tx
+        .send(RetentionResponse {
+            status: RetentionStatus::Running {
+                total_items,
+                processed_items,
+            },
+        })
+        .await;
+
+    // Rows first, chunks last: chunks are referenced by frames and transcriptions
+    let video_chunk_ids: Vec<i64> = plan.video_chunks.iter().map(|c| c.id).collect();
+    let audio_chunk_ids: Vec<i64> = plan.audio_chunks.iter().map(|c| c.id).collect();
+    let steps = [
+        (PurgeTarget::Frames, &plan.frame_ids),
+        (
+            PurgeTarget::AudioTranscriptions,
+            &plan.audio_transcription_ids,
+        ),
+        (PurgeTarget::UiMonitoring, &plan.ui_monitoring_ids),
+        (PurgeTarget::VideoChunks, &video_chunk_ids),
+        (PurgeTarget::AudioChunks, &audio_chunk_ids),
+    ];
+
+    for (target, ids) in steps {
+        for batch in ids.chunks(config.batch_size.max(1)) {
+            while is_paused.load(Ordering::SeqCst) && is_running.load(Ordering::SeqCst) {
+                let _ = status_tx
+                    .send(RetentionResponse {
+                        status: RetentionStatus::Paused {
+                            total_items,
+                            processed_items,
+                        },
+                    })
+                    .await;
+                time::sleep(Duration::from_millis(500)).await;
+            }
+
+            if !is_running.load(Ordering::SeqCst) {
+                return Ok(report);
+            }
+
+            let mut deleted = match target {
+                PurgeTarget::Frames => db.delete_frames(batch).await?,
+                PurgeTarget::AudioTranscriptions => db.delete_audio_transcriptions(batch).await?,
+                PurgeTarget::UiMonitoring => db.delete_ui_monitoring(batch).await?,
+                PurgeTarget::VideoChunks => db.delete_video_chunks(batch).await?,
+                PurgeTarget::AudioChunks => db.delete_audio_chunks(batch).await?,
+            };
+            deleted.bytes = remove_media_files(&deleted.files).await;
+            report.merge(deleted);
+
+            processed_items += batch.len() as i64;
+            let _ = status_tx
+                .send(RetentionResponse {
+                    status: RetentionStatus::Running {
+                        total_items,
+                        processed_items,
+                    },
+                })
+                .await;
+
+            time::sleep(Duration::from_millis(config.batch_delay_ms)).await;
+        }
+    }
+
+    // `/ws/events?since=` would otherwise replay the purged text
+    for content in &plan.journal_content {
+        let deleted = db.delete_journaled_content(content).await?;
+        debug!("deleted {} journaled events of purged content", deleted);
+    }
+
+    Ok(report)
+}
+
+/// Remove media files from disk, returning the number of bytes freed. Files that are
+/// already gone are skipped.
+pub async fn remove_media_files(files: &[String]) -> u64 {
+    let mut freed = 0;
+    for file in files {
+        let size = tokio::fs::metadata(file)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+        match tokio::fs::remove_file(file).await {
+            Ok(_) => freed += size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to remove {}: {}", file, e),
+        }
+    }
+    freed
+}
+
+/// Create a retention worker and return channels to control it
+pub fn create_retention_worker(
+    db: Arc<DatabaseManager>,
+    config: Option<RetentionConfig>,
+) -> (
+    mpsc::Sender<RetentionCommand>,
+    mpsc::Receiver<RetentionResponse>,
+    JoinHandle<()>,
+) {
+    let (cmd_tx, cmd_rx) = mpsc::channel(100);
+    let (status_tx, status_rx) = mpsc::channel(100);
+
+    let worker = RetentionWorker::new(db, cmd_rx, status_tx, config.unwrap_or_default());
+
+    let handle = worker.start();
+
+    (cmd_tx, status_rx, handle)
+}
@@ -0,0 +1,432 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::{frame_fts_query, searched_content};
+use crate::{ContentType, DatabaseManager};
+
+/// Shape of a search aggregate, every field can be left out
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AggregateOptions {
+    /// Width of the histogram buckets, buckets start at multiples of it since the unix epoch
+    pub bucket_seconds: i64,
+    /// Values kept per facet, those with the most time then the most results
+    pub top: usize,
+    /// A frame counts as screen time until the next frame of its monitor, at most this long
+    /// so idle or locked time between captures isn't counted
+    pub max_frame_gap_seconds: f64,
+}
+
+impl Default for AggregateOptions {
+    fn default() -> Self {
+        Self {
+            bucket_seconds: 3600,
+            top: 10,
+            max_frame_gap_seconds: 60.0,
+        }
+    }
+}
+
+/// A facet value with its results and their estimated time
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Facet {
+    pub value: String,
+    /// Matching frames, transcriptions and UI texts
+    pub count: i64,
+    /// Screen time of the frames and speech time of the transcriptions
+    pub seconds: f64,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeakerFacet {
+    /// `None` for speech not attributed to a speaker
+    pub speaker_id: Option<i64>,
+    pub name: Option<String>,
+    pub count: i64,
+    pub seconds: f64,
+}
+
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub start: DateTime<Utc>,
+    pub ocr_count: i64,
+    pub audio_count: i64,
+    pub ui_count: i64,
+    pub screen_seconds: f64,
+    pub speech_seconds: f64,
+}
+
+/// Counts and estimated time of everything a search matches, by facet and over time
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchAggregate {
+    pub ocr_count: i64,
+    pub audio_count: i64,
+    pub ui_count: i64,
+    /// Estimated from the intervals between the matching frames and the next frames
+    pub screen_seconds: f64,
+    /// Sum of the transcriptions' end minus start times
+    pub speech_seconds: f64,
+    pub apps: Vec<Facet>,
+    pub windows: Vec<Facet>,
+    /// Hosts of the browser urls, without `www.`
+    pub domains: Vec<Facet>,
+    /// Monitors of the frames and audio devices of the transcriptions
+    pub devices: Vec<Facet>,
+    pub speakers: Vec<SpeakerFacet>,
+    /// Buckets with at least one result, oldest first
+    pub histogram: Vec<HistogramBucket>,
+}
+
+/// Host of a url, lowercased and without `www.`, `None` when there is none
+pub(crate) fn domain_of(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?.trim().to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+/// (bucket, app, window, browser url, monitor, frames, screen seconds)
+type FrameRow = (i64, String, String, String, String, i64, f64);
+
+/// (bucket, speaker id, speaker name, device, transcriptions, speech seconds)
+type TranscriptionRow = (i64, Option<i64>, Option<String>, String, i64, f64);
+
+#[derive(Default)]
+struct Tally {
+    count: i64,
+    seconds: f64,
+}
+
+#[derive(Default)]
+struct Facets(HashMap<String, Tally>);
+
+impl Facets {
+    fn add(&mut self, value: &str, count: i64, seconds: f64) {
+        if value.is_empty() {
+            return;
+        }
+        let tally = self.0.entry(value.to_string()).or_default();
+        tally.count += count;
+        tally.seconds += seconds;
+    }
+
+    fn top(self, top: usize) -> Vec<Facet> {
+        let mut facets: Vec<Facet> = self
+            .0
+            .into_iter()
+            .map(|(value, tally)| Facet {
+                value,
+                count: tally.count,
+                seconds: tally.seconds,
+            })
+            .collect();
+        facets.sort_by(|a, b| {
+            b.seconds
+                .total_cmp(&a.seconds)
+                .then(b.count.cmp(&a.count))
+                .then_with(|| a.value.cmp(&b.value))
+        });
+        facets.truncate(top);
+        facets
+    }
+}
+
+struct Histogram {
+    bucket_seconds: i64,
+    buckets: BTreeMap<i64, HistogramBucket>,
+}
+
+impl Histogram {
+    /// The bucket of index `index` since the unix epoch
+    fn bucket(&mut self, index: i64) -> &mut HistogramBucket {
+        let start = index * self.bucket_seconds;
+        self.buckets
+            .entry(index)
+            .or_insert_with(|| HistogramBucket {
+                start: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+                ..Default::default()
+            })
+    }
+}
+
+impl DatabaseManager {
+    /// Aggregates everything `search` with the same filters matches: counts and estimated
+    /// screen and speech time per app, window, browser domain, device and speaker, and a
+    /// histogram over time.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn aggregate_search(
+        &self,
+        query: &str,
+        content_type: ContentType,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        frame_name: Option<&str>,
+        browser_url: Option<&str>,
+        focused: Option<bool>,
+        options: &AggregateOptions,
+    ) -> Result<SearchAggregate, sqlx::Error> {
+        let bucket_seconds = options.bucket_seconds.max(1);
+        let (ocr, audio, ui) = searched_content(
+            &content_type,
+            app_name,
+            window_name,
+            frame_name,
+            browser_url,
+            focused,
+        );
+
+        let mut aggregate = SearchAggregate::default();
+        let mut histogram = Histogram {
+            bucket_seconds,
+            buckets: BTreeMap::new(),
+        };
+        let mut apps = Facets::default();
+        let mut windows = Facets::default();
+        let mut domains = Facets::default();
+        let mut devices = Facets::default();
+        let mut speakers: HashMap<Option<i64>, SpeakerFacet> = HashMap::new();
+
+        if ocr {
+            let frame_query =
+                frame_fts_query(app_name, window_name, browser_url, focused, frame_name);
+            let sql = format!(
+                r#"
+                WITH intervals AS (
+                    -- julianday is precise to the millisecond
+                    SELECT
+                        frames.id,
+                        MIN(
+                            COALESCE(
+                                ROUND((julianday(LEAD(frames.timestamp) OVER (
+                                    PARTITION BY video_chunks.device_name
+                                    ORDER BY frames.timestamp
+                                )) - julianday(frames.timestamp)) * 86400, 3),
+                                0
+                            ),
+                            ?8
+                        ) AS seconds
+                    FROM frames
+                    JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+                    WHERE (?2 IS NULL OR frames.timestamp >= ?2)
+                        AND (?3 IS NULL OR frames.timestamp <= ?3)
+                )
+                SELECT
+                    CAST(strftime('%s', frames.timestamp) AS INTEGER) / ?7 AS bucket,
+                    COALESCE(frames.app_name, '') AS frame_app,
+                    COALESCE(frames.window_name, '') AS frame_window,
+                    COALESCE(frames.browser_url, '') AS frame_url,
+                    video_chunks.device_name,
+                    COUNT(*),
+                    TOTAL(intervals.seconds)
+                FROM frames
+                JOIN intervals ON intervals.id = frames.id
+                JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+                {frame_fts_join}
+                WHERE frames.id IN (
+                    SELECT ocr_text.frame_id
+                    FROM ocr_text
+                    {ocr_fts_join}
+                    WHERE (?4 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) >= ?4)
+                        AND (?5 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) <= ?5)
+                        {ocr_fts_condition}
+                )
+                    {frame_fts_condition}
+                GROUP BY bucket, frame_app, frame_window, frame_url, video_chunks.device_name
+                "#,
+                frame_fts_join = if frame_query.is_empty() {
+                    ""
+                } else {
+                    "JOIN frames_fts ON frames.id = frames_fts.id"
+                },
+                frame_fts_condition = if frame_query.is_empty() {
+                    ""
+                } else {
+                    "AND frames_fts MATCH ?1"
+                },
+                ocr_fts_join = if query.trim().is_empty() {
+                    ""
+                } else {
+                    "JOIN ocr_text_fts ON ocr_text.frame_id = ocr_text_fts.frame_id"
+                },
+                ocr_fts_condition = if query.trim().is_empty() {
+                    ""
+                } else {
+                    "AND ocr_text_fts MATCH ?6"
+                },
+            );
+            let rows: Vec<FrameRow> = sqlx::query_as(&sql)
+                .bind((!frame_query.is_empty()).then_some(&frame_query))
+                .bind(start_time)
+                .bind(end_time)
+                .bind(min_length.map(|l| l as i64))
+                .bind(max_length.map(|l| l as i64))
+                .bind((!query.trim().is_empty()).then_some(query))
+                .bind(bucket_seconds)
+                .bind(options.max_frame_gap_seconds)
+                .fetch_all(&self.pool)
+                .await?;
+
+            for (index, app, window, url, device, count, seconds) in rows {
+                aggregate.ocr_count += count;
+                aggregate.screen_seconds += seconds;
+                let bucket = histogram.bucket(index);
+                bucket.ocr_count += count;
+                bucket.screen_seconds += seconds;
+                apps.add(&app, count, seconds);
+                windows.add(&window, count, seconds);
+                if let Some(domain) = domain_of(&url) {
+                    domains.add(&domain, count, seconds);
+                }
+                devices.add(&device, count, seconds);
+            }
+        }
+
+        if audio {
+            // fts rows only know their chunk, a transcription is told apart by its text and
+            // start time like the delete trigger does
+            let sql = format!(
+                r#"
+                SELECT
+                    CAST(strftime('%s', audio_transcriptions.timestamp) AS INTEGER) / ?6 AS bucket,
+                    audio_transcriptions.speaker_id,
+                    speakers.name,
+                    audio_transcriptions.device,
+                    COUNT(*),
+                    TOTAL(MAX(COALESCE(audio_transcriptions.end_time - audio_transcriptions.start_time, 0), 0))
+                FROM audio_transcriptions
+                {fts_join}
+                LEFT JOIN speakers ON speakers.id = audio_transcriptions.speaker_id
+                WHERE (?2 IS NULL OR audio_transcriptions.timestamp >= ?2)
+                    AND (?3 IS NULL OR audio_transcriptions.timestamp <= ?3)
+                    AND (?4 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) >= ?4)
+                    AND (?5 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) <= ?5)
+                    AND COALESCE(speakers.hallucination, 0) = 0
+                    AND (json_array_length(?7) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?7)))
+                    {fts_condition}
+                GROUP BY bucket, audio_transcriptions.speaker_id, audio_transcriptions.device
+                "#,
+                fts_join = if query.trim().is_empty() {
+                    ""
+                } else {
+                    "JOIN audio_transcriptions_fts ON
+                        audio_transcriptions.audio_chunk_id = audio_transcriptions_fts.audio_chunk_id
+                        AND audio_transcriptions.transcription = audio_transcriptions_fts.transcription
+                        AND audio_transcriptions.start_time IS audio_transcriptions_fts.start_time"
+                },
+                fts_condition = if query.trim().is_empty() {
+                    ""
+                } else {
+                    "AND audio_transcriptions_fts MATCH ?1"
+                },
+            );
+            let speaker_ids_json =
+                serde_json::to_string(&speaker_ids.unwrap_or_default()).unwrap_or_default();
+            let rows: Vec<TranscriptionRow> = sqlx::query_as(&sql)
+                .bind(query)
+                .bind(start_time)
+                .bind(end_time)
+                .bind(min_length.map(|l| l as i64))
+                .bind(max_length.map(|l| l as i64))
+                .bind(bucket_seconds)
+                .bind(speaker_ids_json)
+                .fetch_all(&self.pool)
+                .await?;
+
+            for (index, speaker_id, name, device, count, seconds) in rows {
+                aggregate.audio_count += count;
+                aggregate.speech_seconds += seconds;
+                let bucket = histogram.bucket(index);
+                bucket.audio_count += count;
+                bucket.speech_seconds += seconds;
+                devices.add(&device, count, seconds);
+                let speaker = speakers.entry(speaker_id).or_insert_with(|| SpeakerFacet {
+                    speaker_id,
+                    name: name.filter(|name| !name.is_empty()),
+                    count: 0,
+                    seconds: 0.0,
+                });
+                speaker.count += count;
+                speaker.seconds += seconds;
+            }
+        }
+
+        if ui {
+            // same fts query as search_ui_monitoring
+            let mut fts_parts = Vec::new();
+            if !query.is_empty() {
+                fts_parts.push(query.to_owned());
+            }
+            if let Some(app) = app_name {
+                fts_parts.push(format!("app:{}", app));
+            }
+            if let Some(window) = window_name {
+                fts_parts.push(format!("window:{}", window));
+            }
+            let combined_query = fts_parts.join(" ");
+
+            let sql = format!(
+                r#"
+                SELECT
+                    CAST(strftime('%s', ui_monitoring.timestamp) AS INTEGER) / ?4 AS bucket,
+                    COALESCE(ui_monitoring.app, '') AS ui_app,
+                    COALESCE(ui_monitoring.window, '') AS ui_window,
+                    COUNT(*)
+                FROM {}
+                WHERE (?2 IS NULL OR ui_monitoring.timestamp >= ?2)
+                    AND (?3 IS NULL OR ui_monitoring.timestamp <= ?3)
+                    {}
+                GROUP BY bucket, ui_app, ui_window
+                "#,
+                if combined_query.is_empty() {
+                    "ui_monitoring"
+                } else {
+                    "ui_monitoring_fts JOIN ui_monitoring ON ui_monitoring_fts.ui_id = ui_monitoring.id"
+                },
+                if combined_query.is_empty() {
+                    ""
+                } else {
+                    "AND ui_monitoring_fts MATCH ?1"
+                },
+            );
+            let rows: Vec<(i64, String, String, i64)> = sqlx::query_as(&sql)
+                .bind(&combined_query)
+                .bind(start_time)
+                .bind(end_time)
+                .bind(bucket_seconds)
+                .fetch_all(&self.pool)
+                .await?;
+
+            for (index, app, window, count) in rows {
+                aggregate.ui_count += count;
+                histogram.bucket(index).ui_count += count;
+                apps.add(&app, count, 0.0);
+                windows.add(&window, count, 0.0);
+            }
+        }
+
+        aggregate.apps = apps.top(options.top);
+        aggregate.windows = windows.top(options.top);
+        aggregate.domains = domains.top(options.top);
+        aggregate.devices = devices.top(options.top);
+        let mut speakers: Vec<SpeakerFacet> = speakers.into_values().collect();
+        speakers.sort_by(|a, b| {
+            b.seconds
+                .total_cmp(&a.seconds)
+                .then(b.count.cmp(&a.count))
+                .then(a.speaker_id.cmp(&b.speaker_id))
+        });
+        speakers.truncate(options.top);
+        aggregate.speakers = speakers;
+        aggregate.histogram = histogram.buckets.into_values().collect();
+        Ok(aggregate)
+    }
+}
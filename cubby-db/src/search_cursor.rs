@@ -0,0 +1,240 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::{ContentType, DatabaseManager, Order, SearchResult};
+
+pub(crate) const OCR_KIND: u8 = 0;
+pub(crate) const AUDIO_KIND: u8 = 1;
+pub(crate) const UI_KIND: u8 = 2;
+
+/// Position of a search result in the merged stream of OCR, audio and UI results, ordered
+/// by (timestamp, content type, id). Sent to clients as an opaque string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchCursor {
+    timestamp: DateTime<Utc>,
+    kind: u8,
+    id: i64,
+}
+
+impl SearchCursor {
+    pub fn of(result: &SearchResult) -> Self {
+        match result {
+            SearchResult::OCR(ocr) => Self {
+                timestamp: ocr.timestamp,
+                kind: OCR_KIND,
+                id: ocr.frame_id,
+            },
+            SearchResult::Audio(audio) => Self {
+                timestamp: audio.timestamp,
+                kind: AUDIO_KIND,
+                id: audio.transcription_id,
+            },
+            SearchResult::UI(ui) => Self {
+                timestamp: ui.timestamp,
+                kind: UI_KIND,
+                id: ui.id,
+            },
+        }
+    }
+
+    fn cmp_key(&self, other: &Self) -> Ordering {
+        (self.timestamp, self.kind, self.id).cmp(&(other.timestamp, other.kind, other.id))
+    }
+}
+
+impl Display for SearchCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let raw = format!("{}|{}|{}", self.timestamp.to_rfc3339(), self.kind, self.id);
+        write!(f, "{}", general_purpose::URL_SAFE_NO_PAD.encode(raw))
+    }
+}
+
+impl FromStr for SearchCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid search cursor: {}", s);
+        let raw = general_purpose::URL_SAFE_NO_PAD
+            .decode(s)
+            .ok()
+            .and_then(|raw| String::from_utf8(raw).ok())
+            .ok_or_else(invalid)?;
+        let mut parts = raw.split('|');
+        let (Some(timestamp), Some(kind), Some(id), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let kind = kind.parse().map_err(|_| invalid())?;
+        if kind > UI_KIND {
+            return Err(invalid());
+        }
+        Ok(Self {
+            timestamp: DateTime::parse_from_rfc3339(timestamp)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            kind,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// A page of search results and the cursor of the next page, `None` on the last page
+#[derive(Debug)]
+pub struct SearchPage {
+    pub results: Vec<SearchResult>,
+    pub next_cursor: Option<SearchCursor>,
+}
+
+/// Which rows the OCR, audio and UI queries of a search return: ordered by timestamp then
+/// id in `order`, and only those past `after` when given
+pub(crate) struct SearchWindow<'a> {
+    pub(crate) order: &'a Order,
+    pub(crate) after: Option<&'a SearchCursor>,
+}
+
+impl SearchWindow<'_> {
+    pub(crate) const NEWEST_FIRST: SearchWindow<'static> = SearchWindow {
+        order: &Order::Descending,
+        after: None,
+    };
+
+    pub(crate) fn direction(&self) -> &'static str {
+        match self.order {
+            Order::Ascending => "ASC",
+            Order::Descending => "DESC",
+        }
+    }
+
+    fn operator(&self) -> &'static str {
+        match self.order {
+            Order::Ascending => ">",
+            Order::Descending => "<",
+        }
+    }
+
+    /// Condition keeping rows past the cursor, taking its timestamp, kind and id from
+    /// parameters `?n`, `?n+1` and `?n+2`. The parameters are null without a cursor.
+    pub(crate) fn numbered_condition(
+        &self,
+        kind: u8,
+        timestamp_column: &str,
+        id_column: &str,
+        n: usize,
+    ) -> String {
+        let op = self.operator();
+        format!(
+            "AND (?{n} IS NULL OR {ts} {op} ?{n} OR ({ts} = ?{n} AND ({kind} {op} ?{k} OR ({kind} = ?{k} AND {id} {op} ?{i}))))",
+            ts = timestamp_column,
+            id = id_column,
+            k = n + 1,
+            i = n + 2,
+        )
+    }
+
+    /// Same as `numbered_condition` with anonymous parameters, bound as the timestamp
+    /// twice, the kind twice then the id. `None` without a cursor.
+    pub(crate) fn anonymous_condition(
+        &self,
+        kind: u8,
+        timestamp_column: &str,
+        id_column: &str,
+    ) -> Option<String> {
+        self.after?;
+        let op = self.operator();
+        Some(format!(
+            "({ts} {op} ? OR ({ts} = ? AND ({kind} {op} ? OR ({kind} = ? AND {id} {op} ?))))",
+            ts = timestamp_column,
+            id = id_column,
+        ))
+    }
+
+    pub(crate) fn after_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.after.map(|cursor| cursor.timestamp)
+    }
+
+    pub(crate) fn after_kind(&self) -> Option<i64> {
+        self.after.map(|cursor| cursor.kind as i64)
+    }
+
+    pub(crate) fn after_id(&self) -> Option<i64> {
+        self.after.map(|cursor| cursor.id)
+    }
+
+    /// Sorts results of the different queries into one stream
+    pub(crate) fn merge(&self, results: &mut [SearchResult]) {
+        results.sort_by(|a, b| {
+            let ordering = SearchCursor::of(a).cmp_key(&SearchCursor::of(b));
+            match self.order {
+                Order::Ascending => ordering,
+                Order::Descending => ordering.reverse(),
+            }
+        });
+    }
+}
+
+impl DatabaseManager {
+    /// Like `search`, in either order and one page at a time: pages follow each other
+    /// through `cursor`, the `next_cursor` of the previous page, so results are neither
+    /// repeated nor skipped while new content is recorded. `offset` skips results past the
+    /// cursor. OCR, audio and UI results are merged by timestamp.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_page(
+        &self,
+        query: &str,
+        content_type: ContentType,
+        limit: u32,
+        offset: u32,
+        cursor: Option<&SearchCursor>,
+        order: &Order,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        frame_name: Option<&str>,
+        browser_url: Option<&str>,
+        focused: Option<bool>,
+    ) -> Result<SearchPage, sqlx::Error> {
+        let window = SearchWindow {
+            order,
+            after: cursor,
+        };
+        // one row more than the page tells whether there is a next page
+        let results = self
+            .search_merged(
+                query,
+                content_type,
+                offset.saturating_add(limit).saturating_add(1),
+                &window,
+                start_time,
+                end_time,
+                app_name,
+                window_name,
+                min_length,
+                max_length,
+                speaker_ids,
+                frame_name,
+                browser_url,
+                focused,
+            )
+            .await?;
+
+        let mut results: Vec<SearchResult> = results.into_iter().skip(offset as usize).collect();
+        let next_cursor = if results.len() > limit as usize {
+            results.truncate(limit as usize);
+            results.last().map(SearchCursor::of)
+        } else {
+            None
+        };
+        Ok(SearchPage {
+            results,
+            next_cursor,
+        })
+    }
+}
@@ -0,0 +1,340 @@
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{debug, info};
+use zerocopy::AsBytes;
+
+use crate::DatabaseManager;
+
+/// Embeddings kept per speaker. The first one comes from the segment that created the
+/// speaker, the others from segments matched to it later on.
+pub const MAX_SPEAKER_EMBEDDINGS: i64 = 20;
+
+fn default_recluster_threshold() -> f32 {
+    0.35
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReclusterOptions {
+    /// Speakers are grouped while the average cosine distance between the groups stays
+    /// below this, from 0 (identical voices) to 2
+    #[serde(default = "default_recluster_threshold")]
+    pub threshold: f32,
+    /// Only report the merges that would be made
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl Default for ReclusterOptions {
+    fn default() -> Self {
+        Self {
+            threshold: default_recluster_threshold(),
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeakerMerge {
+    /// The named speaker of the group, or the one with the most transcriptions
+    pub speaker_to_keep: i64,
+    pub name: Option<String>,
+    pub speakers_to_merge: Vec<i64>,
+    /// Transcriptions moved to the kept speaker
+    pub transcriptions: i64,
+    /// Largest cosine distance between the kept speaker and a merged one
+    pub max_distance: f32,
+}
+
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReclusterReport {
+    /// Speakers with embeddings that were clustered, hallucinations excluded
+    pub speakers: usize,
+    pub merges: Vec<SpeakerMerge>,
+    /// Speakers merged into another one, or that would be on a dry run
+    pub merged_speakers: usize,
+    pub dry_run: bool,
+}
+
+struct ClusteredSpeaker {
+    id: i64,
+    name: Option<String>,
+    transcriptions: i64,
+    /// Normalized mean of the speaker embeddings
+    centroid: Vec<f32>,
+}
+
+impl DatabaseManager {
+    /// Stores another embedding of a speaker, unless it already has
+    /// `MAX_SPEAKER_EMBEDDINGS`. Returns whether the embedding was stored.
+    pub async fn add_speaker_embedding(
+        &self,
+        speaker_id: i64,
+        embedding: &[f32],
+    ) -> Result<bool, sqlx::Error> {
+        let bytes: &[u8] = embedding.as_bytes();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO speaker_embeddings (embedding, speaker_id)
+            SELECT vec_f32(?1), ?2
+            WHERE (SELECT COUNT(*) FROM speaker_embeddings WHERE speaker_id = ?2) < ?3
+            "#,
+        )
+        .bind(bytes)
+        .bind(speaker_id)
+        .bind(MAX_SPEAKER_EMBEDDINGS)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Groups speakers whose voices are close, with average linkage agglomerative
+    /// clustering over the mean embedding of each speaker, and merges each group into one
+    /// speaker. Speakers with different names are never merged together, unnamed speakers
+    /// of a group go to the closest named one.
+    pub async fn recluster_speakers(
+        &self,
+        options: &ReclusterOptions,
+    ) -> Result<ReclusterReport, sqlx::Error> {
+        let speakers = self.clustered_speakers().await?;
+        let threshold = options.threshold;
+
+        let (speakers, merges) = tokio::task::spawn_blocking(move || {
+            let centroids: Vec<&[f32]> = speakers.iter().map(|s| s.centroid.as_slice()).collect();
+            let clusters = average_linkage_clusters(&centroids, threshold);
+            let mut merges: Vec<SpeakerMerge> = clusters
+                .iter()
+                .filter(|cluster| cluster.len() > 1)
+                .flat_map(|cluster| cluster_merges(&speakers, cluster))
+                .collect();
+            merges.sort_by_key(|merge| merge.speaker_to_keep);
+            (speakers.len(), merges)
+        })
+        .await
+        .map_err(|e| sqlx::Error::Protocol(format!("speaker clustering failed: {}", e)))?;
+
+        let merged_speakers = merges.iter().map(|m| m.speakers_to_merge.len()).sum();
+        info!(
+            "speaker re-clustering: {} speakers, {} merged into {} groups{}",
+            speakers,
+            merged_speakers,
+            merges.len(),
+            if options.dry_run { " (dry run)" } else { "" }
+        );
+
+        if !options.dry_run {
+            for merge in &merges {
+                for &id in &merge.speakers_to_merge {
+                    debug!("merging speaker {} into {}", id, merge.speaker_to_keep);
+                    self.merge_speakers(merge.speaker_to_keep, id).await?;
+                }
+            }
+        }
+
+        Ok(ReclusterReport {
+            speakers,
+            merges,
+            merged_speakers,
+            dry_run: options.dry_run,
+        })
+    }
+
+    async fn clustered_speakers(&self) -> Result<Vec<ClusteredSpeaker>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (i64, Option<String>, i64, Vec<u8>)>(
+            r#"
+            SELECT s.id, NULLIF(TRIM(s.name), ''),
+                (SELECT COUNT(*) FROM audio_transcriptions at WHERE at.speaker_id = s.id),
+                se.embedding
+            FROM speaker_embeddings se
+            JOIN speakers s ON s.id = se.speaker_id
+            WHERE COALESCE(s.hallucination, FALSE) = FALSE
+            ORDER BY s.id, se.id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut speakers: Vec<ClusteredSpeaker> = Vec::new();
+        let mut sums: HashMap<i64, Vec<f32>> = HashMap::new();
+        for (id, name, transcriptions, embedding) in rows {
+            let embedding = normalized(
+                embedding
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            );
+            match sums.get_mut(&id) {
+                Some(sum) => sum.iter_mut().zip(&embedding).for_each(|(s, e)| *s += e),
+                None => {
+                    sums.insert(id, embedding);
+                    speakers.push(ClusteredSpeaker {
+                        id,
+                        name,
+                        transcriptions,
+                        centroid: Vec::new(),
+                    });
+                }
+            }
+        }
+        for speaker in &mut speakers {
+            speaker.centroid = normalized(sums.remove(&speaker.id).unwrap_or_default());
+        }
+
+        Ok(speakers)
+    }
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// Cosine distance between normalized vectors
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>()
+}
+
+/// Average linkage clustering of normalized vectors, stopping at `threshold`. Uses the
+/// nearest neighbor chain algorithm: O(n²) time and memory.
+fn average_linkage_clusters(vectors: &[&[f32]], threshold: f32) -> Vec<Vec<usize>> {
+    let n = vectors.len();
+    let mut distances = vec![0.0f32; n * n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let distance = cosine_distance(vectors[i], vectors[j]);
+            distances[i * n + j] = distance;
+            distances[j * n + i] = distance;
+        }
+    }
+
+    let mut clusters: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    // clusters that may still be merged
+    let mut open = vec![true; n];
+    let mut chain: Vec<usize> = Vec::new();
+
+    loop {
+        if chain.is_empty() {
+            match (0..n).find(|&i| open[i]) {
+                Some(i) => chain.push(i),
+                None => break,
+            }
+        }
+        let a = *chain.last().unwrap();
+        let previous = chain.len().checked_sub(2).map(|i| chain[i]);
+
+        let mut nearest: Option<(usize, f32)> = None;
+        for b in (0..n).filter(|&b| b != a && open[b]) {
+            let distance = distances[a * n + b];
+            let closer = match nearest {
+                None => true,
+                Some((_, best)) => distance < best || (distance == best && Some(b) == previous),
+            };
+            if closer {
+                nearest = Some((b, distance));
+            }
+        }
+
+        let Some((b, distance)) = nearest else {
+            // last open cluster
+            open[a] = false;
+            chain.pop();
+            continue;
+        };
+
+        if Some(b) != previous {
+            chain.push(b);
+            continue;
+        }
+
+        // a and b are each other's nearest neighbors
+        chain.truncate(chain.len() - 2);
+        if distance > threshold {
+            // any later merge with either of them would be further apart
+            open[a] = false;
+            open[b] = false;
+            continue;
+        }
+
+        let (size_a, size_b) = (clusters[a].len() as f32, clusters[b].len() as f32);
+        for k in (0..n).filter(|&k| k != a && k != b && open[k]) {
+            let merged =
+                (size_a * distances[a * n + k] + size_b * distances[b * n + k]) / (size_a + size_b);
+            distances[a * n + k] = merged;
+            distances[k * n + a] = merged;
+        }
+        let members = std::mem::take(&mut clusters[b]);
+        clusters[a].extend(members);
+        open[b] = false;
+    }
+
+    clusters.retain(|cluster| !cluster.is_empty());
+    clusters
+}
+
+/// The merges of a cluster, one per name in it or a single one when nobody is named
+fn cluster_merges(speakers: &[ClusteredSpeaker], cluster: &[usize]) -> Vec<SpeakerMerge> {
+    let mut named: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut unnamed = Vec::new();
+    for &i in cluster {
+        match speakers[i].name.as_deref() {
+            Some(name) => named.entry(name).or_default().push(i),
+            None => unnamed.push(i),
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = if named.is_empty() {
+        vec![unnamed]
+    } else {
+        let mut groups: Vec<Vec<usize>> = named.into_values().collect();
+        for i in unnamed {
+            let closest = groups
+                .iter_mut()
+                .min_by(|a, b| {
+                    let distance_a = group_distance(speakers, a, i);
+                    let distance_b = group_distance(speakers, b, i);
+                    distance_a.total_cmp(&distance_b)
+                })
+                .unwrap();
+            closest.push(i);
+        }
+        groups
+    };
+
+    groups
+        .iter_mut()
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            // named speakers first, then the most heard one, then the oldest
+            group.sort_by_key(|&i| {
+                let speaker = &speakers[i];
+                (speaker.name.is_none(), -speaker.transcriptions, speaker.id)
+            });
+            let keep = &speakers[group[0]];
+            let mut speakers_to_merge: Vec<i64> =
+                group[1..].iter().map(|&i| speakers[i].id).collect();
+            speakers_to_merge.sort_unstable();
+            SpeakerMerge {
+                speaker_to_keep: keep.id,
+                name: keep.name.clone(),
+                speakers_to_merge,
+                transcriptions: group[1..].iter().map(|&i| speakers[i].transcriptions).sum(),
+                max_distance: group[1..]
+                    .iter()
+                    .map(|&i| cosine_distance(&keep.centroid, &speakers[i].centroid))
+                    .fold(0.0, f32::max),
+            }
+        })
+        .collect()
+}
+
+/// Average distance between a speaker and a group of speakers
+fn group_distance(speakers: &[ClusteredSpeaker], group: &[usize], i: usize) -> f32 {
+    group
+        .iter()
+        .map(|&j| cosine_distance(&speakers[i].centroid, &speakers[j].centroid))
+        .sum::<f32>()
+        / group.len() as f32
+}
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::DatabaseManager;
+
+/// What an API token is allowed to do
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TokenScope {
+    /// Search and stream recorded screen, audio and UI content
+    #[serde(rename = "search:read")]
+    SearchRead,
+    /// List audio and video devices
+    #[serde(rename = "audio:read")]
+    AudioRead,
+    /// Start and stop recording devices
+    #[serde(rename = "audio:write")]
+    AudioWrite,
+    /// Add content, tags and edit speakers
+    #[serde(rename = "data:write")]
+    DataWrite,
+    /// Drive the mouse and keyboard, open apps and urls
+    #[serde(rename = "operator:write")]
+    OperatorWrite,
+    /// Everything, including raw SQL, retention and deleting data
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 6] = [
+        TokenScope::SearchRead,
+        TokenScope::AudioRead,
+        TokenScope::AudioWrite,
+        TokenScope::DataWrite,
+        TokenScope::OperatorWrite,
+        TokenScope::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::SearchRead => "search:read",
+            TokenScope::AudioRead => "audio:read",
+            TokenScope::AudioWrite => "audio:write",
+            TokenScope::DataWrite => "data:write",
+            TokenScope::OperatorWrite => "operator:write",
+            TokenScope::Admin => "admin",
+        }
+    }
+}
+
+impl Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TokenScope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown scope '{}', expected one of: {}",
+                    s,
+                    TokenScope::ALL.map(|scope| scope.as_str()).join(", ")
+                )
+            })
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiToken {
+    /// Admin tokens are granted every scope
+    pub fn allows(&self, scope: TokenScope) -> bool {
+        self.scopes.contains(&TokenScope::Admin) || self.scopes.contains(&scope)
+    }
+}
+
+type ApiTokenRow = (
+    i64,
+    String,
+    String,
+    DateTime<Utc>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+);
+
+fn token_from_row(
+    (id, name, scopes, created_at, last_used_at, revoked_at): ApiTokenRow,
+) -> ApiToken {
+    ApiToken {
+        id,
+        name,
+        // unknown scopes from newer versions are ignored rather than failing the lookup
+        scopes: scopes
+            .split_whitespace()
+            .filter_map(|scope| scope.parse().ok())
+            .collect(),
+        created_at,
+        last_used_at,
+        revoked_at,
+    }
+}
+
+impl DatabaseManager {
+    /// Stores a new token. Only the hash of the token is kept, callers hash the secret
+    /// before handing it over.
+    pub async fn create_api_token(
+        &self,
+        name: &str,
+        token_hash: &str,
+        scopes: &[TokenScope],
+    ) -> Result<i64, sqlx::Error> {
+        let scopes = scopes
+            .iter()
+            .map(|scope| scope.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let id = sqlx::query(
+            "INSERT INTO api_tokens (name, token_hash, scopes, created_at) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(name)
+        .bind(token_hash)
+        .bind(scopes)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    pub async fn list_api_tokens(&self) -> Result<Vec<ApiToken>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, ApiTokenRow>(
+            "SELECT id, name, scopes, created_at, last_used_at, revoked_at FROM api_tokens ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(token_from_row).collect())
+    }
+
+    /// Looks up a token that has not been revoked by the hash of its secret
+    pub async fn get_active_api_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<ApiToken>, sqlx::Error> {
+        let row = sqlx::query_as::<_, ApiTokenRow>(
+            r#"
+            SELECT id, name, scopes, created_at, last_used_at, revoked_at
+            FROM api_tokens
+            WHERE token_hash = ?1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(token_from_row))
+    }
+
+    /// Revokes a token, returns false when there is no active token with this id
+    pub async fn revoke_api_token(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE api_tokens SET revoked_at = ?2 WHERE id = ?1 AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn touch_api_token(&self, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = ?2 WHERE id = ?1")
+            .bind(id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::DatabaseManager;
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionWord {
+    pub text: String,
+    /// Offsets in the audio chunk, in seconds
+    pub start_time: f64,
+    pub end_time: f64,
+    pub probability: f32,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionSegment {
+    pub text: String,
+    /// Offsets in the audio chunk, in seconds
+    pub start_time: f64,
+    pub end_time: f64,
+    /// Mean log probability of the segment tokens, very low values are often hallucinations
+    pub avg_logprob: Option<f32>,
+    #[serde(default)]
+    pub words: Vec<TranscriptionWord>,
+}
+
+/// What the transcription engine reported besides the text
+#[derive(OaSchema, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionDetails {
+    pub language: Option<String>,
+    pub avg_logprob: Option<f32>,
+    pub segments: Vec<TranscriptionSegment>,
+}
+
+type SegmentRow = (String, f64, f64, Option<f32>, String);
+
+impl DatabaseManager {
+    /// Stores the language, confidence and segments of a transcription, replacing any
+    /// previously stored segments
+    pub async fn insert_transcription_details(
+        &self,
+        audio_transcription_id: i64,
+        details: &TranscriptionDetails,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE audio_transcriptions SET language = ?2, avg_logprob = ?3 WHERE id = ?1",
+        )
+        .bind(audio_transcription_id)
+        .bind(&details.language)
+        .bind(details.avg_logprob)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM transcription_segments WHERE audio_transcription_id = ?1")
+            .bind(audio_transcription_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for segment in &details.segments {
+            sqlx::query(
+                r#"
+                INSERT INTO transcription_segments
+                    (audio_transcription_id, start_time, end_time, text, avg_logprob, words)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )
+            .bind(audio_transcription_id)
+            .bind(segment.start_time)
+            .bind(segment.end_time)
+            .bind(&segment.text)
+            .bind(segment.avg_logprob)
+            .bind(serde_json::to_string(&segment.words).unwrap_or_else(|_| "[]".to_string()))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The details of a transcription, `None` when there is no transcription with this id
+    pub async fn get_transcription_details(
+        &self,
+        audio_transcription_id: i64,
+    ) -> Result<Option<TranscriptionDetails>, sqlx::Error> {
+        let Some((language, avg_logprob)) = sqlx::query_as::<_, (Option<String>, Option<f32>)>(
+            "SELECT language, avg_logprob FROM audio_transcriptions WHERE id = ?1",
+        )
+        .bind(audio_transcription_id)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let segments = sqlx::query_as::<_, SegmentRow>(
+            r#"
+            SELECT text, start_time, end_time, avg_logprob, words
+            FROM transcription_segments
+            WHERE audio_transcription_id = ?1
+            ORDER BY start_time, id
+            "#,
+        )
+        .bind(audio_transcription_id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(
+            |(text, start_time, end_time, avg_logprob, words)| TranscriptionSegment {
+                text,
+                start_time,
+                end_time,
+                avg_logprob,
+                words: serde_json::from_str(&words).unwrap_or_default(),
+            },
+        )
+        .collect();
+
+        Ok(Some(TranscriptionDetails {
+            language,
+            avg_logprob,
+            segments,
+        }))
+    }
+}
@@ -67,6 +67,30 @@ pub struct OCRResult {
     pub device_name: String,
 }
 
+impl From<OCRResultRaw> for OCRResult {
+    fn from(raw: OCRResultRaw) -> Self {
+        OCRResult {
+            frame_id: raw.frame_id,
+            ocr_text: raw.ocr_text,
+            text_json: raw.text_json,
+            timestamp: raw.timestamp,
+            frame_name: raw.frame_name,
+            file_path: raw.file_path,
+            offset_index: raw.offset_index,
+            app_name: raw.app_name,
+            ocr_engine: raw.ocr_engine,
+            window_name: raw.window_name,
+            device_name: raw.device_name,
+            tags: raw
+                .tags
+                .map(|t| t.split(',').map(String::from).collect())
+                .unwrap_or_default(),
+            browser_url: raw.browser_url,
+            focused: raw.focused,
+        }
+    }
+}
+
 #[derive(OaSchema, Debug, Deserialize, PartialEq, Default, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum ContentType {
@@ -89,6 +113,7 @@ pub enum ContentType {
 #[derive(FromRow)]
 pub struct AudioResultRaw {
     pub audio_chunk_id: i64,
+    pub transcription_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
     pub file_path: String,
@@ -100,6 +125,8 @@ pub struct AudioResultRaw {
     pub speaker_id: Option<i64>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    pub language: Option<String>,
+    pub avg_logprob: Option<f32>,
 }
 
 #[derive(OaSchema, Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -118,6 +145,7 @@ pub enum DeviceType {
 #[derive(OaSchema, Debug, Serialize, Deserialize)]
 pub struct AudioResult {
     pub audio_chunk_id: i64,
+    pub transcription_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
     pub file_path: String,
@@ -129,6 +157,9 @@ pub struct AudioResult {
     pub speaker: Option<Speaker>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    pub language: Option<String>,
+    /// Mean token log probability reported by the engine, low values hint at hallucinations
+    pub avg_logprob: Option<f32>,
 }
 
 #[derive(OaSchema, Debug, Deserialize, PartialEq)]
@@ -275,11 +306,11 @@ pub struct FrameRow {
     pub text_json: String,
 }
 
-#[derive(Deserialize, OaSchema, PartialEq, Default)]
+#[derive(Deserialize, OaSchema, Debug, Clone, Copy, PartialEq, Default)]
 pub enum Order {
-    #[serde(rename = "ascending")]
+    #[serde(rename = "ascending", alias = "asc")]
     Ascending,
-    #[serde(rename = "descending")]
+    #[serde(rename = "descending", alias = "desc")]
     #[default]
     Descending,
 }
@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::DatabaseManager;
+
+/// A url events are posted to. Its secret is left out, it is only shown when the webhook
+/// is created.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    /// Event names or patterns such as `meeting_*`, every event when empty
+    pub events: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A delivery that failed every attempt, or that was never attempted as too many were
+/// waiting for the webhook
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookDeadLetter {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event_name: String,
+    /// Body that was posted
+    pub body: serde_json::Value,
+    /// 0 when the delivery was never attempted
+    pub attempts: u32,
+    pub last_error: String,
+    pub created_at: DateTime<Utc>,
+}
+
+type WebhookRow = (i64, String, String, String, DateTime<Utc>);
+
+type DeadLetterRow = (i64, i64, String, String, u32, String, DateTime<Utc>);
+
+fn webhook_from_row((id, url, events, secret, created_at): WebhookRow) -> (Webhook, String) {
+    let webhook = Webhook {
+        id,
+        url,
+        events: serde_json::from_str(&events).unwrap_or_default(),
+        created_at,
+    };
+    (webhook, secret)
+}
+
+fn dead_letter_from_row(
+    (id, webhook_id, event_name, body, attempts, last_error, created_at): DeadLetterRow,
+) -> WebhookDeadLetter {
+    WebhookDeadLetter {
+        id,
+        webhook_id,
+        event_name,
+        body: serde_json::from_str(&body).unwrap_or_default(),
+        attempts,
+        last_error,
+        created_at,
+    }
+}
+
+impl DatabaseManager {
+    pub async fn create_webhook(
+        &self,
+        url: &str,
+        events: &[String],
+        secret: &str,
+    ) -> Result<Webhook, sqlx::Error> {
+        let created_at = Utc::now();
+        let id = sqlx::query(
+            "INSERT INTO webhooks (url, events, secret, created_at) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(url)
+        .bind(serde_json::to_string(events).unwrap_or_else(|_| "[]".to_string()))
+        .bind(secret)
+        .bind(created_at)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(Webhook {
+            id,
+            url: url.to_string(),
+            events: events.to_vec(),
+            created_at,
+        })
+    }
+
+    pub async fn list_webhooks(&self) -> Result<Vec<Webhook>, sqlx::Error> {
+        Ok(self
+            .list_webhooks_with_secrets()
+            .await?
+            .into_iter()
+            .map(|(webhook, _)| webhook)
+            .collect())
+    }
+
+    /// Webhooks along with the secret their deliveries are signed with
+    pub async fn list_webhooks_with_secrets(&self) -> Result<Vec<(Webhook, String)>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, WebhookRow>(
+            "SELECT id, url, events, secret, created_at FROM webhooks ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(webhook_from_row).collect())
+    }
+
+    /// Deletes a webhook and its dead letters, returns false when there is no webhook
+    /// with this id
+    pub async fn delete_webhook(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM webhook_dead_letters WHERE webhook_id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn insert_webhook_dead_letter(
+        &self,
+        webhook_id: i64,
+        event_name: &str,
+        body: &serde_json::Value,
+        attempts: u32,
+        last_error: &str,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO webhook_dead_letters
+                (webhook_id, event_name, body, attempts, last_error, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(event_name)
+        .bind(body.to_string())
+        .bind(attempts)
+        .bind(last_error)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Up to `limit` dead letters, newest first, of one webhook or of all of them
+    pub async fn list_webhook_dead_letters(
+        &self,
+        webhook_id: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<WebhookDeadLetter>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, DeadLetterRow>(
+            r#"
+            SELECT id, webhook_id, event_name, body, attempts, last_error, created_at
+            FROM webhook_dead_letters
+            WHERE ?1 IS NULL OR webhook_id = ?1
+            ORDER BY id DESC
+            LIMIT ?2
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(dead_letter_from_row).collect())
+    }
+
+    pub async fn get_webhook_dead_letter(
+        &self,
+        id: i64,
+    ) -> Result<Option<WebhookDeadLetter>, sqlx::Error> {
+        let row = sqlx::query_as::<_, DeadLetterRow>(
+            r#"
+            SELECT id, webhook_id, event_name, body, attempts, last_error, created_at
+            FROM webhook_dead_letters
+            WHERE id = ?1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(dead_letter_from_row))
+    }
+
+    /// Returns false when there is no dead letter with this id
+    pub async fn delete_webhook_dead_letter(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM webhook_dead_letters WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -2,9 +2,14 @@
 mod tests {
     use std::sync::Arc;
 
-    use chrono::Utc;
+    use chrono::{DateTime, TimeZone, Utc};
     use cubby_db::{
-        AudioDevice, ContentType, DatabaseManager, DeviceType, Frame, OcrEngine, SearchResult,
+        ensure_read_only_statement, ActivityGroupBy, AggregateOptions, AudioDevice, ContentType,
+        DatabaseManager, DeviceType, EmbeddingSource, ForgetFilter, Frame, HybridSearchOptions,
+        IndexJobStatus, JournalPosition, OcrEngine, Order, RawSqlError, RawSqlOptions,
+        ReclusterOptions, RetentionPolicy, RetentionRule, SearchCursor, SearchResult,
+        SemanticSearchFilter, TokenScope, TranscriptionDetails, TranscriptionSegment,
+        TranscriptionWord, MAX_SPEAKER_EMBEDDINGS,
     };
 
     async fn setup_test_db() -> DatabaseManager {
@@ -1445,4 +1450,2320 @@ mod tests {
             .unwrap();
         assert_eq!(count, 0, "Should count zero results for non-matching query");
     }
+
+    #[tokio::test]
+    async fn test_retention_purge() {
+        let db = setup_test_db().await;
+        let old = Utc::now() - chrono::Duration::days(10);
+
+        let old_video =
+            std::env::temp_dir().join(format!("retention_{}.mp4", rand::random::<u32>()));
+        std::fs::write(&old_video, b"old video").unwrap();
+        let old_video = old_video.to_string_lossy().to_string();
+
+        db.insert_video_chunk(&old_video, "test_device")
+            .await
+            .unwrap();
+        let old_frame = db
+            .insert_frame(
+                "test_device",
+                Some(old),
+                None,
+                Some("old_app"),
+                Some(""),
+                false,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(old_frame, "old screen", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        db.insert_video_chunk("current_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let secret_frame = db
+            .insert_frame(
+                "test_device",
+                None,
+                None,
+                Some("secret_app"),
+                Some(""),
+                false,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            secret_frame,
+            "secret screen",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+        let new_frame = db
+            .insert_frame("test_device", None, None, Some("new_app"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(new_frame, "new screen", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        let audio_chunk_id = db.insert_audio_chunk("old_audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "test".to_string(),
+            device_type: DeviceType::Input,
+        };
+        for (text, start) in [("old audio one", 0.0), ("old audio two", 1.0)] {
+            db.insert_audio_transcription(
+                audio_chunk_id,
+                text,
+                0,
+                "",
+                &device,
+                None,
+                Some(start),
+                Some(start + 1.0),
+            )
+            .await
+            .unwrap();
+        }
+        sqlx::query("UPDATE audio_transcriptions SET timestamp = ?1")
+            .bind(old)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE audio_chunks SET timestamp = ?1")
+            .bind(old)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(7),
+            rules: vec![RetentionRule {
+                app_name: Some("secret".to_string()),
+                window_name: None,
+                max_age_days: 0,
+            }],
+            max_disk_bytes: None,
+        };
+
+        let plan = db
+            .plan_retention(&policy, Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        let preview = plan.report();
+        assert_eq!(preview.frames, 2, "old frame and secret app frame");
+        assert_eq!(preview.ocr_text, 2);
+        assert_eq!(preview.audio_transcriptions, 2);
+        assert_eq!(preview.video_chunks, 1, "latest chunk of a device is kept");
+        assert_eq!(preview.audio_chunks, 1);
+        assert_eq!(preview.bytes, 9);
+        assert_eq!(
+            preview.files,
+            vec![old_video.clone(), "old_audio.mp4".to_string()]
+        );
+
+        db.delete_frames(&plan.frame_ids).await.unwrap();
+        // the secret frame is still in the file of the chunk being recorded, new frames
+        // don't take its offset
+        let next_frame = db
+            .insert_frame("test_device", None, None, Some("new_app"), Some(""), false)
+            .await
+            .unwrap();
+        let next_offset: i64 = sqlx::query_scalar("SELECT offset_index FROM frames WHERE id = ?1")
+            .bind(next_frame)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(next_offset, 2);
+        let deleted = db
+            .delete_audio_transcriptions(&plan.audio_transcription_ids[..1])
+            .await
+            .unwrap();
+        assert_eq!(deleted.audio_transcriptions, 1);
+
+        // deleting one transcription must keep its sibling searchable
+        let results = db
+            .search(
+                "audio",
+                ContentType::Audio,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let chunk_ids: Vec<i64> = plan.video_chunks.iter().map(|c| c.id).collect();
+        let deleted = db.delete_video_chunks(&chunk_ids).await.unwrap();
+        assert_eq!(deleted.files, vec![old_video.clone()]);
+        // once the chunk is finished the purged frame is cut out of it
+        db.insert_video_chunk("next_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let scrubs = db.pending_video_scrubs().await.unwrap();
+        assert_eq!(scrubs.len(), 1);
+        assert_eq!(scrubs[0].file_path, "current_video.mp4");
+        assert_eq!(scrubs[0].removed_offsets, vec![0]);
+        assert!(!scrubs[0].fully_covered);
+        db.finish_video_scrub(&scrubs[0]).await.unwrap();
+        let chunk_ids: Vec<i64> = plan.audio_chunks.iter().map(|c| c.id).collect();
+        let deleted = db.delete_audio_chunks(&chunk_ids).await.unwrap();
+        assert_eq!(deleted.audio_chunks, 1);
+        assert_eq!(deleted.audio_transcriptions, 1);
+        std::fs::remove_file(&old_video).unwrap();
+
+        let results = db
+            .search(
+                "screen",
+                ContentType::All,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        if let SearchResult::OCR(ocr_result) = &results[0] {
+            assert_eq!(ocr_result.frame_id, new_frame);
+        } else {
+            panic!("Expected OCR result");
+        }
+
+        let plan = db.plan_retention(&policy, Utc::now()).await.unwrap();
+        assert_eq!(plan.total_items(), 0, "nothing left to purge");
+    }
+
+    #[tokio::test]
+    async fn test_forget_time_range() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::minutes(10);
+
+        db.insert_video_chunk("chunk_a.mp4", "test_device")
+            .await
+            .unwrap();
+        let mut chunk_a_frames = Vec::new();
+        for app in ["bank", "notes", "bank"] {
+            let frame_id = db
+                .insert_frame("test_device", None, None, Some(app), Some(""), false)
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                &format!("{} screen", app),
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+            chunk_a_frames.push(frame_id);
+        }
+        db.insert_video_chunk("chunk_b.mp4", "test_device")
+            .await
+            .unwrap();
+        db.insert_frame("test_device", None, None, Some("bank"), Some(""), false)
+            .await
+            .unwrap();
+
+        let audio_chunk_id = db.insert_audio_chunk("audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "test".to_string(),
+            device_type: DeviceType::Input,
+        };
+        let mut transcription_ids = Vec::new();
+        for (text, start_time) in [("private words", 0.0), ("earlier words", 2.0)] {
+            transcription_ids.push(
+                db.insert_audio_transcription(
+                    audio_chunk_id,
+                    text,
+                    0,
+                    "",
+                    &device,
+                    None,
+                    Some(start_time),
+                    Some(start_time + 1.0),
+                )
+                .await
+                .unwrap(),
+            );
+        }
+        sqlx::query("UPDATE audio_transcriptions SET timestamp = ?1 WHERE id = ?2")
+            .bind(start - chrono::Duration::minutes(1))
+            .bind(transcription_ids[1])
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let end = Utc::now() + chrono::Duration::minutes(1);
+        let filter = ForgetFilter {
+            start_time: start,
+            end_time: end,
+            app_name: Some("bank".to_string()),
+            window_name: None,
+            browser_url: None,
+            device_name: None,
+        };
+        let plan = db.plan_forget(&filter).await.unwrap();
+        assert_eq!(plan.frames.len(), 3);
+        assert!(
+            plan.audio_transcription_ids.is_empty(),
+            "app scope skips audio"
+        );
+        let report = db.apply_forget(&plan).await.unwrap();
+        assert_eq!(report.frames, 3);
+        assert_eq!(report.ocr_text, 2);
+        assert_eq!(report.reencoded_files, vec!["chunk_a.mp4".to_string()]);
+        assert_eq!(report.pending_files, vec!["chunk_b.mp4".to_string()]);
+        assert!(
+            db.pending_video_scrubs().await.unwrap().is_empty(),
+            "chunk_b is still recording"
+        );
+
+        let offset: i64 = sqlx::query_scalar("SELECT offset_index FROM frames WHERE id = ?1")
+            .bind(chunk_a_frames[1])
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            offset, 0,
+            "remaining frame is compacted to match the re-encoded chunk"
+        );
+
+        let results = db
+            .search(
+                "screen",
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let filter = ForgetFilter {
+            app_name: None,
+            ..filter
+        };
+        let plan = db.plan_forget(&filter).await.unwrap();
+        assert_eq!(plan.audio_transcription_ids, vec![transcription_ids[0]]);
+        assert_eq!(plan.audio_chunks[0].muted_ranges, vec![(0.0, 1.0)]);
+        assert!(!plan.audio_chunks[0].fully_covered);
+        let report = db.apply_forget(&plan).await.unwrap();
+        assert_eq!(report.frames, 1);
+        assert_eq!(report.audio_transcriptions, 1);
+        assert_eq!(report.deleted_files, vec!["chunk_a.mp4".to_string()]);
+        assert_eq!(report.reencoded_files, vec!["audio.mp4".to_string()]);
+
+        let results = db
+            .search(
+                "words",
+                ContentType::Audio,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            results.len(),
+            1,
+            "transcription outside the range stays searchable"
+        );
+
+        // the forgotten frame is still in chunk_b's file until it is finished
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("notes"), Some(""), false)
+            .await
+            .unwrap();
+        let offset_of = |id: i64| {
+            sqlx::query_scalar::<_, i64>("SELECT offset_index FROM frames WHERE id = ?1")
+                .bind(id)
+                .fetch_one(&db.pool)
+        };
+        assert_eq!(offset_of(frame_id).await.unwrap(), 1);
+        db.insert_video_chunk("chunk_c.mp4", "test_device")
+            .await
+            .unwrap();
+        let scrubs = db.pending_video_scrubs().await.unwrap();
+        assert_eq!(scrubs.len(), 1);
+        assert_eq!(scrubs[0].file_path, "chunk_b.mp4");
+        assert_eq!(scrubs[0].removed_offsets, vec![0]);
+        assert!(!scrubs[0].fully_covered);
+        db.finish_video_scrub(&scrubs[0]).await.unwrap();
+        assert_eq!(offset_of(frame_id).await.unwrap(), 0);
+        assert!(db.pending_video_scrubs().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_sql() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let options = RawSqlOptions::default();
+
+        for query in [
+            "DELETE FROM video_chunks",
+            "SELECT 1; DROP TABLE frames",
+            "PRAGMA query_only = OFF",
+            "-- SELECT\nUPDATE video_chunks SET file_path = 'x'",
+            "",
+        ] {
+            assert!(
+                matches!(
+                    db.execute_read_only_sql(query, &options).await,
+                    Err(RawSqlError::Rejected(_))
+                ),
+                "{:?} should be rejected",
+                query
+            );
+        }
+        assert!(ensure_read_only_statement("SELECT ';DROP TABLE frames' AS s;").is_ok());
+
+        // WITH passes the statement check but still can't write
+        let result = db
+            .execute_read_only_sql(
+                "WITH ids AS (SELECT id FROM video_chunks) DELETE FROM video_chunks WHERE id IN ids",
+                &options,
+            )
+            .await;
+        assert!(matches!(result, Err(RawSqlError::Database(_))));
+
+        let result = db
+            .execute_read_only_sql(
+                "SELECT id, file_path, x'cafe' AS data, NULL AS missing FROM video_chunks",
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.columns[0].decl_type, "INTEGER");
+        assert_eq!(result.columns[1].decl_type, "TEXT");
+        assert_eq!(result.columns[2].decl_type, "BLOB");
+        assert_eq!(result.rows[0]["file_path"], "test_video.mp4");
+        assert_eq!(result.rows[0]["data"], "yv4=");
+        assert!(result.rows[0]["missing"].is_null());
+        assert!(!result.truncated);
+
+        let result = db
+            .execute_read_only_sql(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100) SELECT i FROM n",
+                &RawSqlOptions {
+                    max_rows: 10,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.rows.len(), 10);
+        assert!(result.truncated);
+
+        let result = db
+            .execute_read_only_sql(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) SELECT COUNT(*) FROM n",
+                &RawSqlOptions {
+                    timeout: std::time::Duration::from_millis(100),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(RawSqlError::Timeout(_))));
+
+        // the shared connection is writable again afterwards
+        db.insert_video_chunk("after.mp4", "test_device")
+            .await
+            .unwrap();
+
+        // a query dropped half way, e.g. by a client disconnecting, leaves the connection
+        // neither read-only nor interrupted once its deadline has passed
+        let dropped = tokio::time::timeout(
+            std::time::Duration::from_millis(20),
+            db.execute_read_only_sql(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) SELECT COUNT(*) FROM n",
+                &RawSqlOptions {
+                    timeout: std::time::Duration::from_millis(100),
+                    ..Default::default()
+                },
+            ),
+        )
+        .await;
+        assert!(dropped.is_err());
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        for i in 0..5 {
+            db.insert_video_chunk(&format!("after_drop_{}.mp4", i), "test_device")
+                .await
+                .unwrap();
+        }
+        let result = db
+            .execute_read_only_sql("SELECT COUNT(*) AS n FROM video_chunks", &options)
+            .await
+            .unwrap();
+        assert_eq!(result.rows[0]["n"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_api_tokens() {
+        let db = setup_test_db().await;
+
+        let id = db
+            .create_api_token("dashboard", "hash-a", &[TokenScope::SearchRead])
+            .await
+            .unwrap();
+        db.create_api_token("admin", "hash-b", &[TokenScope::Admin])
+            .await
+            .unwrap();
+
+        let token = db.get_active_api_token("hash-a").await.unwrap().unwrap();
+        assert_eq!(token.name, "dashboard");
+        assert!(token.allows(TokenScope::SearchRead));
+        assert!(!token.allows(TokenScope::OperatorWrite));
+        assert!(token.last_used_at.is_none());
+
+        let admin = db.get_active_api_token("hash-b").await.unwrap().unwrap();
+        assert!(admin.allows(TokenScope::OperatorWrite));
+        assert!(db.get_active_api_token("unknown").await.unwrap().is_none());
+
+        db.touch_api_token(id).await.unwrap();
+        assert!(db.list_api_tokens().await.unwrap()[0]
+            .last_used_at
+            .is_some());
+
+        assert!(db.revoke_api_token(id).await.unwrap());
+        assert!(!db.revoke_api_token(id).await.unwrap());
+        assert!(db.get_active_api_token("hash-a").await.unwrap().is_none());
+        assert!(db.list_api_tokens().await.unwrap()[0].revoked_at.is_some());
+
+        assert_eq!("operator:write".parse(), Ok(TokenScope::OperatorWrite));
+        assert!("operator:read".parse::<TokenScope>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_meetings() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::minutes(30);
+
+        let audio_chunk_id = db.insert_audio_chunk("call.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        let alice = db.insert_speaker(&[0.1; 512]).await.unwrap();
+        let bob = db.insert_speaker(&[0.2; 512]).await.unwrap();
+        db.update_speaker_name(alice.id, "alice").await.unwrap();
+
+        let mut transcription_ids = Vec::new();
+        for (minutes, text, speaker_id) in [
+            (40, "before the call", Some(alice.id)),
+            (25, "hello everyone", Some(alice.id)),
+            (20, "hi alice", Some(bob.id)),
+            (15, "lets start", None),
+            (5, "after the call", Some(bob.id)),
+        ] {
+            let id = db
+                .insert_audio_transcription(
+                    audio_chunk_id,
+                    text,
+                    0,
+                    "",
+                    &device,
+                    speaker_id,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            sqlx::query("UPDATE audio_transcriptions SET timestamp = ?1 WHERE id = ?2")
+                .bind(Utc::now() - chrono::Duration::minutes(minutes))
+                .bind(id)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            transcription_ids.push(id);
+        }
+
+        let id = db
+            .start_meeting("zoom.us", Some("Weekly sync"), start)
+            .await
+            .unwrap();
+        // in progress meetings link everything heard so far
+        assert_eq!(db.link_meeting_transcriptions(id).await.unwrap(), 4);
+        assert!(db
+            .end_meeting(id, Utc::now() - chrono::Duration::minutes(10))
+            .await
+            .unwrap());
+        assert!(!db.end_meeting(id, Utc::now()).await.unwrap());
+        assert_eq!(db.link_meeting_transcriptions(id).await.unwrap(), 0);
+
+        let meeting = db.get_meeting(id).await.unwrap().unwrap();
+        assert_eq!(meeting.meeting.app, "zoom.us");
+        assert_eq!(meeting.meeting.title.as_deref(), Some("Weekly sync"));
+        assert_eq!(meeting.meeting.transcription_count, 3);
+        assert_eq!(
+            meeting
+                .transcript
+                .iter()
+                .map(|entry| entry.transcription.as_str())
+                .collect::<Vec<_>>(),
+            vec!["hello everyone", "hi alice", "lets start"]
+        );
+        assert_eq!(
+            meeting.transcript[0]
+                .speaker
+                .as_ref()
+                .and_then(|speaker| speaker.name.as_deref()),
+            Some("alice")
+        );
+        assert!(meeting.transcript[2].speaker.is_none());
+        assert_eq!(
+            meeting
+                .meeting
+                .speakers
+                .iter()
+                .map(|speaker| speaker.id)
+                .collect::<Vec<_>>(),
+            vec![alice.id, bob.id]
+        );
+
+        // meetings left open by a crash are closed at their last transcription
+        let open = db.start_meeting("teams", None, Utc::now()).await.unwrap();
+        assert_eq!(db.close_open_meetings().await.unwrap(), 1);
+
+        let meetings = db.list_meetings(None, None, 10, 0).await.unwrap();
+        assert_eq!(
+            meetings.iter().map(|m| m.id).collect::<Vec<_>>(),
+            vec![open, id]
+        );
+        assert!(meetings.iter().all(|m| m.end_time.is_some()));
+        let earlier = db
+            .list_meetings(None, Some(Utc::now() - chrono::Duration::minutes(1)), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(earlier.len(), 1);
+        assert_eq!(earlier[0].speakers.len(), 2);
+
+        // deleted transcriptions drop out of the meeting
+        sqlx::query("DELETE FROM audio_transcriptions WHERE id = ?1")
+            .bind(transcription_ids[2])
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let meeting = db.get_meeting(id).await.unwrap().unwrap();
+        assert_eq!(meeting.meeting.transcription_count, 2);
+        assert_eq!(meeting.meeting.speakers.len(), 1);
+        assert!(db.get_meeting(open + 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transcription_details() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("segments.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        let mut ids = Vec::new();
+        for (text, start_time) in [("hello there general kenobi", 4.0), ("sibling line", 9.0)] {
+            ids.push(
+                db.insert_audio_transcription(
+                    audio_chunk_id,
+                    text,
+                    0,
+                    "WhisperLargeV3Turbo",
+                    &device,
+                    None,
+                    Some(start_time),
+                    Some(start_time + 2.0),
+                )
+                .await
+                .unwrap(),
+            );
+        }
+
+        let details = TranscriptionDetails {
+            language: Some("en".to_string()),
+            avg_logprob: Some(-0.25),
+            segments: vec![
+                TranscriptionSegment {
+                    text: "general kenobi".to_string(),
+                    start_time: 5.0,
+                    end_time: 6.0,
+                    avg_logprob: Some(-0.3),
+                    words: vec![TranscriptionWord {
+                        text: "general".to_string(),
+                        start_time: 5.0,
+                        end_time: 5.4,
+                        probability: 0.9,
+                    }],
+                },
+                TranscriptionSegment {
+                    text: "hello there".to_string(),
+                    start_time: 4.0,
+                    end_time: 5.0,
+                    avg_logprob: Some(-0.2),
+                    words: vec![],
+                },
+            ],
+        };
+        db.insert_transcription_details(ids[0], &details)
+            .await
+            .unwrap();
+        // storing again replaces the segments
+        db.insert_transcription_details(ids[0], &details)
+            .await
+            .unwrap();
+
+        let stored = db.get_transcription_details(ids[0]).await.unwrap().unwrap();
+        assert_eq!(stored.language.as_deref(), Some("en"));
+        assert_eq!(stored.avg_logprob, Some(-0.25));
+        assert_eq!(
+            stored
+                .segments
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .collect::<Vec<_>>(),
+            vec!["hello there", "general kenobi"]
+        );
+        assert_eq!(stored.segments[1].words, details.segments[0].words);
+        assert!(db
+            .get_transcription_details(ids[1])
+            .await
+            .unwrap()
+            .unwrap()
+            .segments
+            .is_empty());
+        assert!(db
+            .get_transcription_details(ids[1] + 1)
+            .await
+            .unwrap()
+            .is_none());
+
+        // storing the language must not rewrite the search index of sibling transcriptions
+        let mut indexed: Vec<String> = sqlx::query_scalar(
+            "SELECT transcription FROM audio_transcriptions_fts WHERE audio_chunk_id = ?1",
+        )
+        .bind(audio_chunk_id)
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        indexed.sort();
+        assert_eq!(indexed, vec!["hello there general kenobi", "sibling line"]);
+
+        sqlx::query("DELETE FROM audio_transcriptions WHERE id = ?1")
+            .bind(ids[0])
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM transcription_segments")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn test_insert_audio_at() {
+        let db = setup_test_db().await;
+        let recorded_at = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+
+        assert_eq!(db.find_audio_chunk("phone/call.m4a").await.unwrap(), None);
+        let audio_chunk_id = db
+            .insert_audio_chunk_at("phone/call.m4a", recorded_at)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.find_audio_chunk("phone/call.m4a").await.unwrap(),
+            Some(audio_chunk_id)
+        );
+        assert_eq!(
+            db.get_audio_chunk_path(audio_chunk_id).await.unwrap(),
+            Some("phone/call.m4a".to_string())
+        );
+        assert_eq!(
+            db.get_audio_chunk_path(audio_chunk_id + 1).await.unwrap(),
+            None
+        );
+
+        db.insert_audio_transcription_at(
+            audio_chunk_id,
+            "imported from the phone",
+            0,
+            "WhisperLargeV3Turbo",
+            &AudioDevice {
+                name: "imported_files".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            Some(12.0),
+            Some(15.0),
+            recorded_at + chrono::Duration::seconds(12),
+        )
+        .await
+        .unwrap();
+
+        let results = db
+            .search(
+                "phone",
+                ContentType::Audio,
+                100,
+                0,
+                Some(recorded_at),
+                Some(recorded_at + chrono::Duration::minutes(1)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let SearchResult::Audio(audio) = &results[0] else {
+            panic!("expected an audio result");
+        };
+        assert_eq!(audio.timestamp, recorded_at + chrono::Duration::seconds(12));
+    }
+
+    #[tokio::test]
+    async fn test_index_jobs() {
+        let db = setup_test_db().await;
+
+        let options = serde_json::json!({ "copy_videos": false });
+        let id = db
+            .create_index_job("/recordings", Some("monitor"), &options)
+            .await
+            .unwrap();
+        let job = db.get_index_job(id).await.unwrap().unwrap();
+        assert_eq!(job.status, IndexJobStatus::Queued);
+        assert_eq!(job.options, options);
+        assert_eq!(job.total_files, None);
+
+        assert!(db.start_index_job(id, 3).await.unwrap());
+        db.finish_index_job_file(id, "/recordings/a.mp4", 10, None)
+            .await
+            .unwrap();
+        // finishing a file twice counts it once
+        db.finish_index_job_file(id, "/recordings/a.mp4", 10, None)
+            .await
+            .unwrap();
+        db.start_index_job_file(id, "/recordings/b.mp4", 42)
+            .await
+            .unwrap();
+        let job = db.get_index_job(id).await.unwrap().unwrap();
+        assert_eq!(job.status, IndexJobStatus::Running);
+        assert_eq!(job.processed_files, 1);
+        assert_eq!(job.frames, 10);
+
+        // a crash leaves the job running, it is resumed from the files it started on
+        let unfinished = db.unfinished_index_jobs().await.unwrap();
+        assert_eq!(unfinished.len(), 1);
+        assert!(db.start_index_job(id, 3).await.unwrap());
+        let files = db.index_job_files(id).await.unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].finished);
+        assert!(!files[1].finished);
+        assert_eq!(files[1].video_chunk_id, Some(42));
+
+        db.start_index_job_file(id, "/recordings/b.mp4", 43)
+            .await
+            .unwrap();
+        db.finish_index_job_file(id, "/recordings/b.mp4", 5, None)
+            .await
+            .unwrap();
+        db.finish_index_job_file(id, "/recordings/c.mp4", 0, Some("no video stream"))
+            .await
+            .unwrap();
+        assert!(db
+            .finish_index_job(id, IndexJobStatus::Completed, None)
+            .await
+            .unwrap());
+        let job = db.get_index_job(id).await.unwrap().unwrap();
+        assert_eq!(job.status, IndexJobStatus::Completed);
+        assert_eq!(job.processed_files, 3);
+        assert_eq!(job.failed_files, 1);
+        assert_eq!(job.frames, 15);
+        assert!(job.finished_at.is_some());
+        assert_eq!(
+            db.index_job_files(id).await.unwrap()[1].video_chunk_id,
+            Some(43)
+        );
+        assert!(!db.cancel_index_job(id).await.unwrap());
+
+        // a cancelled job is not started or finished by the worker
+        let cancelled = db.create_index_job("/other", None, &options).await.unwrap();
+        assert!(db.cancel_index_job(cancelled).await.unwrap());
+        assert!(!db.start_index_job(cancelled, 1).await.unwrap());
+        assert!(!db
+            .finish_index_job(cancelled, IndexJobStatus::Completed, None)
+            .await
+            .unwrap());
+        assert!(db.unfinished_index_jobs().await.unwrap().is_empty());
+        assert_eq!(
+            db.list_index_jobs(10)
+                .await
+                .unwrap()
+                .iter()
+                .map(|job| job.id)
+                .collect::<Vec<_>>(),
+            vec![cancelled, id]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recluster_speakers() {
+        let db = setup_test_db().await;
+
+        // a voice is a direction in the embedding space, a variant is slightly off it
+        let voice = |axis: usize, offset: usize, amount: f32| {
+            let mut embedding = vec![0.0f32; 512];
+            embedding[axis] = 1.0;
+            embedding[offset] += amount;
+            embedding
+        };
+
+        let alice = db.insert_speaker(&voice(0, 0, 0.0)).await.unwrap();
+        db.update_speaker_name(alice.id, "alice").await.unwrap();
+        let dave = db.insert_speaker(&voice(0, 20, 0.3)).await.unwrap();
+        db.update_speaker_name(dave.id, "dave").await.unwrap();
+        let alice_again = db.insert_speaker(&voice(0, 10, 0.1)).await.unwrap();
+
+        let bob = db.insert_speaker(&voice(1, 11, 0.1)).await.unwrap();
+        let bob_again = db.insert_speaker(&voice(1, 12, 0.1)).await.unwrap();
+        let bob_more = db.insert_speaker(&voice(1, 13, 0.1)).await.unwrap();
+        let bob_noise = db.insert_speaker(&voice(1, 14, 0.1)).await.unwrap();
+        db.mark_speaker_as_hallucination(bob_noise.id)
+            .await
+            .unwrap();
+
+        let carol = db.insert_speaker(&voice(2, 0, 0.0)).await.unwrap();
+
+        // matched segments add embeddings, up to the cap
+        let mut added = 0;
+        for i in 0..MAX_SPEAKER_EMBEDDINGS + 5 {
+            if db
+                .add_speaker_embedding(carol.id, &voice(2, 30 + i as usize, 0.05))
+                .await
+                .unwrap()
+            {
+                added += 1;
+            }
+        }
+        assert_eq!(added, MAX_SPEAKER_EMBEDDINGS - 1);
+
+        let audio_chunk_id = db.insert_audio_chunk("audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        for speaker_id in [alice_again.id, bob.id, bob_again.id, bob_again.id] {
+            db.insert_audio_transcription(
+                audio_chunk_id,
+                "hello",
+                0,
+                "",
+                &device,
+                Some(speaker_id),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        let dry_run = db
+            .recluster_speakers(&ReclusterOptions {
+                dry_run: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(dry_run.speakers, 7);
+        assert_eq!(dry_run.merged_speakers, 3);
+        assert_eq!(dry_run.merges.len(), 2);
+        // named speakers are kept, unnamed ones go to the closest of them
+        assert_eq!(dry_run.merges[0].speaker_to_keep, alice.id);
+        assert_eq!(dry_run.merges[0].name.as_deref(), Some("alice"));
+        assert_eq!(dry_run.merges[0].speakers_to_merge, vec![alice_again.id]);
+        // otherwise the most heard speaker is kept
+        assert_eq!(dry_run.merges[1].speaker_to_keep, bob_again.id);
+        assert_eq!(
+            dry_run.merges[1].speakers_to_merge,
+            vec![bob.id, bob_more.id]
+        );
+        assert_eq!(dry_run.merges[1].transcriptions, 1);
+        assert!(db.get_speaker_by_id(bob.id).await.is_ok());
+
+        let report = db
+            .recluster_speakers(&ReclusterOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(report.merges, dry_run.merges);
+        assert!(db.get_speaker_by_id(bob.id).await.is_err());
+        assert!(db.get_speaker_by_id(alice_again.id).await.is_err());
+        assert!(db.get_speaker_by_id(dave.id).await.is_ok());
+        assert!(db.get_speaker_by_id(bob_noise.id).await.is_ok());
+        let bob_transcriptions: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM audio_transcriptions WHERE speaker_id = ?1")
+                .bind(bob_again.id)
+                .fetch_one(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(bob_transcriptions, 3);
+
+        // nothing left to merge
+        let report = db
+            .recluster_speakers(&ReclusterOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(report.speakers, 4);
+        assert!(report.merges.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_text_embeddings() {
+        let db = setup_test_db().await;
+        let model = "test:model";
+
+        db.insert_video_chunk("video.mp4", "screen").await.unwrap();
+        let mut frame_ids = Vec::new();
+        for text in ["pricing change", "", "weekly sync notes"] {
+            let frame_id = db
+                .insert_frame("screen", None, None, Some("app"), Some(""), false)
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+            frame_ids.push(frame_id);
+        }
+        let audio_chunk_id = db.insert_audio_chunk("audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        let transcription_id = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "we talked about the pricing change",
+                0,
+                "",
+                &device,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // empty text is never embedded
+        let pending = db
+            .pending_embeddings(EmbeddingSource::Ocr, model, 10)
+            .await
+            .unwrap();
+        let ids: Vec<i64> = pending.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![frame_ids[0], frame_ids[2]]);
+        let progress = db
+            .embedding_progress(EmbeddingSource::Ocr, model)
+            .await
+            .unwrap();
+        assert_eq!((progress.embedded, progress.total), (0, 2));
+
+        // a batch moves the cursor
+        db.insert_text_embeddings(
+            EmbeddingSource::Ocr,
+            model,
+            &[
+                (frame_ids[0], vec![1.0, 0.0]),
+                (frame_ids[0], vec![0.8, 0.6]),
+            ],
+            Some(pending[0].cursor),
+        )
+        .await
+        .unwrap();
+        let pending = db
+            .pending_embeddings(EmbeddingSource::Ocr, model, 10)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, frame_ids[2]);
+
+        // rows embedded outside the worker, e.g. while indexing videos, are skipped
+        db.insert_text_embeddings(
+            EmbeddingSource::Ocr,
+            model,
+            &[(frame_ids[2], vec![0.0, 1.0])],
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(db
+            .pending_embeddings(EmbeddingSource::Ocr, model, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // each model has its own cursor
+        let pending = db
+            .pending_embeddings(EmbeddingSource::Ocr, "other:model", 10)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 2);
+
+        let pending = db
+            .pending_embeddings(EmbeddingSource::Audio, model, 10)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, transcription_id);
+        db.insert_text_embeddings(
+            EmbeddingSource::Audio,
+            model,
+            &[(transcription_id, vec![1.0, 0.0])],
+            Some(pending[0].cursor),
+        )
+        .await
+        .unwrap();
+        let progress = db
+            .embedding_progress(EmbeddingSource::Audio, model)
+            .await
+            .unwrap();
+        assert_eq!((progress.embedded, progress.total), (1, 1));
+
+        // ui text has no cursor, a window whose text changes gets embedded again
+        let ui_id = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO ui_monitoring (text_output, timestamp, app, window) VALUES (?, ?, ?, ?) RETURNING id",
+        )
+        .bind("pricing change draft")
+        .bind(Utc::now())
+        .bind("notes")
+        .bind("pricing.md")
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        let pending = db
+            .pending_embeddings(EmbeddingSource::Ui, model, 10)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, ui_id);
+        db.insert_text_embeddings(
+            EmbeddingSource::Ui,
+            model,
+            &[(ui_id, vec![0.0, 1.0])],
+            Some(pending[0].cursor),
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE ui_monitoring SET text_output = ? WHERE id = ?")
+            .bind("pricing change final")
+            .bind(ui_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let progress = db
+            .embedding_progress(EmbeddingSource::Ui, model)
+            .await
+            .unwrap();
+        assert_eq!((progress.embedded, progress.total), (0, 1));
+        let pending = db
+            .pending_embeddings(EmbeddingSource::Ui, model, 10)
+            .await
+            .unwrap();
+        assert_eq!(pending[0].text, "pricing change final");
+
+        // a window the model can't embed stops being pending until its text changes
+        db.skip_text_embeddings(
+            EmbeddingSource::Ui,
+            model,
+            &[(ui_id, "input too long".to_string())],
+        )
+        .await
+        .unwrap();
+        assert!(db
+            .pending_embeddings(EmbeddingSource::Ui, model, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let progress = db
+            .embedding_progress(EmbeddingSource::Ui, model)
+            .await
+            .unwrap();
+        assert_eq!((progress.embedded, progress.total), (1, 1));
+        sqlx::query("UPDATE ui_monitoring SET text_output = ? WHERE id = ?")
+            .bind("pricing change final, v2")
+            .bind(ui_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let pending = db
+            .pending_embeddings(EmbeddingSource::Ui, model, 10)
+            .await
+            .unwrap();
+        assert_eq!(pending[0].text, "pricing change final, v2");
+        db.insert_text_embeddings(
+            EmbeddingSource::Ui,
+            model,
+            &[(ui_id, vec![0.96, 0.28])],
+            Some(pending[0].cursor),
+        )
+        .await
+        .unwrap();
+
+        // a row matches through its closest chunk, closest first, only among vectors of
+        // the model
+        let filter = SemanticSearchFilter::default();
+        let results = db
+            .semantic_search(&[1.0, 0.0], model, &filter, 10, 0.5)
+            .await
+            .unwrap();
+        let ids: Vec<(i64, bool)> = results
+            .iter()
+            .map(|m| match &m.result {
+                SearchResult::OCR(ocr) => (ocr.frame_id, m.distance < 0.01),
+                SearchResult::Audio(audio) => (audio.transcription_id, m.distance < 0.01),
+                SearchResult::UI(ui) => (ui.id, m.distance < 0.01),
+            })
+            .collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids[..2].iter().all(|(_, exact)| *exact));
+        assert_eq!(ids[2], (ui_id, false));
+        assert!(db
+            .semantic_search(&[1.0, 0.0], "other:model", &filter, 10, 0.5)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // content type and filters
+        let filter = SemanticSearchFilter {
+            content_type: ContentType::Audio,
+            ..Default::default()
+        };
+        let results = db
+            .semantic_search(&[1.0, 0.0], model, &filter, 10, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(
+            matches!(&results[0].result, SearchResult::Audio(a) if a.transcription_id == transcription_id)
+        );
+
+        let filter = SemanticSearchFilter {
+            content_type: ContentType::Audio,
+            speaker_ids: Some(vec![12345]),
+            ..Default::default()
+        };
+        assert!(db
+            .semantic_search(&[1.0, 0.0], model, &filter, 10, 0.5)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // app filters leave audio out
+        let filter = SemanticSearchFilter {
+            app_name: Some("note".to_string()),
+            ..Default::default()
+        };
+        let results = db
+            .semantic_search(&[1.0, 0.0], model, &filter, 10, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(&results[0].result, SearchResult::UI(ui) if ui.id == ui_id));
+
+        let filter = SemanticSearchFilter {
+            end_time: Some(Utc::now() - chrono::Duration::days(1)),
+            ..Default::default()
+        };
+        assert!(db
+            .semantic_search(&[1.0, 0.0], model, &filter, 10, 0.5)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search() {
+        let db = setup_test_db().await;
+        let model = "test:model";
+
+        db.insert_video_chunk("video.mp4", "screen").await.unwrap();
+        let mut frame_ids = Vec::new();
+        for text in [
+            "the pricing change is approved",
+            "lunch menu for friday",
+            "cost update for customers",
+        ] {
+            let frame_id = db
+                .insert_frame("screen", None, None, Some("app"), Some(""), false)
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+            frame_ids.push(frame_id);
+        }
+        for (frame_id, embedding) in [
+            (frame_ids[0], vec![1.0, 0.0]),
+            (frame_ids[1], vec![0.0, 1.0]),
+            (frame_ids[2], vec![0.9, 0.1]),
+        ] {
+            db.insert_text_embeddings(EmbeddingSource::Ocr, model, &[(frame_id, embedding)], None)
+                .await
+                .unwrap();
+        }
+        let audio_chunk_id = db.insert_audio_chunk("audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        let transcription_id = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "we talked about pricing",
+                0,
+                "",
+                &device,
+                None,
+                Some(1.0),
+                Some(2.0),
+            )
+            .await
+            .unwrap();
+
+        // rows in both rankings come first, vector only rows still come up
+        let filter = SemanticSearchFilter::default();
+        let options = HybridSearchOptions {
+            recency_weight: 0.0,
+            ..Default::default()
+        };
+        let (results, total) = db
+            .search_hybrid(
+                "pricing change",
+                Some((&[1.0, 0.0], model)),
+                &filter,
+                &options,
+                10,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(total, Some(3));
+        let SearchResult::OCR(first) = &results[0].result else {
+            panic!("expected ocr first, got {:?}", results[0].result);
+        };
+        assert_eq!(first.frame_id, frame_ids[0]);
+        assert_eq!(results[0].relevance.keyword_rank, Some(1));
+        assert_eq!(results[0].relevance.vector_rank, Some(1));
+        assert_eq!(
+            results[0].relevance.snippets,
+            vec!["the <b>pricing</b> <b>change</b> is approved".to_string()]
+        );
+        let kinds: Vec<(Option<u32>, Option<u32>)> = results[1..]
+            .iter()
+            .map(|m| (m.relevance.keyword_rank, m.relevance.vector_rank))
+            .collect();
+        assert!(kinds.contains(&(None, Some(2))));
+        assert!(kinds.contains(&(Some(1), None)));
+        assert!(results
+            .windows(2)
+            .all(|w| w[0].relevance.score >= w[1].relevance.score));
+
+        // content type weights
+        let options = HybridSearchOptions {
+            recency_weight: 0.0,
+            audio_weight: 10.0,
+            ..Default::default()
+        };
+        let (results, _) = db
+            .search_hybrid("pricing", None, &filter, &options, 10, 0)
+            .await
+            .unwrap();
+        assert!(
+            matches!(&results[0].result, SearchResult::Audio(a) if a.transcription_id == transcription_id)
+        );
+        assert_eq!(results.len(), 2);
+
+        // paging
+        let (results, total) = db
+            .search_hybrid(
+                "pricing change",
+                Some((&[1.0, 0.0], model)),
+                &filter,
+                &options,
+                1,
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!((results.len(), total), (1, Some(3)));
+
+        // with more matches than candidates the total is unknown
+        let (results, total) = db
+            .search_hybrid(
+                "pricing change",
+                Some((&[1.0, 0.0], model)),
+                &filter,
+                &HybridSearchOptions {
+                    candidates: 1,
+                    ..options.clone()
+                },
+                1,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!((results.len(), total), (1, None));
+
+        // recency boost puts the newer of two equal matches first
+        let mut ui_ids = Vec::new();
+        for timestamp in [Utc::now() - chrono::Duration::days(2), Utc::now()] {
+            let id = sqlx::query_scalar::<_, i64>(
+                "INSERT INTO ui_monitoring (text_output, timestamp, app, window) VALUES (?, ?, ?, ?) RETURNING id",
+            )
+            .bind("quarterly roadmap")
+            .bind(timestamp)
+            .bind("notes")
+            .bind("roadmap.md")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+            ui_ids.push(id);
+        }
+        let options = HybridSearchOptions {
+            recency_weight: 10.0,
+            recency_half_life_hours: 1.0,
+            ..Default::default()
+        };
+        let filter = SemanticSearchFilter {
+            content_type: ContentType::UI,
+            ..Default::default()
+        };
+        let (results, _) = db
+            .search_hybrid("roadmap", None, &filter, &options, 10, 0)
+            .await
+            .unwrap();
+        let ids: Vec<i64> = results
+            .iter()
+            .map(|m| match &m.result {
+                SearchResult::UI(ui) => ui.id,
+                other => panic!("expected ui, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(ids, vec![ui_ids[1], ui_ids[0]]);
+
+        // fts syntax in the query is taken as plain words
+        let (results, _) = db
+            .search_hybrid(
+                "\"pricing AND (",
+                None,
+                &SemanticSearchFilter::default(),
+                &options,
+                10,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_page() {
+        let db = setup_test_db().await;
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let at = |seconds: i64| t0 + chrono::Duration::seconds(seconds);
+
+        db.insert_video_chunk("video.mp4", "screen").await.unwrap();
+        let mut expected = Vec::new();
+        for seconds in [0, 3] {
+            let frame_id = db
+                .insert_frame("screen", Some(at(seconds)), None, None, None, false)
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                "budget review",
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+            expected.push((at(seconds), 0, frame_id));
+        }
+        let audio_chunk_id = db.insert_audio_chunk("audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        for (offset_index, seconds) in [(0, 1), (1, 3)] {
+            let transcription_id = db
+                .insert_audio_transcription_at(
+                    audio_chunk_id,
+                    "the budget is tight",
+                    offset_index,
+                    "",
+                    &device,
+                    None,
+                    None,
+                    None,
+                    at(seconds),
+                )
+                .await
+                .unwrap();
+            expected.push((at(seconds), 1, transcription_id));
+        }
+        for (window, seconds) in [("first", 2), ("second", 3)] {
+            let ui_id: i64 = sqlx::query_scalar(
+                r#"
+                INSERT INTO ui_monitoring (text_output, timestamp, app, window, initial_traversal_at)
+                VALUES ('budget sheet', ?1, 'sheets', ?2, ?1)
+                RETURNING id
+                "#,
+            )
+            .bind(at(seconds))
+            .bind(window)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+            expected.push((at(seconds), 2, ui_id));
+        }
+        expected.sort();
+
+        let key = |result: &SearchResult| match result {
+            SearchResult::OCR(ocr) => (ocr.timestamp, 0, ocr.frame_id),
+            SearchResult::Audio(audio) => (audio.timestamp, 1, audio.transcription_id),
+            SearchResult::UI(ui) => (ui.timestamp, 2, ui.id),
+        };
+
+        for order in [Order::Descending, Order::Ascending] {
+            let mut seen = Vec::new();
+            let mut cursor: Option<SearchCursor> = None;
+            loop {
+                let page = db
+                    .search_page(
+                        "budget",
+                        ContentType::All,
+                        2,
+                        0,
+                        cursor.as_ref(),
+                        &order,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap();
+                assert!(page.results.len() <= 2);
+                seen.extend(page.results.iter().map(key));
+
+                // content recorded while paging doesn't shift the pages
+                if cursor.is_none() {
+                    let frame_id = db
+                        .insert_frame("screen", Some(at(60)), None, None, None, false)
+                        .await
+                        .unwrap();
+                    db.insert_ocr_text(
+                        frame_id,
+                        "budget later",
+                        "",
+                        Arc::new(OcrEngine::Tesseract),
+                    )
+                    .await
+                    .unwrap();
+                }
+
+                // cursors survive the round trip through their string form
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next.to_string().parse().unwrap()),
+                    None => break,
+                }
+            }
+
+            let mut wanted = expected.clone();
+            if order == Order::Descending {
+                // the frame recorded after the first page is newer than the cursor
+                wanted.reverse();
+            } else {
+                // both frames recorded at 60s come up on the last page
+                let later: Vec<_> = seen.iter().filter(|k| k.0 == at(60)).cloned().collect();
+                assert_eq!(later.len(), 2);
+                wanted.extend(later);
+            }
+            assert_eq!(seen, wanted, "order {:?}", order);
+        }
+
+        assert!("not a cursor".parse::<SearchCursor>().is_err());
+
+        // offset pagination merges the streams by timestamp too
+        let results = db
+            .search(
+                "budget",
+                ContentType::All,
+                3,
+                2,
+                None,
+                Some(at(3)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let keys: Vec<_> = results.iter().map(key).collect();
+        let mut newest_first = expected.clone();
+        newest_first.reverse();
+        assert_eq!(keys, newest_first[2..5].to_vec());
+
+        // transcriptions sharing a chunk offset come back as one result, the latest, with
+        // or without a cursor and in either order
+        let audio_chunk_id = db.insert_audio_chunk("overlap.mp4").await.unwrap();
+        let mut overlapping = Vec::new();
+        for (text, seconds) in [("budget from the mic", 100), ("budget on the call", 101)] {
+            overlapping.push(
+                db.insert_audio_transcription_at(
+                    audio_chunk_id,
+                    text,
+                    0,
+                    "",
+                    &device,
+                    None,
+                    None,
+                    None,
+                    at(seconds),
+                )
+                .await
+                .unwrap(),
+            );
+        }
+        let unpaged: Vec<i64> = db
+            .search(
+                "budget",
+                ContentType::Audio,
+                10,
+                0,
+                Some(at(100)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|result| key(result).2)
+            .collect();
+        assert_eq!(unpaged, vec![overlapping[1]]);
+        for order in [Order::Ascending, Order::Descending] {
+            let mut seen = Vec::new();
+            let mut cursor: Option<SearchCursor> = None;
+            loop {
+                let page = db
+                    .search_page(
+                        "budget",
+                        ContentType::Audio,
+                        1,
+                        0,
+                        cursor.as_ref(),
+                        &order,
+                        Some(at(100)),
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap();
+                seen.extend(page.results.iter().map(|result| key(result).2));
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+            assert_eq!(seen, unpaged);
+        }
+
+        // offsets near u32::MAX don't overflow the row counts asked of each query
+        let page = db
+            .search_page(
+                "",
+                ContentType::All,
+                10,
+                u32::MAX,
+                None,
+                &Order::Descending,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(page.results.is_empty());
+        assert!(db
+            .search(
+                "",
+                ContentType::All,
+                10,
+                u32::MAX,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_search() {
+        let db = setup_test_db().await;
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let at = |seconds: i64| t0 + chrono::Duration::seconds(seconds);
+
+        db.insert_video_chunk("video.mp4", "screen").await.unwrap();
+        for (seconds, app, url) in [
+            (0, "Figma", Some("https://www.figma.com/file/1")),
+            (10, "Figma", Some("https://figma.com/file/2?node=3")),
+            (20, "Slack", None),
+            // 180s after the previous frame, which only counts the 60s gap limit
+            (200, "Figma", None),
+        ] {
+            let frame_id = db
+                .insert_frame("screen", Some(at(seconds)), url, Some(app), Some(""), false)
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                "design review",
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+        }
+
+        let audio_chunk_id = db
+            .insert_audio_chunk_at("audio.mp4", at(3600))
+            .await
+            .unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        db.update_speaker_name(speaker.id, "Ada").await.unwrap();
+        for (offset_index, speaker_id, start, end) in [
+            (0, Some(speaker.id), 0.0, 5.0),
+            (1, Some(speaker.id), 5.0, 8.0),
+            (2, None, 8.0, 9.0),
+        ] {
+            db.insert_audio_transcription_at(
+                audio_chunk_id,
+                "design review notes",
+                offset_index,
+                "",
+                &device,
+                speaker_id,
+                Some(start),
+                Some(end),
+                at(3600 + offset_index),
+            )
+            .await
+            .unwrap();
+        }
+
+        sqlx::query(
+            "INSERT INTO ui_monitoring (text_output, timestamp, app, window, initial_traversal_at)
+            VALUES ('design review', ?1, 'Figma', 'canvas', ?1)",
+        )
+        .bind(at(30))
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let aggregate = db
+            .aggregate_search(
+                "",
+                ContentType::All,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                &AggregateOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            (
+                aggregate.ocr_count,
+                aggregate.audio_count,
+                aggregate.ui_count
+            ),
+            (4, 3, 1)
+        );
+        assert_eq!(aggregate.screen_seconds, 80.0);
+        assert_eq!(aggregate.speech_seconds, 9.0);
+        let apps: Vec<(&str, i64, f64)> = aggregate
+            .apps
+            .iter()
+            .map(|facet| (facet.value.as_str(), facet.count, facet.seconds))
+            .collect();
+        assert_eq!(apps, vec![("Slack", 1, 60.0), ("Figma", 4, 20.0)]);
+        assert_eq!(aggregate.domains.len(), 1);
+        assert_eq!(aggregate.domains[0].value, "figma.com");
+        assert_eq!(aggregate.domains[0].count, 2);
+        let speakers: Vec<(Option<i64>, Option<&str>, i64, f64)> = aggregate
+            .speakers
+            .iter()
+            .map(|s| (s.speaker_id, s.name.as_deref(), s.count, s.seconds))
+            .collect();
+        assert_eq!(
+            speakers,
+            vec![
+                (Some(speaker.id), Some("Ada"), 2, 8.0),
+                (None, None, 1, 1.0)
+            ]
+        );
+        let devices: Vec<&str> = aggregate.devices.iter().map(|d| d.value.as_str()).collect();
+        assert_eq!(devices, vec!["screen", "mic"]);
+
+        assert_eq!(aggregate.histogram.len(), 2);
+        let first = &aggregate.histogram[0];
+        assert_eq!(first.start, t0);
+        assert_eq!(
+            (first.ocr_count, first.ui_count, first.audio_count),
+            (4, 1, 0)
+        );
+        let second = &aggregate.histogram[1];
+        assert_eq!(second.start, at(3600));
+        assert_eq!(second.audio_count, 3);
+        assert_eq!(second.speech_seconds, 9.0);
+
+        // filters match search, screen time still runs until the next frame of any app
+        let aggregate = db
+            .aggregate_search(
+                "",
+                ContentType::All,
+                None,
+                None,
+                Some("Figma"),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                &AggregateOptions {
+                    bucket_seconds: 15,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            (
+                aggregate.ocr_count,
+                aggregate.audio_count,
+                aggregate.ui_count
+            ),
+            (3, 0, 1)
+        );
+        assert_eq!(aggregate.screen_seconds, 20.0);
+        let starts: Vec<_> = aggregate.histogram.iter().map(|b| b.start).collect();
+        assert_eq!(starts, vec![at(0), at(30), at(195)]);
+    }
+
+    #[tokio::test]
+    async fn test_activity_sessions() {
+        let db = setup_test_db().await;
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let at = |seconds: i64| t0 + chrono::Duration::seconds(seconds);
+
+        db.insert_video_chunk("video.mp4", "screen").await.unwrap();
+        for (seconds, app, window, url, focused) in [
+            (0, "editor", "main.rs", None, true),
+            (10, "editor", "main.rs", None, true),
+            // other windows of the same capture don't count
+            (10, "chat", "general", None, false),
+            (20, "editor", "main.rs", None, true),
+            (30, "browser", "docs", Some("https://docs.rs/sqlx"), true),
+            (40, "browser", "docs", Some("https://docs.rs/chrono"), true),
+            // back after the screen was idle for 16 minutes
+            (1000, "editor", "main.rs", None, true),
+        ] {
+            db.insert_frame(
+                "screen",
+                Some(at(seconds)),
+                url,
+                Some(app),
+                Some(window),
+                focused,
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(db.update_activity_sessions().await.unwrap(), 7);
+        let report = db
+            .activity_report(at(0), at(2000), ActivityGroupBy::App, true)
+            .await
+            .unwrap();
+        let sessions: Vec<(&str, DateTime<Utc>, DateTime<Utc>, i64)> = report
+            .sessions
+            .iter()
+            .map(|s| (s.app_name.as_str(), s.start_time, s.end_time, s.frame_count))
+            .collect();
+        assert_eq!(
+            sessions,
+            vec![
+                ("editor", at(0), at(30), 3),
+                ("browser", at(30), at(40), 2),
+                ("editor", at(1000), at(1000), 1),
+            ]
+        );
+
+        // new frames extend the latest session instead of adding one
+        db.insert_frame(
+            "screen",
+            Some(at(1010)),
+            None,
+            Some("editor"),
+            Some("main.rs"),
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.update_activity_sessions().await.unwrap(), 1);
+        assert_eq!(db.update_activity_sessions().await.unwrap(), 0);
+        let report = db
+            .activity_report(at(0), at(2000), ActivityGroupBy::App, false)
+            .await
+            .unwrap();
+        assert!(report.sessions.is_empty());
+        assert_eq!(report.total_seconds, 50.0);
+        let groups: Vec<(&str, f64, i64)> = report
+            .groups
+            .iter()
+            .map(|g| (g.name.as_str(), g.seconds, g.sessions))
+            .collect();
+        assert_eq!(groups, vec![("editor", 40.0, 2), ("browser", 10.0, 1)]);
+
+        // sessions crossing the range only count their part inside it
+        let report = db
+            .activity_report(at(20), at(35), ActivityGroupBy::Window, false)
+            .await
+            .unwrap();
+        let groups: Vec<(&str, Option<&str>, f64)> = report
+            .groups
+            .iter()
+            .map(|g| (g.name.as_str(), g.app_name.as_deref(), g.seconds))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                ("main.rs", Some("editor"), 10.0),
+                ("docs", Some("browser"), 5.0)
+            ]
+        );
+        let report = db
+            .activity_report(at(0), at(2000), ActivityGroupBy::Domain, false)
+            .await
+            .unwrap();
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.groups[0].name, "docs.rs");
+
+        // forgetting frames forgets the sessions they made
+        let plan = db
+            .plan_forget(&ForgetFilter {
+                start_time: at(35),
+                end_time: at(45),
+                app_name: Some("browser".to_string()),
+                window_name: None,
+                browser_url: None,
+                device_name: None,
+            })
+            .await
+            .unwrap();
+        db.apply_forget(&plan).await.unwrap();
+        let report = db
+            .activity_report(at(0), at(2000), ActivityGroupBy::App, false)
+            .await
+            .unwrap();
+        let apps: Vec<&str> = report.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(apps, vec!["editor"]);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_database() {
+        let path = std::env::temp_dir().join(format!("read_only_{}.sqlite", rand::random::<u32>()));
+        let path = path.to_string_lossy().to_string();
+
+        // nothing to open yet, and nothing gets created
+        assert!(DatabaseManager::new_read_only(&path).await.is_err());
+
+        let db = DatabaseManager::new(&path).await.unwrap();
+        assert!(!db.is_read_only());
+        let chunk_id = db.insert_audio_chunk("mic.mp4").await.unwrap();
+
+        let reader = DatabaseManager::new_read_only(&path).await.unwrap();
+        assert!(reader.is_read_only());
+        assert_eq!(
+            reader.get_audio_chunk_path(chunk_id).await.unwrap(),
+            Some("mic.mp4".to_string())
+        );
+        assert!(reader.insert_audio_chunk("other.mp4").await.is_err());
+        assert_eq!(reader.update_activity_sessions().await.unwrap(), 0);
+
+        // the reader sees what is recorded after it opened
+        let later_id = db.insert_audio_chunk("later.mp4").await.unwrap();
+        assert_eq!(
+            reader.get_audio_chunk_path(later_id).await.unwrap(),
+            Some("later.mp4".to_string())
+        );
+
+        reader.pool.close().await;
+        db.pool.close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_journal() {
+        let db = setup_test_db().await;
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+        assert_eq!(db.latest_journal_seq().await.unwrap(), 0);
+
+        let first = db
+            .insert_journal_event("meeting_started", &serde_json::json!({"app": "zoom"}), t0)
+            .await
+            .unwrap();
+        let second = db
+            .insert_journal_event(
+                "transcription",
+                &serde_json::json!({"text": "hello"}),
+                t0 + chrono::Duration::minutes(5),
+            )
+            .await
+            .unwrap();
+        let third = db
+            .insert_journal_event(
+                "meeting_ended",
+                &serde_json::json!({}),
+                t0 + chrono::Duration::minutes(10),
+            )
+            .await
+            .unwrap();
+        assert!(first < second && second < third);
+        assert_eq!(db.latest_journal_seq().await.unwrap(), third);
+
+        let events = db
+            .get_journal_events(JournalPosition::After(first), 100)
+            .await
+            .unwrap();
+        assert_eq!(
+            events.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![second, third]
+        );
+        assert_eq!(events[0].name, "transcription");
+        assert_eq!(events[0].data["text"], "hello");
+
+        let page = db
+            .get_journal_events(JournalPosition::After(0), 1)
+            .await
+            .unwrap();
+        assert_eq!(page[0].seq, first);
+
+        let since = db
+            .get_journal_events(
+                JournalPosition::Since(t0 + chrono::Duration::minutes(5)),
+                100,
+            )
+            .await
+            .unwrap();
+        assert_eq!(since.len(), 2);
+        assert_eq!(since[0].seq, second);
+
+        assert_eq!(
+            "42".parse::<JournalPosition>().unwrap(),
+            JournalPosition::After(42)
+        );
+        assert_eq!(
+            "2024-05-01T09:05:00Z".parse::<JournalPosition>().unwrap(),
+            JournalPosition::Since(t0 + chrono::Duration::minutes(5))
+        );
+        assert!("yesterday".parse::<JournalPosition>().is_err());
+
+        // deleting old events doesn't reuse their sequence numbers
+        assert_eq!(
+            db.delete_journal_events_before(t0 + chrono::Duration::minutes(10))
+                .await
+                .unwrap(),
+            2
+        );
+        let next = db
+            .insert_journal_event("meeting_started", &serde_json::json!({}), t0)
+            .await
+            .unwrap();
+        assert!(next > third);
+        let remaining = db
+            .get_journal_events(JournalPosition::After(0), 100)
+            .await
+            .unwrap();
+        assert_eq!(
+            remaining.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![third, next]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forget_and_retention_delete_journaled_content() {
+        let db = setup_test_db().await;
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+        let at = |minutes: i64| t0 + chrono::Duration::minutes(minutes);
+        let ocr = |app: &str, minutes: i64| {
+            serde_json::json!({
+                "app_name": app,
+                "window_name": "inbox",
+                "text": format!("{} text", app),
+                "timestamp": at(minutes).timestamp_millis(),
+                "browser_url": null,
+            })
+        };
+        let events = [
+            ("ocr_result", ocr("bank", 5)),
+            ("ocr_result", ocr("notes", 5)),
+            ("ocr_result", ocr("bank", 30)),
+            (
+                "transcription",
+                serde_json::json!({"timestamp": at(5), "device": "mic", "transcription": "pin 1234"}),
+            ),
+            (
+                "ui_frame",
+                serde_json::json!({"app": "bank", "window": "inbox", "text_output": "balance"}),
+            ),
+            ("meeting_started", serde_json::json!({"app": "zoom"})),
+        ];
+        for (name, data) in &events {
+            // journaled after the forgotten range ends, only the time they carry falls in
+            // it. `ui_frame` carries none and goes by when it was journaled.
+            let journaled = if *name == "ui_frame" { at(6) } else { at(12) };
+            db.insert_journal_event(name, data, journaled)
+                .await
+                .unwrap();
+        }
+        async fn replay(db: &DatabaseManager) -> Vec<(String, String)> {
+            db.get_journal_events(JournalPosition::After(0), 100)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|e| {
+                    let app = e.data["app_name"].as_str().unwrap_or_default().to_string();
+                    (e.name, app)
+                })
+                .collect()
+        }
+
+        // forgetting an app keeps audio and other apps, and the plan is empty of rows
+        let filter = ForgetFilter {
+            start_time: at(0),
+            end_time: at(10),
+            app_name: Some("bank".to_string()),
+            window_name: None,
+            browser_url: None,
+            device_name: None,
+        };
+        let plan = db.plan_forget(&filter).await.unwrap();
+        assert!(plan.is_empty());
+        db.apply_forget(&plan).await.unwrap();
+        let remaining = replay(&db).await;
+        assert_eq!(
+            remaining,
+            vec![
+                ("ocr_result".to_string(), "notes".to_string()),
+                ("ocr_result".to_string(), "bank".to_string()),
+                ("transcription".to_string(), String::new()),
+                ("meeting_started".to_string(), String::new()),
+            ]
+        );
+
+        // a whole time range takes the transcription too
+        let filter = ForgetFilter {
+            app_name: None,
+            ..filter
+        };
+        db.apply_forget(&db.plan_forget(&filter).await.unwrap())
+            .await
+            .unwrap();
+        let remaining = replay(&db).await;
+        assert_eq!(
+            remaining,
+            vec![
+                ("ocr_result".to_string(), "bank".to_string()),
+                ("meeting_started".to_string(), String::new()),
+            ]
+        );
+
+        // so does a retention rule for the app, once the content is old enough
+        let policy = RetentionPolicy {
+            rules: vec![RetentionRule {
+                app_name: Some("bank".to_string()),
+                window_name: None,
+                max_age_days: 1,
+            }],
+            ..Default::default()
+        };
+        let plan = db.plan_retention(&policy, at(60 * 24 + 40)).await.unwrap();
+        for content in &plan.journal_content {
+            db.delete_journaled_content(content).await.unwrap();
+        }
+        let remaining = replay(&db).await;
+        assert_eq!(
+            remaining,
+            vec![("meeting_started".to_string(), String::new())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhooks_and_dead_letters() {
+        let db = setup_test_db().await;
+
+        let meetings = db
+            .create_webhook(
+                "http://localhost:9000/hook",
+                &["meeting_ended".to_string()],
+                "secret-1",
+            )
+            .await
+            .unwrap();
+        let all = db
+            .create_webhook("http://localhost:9001/hook", &[], "secret-2")
+            .await
+            .unwrap();
+
+        let webhooks = db.list_webhooks_with_secrets().await.unwrap();
+        assert_eq!(webhooks.len(), 2);
+        assert_eq!(webhooks[0], (meetings.clone(), "secret-1".to_string()));
+        assert_eq!(webhooks[1].0.events, Vec::<String>::new());
+        assert_eq!(
+            db.list_webhooks().await.unwrap(),
+            vec![meetings.clone(), all.clone()]
+        );
+
+        let body = serde_json::json!({"name": "meeting_ended", "data": {"app": "zoom"}});
+        let first = db
+            .insert_webhook_dead_letter(meetings.id, "meeting_ended", &body, 5, "HTTP 500")
+            .await
+            .unwrap();
+        let second = db
+            .insert_webhook_dead_letter(all.id, "transcription", &body, 1, "HTTP 404")
+            .await
+            .unwrap();
+
+        let letters = db.list_webhook_dead_letters(None, 10).await.unwrap();
+        assert_eq!(
+            letters.iter().map(|l| l.id).collect::<Vec<_>>(),
+            vec![second, first]
+        );
+        let letters = db
+            .list_webhook_dead_letters(Some(meetings.id), 10)
+            .await
+            .unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].body, body);
+        assert_eq!(letters[0].attempts, 5);
+        assert_eq!(letters[0].last_error, "HTTP 500");
+
+        assert!(db.delete_webhook_dead_letter(second).await.unwrap());
+        assert!(!db.delete_webhook_dead_letter(second).await.unwrap());
+        assert!(db.get_webhook_dead_letter(second).await.unwrap().is_none());
+
+        // deleting a webhook drops its dead letters
+        assert!(db.delete_webhook(meetings.id).await.unwrap());
+        assert!(!db.delete_webhook(meetings.id).await.unwrap());
+        assert!(db.get_webhook_dead_letter(first).await.unwrap().is_none());
+        assert_eq!(db.list_webhooks().await.unwrap(), vec![all]);
+    }
 }
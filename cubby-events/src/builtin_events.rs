@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use oasgen::OaSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::MeetingEvent;
+
+/// Payload of `transcription`, text heard on an audio device while it is transcribed
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RealtimeTranscriptionEvent {
+    pub timestamp: DateTime<Utc>,
+    pub device: String,
+    pub transcription: String,
+    /// `false` while the text may still change
+    pub is_final: bool,
+    /// Heard on a microphone rather than on the computer's output
+    pub is_input: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+/// Payload of `ocr_result`, text recognized in a window of a captured frame. Mirrors how
+/// `cubby_vision::core::WindowOcr` serializes, cubby-vision's tests check they agree.
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct OcrResultEvent {
+    /// Base64 jpeg of the frame, only with `--realtime-vision-include-image` and left out
+    /// of `/ws/events` unless it asks for `images`
+    pub image: Option<String>,
+    pub window_name: String,
+    pub app_name: String,
+    pub text: String,
+    /// Text blocks with their position
+    pub text_json: Vec<HashMap<String, String>>,
+    pub focused: bool,
+    pub confidence: f64,
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+    pub browser_url: Option<String>,
+}
+
+/// Payload of `ui_frame`, text of a window read from its accessibility tree. Mirrors
+/// `cubby_vision::UIFrame`, checked by cubby-vision's tests.
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct UiFrameEvent {
+    pub window: String,
+    pub app: String,
+    pub text_output: String,
+    pub initial_traversal_at: String,
+}
+
+/// Payload of `events_dropped`
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct EventsDroppedEvent {
+    /// Events the subscriber lost
+    pub missed: u64,
+}
+
+/// Events cubby sends by itself, in the `{"name": ..., "data": ...}` shape of
+/// `/ws/events`. Index jobs also send `index_job_progress` and `index_job_finished` with
+/// an `IndexJob`, and embeddings `embedding_progress` with an `EmbeddingProgress`.
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "name", content = "data", rename_all = "snake_case")]
+pub enum BuiltinEvent {
+    Transcription(RealtimeTranscriptionEvent),
+    OcrResult(OcrResultEvent),
+    UiFrame(UiFrameEvent),
+    MeetingStarted(MeetingEvent),
+    MeetingEnded(MeetingEvent),
+    EventsDropped(EventsDroppedEvent),
+}
@@ -0,0 +1,507 @@
+use crate::{send_event, subscribe_to_all_events, EventFilter, EVENTS_DROPPED};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use oasgen::OaSchema;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, error, info, warn};
+
+use super::meetings::meeting_detectors;
+
+/// How often the detectors directory is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Events detectors look at unless they say otherwise
+const DEFAULT_SOURCES: &[&str] = &["ocr_result", "ui_frame", "transcription"];
+
+/// Fields `distinct` can count
+const DISTINCT_FIELDS: &[&str] = &["app", "window", "text", "speaker", "device"];
+
+/// Sends an event when an event of the bus matches its conditions. Patterns are regexes
+/// that ignore case unless they start with `(?-i)`, and conditions that are left out
+/// always hold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Detector {
+    /// Names the detector in the events it sends. A detector with the name of a built-in
+    /// one replaces it.
+    pub name: String,
+    /// Event sent when the detector matches
+    pub event: String,
+    pub enabled: bool,
+    /// Events looked at, names or patterns such as `ocr_*`. By default `ocr_result`,
+    /// `ui_frame` and `transcription`.
+    pub on: Vec<String>,
+    /// Pattern for the app, from `app_name` or `app`
+    pub app: Option<String>,
+    /// Pattern for the window title, from `window_name` or `window`
+    pub window: Option<String>,
+    /// Pattern for the text, from `text`, `text_output` or `transcription`
+    pub text: Option<String>,
+    /// Words or phrases of which the text must contain one, ignoring case
+    pub keywords: Vec<String>,
+    pub speaker: Option<String>,
+    /// Pattern for the audio device of transcriptions
+    pub device: Option<String>,
+    /// Skip transcriptions that may still change
+    pub final_only: bool,
+    /// Matching events needed before the detector fires
+    pub min_count: u32,
+    /// Matching events older than this are forgotten, never when 0
+    pub within_secs: u64,
+    /// Count the different values of this field among the matching events instead of the
+    /// events, one of `app`, `window`, `text`, `speaker` and `device`
+    pub distinct: Option<String>,
+    /// States the detector needs, states never set are false
+    pub when: BTreeMap<String, bool>,
+    /// How long the states in `when` must have held
+    pub settled_secs: u64,
+    /// States set when the detector fires
+    pub set: BTreeMap<String, bool>,
+    /// Least time between two events of the detector
+    pub debounce_secs: u64,
+    /// Fields added to the events sent, over those of `DetectedEvent`
+    pub data: Map<String, Value>,
+}
+
+impl Default for Detector {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            event: String::new(),
+            enabled: true,
+            on: Vec::new(),
+            app: None,
+            window: None,
+            text: None,
+            keywords: Vec::new(),
+            speaker: None,
+            device: None,
+            final_only: false,
+            min_count: 1,
+            within_secs: 0,
+            distinct: None,
+            when: BTreeMap::new(),
+            settled_secs: 0,
+            set: BTreeMap::new(),
+            debounce_secs: 0,
+            data: Map::new(),
+        }
+    }
+}
+
+/// A file of detectors, `[[detectors]]` tables in TOML or a `detectors` array in JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetectorFile {
+    #[serde(default)]
+    pub detectors: Vec<Detector>,
+}
+
+impl DetectorFile {
+    pub fn from_toml(source: &str) -> Result<Self> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn from_json(source: &str) -> Result<Self> {
+        Ok(serde_json::from_str(source)?)
+    }
+
+    /// Reads a `.toml` or `.json` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let file = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&source),
+            Some("json") => Self::from_json(&source),
+            _ => bail!("{} is neither a .toml nor a .json file", path.display()),
+        };
+        file.with_context(|| format!("invalid detectors in {}", path.display()))
+    }
+}
+
+/// Payload of the events sent by detectors, along with the detector's `data`
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedEvent {
+    pub detector: String,
+    /// Name of the event that matched
+    pub source: String,
+    /// App of the event that matched, empty when it has none
+    pub app: String,
+    /// Window title of the event that matched
+    pub title: Option<String>,
+    pub speaker: Option<String>,
+    pub device: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// What detectors read from an event, whichever producer sent it
+struct Fields<'a> {
+    app: Option<&'a str>,
+    window: Option<&'a str>,
+    text: Option<&'a str>,
+    speaker: Option<&'a str>,
+    device: Option<&'a str>,
+    is_final: bool,
+}
+
+impl<'a> Fields<'a> {
+    fn of(data: &'a Value) -> Self {
+        let field = |keys: &[&str]| keys.iter().find_map(|key| data.get(key)?.as_str());
+        Self {
+            app: field(&["app_name", "app"]),
+            window: field(&["window_name", "window"]),
+            text: field(&["text", "text_output", "transcription"]),
+            speaker: field(&["speaker"]),
+            device: field(&["device"]),
+            // only transcriptions say whether they are final
+            is_final: ["isFinal", "is_final"]
+                .iter()
+                .find_map(|key| data.get(key)?.as_bool())
+                .unwrap_or(true),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&'a str> {
+        match name {
+            "app" => self.app,
+            "window" => self.window,
+            "text" => self.text,
+            "speaker" => self.speaker,
+            "device" => self.device,
+            _ => None,
+        }
+    }
+}
+
+struct CompiledDetector {
+    detector: Detector,
+    on: EventFilter,
+    app: Option<Regex>,
+    window: Option<Regex>,
+    text: Option<Regex>,
+    speaker: Option<Regex>,
+    device: Option<Regex>,
+    keywords: Vec<String>,
+    /// Matching events not yet turned into an event, with their `distinct` value, which
+    /// are unique
+    hits: VecDeque<(Instant, Option<String>)>,
+    last_fired: Option<Instant>,
+}
+
+impl CompiledDetector {
+    fn new(detector: Detector) -> Result<Self> {
+        if detector.name.is_empty() {
+            bail!("detectors need a name");
+        }
+        if detector.event.is_empty() {
+            bail!("detector {} has no event to send", detector.name);
+        }
+        if detector.data.contains_key("detector") {
+            bail!(
+                "detector {} can't replace the detector of its events",
+                detector.name
+            );
+        }
+        if let Some(distinct) = &detector.distinct {
+            if !DISTINCT_FIELDS.contains(&distinct.as_str()) {
+                bail!(
+                    "detector {} can't count distinct '{}', expected one of: {}",
+                    detector.name,
+                    distinct,
+                    DISTINCT_FIELDS.join(", ")
+                );
+            }
+        }
+
+        let pattern = |pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(|pattern| {
+                    RegexBuilder::new(pattern)
+                        .case_insensitive(true)
+                        .build()
+                        .map_err(|e| {
+                            anyhow!("invalid pattern in detector {}: {}", detector.name, e)
+                        })
+                })
+                .transpose()
+        };
+        let on = if detector.on.is_empty() {
+            DEFAULT_SOURCES
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        } else {
+            detector.on.clone()
+        };
+
+        Ok(Self {
+            on: EventFilter {
+                events: on,
+                ..Default::default()
+            },
+            app: pattern(&detector.app)?,
+            window: pattern(&detector.window)?,
+            text: pattern(&detector.text)?,
+            speaker: pattern(&detector.speaker)?,
+            device: pattern(&detector.device)?,
+            keywords: detector.keywords.iter().map(|k| k.to_lowercase()).collect(),
+            hits: VecDeque::new(),
+            last_fired: None,
+            detector,
+        })
+    }
+
+    /// Whether an event meets the conditions on its content
+    fn matches(&self, name: &str, data: &Value, fields: &Fields) -> bool {
+        // `EventFilter` lets `events_dropped` through for subscribers, detectors don't care
+        if name == EVENTS_DROPPED || !self.on.matches(name, data) {
+            return false;
+        }
+        if self.detector.final_only && !fields.is_final {
+            return false;
+        }
+
+        let holds = |pattern: &Option<Regex>, value: Option<&str>| match pattern {
+            Some(pattern) => value.is_some_and(|value| pattern.is_match(value)),
+            None => true,
+        };
+        if !holds(&self.app, fields.app)
+            || !holds(&self.window, fields.window)
+            || !holds(&self.text, fields.text)
+            || !holds(&self.speaker, fields.speaker)
+            || !holds(&self.device, fields.device)
+        {
+            return false;
+        }
+
+        self.keywords.is_empty()
+            || fields.text.is_some_and(|text| {
+                let text = text.to_lowercase();
+                self.keywords.iter().any(|keyword| text.contains(keyword))
+            })
+    }
+
+    /// Records a matching event and tells whether enough of them were seen
+    fn count(&mut self, fields: &Fields, now: Instant) -> bool {
+        let within = Duration::from_secs(self.detector.within_secs);
+        if !within.is_zero() {
+            while self
+                .hits
+                .front()
+                .is_some_and(|(at, _)| now.duration_since(*at) > within)
+            {
+                self.hits.pop_front();
+            }
+        }
+
+        let distinct = self.detector.distinct.as_deref();
+        let value = distinct
+            .and_then(|field| fields.get(field))
+            .map(String::from);
+        // events without the counted field don't count
+        if distinct.is_some() && value.is_none() {
+            return false;
+        }
+        let needed = self.detector.min_count.max(1) as usize;
+        if distinct.is_some() {
+            // the latest event of each value is enough
+            self.hits.retain(|(_, seen)| *seen != value);
+        }
+        self.hits.push_back((now, value));
+        if distinct.is_none() && self.hits.len() > needed {
+            self.hits.pop_front();
+        }
+
+        self.hits.len() >= needed
+    }
+}
+
+/// A state and when it last changed, `None` when it never did
+type State = (bool, Option<Instant>);
+
+/// Runs detectors over events, keeping their states between events. Detectors are
+/// checked in order, the states one of them sets apply to the next ones.
+pub struct DetectorEngine {
+    detectors: Vec<CompiledDetector>,
+    states: HashMap<String, State>,
+}
+
+impl DetectorEngine {
+    pub fn new(detectors: Vec<Detector>) -> Result<Self> {
+        let mut engine = Self {
+            detectors: Vec::new(),
+            states: HashMap::new(),
+        };
+        engine.set_detectors(detectors)?;
+        Ok(engine)
+    }
+
+    /// Replaces the detectors, states are kept. Nothing changes when one of them is
+    /// invalid.
+    pub fn set_detectors(&mut self, detectors: Vec<Detector>) -> Result<()> {
+        self.detectors = detectors
+            .into_iter()
+            .filter(|detector| detector.enabled)
+            .map(CompiledDetector::new)
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    pub fn state(&self, name: &str) -> bool {
+        self.states.get(name).is_some_and(|(value, _)| *value)
+    }
+
+    /// Events to send for an event of the bus seen at `now`. Events sent by detectors are
+    /// skipped, a detector matching its own events would send them forever.
+    pub fn process(&mut self, name: &str, data: &Value, now: Instant) -> Vec<(String, Value)> {
+        if data.get("detector").is_some() {
+            return Vec::new();
+        }
+        let fields = Fields::of(data);
+        let mut events = Vec::new();
+
+        for compiled in &mut self.detectors {
+            if !compiled.matches(name, data, &fields) || !compiled.count(&fields, now) {
+                continue;
+            }
+
+            let detector = &compiled.detector;
+            let settled = Duration::from_secs(detector.settled_secs);
+            let states_hold = detector.when.iter().all(|(state, expected)| {
+                let (value, changed_at) = self.states.get(state).copied().unwrap_or_default();
+                value == *expected && changed_at.is_none_or(|at| now.duration_since(at) >= settled)
+            });
+            let debounce = Duration::from_secs(detector.debounce_secs);
+            let debounced = compiled
+                .last_fired
+                .is_some_and(|at| now.duration_since(at) < debounce);
+            if !states_hold || debounced {
+                continue;
+            }
+
+            compiled.hits.clear();
+            compiled.last_fired = Some(now);
+            for (state, value) in &detector.set {
+                let current = self.states.get(state).copied().unwrap_or_default();
+                if current.0 != *value {
+                    self.states.insert(state.clone(), (*value, Some(now)));
+                }
+            }
+
+            let detected = DetectedEvent {
+                detector: detector.name.clone(),
+                source: name.to_string(),
+                app: fields.app.unwrap_or_default().to_string(),
+                title: fields.window.map(String::from),
+                speaker: fields.speaker.map(String::from),
+                device: fields.device.map(String::from),
+                timestamp: Utc::now(),
+            };
+            let mut payload = match serde_json::to_value(detected) {
+                Ok(Value::Object(payload)) => payload,
+                _ => Map::new(),
+            };
+            payload.extend(detector.data.clone());
+            debug!("detector {} sends {}", detector.name, detector.event);
+            events.push((detector.event.clone(), Value::Object(payload)));
+        }
+
+        events
+    }
+}
+
+/// The built-in detectors, replaced or extended by those of the `.toml` and `.json` files
+/// of `dir`, read in name order
+pub fn load_detectors(dir: Option<&Path>) -> Result<Vec<Detector>> {
+    let mut detectors = meeting_detectors();
+    for path in detector_files(dir).into_iter().map(|(path, _)| path) {
+        for detector in DetectorFile::load(&path)?.detectors {
+            match detectors.iter_mut().find(|d| d.name == detector.name) {
+                Some(existing) => *existing = detector,
+                None => detectors.push(detector),
+            }
+        }
+    }
+    Ok(detectors)
+}
+
+/// Detector files of `dir` with their modification time, by name
+fn detector_files(dir: Option<&Path>) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let Some(entries) = dir.and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|extension| extension.to_str()),
+                Some("toml" | "json")
+            )
+        })
+        .map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Runs the built-in detectors and those of `dir` over the events of the bus, and sends
+/// the events they detect. Files added, changed or removed in `dir` are picked up while
+/// running, a file with errors leaves the detectors as they were, or the built-in ones
+/// when starting.
+pub async fn run_detectors(dir: Option<PathBuf>) -> Result<()> {
+    let mut subscription = subscribe_to_all_events();
+
+    let detectors = load_detectors(dir.as_deref()).unwrap_or_else(|e| {
+        error!("failed to load detectors, using the built-in ones: {:#}", e);
+        meeting_detectors()
+    });
+    info!("running {} detectors", detectors.len());
+    let mut engine = DetectorEngine::new(detectors).or_else(|e| {
+        error!("invalid detectors, using the built-in ones: {:#}", e);
+        DetectorEngine::new(meeting_detectors())
+    })?;
+
+    let mut files = detector_files(dir.as_deref());
+    let mut reload = tokio::time::interval(RELOAD_INTERVAL);
+
+    loop {
+        tokio::select! {
+            event = subscription.next() => {
+                let Some(event) = event else {
+                    break;
+                };
+                for (name, data) in engine.process(&event.name, &event.data, Instant::now()) {
+                    send_event(&name, data)?;
+                }
+            }
+            _ = reload.tick() => {
+                let current = detector_files(dir.as_deref());
+                if current == files {
+                    continue;
+                }
+                files = current;
+                match load_detectors(dir.as_deref())
+                    .and_then(|detectors| {
+                        let count = detectors.len();
+                        engine.set_detectors(detectors).map(|_| count)
+                    }) {
+                    Ok(count) => info!("reloaded {} detectors", count),
+                    Err(e) => warn!("keeping the current detectors: {:#}", e),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
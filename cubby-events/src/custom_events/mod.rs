@@ -1 +1,2 @@
+pub mod detectors;
 pub mod meetings;
@@ -8,8 +8,11 @@ use serde_json::Value;
 use std::any::Any;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
+
+use crate::EventsDroppedEvent;
 use tokio::sync::broadcast;
 use tokio::time::interval;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::wrappers::BroadcastStream;
 
 static EVENT_MANAGER: Lazy<EventManager> = Lazy::new(EventManager::new);
@@ -17,6 +20,10 @@ static EVENT_MANAGER: Lazy<EventManager> = Lazy::new(EventManager::new);
 const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
 const SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(600); // 10 minutes
 
+/// Event a subscription to all events yields when it fell behind the bus and lost events,
+/// with `{"missed": <count>}` as data. Subscriptions to one event only log the loss.
+pub const EVENTS_DROPPED: &str = "events_dropped";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Event<T = Value> {
     pub name: String,
@@ -89,7 +96,25 @@ impl<T: DeserializeOwned + Unpin + 'static> Stream for EventSubscription<T> {
                         }
                     }
                 }
-                std::task::Poll::Ready(Some(_)) => continue,
+                std::task::Poll::Ready(Some(Err(BroadcastStreamRecvError::Lagged(missed)))) => {
+                    if me.event_name.is_empty() {
+                        tracing::warn!("event subscriber fell behind, {} events dropped", missed);
+                        let gap = EventsDroppedEvent { missed };
+                        let gap = serde_json::to_value(gap).and_then(serde_json::from_value::<T>);
+                        if let Ok(data) = gap {
+                            return std::task::Poll::Ready(Some(Event {
+                                name: EVENTS_DROPPED.to_string(),
+                                data,
+                            }));
+                        }
+                    } else {
+                        tracing::warn!(
+                            "{} subscriber fell behind, {} events dropped",
+                            me.event_name,
+                            missed
+                        );
+                    }
+                }
                 std::task::Poll::Ready(None) => return std::task::Poll::Ready(None),
                 std::task::Poll::Pending => return std::task::Poll::Pending,
             }
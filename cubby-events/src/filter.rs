@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::EVENTS_DROPPED;
+
+/// Which events a subscriber wants. Patterns may use `*` for any run of characters and
+/// `?` for one character, app and device patterns ignore case.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventFilter {
+    /// Event names or patterns such as `meeting_*`, every event when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// App the event is about, from its `app_name` or `app` field. Events without an app
+    /// don't match.
+    #[serde(default)]
+    pub app_name: Option<String>,
+    /// Audio device the event comes from, from its `device` field. Events without a
+    /// device don't match.
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+impl EventFilter {
+    /// Whether an event passes the filter. `events_dropped` always does, subscribers
+    /// need to know they missed events whatever they were.
+    pub fn matches(&self, name: &str, data: &Value) -> bool {
+        if name == EVENTS_DROPPED {
+            return true;
+        }
+
+        if !self.events.is_empty() && !self.events.iter().any(|pattern| glob_match(pattern, name)) {
+            return false;
+        }
+
+        if let Some(app_name) = &self.app_name {
+            let app = data
+                .get("app_name")
+                .or_else(|| data.get("app"))
+                .and_then(Value::as_str);
+            if !app.is_some_and(|app| glob_match(&app_name.to_lowercase(), &app.to_lowercase())) {
+                return false;
+            }
+        }
+
+        if let Some(device) = &self.device {
+            let event_device = data.get("device").and_then(Value::as_str);
+            if !event_device.is_some_and(|event_device| {
+                glob_match(&device.to_lowercase(), &event_device.to_lowercase())
+            }) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Matches `text` against a pattern where `*` is any run of characters and `?` is one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` and of the text it was matched up to, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // let the `*` take one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
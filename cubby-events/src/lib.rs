@@ -1,7 +1,12 @@
+mod builtin_events;
 mod events_manager;
+mod filter;
 
+pub use builtin_events::*;
 pub use events_manager::*;
+pub use filter::EventFilter;
 
 mod custom_events;
 
+pub use custom_events::detectors::*;
 pub use custom_events::meetings::*;
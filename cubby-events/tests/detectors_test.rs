@@ -0,0 +1,344 @@
+use cubby_events::{
+    load_detectors, meeting_detectors, run_detectors, send_event, subscribe_to_event, Detector,
+    DetectorEngine, DetectorFile, MeetingEvent,
+};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+fn names(events: &[(String, Value)]) -> Vec<&str> {
+    events.iter().map(|(name, _)| name.as_str()).collect()
+}
+
+fn ui_frame(app: &str, window: &str, text: &str) -> Value {
+    json!({
+        "window": window,
+        "app": app,
+        "text_output": text,
+        "initial_traversal_at": "2026-10-17T12:00:00Z",
+    })
+}
+
+fn transcription(device: &str, text: &str, is_final: bool) -> Value {
+    json!({
+        "timestamp": "2026-10-17T12:00:00Z",
+        "device": device,
+        "transcription": text,
+        "isFinal": is_final,
+        "isInput": true,
+    })
+}
+
+#[test]
+fn test_meeting_detection() {
+    let mut engine = DetectorEngine::new(meeting_detectors()).unwrap();
+    let t0 = Instant::now();
+
+    let started = engine.process(
+        "ui_frame",
+        &ui_frame("zoom.us", "Zoom Meeting", "waiting for the host"),
+        t0,
+    );
+    assert_eq!(names(&started), vec!["meeting_started"]);
+    let meeting: MeetingEvent = serde_json::from_value(started[0].1.clone()).unwrap();
+    assert_eq!(meeting.app, "zoom.us");
+    assert_eq!(meeting.title.as_deref(), Some("Zoom Meeting"));
+    assert!(engine.state("in_meeting"));
+
+    // already in a meeting
+    let again = engine.process(
+        "ocr_result",
+        &json!({"app_name": "Zoom", "window_name": "Zoom Meeting", "text": "Mute"}),
+        t0 + Duration::from_secs(1),
+    );
+    assert!(again.is_empty());
+
+    let ended = engine.process(
+        "transcription",
+        &transcription(
+            "MacBook Pro Microphone (input)",
+            "ok the meeting has ended",
+            true,
+        ),
+        t0 + Duration::from_secs(60),
+    );
+    assert_eq!(names(&ended), vec!["meeting_ended"]);
+    assert!(!engine.state("in_meeting"));
+
+    // no new meeting right after one ended
+    let frame = ui_frame("Microsoft Teams", "Call with Ada", "3 participants");
+    assert!(engine
+        .process("ui_frame", &frame, t0 + Duration::from_secs(65))
+        .is_empty());
+    assert_eq!(
+        names(&engine.process("ui_frame", &frame, t0 + Duration::from_secs(71))),
+        vec!["meeting_started"]
+    );
+
+    // the meeting app has no window left
+    assert_eq!(
+        names(&engine.process(
+            "ui_frame",
+            &ui_frame("Microsoft Teams", "", ""),
+            t0 + Duration::from_secs(90)
+        )),
+        vec!["meeting_ended"]
+    );
+}
+
+#[test]
+fn test_meeting_detected_from_audio() {
+    let mut engine = DetectorEngine::new(meeting_detectors()).unwrap();
+    let t0 = Instant::now();
+
+    let mic = transcription("Microphone (input)", "hello", true);
+    let speakers = transcription("Speakers (output)", "hi there", true);
+    assert!(engine.process("transcription", &mic, t0).is_empty());
+    assert!(engine
+        .process("transcription", &mic, t0 + Duration::from_secs(1))
+        .is_empty());
+    // partial transcriptions don't count
+    assert!(engine
+        .process(
+            "transcription",
+            &transcription("Speakers (output)", "hi", false),
+            t0 + Duration::from_secs(2)
+        )
+        .is_empty());
+
+    let started = engine.process("transcription", &speakers, t0 + Duration::from_secs(3));
+    assert_eq!(names(&started), vec!["meeting_started"]);
+    assert_eq!(started[0].1["app"], "Unknown (detected via audio)");
+    assert_eq!(started[0].1["detector"], "meeting_started_audio");
+
+    // devices heard too far apart
+    let mut engine = DetectorEngine::new(meeting_detectors()).unwrap();
+    assert!(engine.process("transcription", &mic, t0).is_empty());
+    assert!(engine
+        .process("transcription", &speakers, t0 + Duration::from_secs(301))
+        .is_empty());
+}
+
+#[test]
+fn test_custom_detectors_from_toml() {
+    let file = DetectorFile::from_toml(
+        r#"
+        [[detectors]]
+        name = "coding_cubby"
+        event = "started_coding"
+        on = ["ocr_result"]
+        app = "^(code|cursor)$"
+        window = "cubby"
+        debounce_secs = 60
+        data = { repo = "cubby" }
+        "#,
+    )
+    .unwrap();
+    let mut engine = DetectorEngine::new(file.detectors).unwrap();
+    let t0 = Instant::now();
+    let editor = json!({"app_name": "Code", "window_name": "server.rs - cubby", "text": ""});
+
+    let events = engine.process("ocr_result", &editor, t0);
+    assert_eq!(names(&events), vec!["started_coding"]);
+    assert_eq!(events[0].1["repo"], "cubby");
+    assert_eq!(events[0].1["source"], "ocr_result");
+    assert_eq!(events[0].1["title"], "server.rs - cubby");
+
+    assert!(engine
+        .process("ocr_result", &editor, t0 + Duration::from_secs(30))
+        .is_empty());
+    assert!(engine
+        .process(
+            "ocr_result",
+            &json!({"app_name": "Code", "window_name": "notes"}),
+            t0 + Duration::from_secs(90)
+        )
+        .is_empty());
+    assert!(engine
+        .process(
+            "ui_frame",
+            &ui_frame("Code", "cubby", ""),
+            t0 + Duration::from_secs(90)
+        )
+        .is_empty());
+    assert_eq!(
+        names(&engine.process("ocr_result", &editor, t0 + Duration::from_secs(90))),
+        vec!["started_coding"]
+    );
+}
+
+#[test]
+fn test_custom_detectors_from_json() {
+    let file = DetectorFile::from_json(
+        r#"{"detectors": [{
+            "name": "acme_mentioned",
+            "event": "customer_mentioned",
+            "on": ["transcription"],
+            "keywords": ["Acme Corp", "acme inc"],
+            "speaker": "^(?!me$)",
+            "final_only": true
+        }]}"#,
+    );
+    // look-arounds are not supported by the regex engine
+    assert!(DetectorEngine::new(file.unwrap().detectors).is_err());
+
+    let file = DetectorFile::from_json(
+        r#"{"detectors": [{
+            "name": "acme_mentioned",
+            "event": "customer_mentioned",
+            "on": ["transcription"],
+            "keywords": ["Acme Corp", "acme inc"],
+            "speaker": "^alice$",
+            "final_only": true
+        }]}"#,
+    )
+    .unwrap();
+    let mut engine = DetectorEngine::new(file.detectors).unwrap();
+    let now = Instant::now();
+
+    let mut heard = transcription("mic", "we should call ACME CORP back", true);
+    assert!(engine.process("transcription", &heard, now).is_empty());
+    heard["speaker"] = json!("Alice");
+    let events = engine.process("transcription", &heard, now);
+    assert_eq!(names(&events), vec!["customer_mentioned"]);
+    assert_eq!(events[0].1["speaker"], "Alice");
+    assert_eq!(events[0].1["device"], "mic");
+
+    heard["isFinal"] = json!(false);
+    assert!(engine.process("transcription", &heard, now).is_empty());
+}
+
+#[test]
+fn test_detectors_skip_detected_events() {
+    let file = DetectorFile::from_toml(
+        r#"
+        [[detectors]]
+        name = "anything_in_zoom"
+        event = "zoom_seen"
+        on = ["*"]
+        app = "zoom"
+        "#,
+    )
+    .unwrap();
+    let mut engine = DetectorEngine::new(file.detectors).unwrap();
+    let now = Instant::now();
+
+    let events = engine.process("ui_frame", &ui_frame("zoom.us", "Zoom", ""), now);
+    assert_eq!(names(&events), vec!["zoom_seen"]);
+    // the detected event has the app too, but isn't detected again
+    let (name, data) = &events[0];
+    assert_eq!(data["app"], "zoom.us");
+    assert!(engine.process(name, data, now).is_empty());
+
+    assert!(DetectorEngine::new(vec![Detector {
+        name: "disguised".to_string(),
+        event: "x".to_string(),
+        data: serde_json::from_value(json!({"detector": "someone_else"})).unwrap(),
+        ..Default::default()
+    }])
+    .is_err());
+}
+
+#[test]
+fn test_invalid_detectors_and_events() {
+    assert!(DetectorFile::from_toml("[[detectors]]\nname = \"x\"\nevnt = \"y\"").is_err());
+    assert!(DetectorEngine::new(vec![Detector {
+        name: "no_event".to_string(),
+        ..Default::default()
+    }])
+    .is_err());
+    assert!(DetectorEngine::new(vec![Detector {
+        name: "bad_pattern".to_string(),
+        event: "x".to_string(),
+        app: Some("(".to_string()),
+        ..Default::default()
+    }])
+    .is_err());
+    assert!(DetectorEngine::new(vec![Detector {
+        name: "bad_distinct".to_string(),
+        event: "x".to_string(),
+        distinct: Some("colour".to_string()),
+        ..Default::default()
+    }])
+    .is_err());
+
+    // events that don't look like what producers send are skipped, not a panic
+    let mut engine = DetectorEngine::new(meeting_detectors()).unwrap();
+    let now = Instant::now();
+    for data in [json!(42), json!(null), json!({"app": 1, "window": []})] {
+        assert!(engine.process("ui_frame", &data, now).is_empty());
+        assert!(engine.process("transcription", &data, now).is_empty());
+    }
+}
+
+#[test]
+fn test_user_detectors_replace_built_in_ones() {
+    let dir = std::env::temp_dir().join(format!("cubby-detectors-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("meetings.toml"),
+        "[[detectors]]\nname = \"meeting_started_audio\"\nevent = \"meeting_started\"\nenabled = false\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("focus.json"),
+        r#"{"detectors": [{"name": "terminal", "event": "terminal_focused", "app": "terminal"}]}"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("notes.txt"), "not detectors").unwrap();
+
+    let detectors = load_detectors(Some(&dir)).unwrap();
+    assert_eq!(detectors.len(), meeting_detectors().len() + 1);
+    let audio = detectors
+        .iter()
+        .find(|d| d.name == "meeting_started_audio")
+        .unwrap();
+    assert!(!audio.enabled);
+
+    let mut engine = DetectorEngine::new(detectors).unwrap();
+    let now = Instant::now();
+    assert!(engine
+        .process("transcription", &transcription("a", "hi", true), now)
+        .is_empty());
+    assert!(engine
+        .process("transcription", &transcription("b", "hi", true), now)
+        .is_empty());
+    assert_eq!(
+        names(&engine.process("ui_frame", &ui_frame("Terminal", "zsh", ""), now)),
+        vec!["terminal_focused"]
+    );
+
+    std::fs::write(dir.join("broken.toml"), "[[detectors]\n").unwrap();
+    assert!(load_detectors(Some(&dir)).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_detector_files_fall_back_to_built_in_ones() {
+    let dir = std::env::temp_dir().join(format!("cubby-detectors-invalid-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("broken.toml"),
+        "[[detectors]]\nname = \"broken\"\nevent = \"x\"\napp = \"(\"\n",
+    )
+    .unwrap();
+
+    let mut meetings = subscribe_to_event::<Value>("meeting_started");
+    let detectors = tokio::spawn(run_detectors(Some(dir.clone())));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!detectors.is_finished());
+
+    send_event(
+        "ui_frame",
+        ui_frame("zoom.us", "Zoom Meeting", "waiting for the host"),
+    )
+    .unwrap();
+    let started = tokio::time::timeout(Duration::from_secs(5), meetings.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(started.data["app"], "zoom.us");
+
+    detectors.abort();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
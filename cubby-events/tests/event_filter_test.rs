@@ -0,0 +1,108 @@
+use cubby_events::{BuiltinEvent, EventFilter, EVENTS_DROPPED};
+use serde_json::json;
+
+#[test]
+fn test_empty_filter_matches_everything() {
+    let filter = EventFilter::default();
+    assert!(filter.matches("transcription", &json!({"device": "mic"})));
+    assert!(filter.matches("custom", &json!(null)));
+}
+
+#[test]
+fn test_event_name_patterns() {
+    let filter = EventFilter {
+        events: vec!["meeting_*".to_string(), "ocr_resul?".to_string()],
+        ..Default::default()
+    };
+    assert!(filter.matches("meeting_started", &json!({})));
+    assert!(filter.matches("meeting_ended", &json!({})));
+    assert!(filter.matches("ocr_result", &json!({})));
+    assert!(!filter.matches("ocr_results", &json!({})));
+    assert!(!filter.matches("transcription", &json!({})));
+    // names are case sensitive
+    assert!(!filter.matches("Meeting_started", &json!({})));
+
+    let filter = EventFilter {
+        events: vec!["*_*_*".to_string()],
+        ..Default::default()
+    };
+    assert!(filter.matches("index_job_progress", &json!({})));
+    assert!(!filter.matches("ui_frame", &json!({})));
+}
+
+#[test]
+fn test_app_and_device_predicates() {
+    let filter = EventFilter {
+        app_name: Some("zoom*".to_string()),
+        ..Default::default()
+    };
+    assert!(filter.matches("ocr_result", &json!({"app_name": "zoom.us"})));
+    assert!(filter.matches("ui_frame", &json!({"app": "Zoom Workplace"})));
+    assert!(!filter.matches("ocr_result", &json!({"app_name": "Slack"})));
+    // events without an app don't match
+    assert!(!filter.matches("transcription", &json!({"device": "mic"})));
+
+    let filter = EventFilter {
+        events: vec!["transcription".to_string()],
+        device: Some("MacBook*".to_string()),
+        ..Default::default()
+    };
+    assert!(filter.matches(
+        "transcription",
+        &json!({"device": "macbook pro microphone (input)"})
+    ));
+    assert!(!filter.matches("transcription", &json!({"device": "airpods (input)"})));
+    assert!(!filter.matches(
+        "ocr_result",
+        &json!({"device": "macbook pro microphone (input)"})
+    ));
+}
+
+#[test]
+fn test_events_dropped_always_matches() {
+    let filter = EventFilter {
+        events: vec!["meeting_*".to_string()],
+        app_name: Some("zoom.us".to_string()),
+        device: Some("mic".to_string()),
+    };
+    assert!(filter.matches(EVENTS_DROPPED, &json!({"missed": 3})));
+}
+
+#[test]
+fn test_filter_from_subscribe_message() {
+    let filter: EventFilter = serde_json::from_value(json!({"events": ["ui_frame"]})).unwrap();
+    assert_eq!(
+        filter,
+        EventFilter {
+            events: vec!["ui_frame".to_string()],
+            app_name: None,
+            device: None,
+        }
+    );
+}
+
+#[test]
+fn test_builtin_event_shape() {
+    let event: BuiltinEvent = serde_json::from_value(json!({
+        "name": "transcription",
+        "data": {
+            "timestamp": "2026-10-17T12:00:00Z",
+            "device": "mic (input)",
+            "transcription": "hello",
+            "isFinal": true,
+            "isInput": true
+        }
+    }))
+    .unwrap();
+    match event {
+        BuiltinEvent::Transcription(event) => {
+            assert_eq!(event.transcription, "hello");
+            assert!(event.speaker.is_none());
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+
+    let event: BuiltinEvent =
+        serde_json::from_value(json!({"name": EVENTS_DROPPED, "data": {"missed": 7}})).unwrap();
+    assert!(matches!(event, BuiltinEvent::EventsDropped(e) if e.missed == 7));
+}
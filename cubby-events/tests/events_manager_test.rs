@@ -1,4 +1,4 @@
-use cubby_events::{send_event, subscribe_to_all_events, subscribe_to_event};
+use cubby_events::{send_event, subscribe_to_all_events, subscribe_to_event, EVENTS_DROPPED};
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -72,6 +72,25 @@ async fn test_dropped_events() {
     assert!(last.data == -1 || last.data >= 0);
 }
 
+#[tokio::test]
+async fn test_lagged_subscribers_report_gaps() {
+    let mut all = subscribe_to_all_events();
+    let mut typed = subscribe_to_event::<i32>("gap_test");
+
+    // more than the bus keeps
+    for i in 0..20000 {
+        let _ = send_event("gap_test", i);
+    }
+
+    let gap = all.next().await.unwrap();
+    assert_eq!(gap.name, EVENTS_DROPPED);
+    assert!(gap.data["missed"].as_u64().unwrap() > 0);
+    assert_eq!(all.next().await.unwrap().name, "gap_test");
+
+    // a typed subscription can't carry the gap and resumes with the oldest event kept
+    assert!(typed.next().await.unwrap().data > 0);
+}
+
 #[tokio::test]
 async fn test_concurrent_senders() {
     let mut stream = subscribe_to_event::<String>("concurrent_send");
@@ -57,6 +57,15 @@ export interface cubbyQueryParams {
 
   /** Filter by browser URL (for web content) */
   browserUrl?: string;
+
+  /** `nextCursor` of the previous page, pages stay stable while new content is recorded */
+  cursor?: string;
+
+  /** Timestamp order of results (default: "desc") */
+  order?: "asc" | "desc";
+
+  /** Count all matches, defaults to true without a cursor */
+  includeTotal?: boolean;
 }
 
 /**
@@ -83,6 +92,7 @@ export interface OCRContent {
  */
 export interface AudioContent {
   chunkId: number;
+  transcriptionId: number;
   transcription: string;
   timestamp: string;
   filePath: string;
@@ -93,6 +103,8 @@ export interface AudioContent {
   speaker?: Speaker;
   startTime?: number;
   endTime?: number;
+  language?: string;
+  avgLogprob?: number;
 }
 
 /**
@@ -134,7 +146,10 @@ export type ContentItem =
 export interface PaginationInfo {
   limit: number;
   offset: number;
-  total: number;
+  /** Estimated number of matches, absent when not counted. */
+  total?: number;
+  /** Cursor of the next page, absent on the last page. */
+  nextCursor?: string;
 }
 
 /**
@@ -1,5 +1,9 @@
 use anyhow::Result;
-use cubby_db::DatabaseManager;
+use chrono::{DateTime, Utc};
+use cubby_audio::core::device::{AudioDevice, DeviceType};
+use cubby_audio::import::{AudioImportOptions, AudioImporter, IMPORT_AUDIO_EXTENSIONS};
+use cubby_core::find_ffmpeg_path;
+use cubby_db::{DatabaseManager, EmbeddingSource};
 use cubby_vision::utils::{compare_with_previous_image, OcrEngine};
 use image::DynamicImage;
 use regex::Regex;
@@ -13,10 +17,14 @@ use cubby_vision::perform_ocr_windows;
 #[allow(unused)]
 use cubby_vision::perform_ocr_tesseract;
 
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 use tokio::fs;
 use tracing::error;
 use tracing::warn;
@@ -26,270 +34,345 @@ use walkdir::WalkDir;
 
 use crate::{
     cli::CliOcrEngine,
-    text_embeds::generate_embedding,
-    video_utils::{extract_frames_from_video, get_video_metadata, VideoMetadataOverrides},
+    embedding::{backend::EmbeddingBackend, worker::embed_texts},
+    video_utils::{
+        extract_frames_from_video, get_media_creation_time, get_video_metadata,
+        VideoMetadataOverrides,
+    },
 };
 
-#[allow(clippy::too_many_arguments)]
-pub async fn handle_index_command(
+/// Options of `cubby index` and of the jobs queued with `POST /index`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexOptions {
+    /// The platform default engine when `None`
+    #[serde(default)]
+    pub ocr_engine: Option<CliOcrEngine>,
+    /// JSON file overriding the metadata read from the videos
+    #[serde(default)]
+    pub metadata_override: Option<PathBuf>,
+    /// Copy the videos to the data directory instead of indexing them in place
+    #[serde(default)]
+    pub copy_videos: bool,
+    /// Embed the extracted text right away instead of leaving it to the embedding worker
+    #[serde(default)]
+    pub use_embedding: bool,
+}
+
+/// A frame whose text was extracted
+pub(crate) struct IndexedFrame<'a> {
+    pub frame_number: i64,
+    pub text: &'a str,
+    pub confidence: f64,
+}
+
+/// A video whose chunk and frames were created, before their text is extracted
+pub(crate) struct IndexedChunk {
+    pub video_chunk_id: i64,
+    /// Path the chunk points to, in the data directory when videos are copied
+    pub video_path: PathBuf,
+    frames: Vec<DynamicImage>,
+    frame_ids: Vec<i64>,
+}
+
+/// Indexes existing videos: frames are extracted and stored as a video chunk, then their
+/// text is extracted like for recorded frames
+pub(crate) struct VideoIndexer {
     cubby_dir: PathBuf,
-    path: String,
-    pattern: Option<String>,
     db: Arc<DatabaseManager>,
-    output_format: crate::cli::OutputFormat,
-    ocr_engine: Option<CliOcrEngine>,
-    metadata_override: Option<PathBuf>,
+    ocr_engine: OcrEngine,
+    metadata_overrides: Option<VideoMetadataOverrides>,
     copy_videos: bool,
-    use_embedding: bool,
-) -> Result<()> {
-    // Load metadata override if provided
-    let metadata_overrides = if let Some(path) = metadata_override {
-        let content = tokio::fs::read_to_string(path).await?;
-        Some(serde_json::from_str::<VideoMetadataOverrides>(&content)?)
-    } else {
-        None
-    };
-
-    // Get list of video files
-    let video_files = find_video_files(&path, pattern.as_deref())?;
-    info!("found {} video files to process", video_files.len());
-
-    // Validate that we have metadata for all files if overrides are provided
-    if let Some(ref overrides) = metadata_overrides {
-        let mut unmatched_files = Vec::new();
+    /// Set when the text is embedded as it is extracted
+    embedding_backend: Option<Arc<dyn EmbeddingBackend>>,
+}
 
-        for video_path in &video_files {
-            let file_str = video_path.to_string_lossy();
-            let matched = overrides
-                .overrides
-                .iter()
-                .any(|override_item| override_item.file_path == file_str);
+impl VideoIndexer {
+    pub async fn new(
+        cubby_dir: PathBuf,
+        db: Arc<DatabaseManager>,
+        options: &IndexOptions,
+        embedding_backend: Option<Arc<dyn EmbeddingBackend>>,
+    ) -> Result<Self> {
+        if options.use_embedding && embedding_backend.is_none() {
+            warn!("embeddings are disabled, the extracted text won't be embedded");
+        }
 
-            if !matched {
-                unmatched_files.push(video_path.clone());
+        let metadata_overrides = match &options.metadata_override {
+            Some(path) => {
+                let content = fs::read_to_string(path).await?;
+                Some(serde_json::from_str::<VideoMetadataOverrides>(&content)?)
             }
-        }
+            None => None,
+        };
 
-        if !unmatched_files.is_empty() {
-            return Err(anyhow::anyhow!(
-                "Missing metadata overrides for files: {:?}",
-                unmatched_files
-            ));
-        }
+        Ok(Self {
+            cubby_dir,
+            db,
+            ocr_engine: options
+                .ocr_engine
+                .clone()
+                .unwrap_or_else(default_ocr_engine)
+                .into(),
+            metadata_overrides,
+            copy_videos: options.copy_videos,
+            embedding_backend: embedding_backend.filter(|_| options.use_embedding),
+        })
     }
 
-    let mut total_frames = 0;
-    let mut total_text = 0;
+    /// The videos under `path`. When metadata overrides are given every video needs one.
+    pub fn find_videos(&self, path: &str, pattern: Option<&str>) -> Result<Vec<PathBuf>> {
+        let video_files = find_video_files(path, pattern)?;
 
-    // Setup channel for OCR results
+        if let Some(ref overrides) = self.metadata_overrides {
+            let unmatched_files: Vec<&PathBuf> = video_files
+                .iter()
+                .filter(|video_path| {
+                    let file_str = video_path.to_string_lossy();
+                    !overrides
+                        .overrides
+                        .iter()
+                        .any(|override_item| override_item.file_path == file_str)
+                })
+                .collect();
+
+            if !unmatched_files.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Missing metadata overrides for files: {:?}",
+                    unmatched_files
+                ));
+            }
+        }
 
-    // At the start of handle_index_command, if JSON output is selected, print the stream start
-    if output_format == crate::cli::OutputFormat::Json {
-        println!("{{\"version\":1,\"stream\":["); // Start of JSON stream
+        Ok(video_files)
     }
 
-    for video_path in video_files {
-        info!("processing video: {}", video_path.display());
-
-        // Get metadata override before copying file
-        let mut metadata = get_video_metadata(video_path.to_str().unwrap()).await?;
+    /// Copies the video if asked to, then stores its frames
+    pub async fn create_chunk(&self, video_path: &Path) -> Result<IndexedChunk> {
+        let mut metadata = get_video_metadata(&video_path.to_string_lossy()).await?;
 
-        // Apply metadata override if provided and matches the file
-        if let Some(ref overrides) = metadata_overrides {
+        if let Some(ref overrides) = self.metadata_overrides {
             let file_str = video_path.to_string_lossy();
-            println!("checking override for path: {}", file_str);
-            println!("available overrides: {:?}", overrides);
-
             if let Some(override_item) = overrides
                 .overrides
                 .iter()
                 .find(|item| item.file_path == file_str)
             {
-                println!("found matching override: {:?}", override_item);
+                debug!("applying metadata override to {}", file_str);
                 override_item.metadata.apply_to(&mut metadata);
-                println!("metadata after override: {:?}", metadata);
-            } else {
-                println!("no matching override found");
             }
         }
 
-        let video_path = if copy_videos {
+        let video_path = if self.copy_videos {
             // Generate unique filename using UUID
             let ext = video_path.extension().unwrap_or_default();
             let new_filename = format!("{}.{}", Uuid::new_v4(), ext.to_string_lossy());
 
             // Construct path in cubby data directory
-            let target_path = Path::new(&cubby_dir).join("data").join(new_filename);
+            let target_path = self.cubby_dir.join("data").join(new_filename);
 
             // Copy the file
             info!("copying video to: {}", target_path.display());
-            fs::copy(&video_path, &target_path).await?;
+            fs::copy(video_path, &target_path).await?;
 
             target_path
         } else {
-            video_path.clone()
+            video_path.to_path_buf()
         };
 
         let frames = extract_frames_from_video(&video_path, None).await?;
 
-        // Create video chunk and frames first
-        let frame_ids = db
+        let (video_chunk_id, frame_ids) = self
+            .db
             .create_video_with_frames(
-                video_path.to_str().unwrap(),
+                &video_path.to_string_lossy(),
                 frames.clone(),
                 metadata.into(),
             )
             .await?;
 
-        let mut previous_image: Option<DynamicImage> = None;
-        let mut frame_counter: i64 = 0;
-
-        for (idx, frame) in frames.iter().enumerate() {
-            // Compare with previous frame to skip similar ones
-            let current_average = if let Some(prev) = &previous_image {
-                compare_with_previous_image(Some(prev), frame, &mut None, idx as u64, &mut 0.0)
-                    .await?
-            } else {
-                1.0
-            };
+        Ok(IndexedChunk {
+            video_chunk_id,
+            video_path,
+            frames,
+            frame_ids,
+        })
+    }
 
-            // Skip if frames are too similar (threshold from core.rs)
-            if current_average < 0.006 && previous_image.is_some() {
-                info!(
-                    "skipping frame {} due to low average difference: {:.3}",
-                    frame_counter, current_average
-                );
-                frame_counter += 1;
-                continue;
+    /// Extracts the text of the frames of a chunk, skipping frames too similar to the
+    /// previous one. Stops between frames once `cancelled` is set. Returns the number of
+    /// frames whose text was extracted.
+    pub async fn extract_text(
+        &self,
+        chunk: &IndexedChunk,
+        cancelled: &AtomicBool,
+        mut on_frame: impl FnMut(&IndexedFrame),
+    ) -> Result<usize> {
+        let mut previous_image: Option<&DynamicImage> = None;
+        let mut indexed_frames = 0;
+
+        for (idx, frame) in chunk.frames.iter().enumerate() {
+            if cancelled.load(Ordering::SeqCst) {
+                break;
             }
+            let frame_id = chunk.frame_ids[idx];
 
-            previous_image = Some(frame.clone());
-
-            // Use specified OCR engine or fall back to platform default
-            let engine = match ocr_engine {
-                Some(ref cli_engine) => cli_engine.clone().into(),
-                None => {
-                    #[cfg(target_os = "macos")]
-                    let engine = OcrEngine::AppleNative;
-                    #[cfg(target_os = "windows")]
-                    let engine = OcrEngine::WindowsNative;
-                    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-                    let engine = OcrEngine::Tesseract;
-                    engine
+            // Compare with previous frame to skip similar ones
+            if let Some(prev) = previous_image {
+                let current_average =
+                    compare_with_previous_image(Some(prev), frame, &mut None, idx as u64, &mut 0.0)
+                        .await?;
+                // Skip if frames are too similar (threshold from core.rs)
+                if current_average < 0.006 {
+                    debug!(
+                        "skipping frame {} due to low average difference: {:.3}",
+                        idx, current_average
+                    );
+                    continue;
                 }
-            };
+            }
+            previous_image = Some(frame);
 
-            // Do OCR processing directly
-            let (text, _, confidence): (String, String, Option<f64>) = match engine.clone() {
+            let (text, _, confidence): (String, String, Option<f64>) = match self.ocr_engine {
                 #[cfg(target_os = "macos")]
                 OcrEngine::AppleNative => perform_ocr_apple(frame, &[]),
                 #[cfg(target_os = "windows")]
-                OcrEngine::WindowsNative => perform_ocr_windows(&frame).await.unwrap(),
+                OcrEngine::WindowsNative => perform_ocr_windows(frame).await?,
+                #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+                OcrEngine::Tesseract => perform_ocr_tesseract(frame, Vec::new()),
                 _ => {
-                    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-                    {
-                        perform_ocr_tesseract(&frame, Vec::new());
-                    }
                     warn!("unsupported ocr engine");
                     ("".to_string(), "".to_string(), None)
                 }
             };
 
-            // Handle OCR results
-            total_frames += 1;
-            total_text += text.len();
-
-            // Only generate embeddings if flag is enabled
-            if use_embedding && !text.is_empty() {
-                match generate_embedding(&text, frame_ids[idx]).await {
-                    Ok(emb) => {
-                        debug!("generated embedding for frame {}", frame_ids[idx]);
-                        if let Err(e) = db
-                            .insert_embeddings(frame_ids[idx], serde_json::to_string(&emb)?)
-                            .await
-                        {
-                            error!("error batch inserting embeddings: {}", e);
-                        }
-                    }
-                    Err(e) => {
-                        error!(
-                            "failed to generate embedding for frame {}: {}",
-                            frame_ids[idx], e
-                        );
-                    }
-                }
-            }
-
-            // Process OCR directly instead of batching
-            if let Err(e) = db
+            if let Err(e) = self
+                .db
                 .insert_ocr_text(
-                    frame_ids[idx],
+                    frame_id,
                     &text,
                     "{}", // empty json
-                    ocr_engine.clone().unwrap().into(),
+                    Arc::new(self.ocr_engine.clone().into()),
                 )
                 .await
             {
                 error!("error inserting ocr text: {}", e);
             }
+            debug!("inserted ocr text for frame {}", frame_id);
 
-            info!("inserted ocr text for frame {}", frame_ids[idx]);
-
-            // Handle output formatting
-            match output_format {
-                crate::cli::OutputFormat::Json => {
-                    if !text.is_empty() {
-                        if total_frames > 0 {
-                            print!(",");
-                        }
-                        print!(
-                            "{}",
-                            serde_json::to_string(&json!({
-                                "type": "frame",
-                                "data": {
-                                    "frame_number": frame_counter,
-                                    "text": text,
-                                    "confidence": confidence.unwrap_or(0.0),
-                                    "video_path": video_path.to_string_lossy()
-                                }
-                            }))?
-                        );
-                    }
-                }
-                crate::cli::OutputFormat::Text => {
-                    if !text.is_empty() {
-                        debug!("frame {}: {}", frame_counter, text);
+            if let Some(backend) = &self.embedding_backend {
+                if !text.trim().is_empty() {
+                    if let Err(e) = self.embed_text(backend.as_ref(), frame_id, &text).await {
+                        error!("failed to embed text of frame {}: {}", frame_id, e);
                     }
                 }
             }
 
-            frame_counter += 1;
+            indexed_frames += 1;
+            on_frame(&IndexedFrame {
+                frame_number: idx as i64,
+                text: &text,
+                confidence: confidence.unwrap_or(0.0),
+            });
         }
+
+        Ok(indexed_frames)
     }
 
-    // wait few seconds for remaining OCR tasks
-    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+    async fn embed_text(
+        &self,
+        backend: &dyn EmbeddingBackend,
+        frame_id: i64,
+        text: &str,
+    ) -> Result<()> {
+        let embeddings = embed_texts(backend, &[(frame_id, text)]).await?;
+        self.db
+            .insert_text_embeddings(EmbeddingSource::Ocr, backend.model(), &embeddings, None)
+            .await?;
+        Ok(())
+    }
+}
 
-    // At the end, close the JSON array
-    match output_format {
-        crate::cli::OutputFormat::Json => {
-            // Add final summary item
-            if total_frames > 0 {
-                print!(",");
-            }
-            print!(
-                "{}",
-                serde_json::to_string(&json!({
-                    "type": "summary",
-                    "data": {
-                        "total_frames": total_frames,
-                        "total_text_chars": total_text
-                    }
-                }))?
-            );
-            println!("]}}"); // End of JSON stream
+/// The engine `cubby` records with by default on this platform
+fn default_ocr_engine() -> CliOcrEngine {
+    #[cfg(target_os = "macos")]
+    let engine = CliOcrEngine::AppleNative;
+    #[cfg(target_os = "windows")]
+    let engine = CliOcrEngine::WindowsNative;
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let engine = CliOcrEngine::Tesseract;
+    engine
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_index_command(
+    cubby_dir: PathBuf,
+    path: String,
+    pattern: Option<String>,
+    db: Arc<DatabaseManager>,
+    output_format: crate::cli::OutputFormat,
+    ocr_engine: Option<CliOcrEngine>,
+    metadata_override: Option<PathBuf>,
+    copy_videos: bool,
+    use_embedding: bool,
+    embedding_backend: Option<Arc<dyn EmbeddingBackend>>,
+) -> Result<()> {
+    let options = IndexOptions {
+        ocr_engine,
+        metadata_override,
+        copy_videos,
+        use_embedding,
+    };
+    let indexer = VideoIndexer::new(cubby_dir, db, &options, embedding_backend).await?;
+
+    let video_files = indexer.find_videos(&path, pattern.as_deref())?;
+    info!("found {} video files to process", video_files.len());
+
+    let mut stream = match output_format {
+        crate::cli::OutputFormat::Json => Some(JsonStream::start()),
+        crate::cli::OutputFormat::Text => None,
+    };
+    let not_cancelled = AtomicBool::new(false);
+    let mut total_frames = 0;
+    let mut total_text = 0;
+
+    for video_path in video_files {
+        info!("processing video: {}", video_path.display());
+
+        let chunk = indexer.create_chunk(&video_path).await?;
+        total_frames += indexer
+            .extract_text(&chunk, &not_cancelled, |frame| {
+                total_text += frame.text.len();
+                if frame.text.is_empty() {
+                    return;
+                }
+                match stream.as_mut() {
+                    Some(stream) => stream.push(json!({
+                        "type": "frame",
+                        "data": {
+                            "frame_number": frame.frame_number,
+                            "text": frame.text,
+                            "confidence": frame.confidence,
+                            "video_path": chunk.video_path.to_string_lossy()
+                        }
+                    })),
+                    None => debug!("frame {}: {}", frame.frame_number, frame.text),
+                }
+            })
+            .await?;
+    }
+
+    match stream {
+        Some(mut stream) => {
+            stream.push(json!({
+                "type": "summary",
+                "data": {
+                    "total_frames": total_frames,
+                    "total_text_chars": total_text
+                }
+            }));
+            stream.end();
         }
-        crate::cli::OutputFormat::Text => {
+        None => {
             info!(
                 "processed {} frames, extracted {} characters of text",
                 total_frames, total_text
@@ -300,8 +383,204 @@ pub async fn handle_index_command(
     Ok(())
 }
 
+/// Sidecar `<recording>.json` overriding what is read from an imported audio file
+#[derive(Debug, Default, Deserialize)]
+struct AudioMetadataOverride {
+    creation_time: Option<DateTime<Utc>>,
+    device_name: Option<String>,
+}
+
+/// Device imported audio is recorded under when its sidecar doesn't name one
+const IMPORTED_AUDIO_DEVICE: &str = "imported_files";
+
+/// Prints the items of a JSON progress stream, `{"version":1,"stream":[...]}`
+struct JsonStream {
+    items: usize,
+}
+
+impl JsonStream {
+    fn start() -> Self {
+        println!("{{\"version\":1,\"stream\":[");
+        Self { items: 0 }
+    }
+
+    fn push(&mut self, item: Value) {
+        if self.items > 0 {
+            print!(",");
+        }
+        print!("{}", item);
+        self.items += 1;
+    }
+
+    fn end(self) {
+        println!("]}}");
+    }
+}
+
+/// Transcribes a directory of existing recordings into audio chunks and transcriptions
+/// dated when they were recorded. Files already imported are skipped.
+pub async fn handle_import_audio_command(
+    path: String,
+    pattern: Option<String>,
+    db: Arc<DatabaseManager>,
+    output_format: crate::cli::OutputFormat,
+    options: AudioImportOptions,
+) -> Result<()> {
+    let audio_files = find_files(&path, pattern.as_deref(), &IMPORT_AUDIO_EXTENSIONS)?;
+    info!("found {} audio files to import", audio_files.len());
+
+    let ffprobe_path = find_ffmpeg_path()
+        .map(|path| path.with_file_name("ffprobe"))
+        .unwrap_or_else(|| PathBuf::from("ffprobe"));
+    let importer = AudioImporter::new(options).await?;
+
+    let mut stream = match output_format {
+        crate::cli::OutputFormat::Json => Some(JsonStream::start()),
+        crate::cli::OutputFormat::Text => None,
+    };
+    let mut imported_files = 0;
+    let mut skipped_files = 0;
+    let mut failed_files = 0;
+    let mut total_transcriptions = 0;
+
+    for audio_path in &audio_files {
+        let file_path = audio_path.to_string_lossy().to_string();
+
+        if db.find_audio_chunk(&file_path).await?.is_some() {
+            skipped_files += 1;
+            match stream.as_mut() {
+                Some(stream) => stream.push(json!({
+                    "type": "file",
+                    "data": { "path": file_path, "status": "skipped" }
+                })),
+                None => println!("skipping {}, already imported", file_path),
+            }
+            continue;
+        }
+
+        let sidecar_path = PathBuf::from(format!("{}.json", file_path));
+        let sidecar = if sidecar_path.exists() {
+            let content = fs::read_to_string(&sidecar_path).await?;
+            serde_json::from_str::<AudioMetadataOverride>(&content)
+                .map_err(|e| anyhow::anyhow!("invalid sidecar {}: {}", sidecar_path.display(), e))?
+        } else {
+            AudioMetadataOverride::default()
+        };
+
+        let recorded_at = match sidecar.creation_time {
+            Some(creation_time) => creation_time,
+            None => get_media_creation_time(&ffprobe_path, &file_path).await?,
+        };
+        let device = AudioDevice::new(
+            sidecar
+                .device_name
+                .unwrap_or_else(|| IMPORTED_AUDIO_DEVICE.to_string()),
+            DeviceType::Input,
+        );
+
+        if stream.is_none() {
+            println!("importing {} recorded at {}", file_path, recorded_at);
+        }
+
+        let result = importer
+            .import_file(
+                &db,
+                audio_path,
+                &device,
+                recorded_at,
+                |transcription| match stream.as_mut() {
+                    Some(stream) => stream.push(json!({
+                        "type": "transcription",
+                        "data": {
+                            "path": file_path,
+                            "id": transcription.id,
+                            "timestamp": transcription.timestamp,
+                            "start_time": transcription.start_time,
+                            "end_time": transcription.end_time,
+                            "speaker_id": transcription.speaker_id,
+                            "transcription": transcription.transcription,
+                        }
+                    })),
+                    None => println!(
+                        "  [{:.1}s] speaker {}: {}",
+                        transcription.start_time,
+                        transcription.speaker_id,
+                        transcription.transcription
+                    ),
+                },
+            )
+            .await;
+
+        match result {
+            Ok(imported) => {
+                imported_files += 1;
+                total_transcriptions += imported.transcriptions;
+                match stream.as_mut() {
+                    Some(stream) => stream.push(json!({
+                        "type": "file",
+                        "data": {
+                            "path": file_path,
+                            "status": "imported",
+                            "recorded_at": recorded_at,
+                            "device": device.name,
+                            "audio_chunk_id": imported.audio_chunk_id,
+                            "duration": imported.duration,
+                            "transcriptions": imported.transcriptions,
+                        }
+                    })),
+                    None => println!(
+                        "imported {} transcriptions from {:.0}s of audio",
+                        imported.transcriptions, imported.duration
+                    ),
+                }
+            }
+            Err(e) => {
+                failed_files += 1;
+                error!("failed to import {}: {}", file_path, e);
+                match stream.as_mut() {
+                    Some(stream) => stream.push(json!({
+                        "type": "file",
+                        "data": { "path": file_path, "status": "failed", "error": e.to_string() }
+                    })),
+                    None => println!("failed to import {}: {}", file_path, e),
+                }
+            }
+        }
+    }
+
+    match stream {
+        Some(mut stream) => {
+            stream.push(json!({
+                "type": "summary",
+                "data": {
+                    "total_files": audio_files.len(),
+                    "imported_files": imported_files,
+                    "skipped_files": skipped_files,
+                    "failed_files": failed_files,
+                    "total_transcriptions": total_transcriptions,
+                }
+            }));
+            stream.end();
+        }
+        None => println!(
+            "imported {} of {} files ({} skipped, {} failed), {} transcriptions",
+            imported_files,
+            audio_files.len(),
+            skipped_files,
+            failed_files,
+            total_transcriptions
+        ),
+    }
+
+    Ok(())
+}
+
 fn find_video_files(root: &str, pattern: Option<&str>) -> Result<Vec<PathBuf>> {
-    let mut video_files = Vec::new();
+    find_files(root, pattern, &["mp4", "mov", "avi"])
+}
+
+fn find_files(root: &str, pattern: Option<&str>, extensions: &[&str]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
     let regex = pattern.map(Regex::new).transpose()?;
 
     for entry in WalkDir::new(root)
@@ -312,18 +591,20 @@ fn find_video_files(root: &str, pattern: Option<&str>) -> Result<Vec<PathBuf>> {
         let path = entry.path();
         if path.is_file() {
             if let Some(ext) = path.extension() {
-                if ext == "mp4" || ext == "mov" || ext == "avi" {
+                let ext = ext.to_string_lossy().to_lowercase();
+                if extensions.contains(&ext.as_str()) {
                     if let Some(ref regex) = regex {
                         if regex.is_match(&path.to_string_lossy()) {
-                            video_files.push(path.to_path_buf());
+                            files.push(path.to_path_buf());
                         }
                     } else {
-                        video_files.push(path.to_path_buf());
+                        files.push(path.to_path_buf());
                     }
                 }
             }
         }
     }
 
-    Ok(video_files)
+    files.sort();
+    Ok(files)
 }
@@ -0,0 +1,335 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Json as JsonResponse, Response},
+};
+use chrono::{Duration, Utc};
+use cubby_db::{ApiToken, TokenScope};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::server::AppState;
+
+/// How the API is protected
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    /// Require a token with the right scope on every route except health checks. Off by
+    /// default: the bundled app, cubby-js and pipes call localhost without a token.
+    pub enabled: bool,
+    /// Browser origins allowed besides localhost, e.g. `https://app.example.com`
+    pub cors_origins: Vec<String>,
+}
+
+/// A new random token. It is only ever shown to the user, the database keeps its hash.
+pub fn generate_token() -> String {
+    format!(
+        "cubby_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Scope needed to call `path`, `None` for routes that are always public. New GET routes
+/// default to `search:read`, anything else that is not listed here needs `admin`.
+pub fn required_scope(method: &Method, path: &str) -> Option<TokenScope> {
+    let scope = match path {
+        "/health" | "/ws/health" | "/openapi.yaml" | "/openapi.json" => return None,
+        "/audio/list" | "/vision/list" => TokenScope::AudioRead,
+        "/audio/start" | "/audio/stop" | "/audio/device/start" | "/audio/device/stop" => {
+            TokenScope::AudioWrite
+        }
+        "/add"
+        | "/index"
+        | "/speakers/update"
+        | "/speakers/delete"
+        | "/speakers/hallucination"
+        | "/speakers/merge"
+        | "/speakers/recluster" => TokenScope::DataWrite,
+        "/open-application" | "/open-url" | "/notify" => TokenScope::OperatorWrite,
+        "/raw_sql" | "/data" => TokenScope::Admin,
+        "/search/keyword"
+        | "/semantic-search"
+        | "/v1/embeddings"
+        | "/experimental/frames/merge"
+        | "/experimental/validate/media" => TokenScope::SearchRead,
+        p if p.starts_with("/experimental/operator") => TokenScope::OperatorWrite,
+        p if p.starts_with("/retention/") => TokenScope::Admin,
+        // pipes run arbitrary code and their configs hold the pipes' own secrets
+        p if p.starts_with("/pipes/") => TokenScope::Admin,
+        // webhooks send events anywhere and list the urls they go to
+        p if p == "/webhooks" || p.starts_with("/webhooks/") => TokenScope::Admin,
+        p if p.starts_with("/tags/") && method != Method::GET => TokenScope::DataWrite,
+        p if p.starts_with("/index/") && method != Method::GET => TokenScope::DataWrite,
+        // tools that drive the mouse and keyboard are checked again per call
+        p if p == "/mcp" || p.starts_with("/mcp/") => TokenScope::SearchRead,
+        _ if method == Method::GET => TokenScope::SearchRead,
+        _ => TokenScope::Admin,
+    };
+    Some(scope)
+}
+
+/// Websockets and event streams can't set headers from a browser, so they may pass the
+/// token as a `token` query parameter instead
+fn accepts_query_token(path: &str) -> bool {
+    path.starts_with("/ws/") || path == "/stream/frames" || path == "/frames/export"
+}
+
+fn request_token(request: &Request) -> Option<String> {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+    if bearer.is_some() || !accepts_query_token(request.uri().path()) {
+        return bearer;
+    }
+
+    request
+        .uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("token="))
+        .map(|token| token.to_string())
+}
+
+/// `uri` with the value of its `token` query parameter hidden, for request logs
+pub fn redacted_uri(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some(("token", _)) => "token=redacted",
+            _ => pair,
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}?{}", uri.path(), query)
+}
+
+fn auth_error(status: StatusCode, message: &str) -> Response {
+    (status, JsonResponse(json!({ "error": message }))).into_response()
+}
+
+/// Middleware checking the bearer token of every request against the scope of its route.
+/// The resolved `ApiToken` is added to the request extensions for handlers that need
+/// finer grained checks.
+pub async fn require_token(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !state.auth_enabled || request.method() == Method::OPTIONS {
+        return next.run(request).await;
+    }
+    let Some(scope) = required_scope(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let Some(token) = request_token(&request) else {
+        return auth_error(StatusCode::UNAUTHORIZED, "missing api token");
+    };
+
+    // requests the server makes to itself, e.g. from mcp tools. Compared in constant time
+    // so response times don't give the token away.
+    if bool::from(token.as_bytes().ct_eq(state.internal_token.as_bytes())) {
+        return next.run(request).await;
+    }
+
+    let api_token = match state.db.get_active_api_token(&hash_token(&token)).await {
+        Ok(Some(api_token)) => api_token,
+        Ok(None) => return auth_error(StatusCode::UNAUTHORIZED, "invalid or revoked api token"),
+        Err(e) => {
+            warn!("failed to look up api token: {}", e);
+            return auth_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to check api token",
+            );
+        }
+    };
+
+    if !api_token.allows(scope) {
+        debug!(
+            "token {} lacks scope {} for {}",
+            api_token.name,
+            scope,
+            request.uri().path()
+        );
+        return auth_error(
+            StatusCode::FORBIDDEN,
+            &format!("api token lacks the {} scope", scope),
+        );
+    }
+
+    let stale = match api_token.last_used_at {
+        Some(used) => Utc::now() - used > Duration::minutes(1),
+        None => true,
+    };
+    if stale {
+        let db = state.db.clone();
+        let id = api_token.id;
+        tokio::spawn(async move {
+            if let Err(e) = db.touch_api_token(id).await {
+                debug!("failed to update api token last use: {}", e);
+            }
+        });
+    }
+
+    request.extensions_mut().insert(api_token);
+    next.run(request).await
+}
+
+/// Whether the token attached to a request by `require_token` may use `scope`. Always true
+/// when auth is disabled or the request came from the server itself.
+pub fn token_allows(state: &AppState, token: Option<&ApiToken>, scope: TokenScope) -> bool {
+    !state.auth_enabled || token.is_some_and(|token| token.allows(scope))
+}
+
+fn is_local_origin(origin: &HeaderValue) -> bool {
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+    let host = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+        .or_else(|| origin.strip_prefix("tauri://"))
+        .unwrap_or_default();
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if !port.ends_with(']') => host,
+        _ => host,
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// CORS for browser clients: localhost on any port plus the configured origins
+pub fn cors_layer(config: &AuthConfig) -> CorsLayer {
+    let allowed: Vec<HeaderValue> = config
+        .cors_origins
+        .iter()
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(origin) => Some(origin),
+            Err(e) => {
+                warn!("ignoring invalid cors origin {}: {}", origin, e);
+                None
+            }
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            is_local_origin(origin) || allowed.contains(origin)
+        }))
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers([header::CONTENT_TYPE, header::CACHE_CONTROL])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_scope() {
+        assert_eq!(required_scope(&Method::GET, "/health"), None);
+        assert_eq!(
+            required_scope(&Method::GET, "/search"),
+            Some(TokenScope::SearchRead)
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/tags/vision/1"),
+            Some(TokenScope::SearchRead)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/tags/vision/1"),
+            Some(TokenScope::DataWrite)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/experimental/operator/click"),
+            Some(TokenScope::OperatorWrite)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/index/jobs/1/cancel"),
+            Some(TokenScope::DataWrite)
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/index/jobs/1"),
+            Some(TokenScope::SearchRead)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/raw_sql"),
+            Some(TokenScope::Admin)
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/pipes/list"),
+            Some(TokenScope::Admin)
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/webhooks"),
+            Some(TokenScope::Admin)
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/webhooks/dead-letters"),
+            Some(TokenScope::Admin)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/some/new/route"),
+            Some(TokenScope::Admin)
+        );
+    }
+
+    #[test]
+    fn test_redacted_uri() {
+        for (uri, redacted) in [
+            ("/ws/events?token=cubby_abc", "/ws/events?token=redacted"),
+            (
+                "/stream/frames?start_time=1&token=cubby_abc&end_time=2",
+                "/stream/frames?start_time=1&token=redacted&end_time=2",
+            ),
+            ("/search?q=token", "/search?q=token"),
+            ("/health", "/health"),
+        ] {
+            assert_eq!(redacted_uri(&uri.parse().unwrap()), redacted);
+        }
+    }
+
+    #[test]
+    fn test_local_origins() {
+        for origin in [
+            "http://localhost:3000",
+            "http://127.0.0.1",
+            "http://[::1]:8080",
+            "tauri://localhost",
+        ] {
+            assert!(
+                is_local_origin(&HeaderValue::from_static(origin)),
+                "{}",
+                origin
+            );
+        }
+        for origin in [
+            "https://example.com",
+            "http://localhost.example.com",
+            "null",
+        ] {
+            assert!(
+                !is_local_origin(&HeaderValue::from_static(origin)),
+                "{}",
+                origin
+            );
+        }
+    }
+}
@@ -3,18 +3,25 @@ use clap::Parser;
 use colored::Colorize;
 use cubby_audio::{
     audio_manager::{AudioManagerBuilder, RealtimeBackend},
-    core::device::{default_input_device, default_output_device, parse_audio_device},
+    core::{
+        device::{default_input_device, default_output_device, parse_audio_device},
+        source::AudioSource,
+    },
+    import::AudioImportOptions,
 };
 use cubby_core::find_ffmpeg_path;
-use cubby_db::DatabaseManager;
+use cubby_db::{DatabaseManager, RetentionConfig, TokenScope};
+use cubby_events::run_detectors;
 use cubby_server::{
+    auth::{generate_token, hash_token},
     cli::{
         Cli, CliApp, CliAudioTranscriptionEngine, CliCommand, CliOcrEngine, CliVadEngine,
-        CliVadSensitivity,
+        CliVadSensitivity, ImportCli, ImportCommand, IndexCli, McpCli, OutputFormat, TokenCli,
+        TokenCommand,
     },
     permission_checker::{trigger_and_check_microphone, trigger_and_check_screen_recording},
     setup_state::{SetupState, TranscriptionBackendPreference},
-    start_continuous_recording, ResourceMonitor, SCServer,
+    start_continuous_recording, AppState, PipeManager, ResourceMonitor, SCServer,
 };
 use cubby_vision::monitor::list_monitors;
 #[cfg(target_os = "macos")]
@@ -154,6 +161,10 @@ async fn main() -> anyhow::Result<()> {
             run_service(&cli).await
         }
         CliCommand::Uninstall => handle_uninstall().await,
+        CliCommand::Token(token_cli) => handle_token_command(token_cli).await,
+        CliCommand::Import(import_cli) => handle_import_command(import_cli).await,
+        CliCommand::Index(index_cli) => handle_index_command(index_cli).await,
+        CliCommand::Mcp(mcp_cli) => handle_mcp_command(mcp_cli).await,
     }
 }
 
@@ -229,6 +240,12 @@ async fn run_service(cli: &Cli) -> anyhow::Result<()> {
         } else {
             // Use specified devices
             for d in &cli.audio_device {
+                if AudioSource::is_source_name(d) {
+                    d.parse::<AudioSource>()
+                        .expect("failed to parse audio source");
+                    audio_devices.push(d.clone());
+                    continue;
+                }
                 let device = parse_audio_device(d).expect("failed to parse audio device");
                 audio_devices.push(device.to_string());
             }
@@ -431,15 +448,30 @@ async fn run_service(cli: &Cli) -> anyhow::Result<()> {
     #[cfg(feature = "llm")]
     debug!("LLM initialized");
 
+    let embedding_backend = cli.embedding.backend();
+    let pipe_manager = Arc::new(PipeManager::new(local_data_dir_clone_2.clone()));
+
     let server = SCServer::new(
         db_server,
         SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), cli.port),
         local_data_dir_clone_2,
+        pipe_manager.clone(),
         cli.disable_vision,
         cli.disable_audio,
         cli.enable_ui_monitoring,
         audio_manager.clone(),
+        RetentionConfig::new(cli.retention_policy(), Some(3600)),
+        cli.raw_sql_options(),
+        cli.auth_config(),
+        embedding_backend.clone(),
+        cli.event_journal_days,
     );
+    if !cli.enable_auth {
+        warn!(
+            "api tokens are not required: every route, /raw_sql included, is open to anything that can reach port {}. start with --enable-auth to require them",
+            cli.port
+        );
+    }
 
     println!(
         "{}\n\n",
@@ -514,10 +546,42 @@ async fn run_service(cli: &Cli) -> anyhow::Result<()> {
         "│ frame cache            │ {:<34} │",
         cli.enable_frame_cache
     );
+    println!(
+        "│ embeddings             │ {:<34} │",
+        format_cell(
+            embedding_backend
+                .as_ref()
+                .map_or("disabled", |backend| backend.model()),
+            VALUE_WIDTH
+        )
+    );
     println!(
         "│ capture unfocused wins │ {:<34} │",
         cli.capture_unfocused_windows
     );
+    println!(
+        "│ retention              │ {:<34} │",
+        format_cell(
+            &match (cli.retention_days, cli.retention_max_disk_gb) {
+                (None, None) => "keep forever".to_string(),
+                (days, gb) => format!("days: {:?}, max disk gb: {:?}", days, gb),
+            },
+            VALUE_WIDTH
+        )
+    );
+    println!(
+        "│ event journal          │ {:<34} │",
+        cli.event_journal_days
+            .map_or("disabled".to_string(), |days| format!("{} days", days))
+    );
+    println!(
+        "│ api tokens             │ {:<34} │",
+        if cli.enable_auth {
+            "required"
+        } else {
+            "disabled"
+        }
+    );
     println!(
         "│ auto-destruct pid      │ {:<34} │",
         cli.auto_destruct_pid.unwrap_or(0)
@@ -697,6 +761,17 @@ async fn run_service(cli: &Cli) -> anyhow::Result<()> {
         });
     }
 
+    debug!("starting pipes");
+    pipe_manager.start_enabled_pipes().await;
+
+    // meeting detection and the detectors users add to the detectors directory
+    let detectors_dir = local_data_dir.join("detectors");
+    tokio::spawn(async move {
+        if let Err(e) = run_detectors(Some(detectors_dir)).await {
+            error!("detectors stopped: {}", e);
+        }
+    });
+
     let server_future = server.start(cli.enable_frame_cache);
     pin_mut!(server_future);
 
@@ -742,6 +817,7 @@ async fn run_service(cli: &Cli) -> anyhow::Result<()> {
         _ = ctrl_c_future => {
             info!("received ctrl+c, initiating shutdown");
             audio_manager.shutdown().await?;
+            pipe_manager.stop_all_pipes().await;
             let _ = shutdown_tx.send(());
         }
     }
@@ -1107,6 +1183,152 @@ async fn ensure_permissions_in_service(cli: &Cli, state: &mut SetupState) -> any
     Ok(())
 }
 
+async fn handle_token_command(token_cli: TokenCli) -> anyhow::Result<()> {
+    let local_data_dir = get_base_dir(&token_cli.data_dir)?;
+    let db = DatabaseManager::new(&format!("{}/db.sqlite", local_data_dir.to_string_lossy()))
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+
+    match token_cli.command {
+        TokenCommand::Create { name, scopes } => {
+            let token = generate_token();
+            let id = db
+                .create_api_token(&name, &hash_token(&token), &scopes)
+                .await?;
+            println!(
+                "created token {} ({}) with scopes: {}",
+                id,
+                name,
+                scopes_list(&scopes)
+            );
+            println!();
+            println!("{}", token.bright_green());
+            println!();
+            println!("store it now, it can't be shown again");
+        }
+        TokenCommand::List { output } => {
+            let tokens = db.list_api_tokens().await?;
+            match output {
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&tokens)?),
+                OutputFormat::Text => {
+                    if tokens.is_empty() {
+                        println!("no tokens, create one with `cubby token create`");
+                    }
+                    for token in tokens {
+                        let status = match (token.revoked_at, token.last_used_at) {
+                            (Some(revoked_at), _) => {
+                                format!("revoked {}", revoked_at.format("%Y-%m-%d"))
+                            }
+                            (None, Some(used)) => {
+                                format!("last used {}", used.format("%Y-%m-%d %H:%M"))
+                            }
+                            (None, None) => "never used".to_string(),
+                        };
+                        println!(
+                            "{:>4}  {:<20} {:<40} {}",
+                            token.id,
+                            token.name,
+                            scopes_list(&token.scopes),
+                            status
+                        );
+                    }
+                }
+            }
+        }
+        TokenCommand::Revoke { id } => {
+            if db.revoke_api_token(id).await? {
+                println!("revoked token {}", id);
+            } else {
+                anyhow::bail!("no active token with id {}", id);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_import_command(import_cli: ImportCli) -> anyhow::Result<()> {
+    let local_data_dir = get_base_dir(&import_cli.data_dir)?;
+    let db = DatabaseManager::new(&format!("{}/db.sqlite", local_data_dir.to_string_lossy()))
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+
+    match import_cli.command {
+        ImportCommand::Audio {
+            path,
+            pattern,
+            audio_transcription_engine,
+            language,
+            vad_engine,
+            deepgram_api_key,
+            output,
+        } => {
+            let mut options = AudioImportOptions::default();
+            if let Some(engine) = audio_transcription_engine {
+                options.transcription_engine = Arc::new(engine.into());
+            }
+            if let Some(vad_engine) = vad_engine {
+                options.vad_engine = vad_engine.into();
+            }
+            if deepgram_api_key.is_some() {
+                options.deepgram_api_key = deepgram_api_key;
+            }
+            options.languages = language;
+
+            cubby_server::handle_import_audio_command(path, pattern, Arc::new(db), output, options)
+                .await
+        }
+    }
+}
+
+async fn handle_index_command(index_cli: IndexCli) -> anyhow::Result<()> {
+    let local_data_dir = get_base_dir(&index_cli.data_dir)?;
+    let db = DatabaseManager::new(&format!("{}/db.sqlite", local_data_dir.to_string_lossy()))
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+
+    cubby_server::handle_index_command(
+        local_data_dir,
+        index_cli.path,
+        index_cli.pattern,
+        Arc::new(db),
+        index_cli.output,
+        index_cli.ocr_engine,
+        index_cli.metadata_override,
+        index_cli.copy_videos,
+        index_cli.use_embedding,
+        index_cli.embedding.backend(),
+    )
+    .await
+}
+
+async fn handle_mcp_command(mcp_cli: McpCli) -> anyhow::Result<()> {
+    // stdout carries the protocol
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .init();
+
+    let local_data_dir = get_base_dir(&mcp_cli.data_dir)?;
+    let db =
+        DatabaseManager::new_read_only(&format!("{}/db.sqlite", local_data_dir.to_string_lossy()))
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+
+    let state = AppState::without_capture(Arc::new(db), local_data_dir);
+    cubby_server::mcp::server::serve_stdio(Arc::new(state)).await
+}
+
+fn scopes_list(scopes: &[TokenScope]) -> String {
+    scopes
+        .iter()
+        .map(|scope| scope.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 async fn handle_uninstall() -> anyhow::Result<()> {
     use cubby_server::cloudflared_downloader::{cleanup_cloudflared, ensure_cloudflared};
     use cubby_server::cloudflared_manager::CloudflaredManager;
@@ -1353,6 +1575,35 @@ fn build_service_args(cli: &Cli, state: &SetupState) -> Vec<String> {
         args.push("--capture-unfocused-windows".to_string());
     }
 
+    if let Some(days) = cli.retention_days {
+        args.push("--retention-days".to_string());
+        args.push(days.to_string());
+    }
+
+    if let Some(gb) = cli.retention_max_disk_gb {
+        args.push("--retention-max-disk-gb".to_string());
+        args.push(gb.to_string());
+    }
+
+    if let Some(days) = cli.event_journal_days {
+        args.push("--event-journal-days".to_string());
+        args.push(days.to_string());
+    }
+
+    if cli.enable_auth {
+        args.push("--enable-auth".to_string());
+    }
+
+    for origin in &cli.cors_origin {
+        args.push("--cors-origin".to_string());
+        args.push(origin.clone());
+    }
+
+    args.push("--raw-sql-max-rows".to_string());
+    args.push(cli.raw_sql_max_rows.to_string());
+    args.push("--raw-sql-timeout-ms".to_string());
+    args.push(cli.raw_sql_timeout_ms.to_string());
+
     if enable_realtime {
         args.push("--enable-realtime-audio-transcription".to_string());
     }
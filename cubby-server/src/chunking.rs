@@ -97,6 +97,45 @@ pub fn text_chunking_simple(text: &str) -> Result<Vec<String>> {
     Ok(chunks)
 }
 
+/// Packs the lines of a text into chunks of at most `max_chars` characters. Lines longer
+/// than that are cut every `max_chars` characters.
+pub fn text_chunking_by_lines(text: &str, max_chars: usize) -> Result<Vec<String>> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let line_len = line.chars().count();
+        if line_len > max_chars {
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            let chars: Vec<char> = line.chars().collect();
+            chunks.extend(chars.chunks(max_chars).map(|piece| piece.iter().collect()));
+            continue;
+        }
+
+        if current_len > 0 && current_len + 1 + line_len > max_chars {
+            chunks.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if current_len > 0 {
+            current.push('\n');
+            current_len += 1;
+        }
+        current.push_str(line);
+        current_len += line_len;
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    Ok(chunks)
+}
+
 fn cosine_similarity(a: &Tensor, b: &Tensor) -> Result<f32> {
     let a = a.flatten_all()?;
     let b = b.flatten_all()?;
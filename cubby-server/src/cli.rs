@@ -1,5 +1,8 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::auth::AuthConfig;
+use crate::embedding::backend::{CandleEmbeddingBackend, EmbeddingBackend, OllamaEmbeddingBackend};
 use clap::ValueEnum;
 use clap::{Args, Parser, Subcommand, ValueHint};
 use cubby_audio::{
@@ -9,7 +12,9 @@ use cubby_audio::{
 use cubby_core::Language;
 use cubby_db::CustomOcrConfig as DBCustomOcrConfig;
 use cubby_db::OcrEngine as DBOcrEngine;
+use cubby_db::{RawSqlOptions, RetentionPolicy, TokenScope};
 use cubby_vision::{custom_ocr::CustomOcrConfig, utils::OcrEngine as CoreOcrEngine};
+use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, ValueEnum, PartialEq)]
 pub enum CliAudioTranscriptionEngine {
     #[clap(name = "deepgram")]
@@ -59,7 +64,8 @@ impl From<CliAudioTranscriptionEngine> for CoreAudioTranscriptionEngine {
     }
 }
 
-#[derive(Clone, Debug, ValueEnum, PartialEq)]
+#[derive(Clone, Debug, ValueEnum, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum CliOcrEngine {
     Unstructured,
     #[cfg(target_os = "linux")]
@@ -147,6 +153,46 @@ impl From<CliVadSensitivity> for VadSensitivity {
     }
 }
 
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliEmbeddingBackend {
+    /// jina-embeddings-v2-base-en, run in process
+    Candle,
+    /// A model served by Ollama
+    Ollama,
+    Disabled,
+}
+
+/// How text is embedded for semantic search
+#[derive(Args, Clone, Debug)]
+pub struct EmbeddingArgs {
+    /// Embedding backend for semantic search. Text embedded with one backend is embedded
+    /// again when switching to another.
+    #[arg(long, value_enum, default_value_t = CliEmbeddingBackend::Candle)]
+    pub embedding_backend: CliEmbeddingBackend,
+
+    /// Ollama server of the ollama embedding backend
+    #[arg(long, default_value = "http://localhost:11434")]
+    pub ollama_url: String,
+
+    /// Model of the ollama embedding backend
+    #[arg(long, default_value = "nomic-embed-text")]
+    pub ollama_embedding_model: String,
+}
+
+impl EmbeddingArgs {
+    /// `None` when embeddings are disabled
+    pub fn backend(&self) -> Option<Arc<dyn EmbeddingBackend>> {
+        match self.embedding_backend {
+            CliEmbeddingBackend::Candle => Some(Arc::new(CandleEmbeddingBackend)),
+            CliEmbeddingBackend::Ollama => Some(Arc::new(OllamaEmbeddingBackend::new(
+                &self.ollama_url,
+                &self.ollama_embedding_model,
+            ))),
+            CliEmbeddingBackend::Disabled => None,
+        }
+    }
+}
+
 #[derive(Args, Clone, Debug)]
 #[command(author, version, about, long_about = None, name = "cubby")]
 pub struct Cli {
@@ -171,7 +217,9 @@ pub struct Cli {
     #[arg(long, default_value_t = false)]
     pub disable_audio: bool,
 
-    /// Audio devices to use (can be specified multiple times)
+    /// Audio devices to use (can be specified multiple times). Also accepts audio sources:
+    /// file:<path>, pcm:[<s16le|f32le>:<sample rate>:<channels>:]<path or -> and
+    /// synthetic:<silence|noise|sine[:<frequency>]>
     #[arg(short = 'i', long)]
     pub audio_device: Vec<String>,
 
@@ -293,9 +341,66 @@ pub struct Cli {
     /// Capture windows that are not focused (default: false)
     #[arg(long, default_value_t = false)]
     pub capture_unfocused_windows: bool,
+
+    /// Delete recorded screen, audio and UI data older than this many days (default: keep forever)
+    #[arg(long)]
+    pub retention_days: Option<u32>,
+
+    /// Delete the oldest video and audio files once they use more than this many GB of disk
+    #[arg(long)]
+    pub retention_max_disk_gb: Option<f64>,
+
+    /// Journal the events of /ws/events for this many days, so that clients can replay
+    /// what they missed with `since` (default: no journal)
+    #[arg(long)]
+    pub event_journal_days: Option<u32>,
+
+    /// Maximum number of rows returned by the /raw_sql endpoint
+    #[arg(long, default_value_t = 10_000)]
+    pub raw_sql_max_rows: usize,
+
+    /// Interrupt /raw_sql queries running longer than this many milliseconds
+    #[arg(long, default_value_t = 10_000)]
+    pub raw_sql_timeout_ms: u64,
+
+    /// Require api tokens (see `cubby token create`) on every route except health checks.
+    /// Without it any local process can call every route
+    #[arg(long, default_value_t = false)]
+    pub enable_auth: bool,
+
+    /// Browser origin allowed to call the api besides localhost (can be repeated)
+    #[arg(long)]
+    pub cors_origin: Vec<String>,
+
+    #[command(flatten)]
+    pub embedding: EmbeddingArgs,
 }
 
 impl Cli {
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age_days: self.retention_days,
+            rules: vec![],
+            max_disk_bytes: self
+                .retention_max_disk_gb
+                .map(|gb| (gb * 1024.0 * 1024.0 * 1024.0) as u64),
+        }
+    }
+
+    pub fn raw_sql_options(&self) -> RawSqlOptions {
+        RawSqlOptions {
+            max_rows: self.raw_sql_max_rows,
+            timeout: std::time::Duration::from_millis(self.raw_sql_timeout_ms),
+        }
+    }
+
+    pub fn auth_config(&self) -> AuthConfig {
+        AuthConfig {
+            enabled: self.enable_auth,
+            cors_origins: self.cors_origin.clone(),
+        }
+    }
+
     pub fn unique_languages(&self) -> Result<Vec<Language>, String> {
         let mut unique_langs = std::collections::HashSet::new();
         for lang in &self.language {
@@ -337,4 +442,123 @@ pub enum CliCommand {
     Service(Cli),
     /// Uninstall cubby service and clean up all data
     Uninstall,
+    /// Manage api tokens
+    Token(TokenCli),
+    /// Import existing recordings
+    Import(ImportCli),
+    /// Index screen recordings (mp4, mov, avi): extract their frames and text so they can
+    /// be searched like recorded screens. The recording time is read from the file metadata.
+    Index(IndexCli),
+    /// Serve MCP over stdio, for MCP clients that launch cubby themselves. The recorded
+    /// data is only read, recording stays with the service.
+    Mcp(McpCli),
+}
+
+#[derive(Args, Debug)]
+pub struct TokenCli {
+    #[command(subcommand)]
+    pub command: TokenCommand,
+
+    /// Data directory. Default to $HOME/.cubby
+    #[arg(long, value_hint = ValueHint::DirPath, global = true)]
+    pub data_dir: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TokenCommand {
+    /// Create a token, it is only printed once
+    Create {
+        /// Name to recognize the token by
+        #[arg(long)]
+        name: String,
+        /// Scope granted to the token (can be repeated): search:read, audio:read,
+        /// audio:write, data:write, operator:write or admin
+        #[arg(long = "scope", required = true)]
+        scopes: Vec<TokenScope>,
+    },
+    /// List tokens
+    List {
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Revoke a token by id
+    Revoke { id: i64 },
+}
+
+#[derive(Args, Debug)]
+pub struct IndexCli {
+    /// Video file or directory to index
+    #[arg(value_hint = ValueHint::AnyPath)]
+    pub path: String,
+    /// Only index files whose path matches this regex
+    #[arg(long)]
+    pub pattern: Option<String>,
+    /// OCR engine to use, the platform default when omitted
+    #[arg(long, value_enum)]
+    pub ocr_engine: Option<CliOcrEngine>,
+    /// JSON file overriding the metadata of the indexed files, every file needs an entry:
+    /// `{"overrides": [{"file_path": "...", "metadata": {"creation_time": "...", ...}}]}`
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub metadata_override: Option<PathBuf>,
+    /// Copy the videos to the data directory. Videos are indexed in place otherwise,
+    /// deleting data from cubby may delete them.
+    #[arg(long, default_value_t = false)]
+    pub copy_videos: bool,
+    /// Embed the extracted text for semantic search while indexing. The server embeds
+    /// it otherwise, once it runs.
+    #[arg(long, default_value_t = false)]
+    pub use_embedding: bool,
+    #[command(flatten)]
+    pub embedding: EmbeddingArgs,
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+    /// Data directory. Default to $HOME/.cubby
+    #[arg(long, value_hint = ValueHint::DirPath)]
+    pub data_dir: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct McpCli {
+    /// Data directory. Default to $HOME/.cubby
+    #[arg(long, value_hint = ValueHint::DirPath)]
+    pub data_dir: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportCli {
+    #[command(subcommand)]
+    pub command: ImportCommand,
+
+    /// Data directory. Default to $HOME/.cubby
+    #[arg(long, value_hint = ValueHint::DirPath, global = true)]
+    pub data_dir: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportCommand {
+    /// Transcribe audio recordings (wav, mp3, m4a, aac, flac, ogg) with speaker
+    /// identification. The recording time is read from the file metadata, a `<file>.json`
+    /// sidecar with `creation_time` and `device_name` overrides it. Recordings are indexed
+    /// in place, deleting data from cubby may delete them.
+    Audio {
+        /// File or directory to import
+        #[arg(value_hint = ValueHint::AnyPath)]
+        path: String,
+        /// Only import files whose path matches this regex
+        #[arg(long)]
+        pattern: Option<String>,
+        /// Audio transcription engine to use
+        #[arg(short = 'a', long, value_enum)]
+        audio_transcription_engine: Option<CliAudioTranscriptionEngine>,
+        #[arg(short = 'l', long, value_enum)]
+        language: Vec<Language>,
+        /// VAD engine to use for speech detection
+        #[arg(long, value_enum)]
+        vad_engine: Option<CliVadEngine>,
+        /// Deepgram API Key for audio transcription
+        #[arg(long = "deepgram-api-key")]
+        deepgram_api_key: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
 }
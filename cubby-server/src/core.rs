@@ -1,9 +1,10 @@
+use crate::meetings::record_meetings;
 use crate::VideoCapture;
 use anyhow::Result;
 use cubby_core::pii_removal::remove_pii;
 use cubby_core::Language;
 use cubby_db::{DatabaseManager, Speaker};
-use cubby_events::{poll_meetings_events, send_event};
+use cubby_events::send_event;
 use cubby_vision::core::WindowOcr;
 use cubby_vision::OcrEngine;
 use futures::future::join_all;
@@ -119,11 +120,10 @@ pub async fn start_continuous_recording(
     };
 
     if !vision_disabled {
+        let meetings_db = db.clone();
         vision_handle.spawn(async move {
-            info!("Starting meeting events polling");
-            match poll_meetings_events().await {
-                Ok(_) => warn!("Meeting events polling completed unexpectedly"),
-                Err(e) => error!("Meeting events polling failed: {}", e),
+            if let Err(e) = record_meetings(meetings_db).await {
+                error!("Meeting recording failed: {}", e);
             }
         });
     }
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::embedding_endpoint::get_or_initialize_model;
+
+/// Turns text into vectors for semantic search. Vectors of different backends, or of
+/// different models of a backend, can't be compared: each stored vector records the
+/// `model` it came from.
+pub trait EmbeddingBackend: Send + Sync {
+    /// Identifies the vectors, e.g. `candle:jina-embeddings-v2-base-en`
+    fn model(&self) -> &str;
+
+    /// One vector per text, in order
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>>;
+}
+
+/// Runs jina-embeddings-v2-base-en in process, the model is downloaded on first use and
+/// shared with `/v1/embeddings`
+#[derive(Debug, Default)]
+pub struct CandleEmbeddingBackend;
+
+impl EmbeddingBackend for CandleEmbeddingBackend {
+    fn model(&self) -> &str {
+        "candle:jina-embeddings-v2-base-en"
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(async move {
+            let model = get_or_initialize_model().await?;
+            let texts = texts.to_vec();
+            tokio::task::spawn_blocking(move || {
+                let model = model.blocking_lock();
+                // texts are embedded one by one, padding a batch would skew the mean
+                // pooling of the shorter ones
+                texts
+                    .iter()
+                    .map(|text| model.generate_embedding(text))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .await?
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaResponse {
+    embedding: Vec<f32>,
+}
+
+/// Calls the embeddings api of an Ollama server
+#[derive(Debug)]
+pub struct OllamaEmbeddingBackend {
+    client: Client,
+    url: String,
+    model_name: String,
+    model: String,
+}
+
+impl OllamaEmbeddingBackend {
+    /// `url` is the server address, e.g. `http://localhost:11434`
+    pub fn new(url: &str, model_name: &str) -> Self {
+        Self {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            model_name: model_name.to_string(),
+            model: format!("ollama:{}", model_name),
+        }
+    }
+}
+
+impl EmbeddingBackend for OllamaEmbeddingBackend {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(async move {
+            let mut embeddings = Vec::with_capacity(texts.len());
+            for text in texts {
+                let response = self
+                    .client
+                    .post(format!("{}/api/embeddings", self.url))
+                    .json(&OllamaRequest {
+                        model: &self.model_name,
+                        prompt: text,
+                    })
+                    .send()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("ollama server not reachable: {}", e))?;
+
+                if !response.status().is_success() {
+                    return Err(anyhow::anyhow!(
+                        "ollama failed to generate embedding: {}",
+                        response.status()
+                    ));
+                }
+                embeddings.push(response.json::<OllamaResponse>().await?.embedding);
+            }
+            debug!("ollama generated {} embeddings", embeddings.len());
+            Ok(embeddings)
+        })
+    }
+}
@@ -1 +1,3 @@
+pub mod backend;
 pub mod embedding_endpoint;
+pub mod worker;
@@ -0,0 +1,364 @@
+use anyhow::Result;
+use cubby_db::{DatabaseManager, EmbeddingProgress, EmbeddingSource};
+use cubby_events::send_event;
+use oasgen::OaSchema;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use super::backend::EmbeddingBackend;
+use crate::chunking::text_chunking_by_lines;
+
+/// Characters per embedded chunk of text
+const CHUNK_CHARS: usize = 512;
+
+/// Progress is counted over whole tables, so it is reported at most this often
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingWorkerConfig {
+    /// Rows of a source embedded at once
+    pub batch_size: u32,
+    /// Wait before looking for new rows once everything is embedded, or after a failure
+    pub idle_interval: Duration,
+}
+
+impl Default for EmbeddingWorkerConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 32,
+            idle_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize)]
+pub struct EmbeddingStatus {
+    pub model: String,
+    pub sources: Vec<EmbeddingProgress>,
+    /// Why the last batch failed, cleared once batches succeed again
+    pub last_error: Option<String>,
+}
+
+/// Embeds the OCR text and transcriptions recorded, indexed or imported, in the order they
+/// were stored, and the UI text of windows, again whenever it changes. Rows stored before
+/// the worker first ran with a model are backfilled the same way, picking up where it
+/// stopped on restart.
+///
+/// Backfill progress is published on the event bus as `embedding_progress`.
+pub struct EmbeddingWorker {
+    db: Arc<DatabaseManager>,
+    backend: Arc<dyn EmbeddingBackend>,
+    last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl EmbeddingWorker {
+    pub fn start(
+        db: Arc<DatabaseManager>,
+        backend: Arc<dyn EmbeddingBackend>,
+        config: EmbeddingWorkerConfig,
+    ) -> Arc<Self> {
+        let last_error = Arc::new(Mutex::new(None));
+
+        tokio::spawn(run(db.clone(), backend.clone(), config, last_error.clone()));
+
+        Arc::new(Self {
+            db,
+            backend,
+            last_error,
+        })
+    }
+
+    /// The backend search queries have to be embedded with
+    pub fn backend(&self) -> &Arc<dyn EmbeddingBackend> {
+        &self.backend
+    }
+
+    pub async fn status(&self) -> Result<EmbeddingStatus> {
+        let model = self.backend.model();
+        let mut sources = Vec::new();
+        for source in EmbeddingSource::ALL {
+            sources.push(self.db.embedding_progress(source, model).await?);
+        }
+        Ok(EmbeddingStatus {
+            model: model.to_string(),
+            sources,
+            last_error: self.last_error.lock().unwrap().clone(),
+        })
+    }
+}
+
+/// Chunks the text of rows and embeds the chunks, as (row id, vector). A chunk repeated
+/// across rows, like the text of a window that stays on screen, is embedded once.
+pub async fn embed_texts(
+    backend: &dyn EmbeddingBackend,
+    texts: &[(i64, &str)],
+) -> Result<Vec<(i64, Vec<f32>)>> {
+    let mut unique: Vec<String> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut chunks: Vec<(i64, usize)> = Vec::new();
+
+    for (id, text) in texts {
+        for chunk in text_chunking_by_lines(text, CHUNK_CHARS)? {
+            let position = *positions.entry(chunk).or_insert_with_key(|chunk| {
+                unique.push(chunk.clone());
+                unique.len() - 1
+            });
+            chunks.push((*id, position));
+        }
+    }
+    if unique.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let vectors = backend.embed(&unique).await?;
+    if vectors.len() != unique.len() {
+        return Err(anyhow::anyhow!(
+            "{} returned {} embeddings for {} texts",
+            backend.model(),
+            vectors.len(),
+            unique.len()
+        ));
+    }
+
+    Ok(chunks
+        .into_iter()
+        .map(|(id, position)| (id, vectors[position].clone()))
+        .collect())
+}
+
+async fn run(
+    db: Arc<DatabaseManager>,
+    backend: Arc<dyn EmbeddingBackend>,
+    config: EmbeddingWorkerConfig,
+    last_error: Arc<Mutex<Option<String>>>,
+) {
+    info!("embedding text with {}", backend.model());
+    let mut reported: HashMap<EmbeddingSource, Instant> = HashMap::new();
+
+    loop {
+        let mut embedded = 0;
+        let mut failure = None;
+
+        for source in EmbeddingSource::ALL {
+            match embed_batch(&db, backend.as_ref(), source, config.batch_size).await {
+                Ok(0) => {}
+                Ok(rows) => {
+                    embedded += rows;
+                    let due = reported
+                        .get(&source)
+                        .is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL);
+                    if due {
+                        reported.insert(source, Instant::now());
+                        report_progress(&db, backend.model(), source).await;
+                    }
+                }
+                Err(e) => {
+                    error!("failed to embed {} text: {}", source, e);
+                    failure = Some(format!("{}: {}", source, e));
+                }
+            }
+        }
+
+        *last_error.lock().unwrap() = failure.clone();
+        if embedded == 0 || failure.is_some() {
+            tokio::time::sleep(config.idle_interval).await;
+        }
+    }
+}
+
+/// Embeds the next rows of a source, returns how many there were. When the batch fails,
+/// its rows are embedded one at a time and those the backend rejects are skipped, so that
+/// one bad row doesn't hold back the whole source.
+async fn embed_batch(
+    db: &DatabaseManager,
+    backend: &dyn EmbeddingBackend,
+    source: EmbeddingSource,
+    batch_size: u32,
+) -> Result<usize> {
+    let model = backend.model();
+    let pending = db.pending_embeddings(source, model, batch_size).await?;
+    let Some(cursor) = pending.last().map(|row| row.cursor) else {
+        return Ok(0);
+    };
+
+    let texts: Vec<(i64, &str)> = pending
+        .iter()
+        .map(|row| (row.id, row.text.as_str()))
+        .collect();
+    let (embeddings, mut skipped) = match embed_texts(backend, &texts).await {
+        Ok(embeddings) => (embeddings, Vec::new()),
+        Err(e) => {
+            let (embeddings, rejected) = embed_one_by_one(backend, &texts, e).await?;
+            for (id, reason) in &rejected {
+                warn!(
+                    "skipping {} row {}, {} rejected it: {}",
+                    source, id, model, reason
+                );
+            }
+            (embeddings, rejected)
+        }
+    };
+    if !source.is_append_only() {
+        // without a cursor, a row whose text chunks to nothing (e.g. only non-breaking
+        // spaces) would come back on every pass
+        let done: HashSet<i64> = embeddings
+            .iter()
+            .map(|(id, _)| *id)
+            .chain(skipped.iter().map(|(id, _)| *id))
+            .collect();
+        skipped.extend(
+            pending
+                .iter()
+                .filter(|row| !done.contains(&row.id))
+                .map(|row| (row.id, "no text to embed".to_string())),
+        );
+    }
+    db.skip_text_embeddings(source, model, &skipped).await?;
+    db.insert_text_embeddings(source, model, &embeddings, Some(cursor))
+        .await?;
+
+    debug!(
+        "embedded {} {} rows into {} vectors",
+        pending.len(),
+        source,
+        embeddings.len()
+    );
+    Ok(pending.len())
+}
+
+/// Embeds rows one at a time after their batch failed with `batch_error`, returns the
+/// embeddings and the rows that were rejected, with why. When no row goes through, a short
+/// probe tells rejected rows from a backend that is down, in which case `batch_error` is
+/// returned and nothing is skipped.
+async fn embed_one_by_one(
+    backend: &dyn EmbeddingBackend,
+    texts: &[(i64, &str)],
+    batch_error: anyhow::Error,
+) -> Result<(Vec<(i64, Vec<f32>)>, Vec<(i64, String)>)> {
+    let mut embeddings = Vec::new();
+    let mut rejected = Vec::new();
+    for text in texts {
+        match embed_texts(backend, std::slice::from_ref(text)).await {
+            Ok(vectors) => embeddings.extend(vectors),
+            Err(e) => rejected.push((text.0, e.to_string())),
+        }
+    }
+
+    if embeddings.is_empty() && backend.embed(&["ok".to_string()]).await.is_err() {
+        return Err(batch_error);
+    }
+    Ok((embeddings, rejected))
+}
+
+async fn report_progress(db: &DatabaseManager, model: &str, source: EmbeddingSource) {
+    match db.embedding_progress(source, model).await {
+        Ok(progress) => {
+            if progress.embedded < progress.total {
+                info!(
+                    "embedding backfill: {} {}/{}",
+                    source, progress.embedded, progress.total
+                );
+            }
+            let _ = send_event("embedding_progress", progress);
+        }
+        Err(e) => error!("failed to read {} embedding progress: {}", source, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cubby_db::{AudioDevice, DeviceType};
+    use futures::future::BoxFuture;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Rejects every batch holding a text with "rejected" in it, and everything while down
+    #[derive(Default)]
+    struct PickyBackend {
+        down: AtomicBool,
+    }
+
+    impl EmbeddingBackend for PickyBackend {
+        fn model(&self) -> &str {
+            "test:picky"
+        }
+
+        fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+            Box::pin(async move {
+                if self.down.load(Ordering::SeqCst) {
+                    anyhow::bail!("connection refused");
+                }
+                if texts.iter().any(|text| text.contains("rejected")) {
+                    anyhow::bail!("400 bad request: input too long");
+                }
+                Ok(texts.iter().map(|_| vec![1.0, 0.0]).collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_rows_are_skipped() {
+        let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+        let audio_chunk_id = db.insert_audio_chunk("audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        for text in ["first words", "rejected words", "last words"] {
+            db.insert_audio_transcription(audio_chunk_id, text, 0, "", &device, None, None, None)
+                .await
+                .unwrap();
+        }
+        let backend = PickyBackend::default();
+        let source = EmbeddingSource::Audio;
+
+        // a backend that is down skips nothing
+        backend.down.store(true, Ordering::SeqCst);
+        assert!(embed_batch(&db, &backend, source, 32).await.is_err());
+        let pending = db
+            .pending_embeddings(source, "test:picky", 32)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 3);
+
+        backend.down.store(false, Ordering::SeqCst);
+        assert_eq!(embed_batch(&db, &backend, source, 32).await.unwrap(), 3);
+        assert!(db
+            .pending_embeddings(source, "test:picky", 32)
+            .await
+            .unwrap()
+            .is_empty());
+        let results = db
+            .semantic_search(&[1.0, 0.0], "test:picky", &Default::default(), 10, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_windows_without_text_to_embed_are_done() {
+        let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+        for text in ["\u{a0}\u{a0}", "\u{c}", "pricing change"] {
+            sqlx::query(
+                "INSERT INTO ui_monitoring (text_output, timestamp, app, window) VALUES (?, ?, ?, ?)",
+            )
+            .bind(text)
+            .bind(chrono::Utc::now())
+            .bind("notes")
+            .bind("pricing.md")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        let backend = PickyBackend::default();
+        let source = EmbeddingSource::Ui;
+
+        assert_eq!(embed_batch(&db, &backend, source, 32).await.unwrap(), 3);
+        assert_eq!(embed_batch(&db, &backend, source, 32).await.unwrap(), 0);
+        let progress = db.embedding_progress(source, "test:picky").await.unwrap();
+        assert_eq!((progress.embedded, progress.total), (3, 3));
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use chrono::Utc;
+use cubby_db::{DatabaseManager, JournalEvent, JournalPosition};
+use cubby_events::subscribe_to_all_events;
+use futures::StreamExt;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+/// How often events older than the retention are deleted
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Journaled events kept for live subscribers that are behind, older ones are read back
+/// from the database
+const LIVE_CAPACITY: usize = 1024;
+
+/// Removes the screenshot carried by `ocr_result` and `ui_frame` events
+pub(crate) fn strip_image(name: &str, data: &mut Value) {
+    if name == "ocr_result" || name == "ui_frame" {
+        if let Some(data) = data.as_object_mut() {
+            data.remove("image");
+        }
+    }
+}
+
+/// Writes every event of the bus to the database under a sequence number, so that clients
+/// can replay what they missed. Journaled events are published again with their sequence
+/// number for the clients following the journal live.
+///
+/// Screenshots are not journaled, only live subscribers get them.
+pub struct EventJournal {
+    db: Arc<DatabaseManager>,
+    live: broadcast::Sender<JournalEvent>,
+}
+
+impl EventJournal {
+    /// Starts journaling, events are deleted once older than `retention_days`
+    pub fn start(db: Arc<DatabaseManager>, retention_days: u32) -> Arc<Self> {
+        let (live, _) = broadcast::channel(LIVE_CAPACITY);
+        tokio::spawn(run_journal(db.clone(), live.clone(), retention_days));
+        Arc::new(Self { db, live })
+    }
+
+    /// Events journaled from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<JournalEvent> {
+        self.live.subscribe()
+    }
+
+    /// Sequence number of the last journaled event, 0 when there is none
+    pub async fn head(&self) -> Result<i64> {
+        Ok(self.db.latest_journal_seq().await?)
+    }
+
+    /// Up to `limit` journaled events from `position`, oldest first
+    pub async fn replay(&self, position: JournalPosition, limit: u32) -> Result<Vec<JournalEvent>> {
+        Ok(self.db.get_journal_events(position, limit).await?)
+    }
+}
+
+async fn run_journal(
+    db: Arc<DatabaseManager>,
+    live: broadcast::Sender<JournalEvent>,
+    retention_days: u32,
+) {
+    // falling behind the bus shows up in the journal as an `events_dropped` event
+    let mut events = subscribe_to_all_events();
+    let mut cleanup = tokio::time::interval(CLEANUP_INTERVAL);
+    info!("journaling events for {} days", retention_days);
+
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else {
+                    break;
+                };
+                let mut stored = event.data.clone();
+                strip_image(&event.name, &mut stored);
+                let timestamp = Utc::now();
+                match db.insert_journal_event(&event.name, &stored, timestamp).await {
+                    Ok(seq) => {
+                        // nobody may be listening
+                        let _ = live.send(JournalEvent {
+                            seq,
+                            name: event.name,
+                            data: event.data,
+                            timestamp,
+                        });
+                    }
+                    Err(e) => error!("failed to journal {} event: {}", event.name, e),
+                }
+            }
+            _ = cleanup.tick() => {
+                let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
+                match db.delete_journal_events_before(cutoff).await {
+                    Ok(deleted) => debug!("deleted {} journaled events", deleted),
+                    Err(e) => error!("failed to delete old journaled events: {}", e),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use anyhow::Result;
+use cubby_db::{remove_media_files, DatabaseManager, IndexJob, IndexJobStatus};
+use cubby_events::send_event;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::add::{IndexOptions, VideoIndexer};
+use crate::embedding::backend::EmbeddingBackend;
+
+/// Runs the video indexing jobs queued with `POST /index`, one at a time. Jobs are stored
+/// in the database and jobs a previous run left unfinished are resumed on start.
+///
+/// Progress is published on the event bus as `index_job_progress` after each file and
+/// `index_job_finished` once a job ends, both carrying the job.
+pub struct IndexJobManager {
+    db: Arc<DatabaseManager>,
+    queue: mpsc::UnboundedSender<i64>,
+    /// The running job and its cancellation flag
+    running: Arc<Mutex<Option<(i64, Arc<AtomicBool>)>>>,
+}
+
+impl IndexJobManager {
+    /// Starts the worker, which first resumes unfinished jobs. Jobs asking for embeddings
+    /// get them from `embedding_backend`.
+    pub fn start(
+        db: Arc<DatabaseManager>,
+        cubby_dir: PathBuf,
+        embedding_backend: Option<Arc<dyn EmbeddingBackend>>,
+    ) -> Arc<Self> {
+        let (queue, queue_rx) = mpsc::unbounded_channel();
+        let running = Arc::new(Mutex::new(None));
+
+        tokio::spawn(run_jobs(
+            db.clone(),
+            cubby_dir,
+            embedding_backend,
+            queue_rx,
+            running.clone(),
+        ));
+
+        Arc::new(Self { db, queue, running })
+    }
+
+    /// Manager for processes that don't index, jobs can be listed but not run
+    pub fn without_worker(db: Arc<DatabaseManager>) -> Arc<Self> {
+        let (queue, _) = mpsc::unbounded_channel();
+        Arc::new(Self {
+            db,
+            queue,
+            running: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Queues a job indexing the videos under `path`
+    pub async fn create_job(
+        &self,
+        path: &str,
+        pattern: Option<&str>,
+        options: &IndexOptions,
+    ) -> Result<IndexJob> {
+        let id = self
+            .db
+            .create_index_job(path, pattern, &serde_json::to_value(options)?)
+            .await?;
+        self.queue.send(id)?;
+        info!("queued index job {} for {}", id, path);
+
+        self.db
+            .get_index_job(id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("index job {} disappeared", id))
+    }
+
+    /// Cancels a queued or running job, the running one stops after its current frame.
+    /// Returns false when the job has already ended.
+    pub async fn cancel_job(&self, id: i64) -> Result<bool> {
+        if !self.db.cancel_index_job(id).await? {
+            return Ok(false);
+        }
+        if let Some((running_id, cancelled)) = self.running.lock().unwrap().as_ref() {
+            if *running_id == id {
+                cancelled.store(true, Ordering::SeqCst);
+            }
+        }
+        info!("cancelled index job {}", id);
+        Ok(true)
+    }
+}
+
+async fn run_jobs(
+    db: Arc<DatabaseManager>,
+    cubby_dir: PathBuf,
+    embedding_backend: Option<Arc<dyn EmbeddingBackend>>,
+    mut queue_rx: mpsc::UnboundedReceiver<i64>,
+    running: Arc<Mutex<Option<(i64, Arc<AtomicBool>)>>>,
+) {
+    let resumed = match db.unfinished_index_jobs().await {
+        Ok(jobs) => jobs.into_iter().map(|job| job.id).collect(),
+        Err(e) => {
+            error!("failed to list unfinished index jobs: {}", e);
+            Vec::new()
+        }
+    };
+    if !resumed.is_empty() {
+        info!("resuming {} index jobs", resumed.len());
+    }
+
+    let mut resumed = resumed.into_iter();
+    loop {
+        let id = match resumed.next() {
+            Some(id) => id,
+            None => match queue_rx.recv().await {
+                Some(id) => id,
+                None => break,
+            },
+        };
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        *running.lock().unwrap() = Some((id, cancelled.clone()));
+        let result = run_job(&db, &cubby_dir, embedding_backend.clone(), id, &cancelled).await;
+        *running.lock().unwrap() = None;
+
+        if let Err(e) = result {
+            error!("index job {} failed: {}", id, e);
+            if let Err(e) = db
+                .finish_index_job(id, IndexJobStatus::Failed, Some(&e.to_string()))
+                .await
+            {
+                error!("failed to record failure of index job {}: {}", id, e);
+            }
+        }
+        publish(&db, id, "index_job_finished").await;
+    }
+}
+
+async fn run_job(
+    db: &Arc<DatabaseManager>,
+    cubby_dir: &Path,
+    embedding_backend: Option<Arc<dyn EmbeddingBackend>>,
+    id: i64,
+    cancelled: &AtomicBool,
+) -> Result<()> {
+    let Some(job) = db.get_index_job(id).await? else {
+        return Ok(());
+    };
+    // cancelled while queued
+    if job.status.is_finished() {
+        return Ok(());
+    }
+
+    let options: IndexOptions = serde_json::from_value(job.options.clone())?;
+    let indexer = VideoIndexer::new(
+        cubby_dir.to_path_buf(),
+        db.clone(),
+        &options,
+        embedding_backend,
+    )
+    .await?;
+    let video_files = indexer.find_videos(&job.path, job.pattern.as_deref())?;
+    if !db.start_index_job(id, video_files.len() as i64).await? {
+        return Ok(());
+    }
+
+    // a file the job was interrupted in is indexed again from scratch
+    let started = db.index_job_files(id).await?;
+    let interrupted: Vec<i64> = started
+        .iter()
+        .filter(|file| !file.finished)
+        .filter_map(|file| file.video_chunk_id)
+        .collect();
+    if !interrupted.is_empty() {
+        let report = db.delete_video_chunks(&interrupted).await?;
+        if options.copy_videos {
+            remove_media_files(&report.files).await;
+        }
+        info!(
+            "index job {} resumed, dropped {} partially indexed videos",
+            id,
+            interrupted.len()
+        );
+    }
+    let finished: HashSet<String> = started
+        .into_iter()
+        .filter(|file| file.finished)
+        .map(|file| file.file_path)
+        .collect();
+
+    publish(db, id, "index_job_progress").await;
+
+    for video_path in video_files {
+        if cancelled.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let file_path = video_path.to_string_lossy().to_string();
+        if finished.contains(&file_path) {
+            continue;
+        }
+
+        info!("index job {}: processing {}", id, file_path);
+        let result = async {
+            let chunk = indexer.create_chunk(&video_path).await?;
+            db.start_index_job_file(id, &file_path, chunk.video_chunk_id)
+                .await?;
+            indexer.extract_text(&chunk, cancelled, |_| {}).await
+        }
+        .await;
+
+        // the video is left as indexed so far, cancelled jobs are not resumed
+        if cancelled.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        match result {
+            Ok(frames) => {
+                db.finish_index_job_file(id, &file_path, frames as i64, None)
+                    .await?
+            }
+            Err(e) => {
+                warn!("index job {}: failed to index {}: {}", id, file_path, e);
+                db.finish_index_job_file(id, &file_path, 0, Some(&e.to_string()))
+                    .await?
+            }
+        }
+        publish(db, id, "index_job_progress").await;
+    }
+
+    db.finish_index_job(id, IndexJobStatus::Completed, None)
+        .await?;
+    Ok(())
+}
+
+async fn publish(db: &DatabaseManager, id: i64, event: &str) {
+    match db.get_index_job(id).await {
+        Ok(Some(job)) => {
+            let _ = send_event(event, job);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("failed to read index job {}: {}", id, e),
+    }
+}
@@ -1,4 +1,5 @@
 mod add;
+pub mod auth;
 mod auto_destruct;
 pub mod chunking;
 pub mod cli;
@@ -6,9 +7,12 @@ pub mod cloudflared_downloader;
 pub mod cloudflared_manager;
 pub mod core;
 pub mod cubby_api_client;
+mod event_journal;
 pub mod filtering;
+mod index_jobs;
 pub mod mac_notifications;
 pub mod mcp;
+mod meetings;
 pub mod onboarding;
 pub mod permission_checker;
 pub mod pipe_manager;
@@ -16,11 +20,11 @@ mod resource_monitor;
 mod server;
 pub mod service_manager;
 pub mod setup_state;
-pub mod text_embeds;
 mod video;
 pub mod video_cache;
 pub mod video_utils;
-pub use add::handle_index_command;
+pub mod webhooks;
+pub use add::{handle_import_audio_command, handle_index_command, IndexOptions};
 pub use auto_destruct::watch_pid;
 pub use axum::Json as JsonResponse;
 pub use cli::{Cli, CliApp, CliCommand};
@@ -29,6 +33,8 @@ pub use cloudflared_manager::CloudflaredManager;
 pub use core::start_continuous_recording;
 pub use cubby_api_client::CubbyApiClient;
 pub use cubby_core::Language;
+pub use event_journal::EventJournal;
+pub use index_jobs::IndexJobManager;
 pub use onboarding::run_onboarding_flow;
 pub use pipe_manager::PipeManager;
 pub use resource_monitor::{ResourceMonitor, RestartSignal};
@@ -1 +1,4 @@
+mod progress;
+mod prompts;
+mod resources;
 pub mod server;
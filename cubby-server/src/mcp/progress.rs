@@ -0,0 +1,95 @@
+use rmcp::model::*;
+use rmcp::service::{RequestContext, RoleServer};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How often `with_heartbeat` reports a step that is still running
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Progress notifications and cancellation of one tool call. Progress is only sent when
+/// the client asked for it with a progress token, cancellation comes from the client's
+/// `notifications/cancelled`.
+#[derive(Clone)]
+pub(crate) struct ToolCall {
+    context: RequestContext<RoleServer>,
+    progress_token: Option<ProgressToken>,
+}
+
+impl ToolCall {
+    pub(crate) fn new(context: RequestContext<RoleServer>) -> Self {
+        let progress_token = context.meta.get_progress_token();
+        Self {
+            context,
+            progress_token,
+        }
+    }
+
+    /// Whether the client listens to progress
+    pub(crate) fn reports_progress(&self) -> bool {
+        self.progress_token.is_some()
+    }
+
+    /// Runs `future` until it completes or the call is cancelled. Dropping it stops the
+    /// database queries it runs between two rows and closes its requests to the local
+    /// api, whose handlers then stop as well.
+    pub(crate) async fn run<T>(
+        &self,
+        future: impl Future<Output = Result<T, ErrorData>>,
+    ) -> Result<T, ErrorData> {
+        tokio::select! {
+            biased;
+            _ = self.context.ct.cancelled() => Err(cancelled()),
+            result = future => result,
+        }
+    }
+
+    /// Reports progress, which must grow from one notification to the next. `total` is
+    /// `None` when unknown.
+    pub(crate) async fn progress(&self, progress: f64, total: Option<f64>, message: String) {
+        let Some(progress_token) = &self.progress_token else {
+            return;
+        };
+        let notification = ProgressNotificationParam {
+            progress_token: progress_token.clone(),
+            progress,
+            total,
+            message: Some(message),
+        };
+        if let Err(e) = self.context.peer.notify_progress(notification).await {
+            warn!("failed to send mcp progress: {}", e);
+        }
+    }
+
+    /// Runs a step that reports nothing by itself, telling the client every few seconds
+    /// that it is still running so that it doesn't give up on the call
+    pub(crate) async fn with_heartbeat<T>(
+        &self,
+        message: &str,
+        future: impl Future<Output = T>,
+    ) -> T {
+        tokio::pin!(future);
+        let started = Instant::now();
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            tokio::select! {
+                result = &mut future => return result,
+                _ = heartbeat.tick() => {
+                    let elapsed = started.elapsed();
+                    self.progress(
+                        elapsed.as_secs_f64(),
+                        None,
+                        format!("{} ({}s)", message, elapsed.as_secs()),
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+}
+
+/// Error answering a cancelled call, which the client no longer waits for
+fn cancelled() -> ErrorData {
+    // JSON-RPC leaves cancellation to each protocol, this is the code LSP uses
+    ErrorData::new(ErrorCode(-32800), "request cancelled", None)
+}
@@ -0,0 +1,191 @@
+use crate::server::AppState;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use rmcp::model::*;
+use serde_json::Value;
+
+use super::server::format_meeting_transcript;
+
+fn argument(name: &str, description: &str, required: bool) -> PromptArgument {
+    PromptArgument {
+        name: name.to_string(),
+        title: None,
+        description: Some(description.to_string()),
+        required: Some(required),
+    }
+}
+
+pub(crate) fn list_prompts() -> Vec<Prompt> {
+    vec![
+        Prompt::new(
+            "summarize-my-day",
+            Some("Summarize what was worked on, read and discussed during a day"),
+            Some(vec![argument(
+                "date",
+                "Day to summarize as YYYY-MM-DD, today by default",
+                false,
+            )]),
+        ),
+        Prompt::new(
+            "recap-meeting",
+            Some("Recap a recorded meeting with decisions and action items"),
+            Some(vec![argument(
+                "meeting_id",
+                "Meeting to recap, from get-meetings",
+                true,
+            )]),
+        ),
+        Prompt::new(
+            "find-where-i-saw",
+            Some("Find where and when something was seen or heard"),
+            Some(vec![argument(
+                "topic",
+                "What to look for, in your own words",
+                true,
+            )]),
+        ),
+    ]
+}
+
+fn string_argument<'a>(arguments: &'a Option<JsonObject>, name: &str) -> Option<&'a str> {
+    arguments
+        .as_ref()?
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn required_argument<'a>(
+    arguments: &'a Option<JsonObject>,
+    name: &str,
+) -> Result<&'a str, ErrorData> {
+    string_argument(arguments, name)
+        .ok_or_else(|| ErrorData::invalid_params(format!("missing argument: {}", name), None))
+}
+
+/// Start and end of a local day in UTC
+fn day_range(day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let local_midnight = |day: NaiveDate| {
+        let midnight = day.and_hms_opt(0, 0, 0).unwrap();
+        Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .unwrap_or_else(|| Utc.from_utc_datetime(&midnight).with_timezone(&Local))
+            .with_timezone(&Utc)
+    };
+    let next_day = day.succ_opt().unwrap_or(day);
+    (local_midnight(day), local_midnight(next_day))
+}
+
+pub(crate) async fn get_prompt(
+    state: &AppState,
+    request: GetPromptRequestParam,
+) -> Result<GetPromptResult, ErrorData> {
+    let arguments = request.arguments;
+    match request.name.as_str() {
+        "summarize-my-day" => {
+            let day = match string_argument(&arguments, "date") {
+                Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|e| ErrorData::invalid_params(format!("invalid date: {}", e), None))?,
+                None => Local::now().date_naive(),
+            };
+            let (start, end) = day_range(day);
+            let (start, end) = (start.to_rfc3339(), end.to_rfc3339());
+            Ok(GetPromptResult {
+                description: Some(format!("Summary of {}", day)),
+                messages: vec![PromptMessage::new_text(
+                    PromptMessageRole::User,
+                    format!(
+                        "Summarize my day on {day} from what cubby recorded between {start} and {end}.\n\n\
+                         1. Call get-activity with start {start} and end {end} to see which apps and \
+                         websites I spent time in, then again with group_by window for the main ones.\n\
+                         2. Call get-meetings with start_time {start} and end_time {end} and read the \
+                         transcripts of the meetings that look important.\n\
+                         3. Call search-content over the same range, filtered on the top apps, to see what \
+                         I was reading and writing. Open a frame resource when the text alone is unclear.\n\n\
+                         Then write a short summary grouped by project or theme, with the time spent on \
+                         each, the meetings and what was decided in them, and anything left unfinished."
+                    ),
+                )],
+            })
+        }
+        "recap-meeting" => {
+            let meeting_id: i64 = required_argument(&arguments, "meeting_id")?
+                .parse()
+                .map_err(|_| ErrorData::invalid_params("meeting_id must be a number", None))?;
+            let meeting = state
+                .db
+                .get_meeting(meeting_id)
+                .await
+                .map_err(|e| ErrorData::internal_error(format!("get meeting failed: {}", e), None))?
+                .ok_or_else(|| {
+                    ErrorData::invalid_params(format!("meeting {} not found", meeting_id), None)
+                })?;
+            Ok(GetPromptResult {
+                description: Some(format!("Recap of meeting {}", meeting_id)),
+                messages: vec![PromptMessage::new_text(
+                    PromptMessageRole::User,
+                    format!(
+                        "Recap this meeting: who took part, what was discussed, the decisions made \
+                         and the action items with their owners. Quote the transcript where it \
+                         matters and say so when a speaker can't be told apart.\n\n{}",
+                        format_meeting_transcript(&meeting)
+                    ),
+                )],
+            })
+        }
+        "find-where-i-saw" => {
+            let topic = required_argument(&arguments, "topic")?;
+            Ok(GetPromptResult {
+                description: Some(format!("Where {} was seen or heard", topic)),
+                messages: vec![PromptMessage::new_text(
+                    PromptMessageRole::User,
+                    format!(
+                        "Find where and when I saw or heard about: {topic}\n\n\
+                         Call search-content with mode hybrid and a short query for it, then try \
+                         related keywords if nothing comes up. Open the frame and audio resources \
+                         of the best results to confirm them. Answer with the app, window or \
+                         website, the time and what exactly was there, best match first."
+                    ),
+                )],
+            })
+        }
+        _ => Err(ErrorData::invalid_params(
+            format!("unknown prompt: {}", request.name),
+            None,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_range_covers_one_day() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let (start, end) = day_range(day);
+        assert!(start < end);
+        assert!(end - start <= chrono::Duration::hours(25));
+        assert_eq!(start.with_timezone(&Local).date_naive(), day);
+    }
+
+    #[test]
+    fn test_prompt_arguments() {
+        let prompts = list_prompts();
+        let recap = prompts
+            .iter()
+            .find(|prompt| prompt.name == "recap-meeting")
+            .expect("recap-meeting should be listed");
+        let arguments = recap.arguments.as_ref().unwrap();
+        assert_eq!(arguments[0].name, "meeting_id");
+        assert_eq!(arguments[0].required, Some(true));
+
+        let mut given = JsonObject::new();
+        given.insert("meeting_id".to_string(), Value::String(" 12 ".to_string()));
+        let given = Some(given);
+        assert_eq!(string_argument(&given, "meeting_id"), Some("12"));
+        assert_eq!(string_argument(&given, "topic"), None);
+        assert!(required_argument(&None, "topic").is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::server::{get_frame_data, AppState};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use base64::{engine::general_purpose, Engine as _};
+use rmcp::model::*;
+use std::sync::Arc;
+
+use super::server::format_meeting_transcript;
+
+const SCHEME: &str = "cubby://";
+
+/// Meetings listed by `resources/list`, older ones are still readable through the template
+const LISTED_MEETINGS: u32 = 20;
+
+/// Recorded content exposed as an MCP resource, addressed as `cubby://frame/{id}`,
+/// `cubby://audio/{chunk_id}` or `cubby://transcript/{meeting_id}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CubbyResource {
+    Frame(i64),
+    Audio(i64),
+    Transcript(i64),
+}
+
+impl CubbyResource {
+    pub(crate) fn parse(uri: &str) -> Option<Self> {
+        let (kind, id) = uri.strip_prefix(SCHEME)?.split_once('/')?;
+        let id = id.parse().ok()?;
+        match kind {
+            "frame" => Some(Self::Frame(id)),
+            "audio" => Some(Self::Audio(id)),
+            "transcript" => Some(Self::Transcript(id)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn uri(&self) -> String {
+        match self {
+            Self::Frame(id) => format!("{}frame/{}", SCHEME, id),
+            Self::Audio(id) => format!("{}audio/{}", SCHEME, id),
+            Self::Transcript(id) => format!("{}transcript/{}", SCHEME, id),
+        }
+    }
+
+    /// Link to the resource for tool results
+    pub(crate) fn link(&self, description: String, mime_type: Option<&str>) -> RawResource {
+        let name = match self {
+            Self::Frame(id) => format!("frame {}", id),
+            Self::Audio(id) => format!("audio chunk {}", id),
+            Self::Transcript(id) => format!("meeting {} transcript", id),
+        };
+        let mut link = RawResource::new(self.uri(), name);
+        link.description = Some(description);
+        link.mime_type = mime_type.map(str::to_string);
+        link
+    }
+}
+
+/// Mime type of a recorded audio file, from its extension
+pub(crate) fn audio_mime_type(file_path: &str) -> &'static str {
+    let extension = std::path::Path::new(file_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("mp4") | Some("m4a") => "audio/mp4",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("ogg") | Some("opus") => "audio/ogg",
+        _ => "application/octet-stream",
+    }
+}
+
+pub(crate) fn resource_templates() -> Vec<ResourceTemplate> {
+    [
+        (
+            "cubby://frame/{frame_id}",
+            "frame",
+            "Screenshot of a recorded frame, as returned by search-content",
+            "image/jpeg",
+        ),
+        (
+            "cubby://audio/{chunk_id}",
+            "audio",
+            "Recorded audio chunk a transcription came from",
+            "audio/mp4",
+        ),
+        (
+            "cubby://transcript/{meeting_id}",
+            "transcript",
+            "Transcript of a meeting with each line attributed to its speaker",
+            "text/plain",
+        ),
+    ]
+    .into_iter()
+    .map(|(uri_template, name, description, mime_type)| {
+        RawResourceTemplate {
+            uri_template: uri_template.to_string(),
+            name: name.to_string(),
+            title: None,
+            description: Some(description.to_string()),
+            mime_type: Some(mime_type.to_string()),
+        }
+        .no_annotation()
+    })
+    .collect()
+}
+
+/// Transcripts of the latest meetings, frames and audio are too many to list
+pub(crate) async fn list_resources(state: &AppState) -> Result<Vec<Resource>, ErrorData> {
+    let meetings = state
+        .db
+        .list_meetings(None, None, LISTED_MEETINGS, 0)
+        .await
+        .map_err(|e| ErrorData::internal_error(format!("list meetings failed: {}", e), None))?;
+
+    Ok(meetings
+        .iter()
+        .map(|meeting| {
+            let mut description = format!(
+                "{} meeting started {}",
+                meeting.app,
+                meeting.start_time.to_rfc3339()
+            );
+            if let Some(title) = meeting.title.as_deref().filter(|title| !title.is_empty()) {
+                description.push_str(&format!(": {}", title));
+            }
+            CubbyResource::Transcript(meeting.id)
+                .link(description, Some("text/plain"))
+                .no_annotation()
+        })
+        .collect())
+}
+
+/// Jpeg of a recorded frame, extracted from its video chunk
+pub(crate) async fn frame_image(
+    state: Arc<AppState>,
+    frame_id: i64,
+) -> Result<axum::body::Bytes, ErrorData> {
+    let response = match get_frame_data(State(state), Path(frame_id)).await {
+        Ok(response) => response,
+        Err((status, body)) => {
+            let message = format!("frame {}: {}", frame_id, body.0["error"]);
+            return Err(if status == StatusCode::NOT_FOUND {
+                ErrorData::resource_not_found(message, None)
+            } else {
+                ErrorData::internal_error(message, None)
+            });
+        }
+    };
+    axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|e| ErrorData::internal_error(format!("failed to read frame: {}", e), None))
+}
+
+pub(crate) async fn read_resource(
+    state: Arc<AppState>,
+    uri: &str,
+) -> Result<ReadResourceResult, ErrorData> {
+    let resource = CubbyResource::parse(uri)
+        .ok_or_else(|| ErrorData::resource_not_found(format!("unknown resource: {}", uri), None))?;
+
+    let contents = match resource {
+        CubbyResource::Frame(frame_id) => ResourceContents::BlobResourceContents {
+            uri: resource.uri(),
+            mime_type: Some("image/jpeg".to_string()),
+            blob: general_purpose::STANDARD.encode(frame_image(state, frame_id).await?),
+            meta: None,
+        },
+        CubbyResource::Audio(chunk_id) => {
+            let file_path = state
+                .db
+                .get_audio_chunk_path(chunk_id)
+                .await
+                .map_err(|e| {
+                    ErrorData::internal_error(format!("get audio chunk failed: {}", e), None)
+                })?
+                .ok_or_else(|| {
+                    ErrorData::resource_not_found(
+                        format!("audio chunk {} not found", chunk_id),
+                        None,
+                    )
+                })?;
+            let audio = tokio::fs::read(&file_path).await.map_err(|e| {
+                ErrorData::internal_error(format!("failed to read {}: {}", file_path, e), None)
+            })?;
+            ResourceContents::BlobResourceContents {
+                uri: resource.uri(),
+                mime_type: Some(audio_mime_type(&file_path).to_string()),
+                blob: general_purpose::STANDARD.encode(audio),
+                meta: None,
+            }
+        }
+        CubbyResource::Transcript(meeting_id) => {
+            let meeting = state
+                .db
+                .get_meeting(meeting_id)
+                .await
+                .map_err(|e| ErrorData::internal_error(format!("get meeting failed: {}", e), None))?
+                .ok_or_else(|| {
+                    ErrorData::resource_not_found(format!("meeting {} not found", meeting_id), None)
+                })?;
+            ResourceContents::TextResourceContents {
+                uri: resource.uri(),
+                mime_type: Some("text/plain".to_string()),
+                text: format_meeting_transcript(&meeting),
+                meta: None,
+            }
+        }
+    };
+
+    Ok(ReadResourceResult {
+        contents: vec![contents],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_uris_round_trip() {
+        for resource in [
+            CubbyResource::Frame(42),
+            CubbyResource::Audio(7),
+            CubbyResource::Transcript(3),
+        ] {
+            assert_eq!(CubbyResource::parse(&resource.uri()), Some(resource));
+        }
+        assert_eq!(
+            CubbyResource::parse("cubby://frame/12"),
+            Some(CubbyResource::Frame(12))
+        );
+        assert_eq!(CubbyResource::parse("cubby://frame/abc"), None);
+        assert_eq!(CubbyResource::parse("cubby://video/1"), None);
+        assert_eq!(CubbyResource::parse("file:///frame/1"), None);
+    }
+
+    #[test]
+    fn test_audio_mime_type() {
+        assert_eq!(audio_mime_type("/data/mic_2024-05-01.mp4"), "audio/mp4");
+        assert_eq!(audio_mime_type("phone/call.M4A"), "audio/mp4");
+        assert_eq!(audio_mime_type("import.wav"), "audio/wav");
+        assert_eq!(audio_mime_type("no_extension"), "application/octet-stream");
+    }
+}
@@ -1,11 +1,30 @@
-use crate::server::{AppState, SearchQuery, SearchResponse};
-use axum::extract::{Query, State};
+use super::progress::ToolCall;
+use super::prompts;
+use super::resources::{self, audio_mime_type, CubbyResource};
+use crate::auth::token_allows;
+use crate::server::{
+    add_tags, api_list_audio_devices, api_list_monitors, get_unnamed_speakers_handler,
+    merge_speakers_handler, remove_tags, search_speakers_handler, update_speaker_handler, AppState,
+    AudioContent, ContentItem, PaginationInfo, SearchQuery, SearchResponse,
+};
+use axum::extract::{Path, Query, State};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::Json;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use cubby_db::{
+    ActivityGroupBy, ActivityReport, ApiToken, Meeting, MeetingSpeaker, MeetingTranscript, Speaker,
+    TokenScope,
+};
 use rmcp::handler::server::ServerHandler;
 use rmcp::model::*;
 use rmcp::service::{RequestContext, RoleServer};
+use rmcp::transport::stdio;
 use rmcp::transport::streamable_http_server::{
     session::local::LocalSessionManager, StreamableHttpService,
 };
+use rmcp::ServiceExt;
 use schemars::{schema_for, JsonSchema};
 use serde::Deserialize;
 use serde_json::Value;
@@ -20,6 +39,12 @@ impl CubbyMcpServer {
     pub fn new(state: Arc<AppState>) -> Self {
         Self { state }
     }
+
+    /// Tools writing data or driving the ui need the recording server, only the ones
+    /// reading recorded data are served from a read-only database
+    fn tool_available(&self, name: &str) -> bool {
+        !self.state.db.is_read_only() || reads_recorded_data(name)
+    }
 }
 
 impl ServerHandler for CubbyMcpServer {
@@ -32,6 +57,11 @@ impl ServerHandler for CubbyMcpServer {
             protocol_version: ProtocolVersion::default(),
             capabilities: ServerCapabilities {
                 tools: Some(ToolsCapability { list_changed: None }),
+                resources: Some(ResourcesCapability {
+                    subscribe: None,
+                    list_changed: None,
+                }),
+                prompts: Some(PromptsCapability { list_changed: None }),
                 ..Default::default()
             },
             server_info: Implementation {
@@ -42,7 +72,10 @@ impl ServerHandler for CubbyMcpServer {
                 icons: None,
             },
             instructions: Some(
-                "cubby mcp server - access to screen recordings and ui automation".to_string(),
+                "cubby mcp server - access to screen recordings and ui automation. search results \
+                 link to cubby://frame/{id} and cubby://audio/{chunk_id} resources, meeting \
+                 transcripts are cubby://transcript/{meeting_id}"
+                    .to_string(),
             ),
         })
     }
@@ -50,25 +83,67 @@ impl ServerHandler for CubbyMcpServer {
     async fn call_tool(
         &self,
         params: CallToolRequestParam,
-        _ctx: RequestContext<RoleServer>,
+        ctx: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, ErrorData> {
-        let arguments = params.arguments.unwrap_or_default();
-
-        match params.name.as_ref() {
-            "search-content" => handle_search_tool(self.state.clone(), arguments).await,
-            "pixel-control" => handle_pixel_control_tool(self.state.clone(), arguments).await,
-            "find-elements" => handle_find_elements_tool(self.state.clone(), arguments).await,
-            "click-element" => handle_click_element_tool(self.state.clone(), arguments).await,
-            "fill-element" => handle_fill_element_tool(self.state.clone(), arguments).await,
-            "scroll-element" => handle_scroll_element_tool(self.state.clone(), arguments).await,
-            "open-application" => handle_open_application_tool(self.state.clone(), arguments).await,
-            "open-url" => handle_open_url_tool(self.state.clone(), arguments).await,
-            _ => Err(ErrorData::new(
+        if !self.tool_available(&params.name) {
+            return Err(ErrorData::new(
                 ErrorCode::METHOD_NOT_FOUND,
-                format!("unknown tool: {}", params.name),
+                format!(
+                    "{} needs the cubby server, it is not available over stdio",
+                    params.name
+                ),
                 None,
-            )),
+            ));
+        }
+
+        let scope = tool_scope(&params.name);
+        let token = ctx
+            .extensions
+            .get::<Parts>()
+            .and_then(|parts| parts.extensions.get::<ApiToken>());
+        if !token_allows(&self.state, token, scope) {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_REQUEST,
+                format!("api token lacks the {} scope", scope),
+                None,
+            ));
         }
+
+        let arguments = params.arguments.unwrap_or_default();
+        let call = ToolCall::new(ctx);
+
+        call.run(async {
+            match params.name.as_ref() {
+                "search-content" => handle_search_tool(self.state.clone(), arguments, &call).await,
+                "pixel-control" => handle_pixel_control_tool(self.state.clone(), arguments).await,
+                "find-elements" => {
+                    handle_find_elements_tool(self.state.clone(), arguments, &call).await
+                }
+                "click-element" => handle_click_element_tool(self.state.clone(), arguments).await,
+                "fill-element" => handle_fill_element_tool(self.state.clone(), arguments).await,
+                "scroll-element" => handle_scroll_element_tool(self.state.clone(), arguments).await,
+                "open-application" => {
+                    handle_open_application_tool(self.state.clone(), arguments).await
+                }
+                "open-url" => handle_open_url_tool(self.state.clone(), arguments).await,
+                "get-meetings" => handle_meetings_tool(self.state.clone(), arguments).await,
+                "get-activity" => handle_activity_tool(self.state.clone(), arguments).await,
+                "list-speakers" => handle_list_speakers_tool(self.state.clone(), arguments).await,
+                "update-speaker" => handle_update_speaker_tool(self.state.clone(), arguments).await,
+                "merge-speakers" => handle_merge_speakers_tool(self.state.clone(), arguments).await,
+                "add-tags" => handle_tags_tool(self.state.clone(), arguments, true).await,
+                "remove-tags" => handle_tags_tool(self.state.clone(), arguments, false).await,
+                "get-frame" => handle_get_frame_tool(self.state.clone(), arguments).await,
+                "list-devices" => handle_list_devices_tool(self.state.clone()).await,
+                "get-timeline" => handle_timeline_tool(self.state.clone(), arguments, &call).await,
+                _ => Err(ErrorData::new(
+                    ErrorCode::METHOD_NOT_FOUND,
+                    format!("unknown tool: {}", params.name),
+                    None,
+                )),
+            }
+        })
+        .await
     }
 
     async fn list_tools(
@@ -76,7 +151,7 @@ impl ServerHandler for CubbyMcpServer {
         _params: Option<PaginatedRequestParam>,
         _ctx: RequestContext<RoleServer>,
     ) -> Result<ListToolsResult, ErrorData> {
-        let tools = vec![
+        let mut tools = vec![
             create_search_tool(),
             create_pixel_control_tool(),
             create_find_elements_tool(),
@@ -85,9 +160,88 @@ impl ServerHandler for CubbyMcpServer {
             create_scroll_element_tool(),
             create_open_application_tool(),
             create_open_url_tool(),
+            create_meetings_tool(),
+            create_activity_tool(),
+            create_list_speakers_tool(),
+            create_update_speaker_tool(),
+            create_merge_speakers_tool(),
+            create_add_tags_tool(),
+            create_remove_tags_tool(),
+            create_get_frame_tool(),
+            create_list_devices_tool(),
+            create_timeline_tool(),
         ];
+        tools.retain(|tool| self.tool_available(&tool.name));
         Ok(ListToolsResult::with_all_items(tools))
     }
+
+    async fn list_resources(
+        &self,
+        _params: Option<PaginatedRequestParam>,
+        _ctx: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        let resources = resources::list_resources(&self.state).await?;
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _params: Option<PaginatedRequestParam>,
+        _ctx: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, ErrorData> {
+        Ok(ListResourceTemplatesResult::with_all_items(
+            resources::resource_templates(),
+        ))
+    }
+
+    async fn read_resource(
+        &self,
+        params: ReadResourceRequestParam,
+        _ctx: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        resources::read_resource(self.state.clone(), &params.uri).await
+    }
+
+    async fn list_prompts(
+        &self,
+        _params: Option<PaginatedRequestParam>,
+        _ctx: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, ErrorData> {
+        Ok(ListPromptsResult::with_all_items(prompts::list_prompts()))
+    }
+
+    async fn get_prompt(
+        &self,
+        params: GetPromptRequestParam,
+        _ctx: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        prompts::get_prompt(&self.state, params).await
+    }
+}
+
+/// Scope a token needs to call a tool, the /mcp route itself only requires `search:read`
+fn tool_scope(name: &str) -> TokenScope {
+    match name {
+        "search-content" | "get-meetings" | "get-activity" | "list-speakers" | "get-frame"
+        | "get-timeline" => TokenScope::SearchRead,
+        "list-devices" => TokenScope::AudioRead,
+        "update-speaker" | "merge-speakers" | "add-tags" | "remove-tags" => TokenScope::DataWrite,
+        _ => TokenScope::OperatorWrite,
+    }
+}
+
+fn reads_recorded_data(name: &str) -> bool {
+    matches!(
+        tool_scope(name),
+        TokenScope::SearchRead | TokenScope::AudioRead
+    )
+}
+
+/// POST to one of this server's own routes, authenticated with its internal token
+fn local_api_post(state: &AppState, path: &str) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .post(format!("http://localhost:3030{}", path))
+        .bearer_auth(&state.internal_token)
 }
 
 pub fn create_mcp_service(
@@ -101,6 +255,13 @@ pub fn create_mcp_service(
     )
 }
 
+/// Serves MCP over stdin and stdout until the client disconnects
+pub async fn serve_stdio(app_state: Arc<AppState>) -> anyhow::Result<()> {
+    let service = CubbyMcpServer::new(app_state).serve(stdio()).await?;
+    service.waiting().await?;
+    Ok(())
+}
+
 // MCP-specific request structs with proper types matching Python implementation
 
 fn default_limit() -> u32 {
@@ -143,6 +304,14 @@ struct McpSearchRequest {
     focused: Option<bool>,
     #[schemars(description = "Filter by browser URL")]
     browser_url: Option<String>,
+    #[schemars(
+        description = "Ranking: 'fts' (default) lists keyword matches newest first, 'hybrid' ranks by keywords and meaning together, most relevant first, with a score and highlighted snippets per result"
+    )]
+    mode: Option<String>,
+    #[schemars(description = "next_cursor of the previous results, to get the page after them")]
+    cursor: Option<String>,
+    #[schemars(description = "Timestamp order: 'desc' (default, newest first) or 'asc'")]
+    order: Option<String>,
 }
 
 #[derive(Deserialize, JsonSchema)]
@@ -239,6 +408,110 @@ struct McpOpenUrlRequest {
     browser: Option<String>,
 }
 
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "List recorded meetings or read the transcript of one")]
+struct McpMeetingsRequest {
+    #[schemars(
+        description = "Meeting to return the full transcript of, lists meetings when omitted"
+    )]
+    meeting_id: Option<i64>,
+    #[schemars(description = "Only meetings after this time, ISO format UTC")]
+    start_time: Option<String>,
+    #[schemars(description = "Only meetings before this time, ISO format UTC")]
+    end_time: Option<String>,
+    #[serde(default = "default_limit")]
+    #[schemars(description = "Maximum number of meetings to list")]
+    limit: u32,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "Report time spent per app, website or window")]
+struct McpActivityRequest {
+    #[schemars(description = "Start of the report, ISO format UTC, a day before end by default")]
+    start: Option<String>,
+    #[schemars(description = "End of the report, ISO format UTC, now by default")]
+    end: Option<String>,
+    #[schemars(description = "What to total time by: app (default), domain or window")]
+    group_by: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Also list the individual sessions, oldest first")]
+    include_sessions: bool,
+}
+
+fn default_timeline_limit() -> u32 {
+    100
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "List the speakers told apart in recorded audio")]
+struct McpListSpeakersRequest {
+    #[schemars(description = "Only speakers whose name starts with this")]
+    name: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Only speakers that have no name yet, most heard first")]
+    unnamed_only: bool,
+    #[serde(default = "default_limit")]
+    #[schemars(description = "Maximum number of unnamed speakers to return")]
+    limit: u32,
+    #[serde(default)]
+    #[schemars(description = "Number of unnamed speakers to skip (for pagination)")]
+    offset: u32,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "Name a speaker or change its metadata")]
+struct McpUpdateSpeakerRequest {
+    #[schemars(description = "Id of the speaker, from list-speakers or search results")]
+    id: i64,
+    #[schemars(description = "New name of the speaker")]
+    name: Option<String>,
+    #[schemars(description = "New metadata of the speaker, a JSON string")]
+    metadata: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "Merge two speakers that are the same person")]
+struct McpMergeSpeakersRequest {
+    #[schemars(description = "Speaker that remains, with the transcriptions of both")]
+    speaker_to_keep_id: i64,
+    #[schemars(description = "Speaker merged into the other one and removed")]
+    speaker_to_merge_id: i64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "Tag a frame or an audio chunk")]
+struct McpTagsRequest {
+    #[schemars(description = "'vision' for a frame, 'audio' for an audio chunk")]
+    content_type: String,
+    #[schemars(description = "frame_id of a screen result or chunk_id of an audio result")]
+    id: i64,
+    #[schemars(description = "Tags to add or remove")]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "Get the screenshot of a recorded frame")]
+struct McpGetFrameRequest {
+    #[schemars(description = "frame_id of a screen search result")]
+    frame_id: i64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "List the audio devices and monitors cubby can record")]
+struct McpListDevicesRequest {}
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(description = "What happened over a time range")]
+struct McpTimelineRequest {
+    #[schemars(description = "Start of the range, ISO format UTC")]
+    start_time: String,
+    #[schemars(description = "End of the range, ISO format UTC")]
+    end_time: String,
+    #[serde(default = "default_timeline_limit")]
+    #[schemars(description = "Maximum number of transcriptions to include")]
+    limit: u32,
+}
+
 // Tool creation functions using schemars
 
 fn create_search_tool() -> Tool {
@@ -258,7 +531,7 @@ fn create_search_tool() -> Tool {
     Tool {
         name: "search-content".into(),
         title: None,
-        description: Some("Search through cubby recorded content (OCR text, audio transcriptions, UI elements). Use this to find specific content that has appeared on your screen or been spoken. Results include timestamps, app context, and the content itself.".into()),
+        description: Some("Search through cubby recorded content (OCR text, audio transcriptions, UI elements). Use this to find specific content that has appeared on your screen or been spoken. Results include timestamps, app context, and the content itself. Use mode 'hybrid' for the most relevant results of a question rather than the latest keyword matches.".into()),
         input_schema: Arc::new(schema_obj),
         output_schema: None,
         annotations: None,
@@ -433,7 +706,247 @@ fn create_open_url_tool() -> Tool {
     Tool {
         name: "open-url".into(),
         title: None,
-        description: Some("Open a URL in a browser".into()),
+        description: Some("Open a URL in a browser".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_meetings_tool() -> Tool {
+    let schema = schema_for!(McpMeetingsRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "get-meetings".into(),
+        title: None,
+        description: Some("List the meetings and calls cubby detected, with their app, time span and participants. Pass a meeting_id to get the full transcript of that meeting with each line attributed to its speaker.".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_activity_tool() -> Tool {
+    let schema = schema_for!(McpActivityRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "get-activity".into(),
+        title: None,
+        description: Some("Report how much time was spent in each app, website domain or window over a time range, from the focused windows cubby recorded. Idle time is left out. Use this for questions like 'what did I work on today' or 'how long was I on github this week'.".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_list_speakers_tool() -> Tool {
+    let schema = schema_for!(McpListSpeakersRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "list-speakers".into(),
+        title: None,
+        description: Some("List the speakers cubby told apart in recorded audio, with their ids and names. Use unnamed_only to find the speakers that still need a name.".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_update_speaker_tool() -> Tool {
+    let schema = schema_for!(McpUpdateSpeakerRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "update-speaker".into(),
+        title: None,
+        description: Some("Name a speaker, or replace its metadata. Transcriptions of the speaker are attributed to the new name from then on.".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_merge_speakers_tool() -> Tool {
+    let schema = schema_for!(McpMergeSpeakersRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "merge-speakers".into(),
+        title: None,
+        description: Some("Merge a speaker into another one when both are the same person. The transcriptions and voice samples of speaker_to_merge_id move to speaker_to_keep_id, which remains.".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_add_tags_tool() -> Tool {
+    let schema = schema_for!(McpTagsRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "add-tags".into(),
+        title: None,
+        description: Some("Add tags to a recorded frame (content_type 'vision', id is the frame_id) or audio chunk (content_type 'audio', id is the chunk_id).".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_remove_tags_tool() -> Tool {
+    let schema = schema_for!(McpTagsRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "remove-tags".into(),
+        title: None,
+        description: Some("Remove tags from a recorded frame (content_type 'vision', id is the frame_id) or audio chunk (content_type 'audio', id is the chunk_id).".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_get_frame_tool() -> Tool {
+    let schema = schema_for!(McpGetFrameRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "get-frame".into(),
+        title: None,
+        description: Some("Get the screenshot of a recorded frame as an image, to see what was on screen beyond the OCR text. Frame ids come from screen results of search-content.".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_list_devices_tool() -> Tool {
+    let schema = schema_for!(McpListDevicesRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "list-devices".into(),
+        title: None,
+        description: Some("List the audio input and output devices and the monitors cubby can record, with the defaults marked.".into()),
+        input_schema: Arc::new(schema_obj),
+        output_schema: None,
+        annotations: None,
+        icons: None,
+    }
+}
+
+fn create_timeline_tool() -> Tool {
+    let schema = schema_for!(McpTimelineRequest);
+    let mut schema_obj = serde_json::to_value(&schema.schema)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .clone();
+
+    schema_obj.insert(
+        "type".to_string(),
+        serde_json::Value::String("object".to_string()),
+    );
+
+    Tool {
+        name: "get-timeline".into(),
+        title: None,
+        description: Some("Tell what happened over a time range, e.g. between 2 and 3pm: the apps and windows used, the meetings and what was said, in order.".into()),
         input_schema: Arc::new(schema_obj),
         output_schema: None,
         annotations: None,
@@ -443,9 +956,13 @@ fn create_open_url_tool() -> Tool {
 
 // Tool handler functions
 
+/// Results fetched per query when a search streams its results
+const SEARCH_BATCH: u32 = 20;
+
 async fn handle_search_tool(
     app_state: Arc<AppState>,
     arguments: JsonObject,
+    call: &ToolCall,
 ) -> Result<CallToolResult, ErrorData> {
     // Deserialize MCP request with integer types
     let mcp_args: McpSearchRequest = serde_json::from_value(Value::Object(arguments))
@@ -493,41 +1010,201 @@ async fn handle_search_tool(
     if let Some(browser_url) = mcp_args.browser_url {
         query_json["browser_url"] = serde_json::Value::String(browser_url);
     }
+    // hybrid ranks every result at once, keyword results come newest or oldest first and
+    // can be sent as they are found
+    let streamed = mcp_args.mode.as_deref() != Some("hybrid")
+        && mcp_args.limit > SEARCH_BATCH
+        && call.reports_progress();
+    if let Some(mode) = mcp_args.mode {
+        query_json["mode"] = serde_json::Value::String(mode);
+    }
+    if let Some(cursor) = mcp_args.cursor {
+        query_json["cursor"] = serde_json::Value::String(cursor);
+    }
+    if let Some(order) = mcp_args.order {
+        query_json["order"] = serde_json::Value::String(order);
+    }
+
+    if !streamed {
+        let result = search_page(app_state, query_json).await?;
+        return Ok(CallToolResult::success(search_result_contents(result)));
+    }
+
+    // batches follow each other's cursor, the first one starts at the requested offset
+    let mut data: Vec<ContentItem> = Vec::new();
+    let mut total = None;
+    let mut next_cursor = None;
+    loop {
+        let batch = SEARCH_BATCH.min(mcp_args.limit - data.len() as u32);
+        let mut batch_query = query_json.clone();
+        batch_query["limit"] = Value::String(batch.to_string());
+        if !data.is_empty() {
+            batch_query["offset"] = Value::String("0".to_string());
+            batch_query["cursor"] = Value::from(next_cursor.take());
+        }
+        let page = search_page(app_state.clone(), batch_query).await?;
+        if data.is_empty() {
+            total = page.pagination.total;
+        }
+        next_cursor = page.pagination.next_cursor;
+
+        let found = data.len() + page.data.len();
+        call.progress(
+            found as f64,
+            Some(mcp_args.limit as f64),
+            search_progress_message(&page.data, data.len()),
+        )
+        .await;
+        let last = page.data.len() < batch as usize || next_cursor.is_none();
+        data.extend(page.data);
+        if last || data.len() >= mcp_args.limit as usize {
+            break;
+        }
+    }
+
+    Ok(CallToolResult::success(search_result_contents(
+        SearchResponse {
+            data,
+            pagination: PaginationInfo {
+                limit: mcp_args.limit,
+                offset: mcp_args.offset,
+                total,
+                next_cursor,
+            },
+        },
+    )))
+}
 
+async fn search_page(
+    app_state: Arc<AppState>,
+    query_json: Value,
+) -> Result<SearchResponse, ErrorData> {
     let query: SearchQuery = serde_json::from_value(query_json).map_err(|e| {
         ErrorData::invalid_params(format!("failed to convert search params: {}", e), None)
     })?;
 
-    let result = crate::server::search(Query(query), State(app_state))
+    crate::server::search(Query(query), State(app_state))
         .await
-        .map_err(|e| ErrorData::internal_error(format!("search failed: {:?}", e), None))?;
+        .map(|result| result.0)
+        .map_err(|e| ErrorData::internal_error(format!("search failed: {:?}", e), None))
+}
 
-    let response_text = format_search_results(result.0);
-    Ok(CallToolResult::success(vec![Annotated::new(
-        RawContent::text(response_text),
-        None,
-    )]))
+/// Results of one batch of a streamed search, numbered after the `previous` ones
+fn search_progress_message(items: &[ContentItem], previous: usize) -> String {
+    let mut message = format!("found {} results so far", previous + items.len());
+    for (i, item) in items.iter().enumerate() {
+        message.push_str(&format!(
+            "\n{}. {}",
+            previous + i + 1,
+            result_line(item, &mut Vec::new())
+        ));
+    }
+    message
 }
 
-fn format_search_results(response: SearchResponse) -> String {
+/// Longest text shown per search result, the rest is in the linked resource or a narrower
+/// search
+const RESULT_TEXT_CHARS: usize = 300;
+
+fn result_text(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(RESULT_TEXT_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+fn audio_speaker(audio: &AudioContent) -> String {
+    match &audio.speaker {
+        Some(speaker) if !speaker.name.is_empty() => speaker.name.clone(),
+        Some(speaker) => format!("speaker {}", speaker.id),
+        None => "unknown speaker".to_string(),
+    }
+}
+
+/// A line per result followed by links to the frames and audio the results came from
+fn search_result_contents(response: SearchResponse) -> Vec<Content> {
     if response.data.is_empty() {
-        return "no results found".to_string();
+        return vec![Annotated::new(
+            RawContent::text("no results found".to_string()),
+            None,
+        )];
     }
 
     let mut output = format!("found {} results:\n\n", response.data.len());
-    for (i, item) in response.data.iter().take(10).enumerate() {
-        output.push_str(&format!("result {}:\n", i + 1));
-        output.push_str(&format!(
-            "  {}\n",
-            serde_json::to_string_pretty(item).unwrap_or_else(|_| format!("{:?}", item))
-        ));
-        output.push_str("---\n");
+    let mut links = Vec::new();
+    for (i, item) in response.data.iter().enumerate() {
+        output.push_str(&format!("{}. {}\n", i + 1, result_line(item, &mut links)));
+    }
+    if let Some(next_cursor) = response.pagination.next_cursor {
+        output.push_str(&format!("\nnext_cursor: {}\n", next_cursor));
+    }
+
+    let mut contents = vec![Annotated::new(RawContent::text(output), None)];
+    // results of one audio chunk share their link
+    links.dedup_by(|a, b| a.uri == b.uri);
+    contents.extend(
+        links
+            .into_iter()
+            .map(|link| Annotated::new(RawContent::resource_link(link), None)),
+    );
+    contents
+}
+
+/// One line per search result, adding the resource it comes from to `links`
+fn result_line(item: &ContentItem, links: &mut Vec<RawResource>) -> String {
+    match item {
+        ContentItem::OCR(ocr) => {
+            let resource = CubbyResource::Frame(ocr.frame_id);
+            links.push(resource.link(
+                format!(
+                    "{} - {} at {}",
+                    ocr.app_name,
+                    ocr.window_name,
+                    ocr.timestamp.to_rfc3339()
+                ),
+                Some("image/jpeg"),
+            ));
+            format!(
+                "[screen] {} {} - {} ({}): {}",
+                ocr.timestamp.to_rfc3339(),
+                ocr.app_name,
+                ocr.window_name,
+                resource.uri(),
+                result_text(&ocr.text)
+            )
+        }
+        ContentItem::Audio(audio) => {
+            let resource = CubbyResource::Audio(audio.chunk_id);
+            links.push(resource.link(
+                format!(
+                    "{} audio at {}",
+                    audio.device_name,
+                    audio.timestamp.to_rfc3339()
+                ),
+                Some(audio_mime_type(&audio.file_path)),
+            ));
+            format!(
+                "[audio] {} {}, {} ({}): {}",
+                audio.timestamp.to_rfc3339(),
+                audio.device_name,
+                audio_speaker(audio),
+                resource.uri(),
+                result_text(&audio.transcription)
+            )
+        }
+        ContentItem::UI(ui) => format!(
+            "[ui] {} {} - {}: {}",
+            ui.timestamp.to_rfc3339(),
+            ui.app_name,
+            ui.window_name,
+            result_text(&ui.text)
+        ),
     }
-    output
 }
 
 async fn handle_pixel_control_tool(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     arguments: JsonObject,
 ) -> Result<CallToolResult, ErrorData> {
     let mcp_args: McpPixelControlRequest = serde_json::from_value(Value::Object(arguments))
@@ -542,8 +1219,7 @@ async fn handle_pixel_control_tool(
         }
     });
 
-    let result = reqwest::Client::new()
-        .post("http://localhost:3030/experimental/operator/pixel")
+    let result = local_api_post(&state, "/experimental/operator/pixel")
         .json(&payload)
         .send()
         .await
@@ -561,8 +1237,9 @@ async fn handle_pixel_control_tool(
 }
 
 async fn handle_find_elements_tool(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     arguments: JsonObject,
+    call: &ToolCall,
 ) -> Result<CallToolResult, ErrorData> {
     let mcp_args: McpFindElementsRequest = serde_json::from_value(Value::Object(arguments))
         .map_err(|e| {
@@ -581,10 +1258,17 @@ async fn handle_find_elements_tool(
         "max_depth": mcp_args.max_depth,
     });
 
-    let result = reqwest::Client::new()
-        .post("http://localhost:3030/experimental/operator/find-elements")
+    let request = local_api_post(&state, "/experimental/operator")
         .json(&payload)
-        .send()
+        .send();
+    let result = call
+        .with_heartbeat(
+            &format!(
+                "searching the accessibility tree of {}",
+                mcp_args.selector.app_name
+            ),
+            request,
+        )
         .await
         .map_err(|e| ErrorData::internal_error(format!("find elements failed: {}", e), None))?;
 
@@ -600,7 +1284,7 @@ async fn handle_find_elements_tool(
 }
 
 async fn handle_click_element_tool(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     arguments: JsonObject,
 ) -> Result<CallToolResult, ErrorData> {
     let mcp_args: McpClickElementRequest = serde_json::from_value(Value::Object(arguments))
@@ -618,8 +1302,7 @@ async fn handle_click_element_tool(
         }
     });
 
-    let result = reqwest::Client::new()
-        .post("http://localhost:3030/experimental/operator/click")
+    let result = local_api_post(&state, "/experimental/operator/click")
         .json(&payload)
         .send()
         .await
@@ -637,7 +1320,7 @@ async fn handle_click_element_tool(
 }
 
 async fn handle_fill_element_tool(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     arguments: JsonObject,
 ) -> Result<CallToolResult, ErrorData> {
     let mcp_args: McpFillElementRequest = serde_json::from_value(Value::Object(arguments))
@@ -656,8 +1339,7 @@ async fn handle_fill_element_tool(
         "text": mcp_args.text
     });
 
-    let result = reqwest::Client::new()
-        .post("http://localhost:3030/experimental/operator/type")
+    let result = local_api_post(&state, "/experimental/operator/type")
         .json(&payload)
         .send()
         .await
@@ -675,7 +1357,7 @@ async fn handle_fill_element_tool(
 }
 
 async fn handle_scroll_element_tool(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     arguments: JsonObject,
 ) -> Result<CallToolResult, ErrorData> {
     let mcp_args: McpScrollElementRequest = serde_json::from_value(Value::Object(arguments))
@@ -695,8 +1377,7 @@ async fn handle_scroll_element_tool(
         "amount": mcp_args.amount
     });
 
-    let result = reqwest::Client::new()
-        .post("http://localhost:3030/experimental/operator/scroll")
+    let result = local_api_post(&state, "/experimental/operator/scroll")
         .json(&payload)
         .send()
         .await
@@ -714,7 +1395,7 @@ async fn handle_scroll_element_tool(
 }
 
 async fn handle_open_application_tool(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     arguments: JsonObject,
 ) -> Result<CallToolResult, ErrorData> {
     let mcp_args: McpOpenApplicationRequest = serde_json::from_value(Value::Object(arguments))
@@ -726,8 +1407,7 @@ async fn handle_open_application_tool(
         "app_name": mcp_args.app_name
     });
 
-    let result = reqwest::Client::new()
-        .post("http://localhost:3030/open-application")
+    let result = local_api_post(&state, "/open-application")
         .json(&payload)
         .send()
         .await
@@ -745,7 +1425,7 @@ async fn handle_open_application_tool(
 }
 
 async fn handle_open_url_tool(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     arguments: JsonObject,
 ) -> Result<CallToolResult, ErrorData> {
     let mcp_args: McpOpenUrlRequest = serde_json::from_value(Value::Object(arguments))
@@ -756,8 +1436,7 @@ async fn handle_open_url_tool(
         "browser": mcp_args.browser
     });
 
-    let result = reqwest::Client::new()
-        .post("http://localhost:3030/open-url")
+    let result = local_api_post(&state, "/open-url")
         .json(&payload)
         .send()
         .await
@@ -774,6 +1453,562 @@ async fn handle_open_url_tool(
     )]))
 }
 
+fn parse_time(name: &str, value: &str) -> Result<DateTime<Utc>, ErrorData> {
+    value
+        .parse::<DateTime<Utc>>()
+        .map_err(|e| ErrorData::invalid_params(format!("invalid {}: {}", name, e), None))
+}
+
+fn parse_time_param(name: &str, value: Option<String>) -> Result<Option<DateTime<Utc>>, ErrorData> {
+    value.map(|value| parse_time(name, &value)).transpose()
+}
+
+async fn handle_meetings_tool(
+    state: Arc<AppState>,
+    arguments: JsonObject,
+) -> Result<CallToolResult, ErrorData> {
+    let mcp_args: McpMeetingsRequest = serde_json::from_value(Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(format!("invalid meetings params: {}", e), None))?;
+
+    let response_text = match mcp_args.meeting_id {
+        Some(id) => {
+            let meeting = state
+                .db
+                .get_meeting(id)
+                .await
+                .map_err(|e| ErrorData::internal_error(format!("get meeting failed: {}", e), None))?
+                .ok_or_else(|| {
+                    ErrorData::invalid_params(format!("meeting {} not found", id), None)
+                })?;
+            format_meeting_transcript(&meeting)
+        }
+        None => {
+            let start_time = parse_time_param("start_time", mcp_args.start_time)?;
+            let end_time = parse_time_param("end_time", mcp_args.end_time)?;
+            let meetings = state
+                .db
+                .list_meetings(start_time, end_time, mcp_args.limit, 0)
+                .await
+                .map_err(|e| {
+                    ErrorData::internal_error(format!("list meetings failed: {}", e), None)
+                })?;
+            format_meetings(&meetings)
+        }
+    };
+
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::text(response_text),
+        None,
+    )]))
+}
+
+async fn handle_activity_tool(
+    state: Arc<AppState>,
+    arguments: JsonObject,
+) -> Result<CallToolResult, ErrorData> {
+    let mcp_args: McpActivityRequest = serde_json::from_value(Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(format!("invalid activity params: {}", e), None))?;
+
+    let end = parse_time_param("end", mcp_args.end)?.unwrap_or_else(Utc::now);
+    let start =
+        parse_time_param("start", mcp_args.start)?.unwrap_or(end - chrono::Duration::days(1));
+    if start > end {
+        return Err(ErrorData::invalid_params("start must be before end", None));
+    }
+    let group_by = match mcp_args.group_by {
+        Some(group_by) => serde_json::from_value::<ActivityGroupBy>(Value::String(group_by))
+            .map_err(|_| {
+                ErrorData::invalid_params("group_by must be app, domain or window", None)
+            })?,
+        None => ActivityGroupBy::default(),
+    };
+
+    state
+        .db
+        .update_activity_sessions()
+        .await
+        .map_err(|e| ErrorData::internal_error(format!("update activity failed: {}", e), None))?;
+    let report = state
+        .db
+        .activity_report(start, end, group_by, mcp_args.include_sessions)
+        .await
+        .map_err(|e| ErrorData::internal_error(format!("activity report failed: {}", e), None))?;
+
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::text(format_activity(&report)),
+        None,
+    )]))
+}
+
+fn format_duration(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as i64;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+fn format_activity(report: &ActivityReport) -> String {
+    let mut output = format!(
+        "activity from {} to {}: {} active\n\n",
+        report.start.to_rfc3339(),
+        report.end.to_rfc3339(),
+        format_duration(report.total_seconds)
+    );
+    if report.groups.is_empty() {
+        output.push_str("no activity recorded in this range\n");
+    }
+    for group in &report.groups {
+        match &group.app_name {
+            Some(app_name) => output.push_str(&format!(
+                "{} ({}): {} in {} sessions\n",
+                group.name,
+                app_name,
+                format_duration(group.seconds),
+                group.sessions
+            )),
+            None => output.push_str(&format!(
+                "{}: {} in {} sessions\n",
+                group.name,
+                format_duration(group.seconds),
+                group.sessions
+            )),
+        }
+    }
+    if !report.sessions.is_empty() {
+        output.push_str("\nsessions:\n");
+        for session in &report.sessions {
+            output.push_str(&format!(
+                "[{} - {}] {} - {}",
+                session.start_time.format("%H:%M:%S"),
+                session.end_time.format("%H:%M:%S"),
+                session.app_name,
+                session.window_name
+            ));
+            if let Some(domain) = &session.domain {
+                output.push_str(&format!(" ({})", domain));
+            }
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Error of a wrapped HTTP handler, client errors are the caller's
+fn handler_error(action: &str, (status, body): (StatusCode, Json<Value>)) -> ErrorData {
+    let message = format!("{} failed: {}", action, body.0["error"]);
+    if status.is_client_error() {
+        ErrorData::invalid_params(message, None)
+    } else {
+        ErrorData::internal_error(message, None)
+    }
+}
+
+fn format_speaker(speaker: &Speaker) -> String {
+    if speaker.name.is_empty() {
+        format!("speaker {} (unnamed)", speaker.id)
+    } else {
+        format!("speaker {}: {}", speaker.id, speaker.name)
+    }
+}
+
+async fn handle_list_speakers_tool(
+    state: Arc<AppState>,
+    arguments: JsonObject,
+) -> Result<CallToolResult, ErrorData> {
+    let mcp_args: McpListSpeakersRequest = serde_json::from_value(Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(format!("invalid speakers params: {}", e), None))?;
+
+    let speakers = if mcp_args.unnamed_only {
+        let request = serde_json::from_value(serde_json::json!({
+            "limit": mcp_args.limit,
+            "offset": mcp_args.offset,
+        }))
+        .map_err(|e| ErrorData::internal_error(format!("invalid speakers query: {}", e), None))?;
+        get_unnamed_speakers_handler(State(state), Query(request)).await
+    } else {
+        let request = serde_json::from_value(serde_json::json!({ "name": mcp_args.name }))
+            .map_err(|e| {
+                ErrorData::internal_error(format!("invalid speakers query: {}", e), None)
+            })?;
+        search_speakers_handler(State(state), Query(request)).await
+    }
+    .map_err(|e| handler_error("list speakers", e))?
+    .0;
+
+    let response_text = if speakers.is_empty() {
+        "no speakers found".to_string()
+    } else {
+        let lines: Vec<String> = speakers.iter().map(format_speaker).collect();
+        format!("found {} speakers:\n{}", speakers.len(), lines.join("\n"))
+    };
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::text(response_text),
+        None,
+    )]))
+}
+
+async fn handle_update_speaker_tool(
+    state: Arc<AppState>,
+    arguments: JsonObject,
+) -> Result<CallToolResult, ErrorData> {
+    let mcp_args: McpUpdateSpeakerRequest = serde_json::from_value(Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(format!("invalid speaker params: {}", e), None))?;
+    if mcp_args.name.is_none() && mcp_args.metadata.is_none() {
+        return Err(ErrorData::invalid_params(
+            "give a name or metadata to update",
+            None,
+        ));
+    }
+
+    let request = serde_json::from_value(serde_json::json!({
+        "id": mcp_args.id,
+        "name": mcp_args.name,
+        "metadata": mcp_args.metadata,
+    }))
+    .map_err(|e| ErrorData::internal_error(format!("invalid speaker update: {}", e), None))?;
+    let speaker = update_speaker_handler(State(state), Json(request))
+        .await
+        .map_err(|e| handler_error("update speaker", e))?
+        .0;
+
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::text(format!("updated {}", format_speaker(&speaker))),
+        None,
+    )]))
+}
+
+async fn handle_merge_speakers_tool(
+    state: Arc<AppState>,
+    arguments: JsonObject,
+) -> Result<CallToolResult, ErrorData> {
+    let mcp_args: McpMergeSpeakersRequest = serde_json::from_value(Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(format!("invalid merge params: {}", e), None))?;
+    if mcp_args.speaker_to_keep_id == mcp_args.speaker_to_merge_id {
+        return Err(ErrorData::invalid_params(
+            "can't merge a speaker into itself",
+            None,
+        ));
+    }
+
+    let request = serde_json::from_value(serde_json::json!({
+        "speaker_to_keep_id": mcp_args.speaker_to_keep_id,
+        "speaker_to_merge_id": mcp_args.speaker_to_merge_id,
+    }))
+    .map_err(|e| ErrorData::internal_error(format!("invalid speaker merge: {}", e), None))?;
+    let _ = merge_speakers_handler(State(state), Json(request))
+        .await
+        .map_err(|e| handler_error("merge speakers", e))?;
+
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::text(format!(
+            "merged speaker {} into speaker {}",
+            mcp_args.speaker_to_merge_id, mcp_args.speaker_to_keep_id
+        )),
+        None,
+    )]))
+}
+
+async fn handle_tags_tool(
+    state: Arc<AppState>,
+    arguments: JsonObject,
+    add: bool,
+) -> Result<CallToolResult, ErrorData> {
+    let mcp_args: McpTagsRequest = serde_json::from_value(Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(format!("invalid tags params: {}", e), None))?;
+    if mcp_args.tags.is_empty() {
+        return Err(ErrorData::invalid_params("tags can't be empty", None));
+    }
+
+    let path = Path((mcp_args.content_type.clone(), mcp_args.id));
+    let request = serde_json::json!({ "tags": mcp_args.tags });
+    let invalid_tags =
+        |e: serde_json::Error| ErrorData::internal_error(format!("invalid tags: {}", e), None);
+    let action = if add {
+        add_tags(
+            State(state),
+            path,
+            Json(serde_json::from_value(request).map_err(invalid_tags)?),
+        )
+        .await
+        .map(|_| "added")
+        .map_err(|e| handler_error("add tags", e))?
+    } else {
+        remove_tags(
+            State(state),
+            path,
+            Json(serde_json::from_value(request).map_err(invalid_tags)?),
+        )
+        .await
+        .map(|_| "removed")
+        .map_err(|e| handler_error("remove tags", e))?
+    };
+
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::text(format!(
+            "{} tags {} on {} {}",
+            action,
+            mcp_args.tags.join(", "),
+            mcp_args.content_type,
+            mcp_args.id
+        )),
+        None,
+    )]))
+}
+
+async fn handle_get_frame_tool(
+    state: Arc<AppState>,
+    arguments: JsonObject,
+) -> Result<CallToolResult, ErrorData> {
+    let mcp_args: McpGetFrameRequest = serde_json::from_value(Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(format!("invalid frame params: {}", e), None))?;
+
+    let image = resources::frame_image(state, mcp_args.frame_id).await?;
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::image(general_purpose::STANDARD.encode(image), "image/jpeg"),
+        None,
+    )]))
+}
+
+async fn handle_list_devices_tool(state: Arc<AppState>) -> Result<CallToolResult, ErrorData> {
+    let mut output = "audio devices:\n".to_string();
+    match api_list_audio_devices(State(state)).await {
+        Ok(devices) => {
+            for device in devices.0 {
+                output.push_str(&format!("  {}", device.name));
+                if device.is_default {
+                    output.push_str(" (default)");
+                }
+                output.push('\n');
+            }
+        }
+        Err((_, body)) => output.push_str(&format!("  {}\n", body.0["error"])),
+    }
+
+    output.push_str("monitors:\n");
+    match api_list_monitors().await {
+        Ok(monitors) => {
+            for monitor in monitors.0 {
+                output.push_str(&format!(
+                    "  {}: {} {}x{}",
+                    monitor.id, monitor.name, monitor.width, monitor.height
+                ));
+                if monitor.is_default {
+                    output.push_str(" (default)");
+                }
+                output.push('\n');
+            }
+        }
+        Err((_, body)) => output.push_str(&format!("  {}\n", body.0["error"])),
+    }
+
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::text(output),
+        None,
+    )]))
+}
+
+async fn handle_timeline_tool(
+    state: Arc<AppState>,
+    arguments: JsonObject,
+    call: &ToolCall,
+) -> Result<CallToolResult, ErrorData> {
+    let mcp_args: McpTimelineRequest = serde_json::from_value(Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(format!("invalid timeline params: {}", e), None))?;
+    let start = parse_time("start_time", &mcp_args.start_time)?;
+    let end = parse_time("end_time", &mcp_args.end_time)?;
+    if start > end {
+        return Err(ErrorData::invalid_params(
+            "start_time must be before end_time",
+            None,
+        ));
+    }
+
+    call.progress(0.0, Some(3.0), "reading activity".to_string())
+        .await;
+    state
+        .db
+        .update_activity_sessions()
+        .await
+        .map_err(|e| ErrorData::internal_error(format!("update activity failed: {}", e), None))?;
+    let activity = state
+        .db
+        .activity_report(start, end, ActivityGroupBy::App, true)
+        .await
+        .map_err(|e| ErrorData::internal_error(format!("activity report failed: {}", e), None))?;
+    call.progress(1.0, Some(3.0), "reading meetings".to_string())
+        .await;
+    let meetings = state
+        .db
+        .list_meetings(Some(start), Some(end), 100, 0)
+        .await
+        .map_err(|e| ErrorData::internal_error(format!("list meetings failed: {}", e), None))?;
+    let query: SearchQuery = serde_json::from_value(serde_json::json!({
+        "content_type": "audio",
+        "order": "asc",
+        "limit": mcp_args.limit.to_string(),
+        "offset": "0",
+        "start_time": start,
+        "end_time": end,
+        "include_total": false,
+    }))
+    .map_err(|e| ErrorData::internal_error(format!("invalid timeline search: {}", e), None))?;
+    call.progress(2.0, Some(3.0), "reading transcriptions".to_string())
+        .await;
+    let audio = crate::server::search(Query(query), State(state))
+        .await
+        .map_err(|e| handler_error("search", e))?
+        .0;
+
+    let same_day = start.date_naive() == end.date_naive();
+    let time = |timestamp: DateTime<Utc>| {
+        if same_day {
+            timestamp.format("%H:%M:%S").to_string()
+        } else {
+            timestamp.format("%m-%d %H:%M:%S").to_string()
+        }
+    };
+
+    let mut entries: Vec<(DateTime<Utc>, String)> = Vec::new();
+    for session in &activity.sessions {
+        let mut line = format!(
+            "[{} - {}] {} - {}",
+            time(session.start_time),
+            time(session.end_time),
+            session.app_name,
+            session.window_name
+        );
+        if let Some(domain) = &session.domain {
+            line.push_str(&format!(" ({})", domain));
+        }
+        entries.push((session.start_time, line));
+    }
+    for meeting in &meetings {
+        entries.push((
+            meeting.start_time,
+            format!(
+                "[{}] {} ({})",
+                time(meeting.start_time),
+                format_meeting_header(meeting).replace('\n', ";"),
+                CubbyResource::Transcript(meeting.id).uri()
+            ),
+        ));
+    }
+    for item in &audio.data {
+        if let ContentItem::Audio(audio) = item {
+            entries.push((
+                audio.timestamp,
+                format!(
+                    "[{}] {}, {}: {}",
+                    time(audio.timestamp),
+                    audio.device_name,
+                    audio_speaker(audio),
+                    result_text(&audio.transcription)
+                ),
+            ));
+        }
+    }
+    // stable, so sessions come before what was said when they started
+    entries.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let mut output = format!(
+        "timeline from {} to {} (UTC), {} active:\n\n",
+        start.to_rfc3339(),
+        end.to_rfc3339(),
+        format_duration(activity.total_seconds)
+    );
+    if entries.is_empty() {
+        output.push_str("nothing was recorded in this range\n");
+    }
+    for (_, line) in &entries {
+        output.push_str(line);
+        output.push('\n');
+    }
+    if audio.pagination.next_cursor.is_some() {
+        output.push_str(&format!(
+            "\nonly the first {} transcriptions are shown, narrow the range or raise limit for more\n",
+            mcp_args.limit
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Annotated::new(
+        RawContent::text(output),
+        None,
+    )]))
+}
+
+fn speaker_label(speaker: Option<&MeetingSpeaker>) -> String {
+    match speaker {
+        Some(MeetingSpeaker {
+            name: Some(name), ..
+        }) if !name.is_empty() => name.clone(),
+        Some(speaker) => format!("speaker {}", speaker.id),
+        None => "unknown speaker".to_string(),
+    }
+}
+
+fn format_meeting_header(meeting: &Meeting) -> String {
+    let mut header = format!("meeting {} in {}", meeting.id, meeting.app);
+    if let Some(title) = meeting.title.as_deref().filter(|title| !title.is_empty()) {
+        header.push_str(&format!(" ({})", title));
+    }
+    match meeting.end_time {
+        Some(end_time) => header.push_str(&format!(
+            ", {} to {}",
+            meeting.start_time.to_rfc3339(),
+            end_time.to_rfc3339()
+        )),
+        None => header.push_str(&format!(
+            ", started {}, in progress",
+            meeting.start_time.to_rfc3339()
+        )),
+    }
+    if !meeting.speakers.is_empty() {
+        let speakers: Vec<String> = meeting
+            .speakers
+            .iter()
+            .map(|speaker| speaker_label(Some(speaker)))
+            .collect();
+        header.push_str(&format!("\n  speakers: {}", speakers.join(", ")));
+    }
+    header
+}
+
+fn format_meetings(meetings: &[Meeting]) -> String {
+    if meetings.is_empty() {
+        return "no meetings found".to_string();
+    }
+
+    let mut output = format!("found {} meetings:\n\n", meetings.len());
+    for meeting in meetings {
+        output.push_str(&format_meeting_header(meeting));
+        output.push_str(&format!(
+            "\n  {} transcriptions\n---\n",
+            meeting.transcription_count
+        ));
+    }
+    output
+}
+
+pub(crate) fn format_meeting_transcript(meeting: &MeetingTranscript) -> String {
+    let mut output = format_meeting_header(&meeting.meeting);
+    output.push_str("\n\n");
+    if meeting.transcript.is_empty() {
+        output.push_str("no transcriptions were recorded during this meeting");
+        return output;
+    }
+    for entry in &meeting.transcript {
+        output.push_str(&format!(
+            "[{}] {}: {}\n",
+            entry.timestamp.format("%H:%M:%S"),
+            speaker_label(entry.speaker.as_ref()),
+            entry.transcription.trim()
+        ));
+    }
+    output
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -985,5 +2220,118 @@ mod tests {
             Some(&json!(["string", "null"])),
             "McpOpenUrlRequest.browser should have type [\"string\", \"null\"]"
         );
+
+        // test McpMeetingsRequest
+        let meetings_schema = schema_for!(McpMeetingsRequest);
+        let meetings_value = serde_json::to_value(&meetings_schema.schema).unwrap();
+        let meeting_id = meetings_value
+            .get("properties")
+            .and_then(|props| props.get("meeting_id"))
+            .expect("meeting_id should exist");
+        assert!(
+            meeting_id.get("default").is_none(),
+            "McpMeetingsRequest.meeting_id should NOT have 'default': null"
+        );
+        assert_eq!(
+            meeting_id.get("type"),
+            Some(&json!(["integer", "null"])),
+            "McpMeetingsRequest.meeting_id should have type [\"integer\", \"null\"]"
+        );
+
+        // test McpActivityRequest
+        let activity_schema = schema_for!(McpActivityRequest);
+        let activity_value = serde_json::to_value(&activity_schema.schema).unwrap();
+        let group_by = activity_value
+            .get("properties")
+            .and_then(|props| props.get("group_by"))
+            .expect("group_by should exist");
+        assert!(
+            group_by.get("default").is_none(),
+            "McpActivityRequest.group_by should NOT have 'default': null"
+        );
+        assert_eq!(
+            group_by.get("type"),
+            Some(&json!(["string", "null"])),
+            "McpActivityRequest.group_by should have type [\"string\", \"null\"]"
+        );
+
+        // test McpUpdateSpeakerRequest
+        let speaker_schema = schema_for!(McpUpdateSpeakerRequest);
+        let speaker_value = serde_json::to_value(&speaker_schema.schema).unwrap();
+        let name = speaker_value
+            .get("properties")
+            .and_then(|props| props.get("name"))
+            .expect("name should exist");
+        assert!(
+            name.get("default").is_none(),
+            "McpUpdateSpeakerRequest.name should NOT have 'default': null"
+        );
+        assert_eq!(
+            speaker_value.get("required"),
+            Some(&json!(["id"])),
+            "McpUpdateSpeakerRequest should only require id"
+        );
+    }
+
+    #[test]
+    fn test_tool_scopes() {
+        for tool in ["list-speakers", "get-frame", "get-timeline"] {
+            assert_eq!(tool_scope(tool), TokenScope::SearchRead, "{}", tool);
+        }
+        assert_eq!(tool_scope("list-devices"), TokenScope::AudioRead);
+        for tool in [
+            "update-speaker",
+            "merge-speakers",
+            "add-tags",
+            "remove-tags",
+        ] {
+            assert_eq!(tool_scope(tool), TokenScope::DataWrite, "{}", tool);
+        }
+        assert_eq!(tool_scope("click-element"), TokenScope::OperatorWrite);
+    }
+
+    #[test]
+    fn test_search_progress_message() {
+        let items: Vec<ContentItem> = (0..2)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({
+                    "type": "UI",
+                    "content": {
+                        "id": i,
+                        "text": format!("line {}", i),
+                        "timestamp": "2024-05-01T12:00:00Z",
+                        "app_name": "editor",
+                        "window_name": "main.rs",
+                        "initial_traversal_at": null,
+                        "file_path": "",
+                        "offset_index": 0,
+                        "frame_name": null,
+                        "browser_url": null,
+                    },
+                }))
+                .unwrap()
+            })
+            .collect();
+
+        let message = search_progress_message(&items, 20);
+        let lines: Vec<&str> = message.lines().collect();
+        assert_eq!(lines[0], "found 22 results so far");
+        assert!(lines[1].starts_with("21. [ui] "), "{}", lines[1]);
+        assert!(lines[2].starts_with("22. [ui] ") && lines[2].ends_with("line 1"));
+    }
+
+    #[test]
+    fn test_tools_served_read_only() {
+        for tool in [
+            "search-content",
+            "get-activity",
+            "get-frame",
+            "list-devices",
+        ] {
+            assert!(reads_recorded_data(tool), "{}", tool);
+        }
+        for tool in ["add-tags", "merge-speakers", "open-url", "pixel-control"] {
+            assert!(!reads_recorded_data(tool), "{}", tool);
+        }
     }
 }
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use cubby_db::DatabaseManager;
+use cubby_events::{subscribe_to_all_events, MeetingEvent};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// How often the transcriptions of a meeting in progress are linked to it, so it can be
+/// read before it ends
+const LINK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Persists the meetings announced by the meeting detectors and links each of them to the
+/// transcriptions heard while it lasted
+pub async fn record_meetings(db: Arc<DatabaseManager>) -> Result<()> {
+    let mut subscription = subscribe_to_all_events();
+
+    let closed = db.close_open_meetings().await?;
+    if closed > 0 {
+        info!("closed {} meetings left open by a previous run", closed);
+    }
+
+    let mut current: Option<i64> = None;
+    let mut link_interval = tokio::time::interval(LINK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            event = subscription.next() => {
+                let Some(event) = event else {
+                    break;
+                };
+                if event.name != "meeting_started" && event.name != "meeting_ended" {
+                    continue;
+                }
+                let meeting: MeetingEvent = match serde_json::from_value(event.data) {
+                    Ok(meeting) => meeting,
+                    Err(e) => {
+                        warn!("invalid {} event: {}", event.name, e);
+                        continue;
+                    }
+                };
+
+                match (event.name.as_str(), current) {
+                    ("meeting_started", None) => {
+                        match db
+                            .start_meeting(
+                                &meeting.app,
+                                meeting.title.as_deref(),
+                                meeting.timestamp,
+                            )
+                            .await
+                        {
+                            Ok(id) => {
+                                info!("meeting {} started in {}", id, meeting.app);
+                                current = Some(id);
+                            }
+                            Err(e) => error!("failed to record meeting start: {}", e),
+                        }
+                    }
+                    ("meeting_ended", Some(id)) => {
+                        current = None;
+                        match db.end_meeting(id, meeting.timestamp).await {
+                            Ok(_) => info!("meeting {} ended", id),
+                            Err(e) => error!("failed to record end of meeting {}: {}", id, e),
+                        }
+                    }
+                    (name, _) => debug!("ignoring {} event from {}", name, meeting.app),
+                }
+            }
+            _ = link_interval.tick() => {
+                if let Some(id) = current {
+                    if let Err(e) = db.link_meeting_transcriptions(id).await {
+                        warn!("failed to link transcriptions to meeting {}: {}", id, e);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use cubby_core::{download_pipe, download_pipe_private, PipeState};
+use oasgen::OaSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -12,7 +13,7 @@ use tokio::sync::mpsc::{self, Sender};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, OaSchema)]
 pub struct PipeInfo {
     pub id: String,
     pub enabled: bool,
@@ -24,6 +25,17 @@ pub struct PipeInfo {
     pub build_status: Option<Value>,
 }
 
+/// What `/health` reports about an installed pipe, without its config which may hold
+/// secrets
+#[derive(Clone, Serialize, Deserialize, Debug, OaSchema)]
+pub struct PipeStatus {
+    pub id: String,
+    /// `running`, `stopped` when enabled but not running, or `disabled`
+    pub status: String,
+    pub port: Option<u16>,
+    pub build_status: Option<Value>,
+}
+
 struct PipeHandle {
     state: PipeState,
     kill_tx: Sender<()>,
@@ -204,6 +216,60 @@ impl PipeManager {
         pipe_infos
     }
 
+    pub async fn pipe_statuses(&self) -> Vec<PipeStatus> {
+        let pipes = self.list_pipes().await;
+        let running_pipes = self.running_pipes.read().await;
+
+        pipes
+            .into_iter()
+            .map(|pipe| {
+                let (status, port) = match running_pipes.get(&pipe.id).map(|handle| handle.state) {
+                    Some(PipeState::Port(port)) => ("running", Some(port)),
+                    Some(PipeState::Pid(_)) => ("running", pipe.port),
+                    None if pipe.enabled => ("stopped", pipe.port),
+                    None => ("disabled", pipe.port),
+                };
+                PipeStatus {
+                    id: pipe.id,
+                    status: status.to_string(),
+                    port,
+                    build_status: pipe.build_status,
+                }
+            })
+            .collect()
+    }
+
+    /// Starts every enabled pipe, as on boot
+    pub async fn start_enabled_pipes(&self) {
+        for pipe in self.list_pipes().await {
+            if !pipe.enabled {
+                debug!("pipe {} is disabled", pipe.id);
+                continue;
+            }
+
+            match self.start_pipe_task(pipe.id.clone()).await {
+                Ok(future) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = future.await {
+                            warn!("pipe {} stopped: {}", pipe.id, e);
+                        }
+                    });
+                }
+                Err(e) => error!("failed to start pipe {}: {}", pipe.id, e),
+            }
+        }
+    }
+
+    /// Stops every running pipe, leaving them enabled for the next boot
+    pub async fn stop_all_pipes(&self) {
+        let ids: Vec<String> = self.running_pipes.read().await.keys().cloned().collect();
+        for id in ids {
+            if let Err(e) = self.stop_pipe(&id).await {
+                warn!("failed to stop pipe {}: {}", id, e);
+            }
+        }
+    }
+
     pub async fn download_pipe(&self, url: &str) -> Result<String> {
         // Remove any surrounding quotes and normalize backslashes
         let normalized_url = url.trim_matches('"').replace("\\", "/");
@@ -5,6 +5,7 @@ use axum::{
         Json, Path, Query, State,
     },
     http::StatusCode,
+    middleware,
     response::{IntoResponse, Json as JsonResponse, Response},
     routing::get,
     serve, Router,
@@ -14,16 +15,22 @@ use oasgen::{oasgen, OaSchema, Server};
 use cubby_core::Desktop;
 
 use chrono::TimeZone;
+use clap::ValueEnum;
 use cubby_db::{
-    ContentType, DatabaseManager, FrameData, Order, SearchMatch, SearchResult, Speaker,
-    TagContentType,
+    create_retention_worker, remove_media_files, ActivityGroupBy, ActivityReport, AggregateOptions,
+    ContentType, DatabaseManager, ForgetFilter, ForgetReport, FrameData, HybridMatch,
+    HybridSearchOptions, IndexJob, JournalEvent, JournalPosition, Meeting, MeetingTranscript,
+    Order, RawSqlError, RawSqlOptions, ReclusterOptions, ReclusterReport, Relevance,
+    RetentionCommand, RetentionConfig, RetentionPolicy, RetentionReport, RetentionStatus,
+    SearchAggregate, SearchCursor, SearchMatch, SearchMode, SearchResult, SemanticSearchFilter,
+    Speaker, TagContentType, TranscriptionDetails, Webhook, WebhookDeadLetter,
 };
 
 use tokio_util::io::ReaderStream;
 
 use tokio::fs::File;
 
-use cubby_events::{send_event, subscribe_to_all_events, Event as cubbyEvent};
+use cubby_events::{send_event, subscribe_to_all_events, Event as cubbyEvent, EventFilter};
 use futures::{
     future::{try_join, try_join_all},
     SinkExt, StreamExt,
@@ -31,13 +38,25 @@ use futures::{
 use image::ImageFormat::{self};
 
 use crate::{
-    embedding::embedding_endpoint::create_embeddings,
+    add::IndexOptions,
+    auth::{cors_layer, generate_token, redacted_uri, require_token, AuthConfig},
+    cli::CliOcrEngine,
+    embedding::{
+        backend::EmbeddingBackend,
+        embedding_endpoint::create_embeddings,
+        worker::{EmbeddingStatus, EmbeddingWorker, EmbeddingWorkerConfig},
+    },
+    event_journal::{strip_image, EventJournal},
+    index_jobs::IndexJobManager,
+    pipe_manager::{PipeInfo, PipeManager, PipeStatus},
     video::{finish_ffmpeg_process, start_ffmpeg_process, write_frame_to_ffmpeg, MAX_FPS},
     video_cache::{AudioEntry, DeviceFrame, FrameCache, FrameMetadata, TimeSeriesFrame},
     video_utils::{
         extract_frame, extract_frame_from_video, extract_high_quality_frame, merge_videos,
-        validate_media, MergeVideosRequest, MergeVideosResponse, ValidateMediaParams,
+        mute_audio_ranges, remove_frames_from_video, validate_media, MergeVideosRequest,
+        MergeVideosResponse, ValidateMediaParams,
     },
+    webhooks::{generate_secret, WebhookManager},
 };
 use chrono::{DateTime, Utc};
 use cubby_audio::{
@@ -46,7 +65,7 @@ use cubby_audio::{
         default_input_device, default_output_device, list_audio_devices, AudioDevice, DeviceType,
     },
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use cubby_vision::monitor::{get_monitor_by_id, list_monitors};
 use cubby_vision::OcrEngine;
@@ -56,7 +75,10 @@ use std::{
     net::SocketAddr,
     num::NonZeroUsize,
     path::PathBuf,
-    sync::{atomic::Ordering, Arc},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
@@ -67,18 +89,15 @@ use crate::mac_notifications;
 
 use tokio::{
     net::TcpListener,
-    sync::{mpsc, Mutex},
+    sync::{broadcast, mpsc, watch, Mutex},
     time::timeout,
 };
 
-use tower_http::{cors::Any, trace::TraceLayer};
-use tower_http::{cors::CorsLayer, trace::DefaultMakeSpan};
+use tower_http::trace::TraceLayer;
 
 use enigo::{Enigo, Key, Settings};
 use std::str::FromStr;
 
-use crate::text_embeds::generate_embedding;
-
 use cubby_core::UIElement;
 use std::collections::{HashMap, HashSet};
 use uuid::Uuid; // or sentry::protocol::Uuid depending on which you want to use
@@ -87,15 +106,64 @@ pub type FrameImageCache = LruCache<i64, (String, Instant)>;
 
 pub struct AppState {
     pub db: Arc<DatabaseManager>,
-    pub audio_manager: Arc<AudioManager>,
+    /// `None` when this process doesn't capture audio, like `cubby mcp`
+    pub audio_manager: Option<Arc<AudioManager>>,
     pub app_start_time: DateTime<Utc>,
     pub cubby_dir: PathBuf,
+    pub pipe_manager: Arc<PipeManager>,
     pub vision_disabled: bool,
     pub audio_disabled: bool,
     pub ui_monitoring_enabled: bool,
     pub frame_cache: Option<Arc<FrameCache>>,
     pub frame_image_cache: Option<Arc<Mutex<FrameImageCache>>>,
     pub element_cache: Arc<Mutex<Option<(Vec<UIElement>, Instant, String)>>>,
+    pub retention_policy: RetentionPolicy,
+    pub retention_tx: mpsc::Sender<RetentionCommand>,
+    pub retention_status: Arc<Mutex<RetentionStatus>>,
+    pub raw_sql_options: RawSqlOptions,
+    pub auth_enabled: bool,
+    /// Token the server uses to call its own routes, never stored
+    pub internal_token: String,
+    pub index_jobs: Arc<IndexJobManager>,
+    /// `None` when embeddings are disabled
+    pub embeddings: Option<Arc<EmbeddingWorker>>,
+    /// `None` when events are not journaled
+    pub event_journal: Option<Arc<EventJournal>>,
+    pub webhooks: Arc<WebhookManager>,
+}
+
+impl AppState {
+    /// State for serving recorded data from a process that doesn't record, such as
+    /// `cubby mcp`: no capture, retention, index jobs or embeddings run in it and auth is
+    /// left to the transport.
+    pub fn without_capture(db: Arc<DatabaseManager>, cubby_dir: PathBuf) -> Self {
+        let (retention_tx, _) = mpsc::channel(1);
+        AppState {
+            db: db.clone(),
+            audio_manager: None,
+            app_start_time: Utc::now(),
+            pipe_manager: Arc::new(PipeManager::new(cubby_dir.clone())),
+            cubby_dir,
+            vision_disabled: true,
+            audio_disabled: true,
+            ui_monitoring_enabled: false,
+            frame_cache: None,
+            frame_image_cache: Some(Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(100).unwrap(),
+            )))),
+            element_cache: Arc::new(Mutex::new(None)),
+            retention_policy: RetentionPolicy::default(),
+            retention_tx,
+            retention_status: Arc::new(Mutex::new(RetentionStatus::NotStarted)),
+            raw_sql_options: RawSqlOptions::default(),
+            auth_enabled: false,
+            internal_token: generate_token(),
+            index_jobs: IndexJobManager::without_worker(db.clone()),
+            embeddings: None,
+            event_journal: None,
+            webhooks: WebhookManager::without_worker(db),
+        }
+    }
 }
 
 // Update the SearchQuery struct
@@ -131,6 +199,50 @@ pub(crate) struct SearchQuery {
     focused: Option<bool>,
     #[serde(default)]
     browser_url: Option<String>,
+    /// `hybrid` ranks by keywords and meaning together, most relevant first, and adds the
+    /// relevance of each result
+    #[serde(default)]
+    mode: SearchMode,
+    /// Weights of content types in hybrid mode
+    #[serde(default, deserialize_with = "deserialize_optional_f32")]
+    ocr_weight: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_optional_f32")]
+    audio_weight: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_optional_f32")]
+    ui_weight: Option<f32>,
+    /// Boost of recent results in hybrid mode, 0 turns it off
+    #[serde(default, deserialize_with = "deserialize_optional_f32")]
+    recency_weight: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_optional_f32")]
+    recency_half_life_hours: Option<f32>,
+    /// `next_cursor` of the previous page, pages then continue after its last result even
+    /// while new content is recorded
+    #[serde(default)]
+    cursor: Option<String>,
+    /// `ascending`/`asc` or `descending`/`desc`, by timestamp. Only full text search
+    /// takes it, hybrid search orders by relevance.
+    #[serde(default)]
+    order: Order,
+    /// Whether to count all matching results, an extra scan. Defaults to true for the first
+    /// page and false when following a cursor.
+    #[serde(default)]
+    include_total: Option<bool>,
+}
+
+impl SearchQuery {
+    fn hybrid_options(&self) -> HybridSearchOptions {
+        let defaults = HybridSearchOptions::default();
+        HybridSearchOptions {
+            ocr_weight: self.ocr_weight.unwrap_or(defaults.ocr_weight),
+            audio_weight: self.audio_weight.unwrap_or(defaults.audio_weight),
+            ui_weight: self.ui_weight.unwrap_or(defaults.ui_weight),
+            recency_weight: self.recency_weight.unwrap_or(defaults.recency_weight),
+            recency_half_life_hours: self
+                .recency_half_life_hours
+                .unwrap_or(defaults.recency_half_life_hours),
+            ..defaults
+        }
+    }
 }
 
 #[derive(OaSchema, Deserialize)]
@@ -151,6 +263,25 @@ where
     s.parse().map_err(serde::de::Error::custom)
 }
 
+/// Takes a number as a string, the way query strings carry it, or as a json number
+fn deserialize_optional_f32<'de, D>(deserializer: D) -> Result<Option<f32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        String(String),
+        Number(f32),
+    }
+
+    match Option::<Number>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Number::Number(n)) => Ok(Some(n)),
+        Some(Number::String(s)) => s.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
 // Response structs
 #[derive(Serialize, Deserialize)]
 pub struct PaginatedResponse<T> {
@@ -162,7 +293,12 @@ pub struct PaginatedResponse<T> {
 pub struct PaginationInfo {
     pub limit: u32,
     pub offset: u32,
-    pub total: i64,
+    /// Estimated number of matching results, left out when not counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// Cursor of the next page, left out on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -207,6 +343,72 @@ pub enum ContentItem {
     UI(UiContent),
 }
 
+impl From<HybridMatch> for ContentItem {
+    fn from(hybrid_match: HybridMatch) -> Self {
+        let mut item = ContentItem::from(hybrid_match.result);
+        let relevance = Some(hybrid_match.relevance);
+        match &mut item {
+            ContentItem::OCR(ocr) => ocr.relevance = relevance,
+            ContentItem::Audio(audio) => audio.relevance = relevance,
+            ContentItem::UI(ui) => ui.relevance = relevance,
+        }
+        item
+    }
+}
+
+impl From<SearchResult> for ContentItem {
+    fn from(result: SearchResult) -> Self {
+        match result {
+            SearchResult::OCR(ocr) => ContentItem::OCR(OCRContent {
+                frame_id: ocr.frame_id,
+                text: ocr.ocr_text,
+                timestamp: ocr.timestamp,
+                file_path: ocr.file_path,
+                offset_index: ocr.offset_index,
+                app_name: ocr.app_name,
+                window_name: ocr.window_name,
+                tags: ocr.tags,
+                frame: None,
+                frame_name: Some(ocr.frame_name),
+                browser_url: ocr.browser_url,
+                focused: ocr.focused,
+                device_name: ocr.device_name,
+                relevance: None,
+            }),
+            SearchResult::Audio(audio) => ContentItem::Audio(AudioContent {
+                chunk_id: audio.audio_chunk_id,
+                transcription_id: audio.transcription_id,
+                transcription: audio.transcription,
+                timestamp: audio.timestamp,
+                file_path: audio.file_path,
+                offset_index: audio.offset_index,
+                tags: audio.tags,
+                device_name: audio.device_name,
+                device_type: audio.device_type.into(),
+                speaker: audio.speaker,
+                start_time: audio.start_time,
+                end_time: audio.end_time,
+                language: audio.language,
+                avg_logprob: audio.avg_logprob,
+                relevance: None,
+            }),
+            SearchResult::UI(ui) => ContentItem::UI(UiContent {
+                id: ui.id,
+                text: ui.text,
+                timestamp: ui.timestamp,
+                app_name: ui.app_name,
+                window_name: ui.window_name,
+                initial_traversal_at: ui.initial_traversal_at,
+                file_path: ui.file_path,
+                offset_index: ui.offset_index,
+                frame_name: ui.frame_name,
+                browser_url: ui.browser_url,
+                relevance: None,
+            }),
+        }
+    }
+}
+
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
 pub struct OCRContent {
     pub frame_id: i64,
@@ -222,11 +424,15 @@ pub struct OCRContent {
     pub browser_url: Option<String>,
     pub focused: Option<bool>,
     pub device_name: String,
+    /// Set by hybrid search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<Relevance>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
 pub struct AudioContent {
     pub chunk_id: i64,
+    pub transcription_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
     pub file_path: String,
@@ -237,6 +443,11 @@ pub struct AudioContent {
     pub speaker: Option<Speaker>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    pub language: Option<String>,
+    pub avg_logprob: Option<f32>,
+    /// Set by hybrid search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<Relevance>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -251,12 +462,15 @@ pub struct UiContent {
     pub offset_index: i64,
     pub frame_name: Option<String>,
     pub browser_url: Option<String>,
+    /// Set by hybrid search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<Relevance>,
 }
 
 #[derive(OaSchema, Serialize)]
 pub(crate) struct ListDeviceResponse {
-    name: String,
-    is_default: bool,
+    pub(crate) name: String,
+    pub(crate) is_default: bool,
 }
 
 #[derive(OaSchema, Serialize)]
@@ -306,6 +520,7 @@ pub struct HealthCheckResponse {
     pub message: String,
     pub verbose_instructions: Option<String>,
     pub device_status_details: Option<String>,
+    pub pipes: Vec<PipeStatus>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize)]
@@ -338,96 +553,18 @@ pub(crate) async fn search(
         query.focused,
     );
 
-    let query_str = query.q.as_deref().unwrap_or("");
-
-    let content_type = query.content_type.clone();
-
-    let (results, total) = try_join(
-        state.db.search(
-            query_str,
-            content_type.clone(),
-            query.pagination.limit,
-            query.pagination.offset,
-            query.start_time,
-            query.end_time,
-            query.app_name.as_deref(),
-            query.window_name.as_deref(),
-            query.min_length,
-            query.max_length,
-            query.speaker_ids.clone(),
-            query.frame_name.as_deref(),
-            query.browser_url.as_deref(),
-            query.focused,
-        ),
-        state.db.count_search_results(
-            query_str,
-            content_type,
-            query.start_time,
-            query.end_time,
-            query.app_name.as_deref(),
-            query.window_name.as_deref(),
-            query.min_length,
-            query.max_length,
-            query.speaker_ids.clone(),
-            query.frame_name.as_deref(),
-            query.browser_url.as_deref(),
-            query.focused,
-        ),
-    )
-    .await
-    .map_err(|e| {
-        error!("failed to perform search operations: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            JsonResponse(json!({"error": format!("failed to perform search operations: {}", e)})),
-        )
-    })?;
-
-    let mut content_items: Vec<ContentItem> = results
-        .iter()
-        .map(|result| match result {
-            SearchResult::OCR(ocr) => ContentItem::OCR(OCRContent {
-                frame_id: ocr.frame_id,
-                text: ocr.ocr_text.clone(),
-                timestamp: ocr.timestamp,
-                file_path: ocr.file_path.clone(),
-                offset_index: ocr.offset_index,
-                app_name: ocr.app_name.clone(),
-                window_name: ocr.window_name.clone(),
-                tags: ocr.tags.clone(),
-                frame: None,
-                frame_name: Some(ocr.frame_name.clone()),
-                browser_url: ocr.browser_url.clone(),
-                focused: ocr.focused,
-                device_name: ocr.device_name.clone(),
-            }),
-            SearchResult::Audio(audio) => ContentItem::Audio(AudioContent {
-                chunk_id: audio.audio_chunk_id,
-                transcription: audio.transcription.clone(),
-                timestamp: audio.timestamp,
-                file_path: audio.file_path.clone(),
-                offset_index: audio.offset_index,
-                tags: audio.tags.clone(),
-                device_name: audio.device_name.clone(),
-                device_type: audio.device_type.clone().into(),
-                speaker: audio.speaker.clone(),
-                start_time: audio.start_time,
-                end_time: audio.end_time,
-            }),
-            SearchResult::UI(ui) => ContentItem::UI(UiContent {
-                id: ui.id,
-                text: ui.text.clone(),
-                timestamp: ui.timestamp,
-                app_name: ui.app_name.clone(),
-                window_name: ui.window_name.clone(),
-                initial_traversal_at: ui.initial_traversal_at,
-                file_path: ui.file_path.clone(),
-                offset_index: ui.offset_index,
-                frame_name: ui.frame_name.clone(),
-                browser_url: ui.browser_url.clone(),
-            }),
-        })
-        .collect();
+    let (mut content_items, total, next_cursor) = if query.mode == SearchMode::Hybrid {
+        if query.cursor.is_some() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                JsonResponse(json!({"error": "cursor is not supported with mode=hybrid"})),
+            ));
+        }
+        let (content_items, total) = search_hybrid(&query, &state).await?;
+        (content_items, total, None)
+    } else {
+        search_fts(&query, &state).await?
+    };
 
     if query.include_frames {
         debug!("extracting frames for ocr content");
@@ -454,17 +591,265 @@ pub(crate) async fn search(
         }
     }
 
-    info!("search completed: found {} results", total);
+    info!(
+        "search completed: returned {} results of {:?}",
+        content_items.len(),
+        total
+    );
     Ok(JsonResponse(SearchResponse {
         data: content_items,
         pagination: PaginationInfo {
             limit: query.pagination.limit,
             offset: query.pagination.offset,
-            total: total as i64,
+            total: total.map(|total| total as i64),
+            next_cursor,
         },
     }))
 }
 
+/// `/search?mode=fts`, OCR, audio and UI results merged by timestamp with a cursor to the
+/// next page
+async fn search_fts(
+    query: &SearchQuery,
+    state: &AppState,
+) -> Result<(Vec<ContentItem>, Option<usize>, Option<String>), (StatusCode, JsonResponse<Value>)> {
+    let cursor = match query.cursor.as_deref().map(SearchCursor::from_str) {
+        Some(Ok(cursor)) => Some(cursor),
+        Some(Err(e)) => return Err((StatusCode::BAD_REQUEST, JsonResponse(json!({"error": e})))),
+        None => None,
+    };
+    let include_total = query.include_total.unwrap_or(cursor.is_none());
+    let query_str = query.q.as_deref().unwrap_or("");
+
+    let page = state.db.search_page(
+        query_str,
+        query.content_type.clone(),
+        query.pagination.limit,
+        query.pagination.offset,
+        cursor.as_ref(),
+        &query.order,
+        query.start_time,
+        query.end_time,
+        query.app_name.as_deref(),
+        query.window_name.as_deref(),
+        query.min_length,
+        query.max_length,
+        query.speaker_ids.clone(),
+        query.frame_name.as_deref(),
+        query.browser_url.as_deref(),
+        query.focused,
+    );
+    let total = async {
+        if !include_total {
+            return Ok(None);
+        }
+        state
+            .db
+            .count_search_results(
+                query_str,
+                query.content_type.clone(),
+                query.start_time,
+                query.end_time,
+                query.app_name.as_deref(),
+                query.window_name.as_deref(),
+                query.min_length,
+                query.max_length,
+                query.speaker_ids.clone(),
+                query.frame_name.as_deref(),
+                query.browser_url.as_deref(),
+                query.focused,
+            )
+            .await
+            .map(Some)
+    };
+
+    let (page, total) = try_join(page, total).await.map_err(|e| {
+        error!("failed to perform search operations: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": format!("failed to perform search operations: {}", e)})),
+        )
+    })?;
+
+    Ok((
+        page.results.into_iter().map(ContentItem::from).collect(),
+        total,
+        page.next_cursor.map(|cursor| cursor.to_string()),
+    ))
+}
+
+/// `/search?mode=hybrid`, ranks by keywords and, when embeddings are on, by meaning too.
+/// The total is `None` when there were more matches than ranked candidates.
+async fn search_hybrid(
+    query: &SearchQuery,
+    state: &AppState,
+) -> Result<(Vec<ContentItem>, Option<usize>), (StatusCode, JsonResponse<Value>)> {
+    if query.frame_name.is_some()
+        || query.browser_url.is_some()
+        || query.focused.is_some()
+        || query.min_length.is_some()
+        || query.max_length.is_some()
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({
+                "error": "frame_name, browser_url, focused, min_length and max_length are not supported with mode=hybrid"
+            })),
+        ));
+    }
+
+    let query_str = query.q.as_deref().unwrap_or("");
+    let embedding = match &state.embeddings {
+        Some(embeddings) if !query_str.trim().is_empty() => {
+            let backend = embeddings.backend();
+            // a failing embedding backend leaves keyword ranking, search still answers
+            match backend.embed(&[query_str.to_string()]).await {
+                Ok(mut vectors) if vectors.len() == 1 => Some((vectors.remove(0), backend.model())),
+                Ok(_) => {
+                    warn!(
+                        "{} returned no embedding, ranking by keywords only",
+                        backend.model()
+                    );
+                    None
+                }
+                Err(e) => {
+                    warn!(
+                        "failed to embed search query, ranking by keywords only: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let filter = SemanticSearchFilter {
+        content_type: query.content_type.clone(),
+        start_time: query.start_time,
+        end_time: query.end_time,
+        app_name: query.app_name.clone(),
+        window_name: query.window_name.clone(),
+        speaker_ids: query.speaker_ids.clone(),
+    };
+    let (matches, total) = state
+        .db
+        .search_hybrid(
+            query_str,
+            embedding
+                .as_ref()
+                .map(|(vector, model)| (vector.as_slice(), *model)),
+            &filter,
+            &query.hybrid_options(),
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to perform hybrid search: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to perform hybrid search: {}", e)})),
+            )
+        })?;
+
+    Ok((matches.into_iter().map(ContentItem::from).collect(), total))
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct SearchAggregateQuery {
+    q: Option<String>,
+    #[serde(default)]
+    content_type: ContentType,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    window_name: Option<String>,
+    #[serde(default)]
+    frame_name: Option<String>,
+    #[serde(default)]
+    min_length: Option<usize>,
+    #[serde(default)]
+    max_length: Option<usize>,
+    #[serde(
+        deserialize_with = "from_comma_separated_array",
+        default = "default_speaker_ids"
+    )]
+    speaker_ids: Option<Vec<i64>>,
+    #[serde(default)]
+    focused: Option<bool>,
+    #[serde(default)]
+    browser_url: Option<String>,
+    /// Width of the histogram buckets, 3600 by default
+    #[serde(default)]
+    bucket_seconds: Option<i64>,
+    /// Values kept per facet, 10 by default
+    #[serde(default)]
+    top: Option<usize>,
+    /// Longest gap between two frames counted as screen time, 60 by default
+    #[serde(default)]
+    max_frame_gap_seconds: Option<f64>,
+}
+
+/// Counts and estimated screen and speech time of what `/search` would match with the same
+/// filters, per app, window, domain, device and speaker and over time
+#[oasgen]
+pub(crate) async fn search_aggregate_handler(
+    Query(query): Query<SearchAggregateQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<SearchAggregate>, (StatusCode, JsonResponse<Value>)> {
+    let defaults = AggregateOptions::default();
+    let options = AggregateOptions {
+        bucket_seconds: query.bucket_seconds.unwrap_or(defaults.bucket_seconds),
+        top: query.top.unwrap_or(defaults.top),
+        max_frame_gap_seconds: query
+            .max_frame_gap_seconds
+            .unwrap_or(defaults.max_frame_gap_seconds),
+    };
+    if options.bucket_seconds < 1 || options.max_frame_gap_seconds < 0.0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({
+                "error": "bucket_seconds must be at least 1 and max_frame_gap_seconds can't be negative"
+            })),
+        ));
+    }
+
+    let aggregate = state
+        .db
+        .aggregate_search(
+            query.q.as_deref().unwrap_or(""),
+            query.content_type,
+            query.start_time,
+            query.end_time,
+            query.app_name.as_deref(),
+            query.window_name.as_deref(),
+            query.min_length,
+            query.max_length,
+            query.speaker_ids,
+            query.frame_name.as_deref(),
+            query.browser_url.as_deref(),
+            query.focused,
+            &options,
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to aggregate search results: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to aggregate search results: {}", e)}),
+                ),
+            )
+        })?;
+
+    Ok(JsonResponse(aggregate))
+}
+
 #[oasgen]
 pub(crate) async fn api_list_audio_devices(
     State(_state): State<Arc<AppState>>,
@@ -615,7 +1000,11 @@ pub async fn health_check(State(state): State<Arc<AppState>>) -> JsonResponse<He
     let grace_period = 120; // 2 minutes in seconds
 
     // Get the status of all devices
-    let audio_devices = state.audio_manager.current_devices();
+    let audio_devices = state
+        .audio_manager
+        .as_ref()
+        .map(|audio_manager| audio_manager.current_devices())
+        .unwrap_or_default();
     let mut device_statuses = Vec::new();
     let mut global_audio_active = false;
     let mut most_recent_audio_timestamp = 0; // Track the most recent timestamp
@@ -782,6 +1171,7 @@ pub async fn health_check(State(state): State<Arc<AppState>>) -> JsonResponse<He
         message,
         verbose_instructions,
         device_status_details,
+        pipes: state.pipe_manager.pipe_statuses().await,
     })
 }
 
@@ -838,16 +1228,157 @@ struct UpdatePipeVersionRequest {
     source: String,
 }
 
-#[oasgen]
-async fn send_notification(
-    Json(payload): Json<NotificationPayload>,
-) -> Result<Json<ApiResponse>, (StatusCode, String)> {
-    info!("received notification request: {:?}", payload);
+/// Error for a failed pipe operation, logged as it may have left the pipe half started
+fn pipe_error(action: &str, pipe_id: &str, e: anyhow::Error) -> (StatusCode, JsonResponse<Value>) {
+    error!("failed to {} pipe {}: {}", action, pipe_id, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        JsonResponse(json!({"error": format!("failed to {} pipe: {}", action, e)})),
+    )
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        return match mac_notifications::send(&payload.title, &payload.body) {
-            Ok(_) => {
+/// Installed pipe with this id. Ids are matched against the pipes directory, so one
+/// pointing outside of it is never found.
+async fn installed_pipe(
+    state: &AppState,
+    pipe_id: &str,
+) -> Result<PipeInfo, (StatusCode, JsonResponse<Value>)> {
+    state
+        .pipe_manager
+        .get_pipe_info(pipe_id)
+        .await
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                JsonResponse(json!({"error": format!("pipe {} not found", pipe_id)})),
+            )
+        })
+}
+
+#[oasgen]
+async fn list_pipes_handler(State(state): State<Arc<AppState>>) -> JsonResponse<Vec<PipeInfo>> {
+    JsonResponse(state.pipe_manager.list_pipes().await)
+}
+
+#[oasgen]
+async fn download_pipe_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<DownloadPipeRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let pipe_id = state
+        .pipe_manager
+        .download_pipe(&payload.url)
+        .await
+        .map_err(|e| pipe_error("download", &payload.url, e))?;
+    Ok(JsonResponse(json!({"success": true, "pipe_id": pipe_id})))
+}
+
+/// Enabling a pipe starts it and disabling stops it, enabling a running pipe restarts it
+async fn set_pipe_enabled(
+    state: &AppState,
+    pipe_id: &str,
+    enabled: bool,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    installed_pipe(state, pipe_id).await?;
+    state
+        .pipe_manager
+        .update_config(pipe_id, json!({"enabled": enabled}))
+        .await
+        .map_err(|e| pipe_error(if enabled { "enable" } else { "disable" }, pipe_id, e))?;
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+#[oasgen]
+async fn enable_pipe_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<RunPipeRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    set_pipe_enabled(&state, &payload.pipe_id, true).await
+}
+
+#[oasgen]
+async fn disable_pipe_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<RunPipeRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    set_pipe_enabled(&state, &payload.pipe_id, false).await
+}
+
+#[oasgen]
+async fn update_pipe_config_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<UpdatePipeConfigRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    if !payload.config.is_object() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "config must be an object"})),
+        ));
+    }
+    installed_pipe(&state, &payload.pipe_id).await?;
+    state
+        .pipe_manager
+        .update_config(&payload.pipe_id, payload.config)
+        .await
+        .map_err(|e| pipe_error("update", &payload.pipe_id, e))?;
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+/// Replaces the pipe's code with the version at `source` and restarts it when enabled,
+/// its config is kept
+#[oasgen]
+async fn update_pipe_version_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<UpdatePipeVersionRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    installed_pipe(&state, &payload.pipe_id).await?;
+    state
+        .pipe_manager
+        .update_pipe_version(&payload.pipe_id, &payload.source)
+        .await
+        .map_err(|e| pipe_error("update", &payload.pipe_id, e))?;
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+#[oasgen]
+async fn delete_pipe_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<DeletePipeRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    installed_pipe(&state, &payload.pipe_id).await?;
+    state
+        .pipe_manager
+        .delete_pipe(&payload.pipe_id)
+        .await
+        .map_err(|e| pipe_error("delete", &payload.pipe_id, e))?;
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+#[oasgen]
+async fn purge_pipes_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(_): JsonResponse<PurgePipeRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    state.pipe_manager.purge_pipes().await.map_err(|e| {
+        error!("failed to purge pipes: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": format!("failed to purge pipes: {}", e)})),
+        )
+    })?;
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+#[oasgen]
+async fn send_notification(
+    Json(payload): Json<NotificationPayload>,
+) -> Result<Json<ApiResponse>, (StatusCode, String)> {
+    info!("received notification request: {:?}", payload);
+
+    #[cfg(target_os = "macos")]
+    {
+        return match mac_notifications::send(&payload.title, &payload.body) {
+            Ok(_) => {
                 info!("notification sent successfully");
                 Ok(Json(ApiResponse {
                     success: true,
@@ -923,9 +1454,15 @@ pub struct SCServer {
     addr: SocketAddr,
     audio_manager: Arc<AudioManager>,
     cubby_dir: PathBuf,
+    pipe_manager: Arc<PipeManager>,
     vision_disabled: bool,
     audio_disabled: bool,
     ui_monitoring_enabled: bool,
+    retention_config: RetentionConfig,
+    raw_sql_options: RawSqlOptions,
+    auth_config: AuthConfig,
+    embedding_backend: Option<Arc<dyn EmbeddingBackend>>,
+    event_journal_days: Option<u32>,
 }
 
 impl SCServer {
@@ -934,19 +1471,31 @@ impl SCServer {
         db: Arc<DatabaseManager>,
         addr: SocketAddr,
         cubby_dir: PathBuf,
+        pipe_manager: Arc<PipeManager>,
         vision_disabled: bool,
         audio_disabled: bool,
         ui_monitoring_enabled: bool,
         audio_manager: Arc<AudioManager>,
+        retention_config: RetentionConfig,
+        raw_sql_options: RawSqlOptions,
+        auth_config: AuthConfig,
+        embedding_backend: Option<Arc<dyn EmbeddingBackend>>,
+        event_journal_days: Option<u32>,
     ) -> Self {
         SCServer {
             db,
             addr,
             cubby_dir,
+            pipe_manager,
             vision_disabled,
             audio_disabled,
             ui_monitoring_enabled,
             audio_manager,
+            retention_config,
+            raw_sql_options,
+            auth_config,
+            embedding_backend,
+            event_journal_days,
         }
     }
 
@@ -970,11 +1519,30 @@ impl SCServer {
     }
 
     pub async fn create_router(&self, enable_frame_cache: bool) -> Router {
+        let (retention_tx, mut retention_rx, _) =
+            create_retention_worker(self.db.clone(), Some(self.retention_config.clone()));
+        let retention_status = Arc::new(Mutex::new(RetentionStatus::NotStarted));
+        let latest_retention_status = retention_status.clone();
+        tokio::spawn(async move {
+            while let Some(response) = retention_rx.recv().await {
+                *latest_retention_status.lock().await = response.status;
+            }
+        });
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(VIDEO_SCRUB_INTERVAL);
+            loop {
+                interval.tick().await;
+                scrub_finished_video_chunks(&db).await;
+            }
+        });
+
         let app_state = Arc::new(AppState {
             db: self.db.clone(),
-            audio_manager: self.audio_manager.clone(),
+            audio_manager: Some(self.audio_manager.clone()),
             app_start_time: Utc::now(),
             cubby_dir: self.cubby_dir.clone(),
+            pipe_manager: self.pipe_manager.clone(),
             vision_disabled: self.vision_disabled,
             audio_disabled: self.audio_disabled,
             ui_monitoring_enabled: self.ui_monitoring_enabled,
@@ -995,18 +1563,30 @@ impl SCServer {
                 None
             },
             element_cache: Arc::new(Mutex::new(None)),
+            retention_policy: self.retention_config.policy.clone(),
+            retention_tx,
+            retention_status,
+            raw_sql_options: self.raw_sql_options.clone(),
+            auth_enabled: self.auth_config.enabled,
+            internal_token: generate_token(),
+            index_jobs: IndexJobManager::start(
+                self.db.clone(),
+                self.cubby_dir.clone(),
+                self.embedding_backend.clone(),
+            ),
+            embeddings: self.embedding_backend.clone().map(|backend| {
+                EmbeddingWorker::start(self.db.clone(), backend, EmbeddingWorkerConfig::default())
+            }),
+            event_journal: self
+                .event_journal_days
+                .map(|days| EventJournal::start(self.db.clone(), days)),
+            webhooks: WebhookManager::start(self.db.clone()),
         });
 
-        let cors = CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods(Any)
-            .allow_headers(Any)
-            .expose_headers([
-                axum::http::header::CONTENT_TYPE,
-                axum::http::header::CACHE_CONTROL,
-            ]);
+        let cors = cors_layer(&self.auth_config);
         let server = Server::axum()
             .get("/search", search)
+            .get("/search/aggregate", search_aggregate_handler)
             .get("/audio/list", api_list_audio_devices)
             .get("/vision/list", api_list_monitors)
             .post("/tags/:content_type/:id", add_tags)
@@ -1021,6 +1601,7 @@ impl SCServer {
             .post("/speakers/delete", delete_speaker_handler)
             .post("/speakers/hallucination", mark_as_hallucination_handler)
             .post("/speakers/merge", merge_speakers_handler)
+            .post("/speakers/recluster", recluster_speakers_handler)
             .get("/speakers/similar", get_similar_speakers_handler)
             .post("/experimental/frames/merge", merge_frames_handler)
             .get("/experimental/validate/media", validate_media_handler)
@@ -1052,11 +1633,44 @@ impl SCServer {
             .post("/audio/start", start_audio)
             .post("/audio/stop", stop_audio)
             .get("/semantic-search", semantic_search_handler)
+            .get("/embeddings/status", embeddings_status_handler)
             .get("/search/keyword", keyword_search_handler)
             .post("/v1/embeddings", create_embeddings)
             .post("/audio/device/start", start_audio_device)
             .post("/audio/device/stop", stop_audio_device)
             .post("/notify", send_notification)
+            .post("/retention/preview", retention_preview_handler)
+            .post("/retention/run", retention_run_handler)
+            .get("/retention/status", retention_status_handler)
+            .delete("/data", forget_data_handler)
+            .get("/meetings", list_meetings_handler)
+            .get("/meetings/:id", get_meeting_handler)
+            .get("/activity", activity_handler)
+            .get(
+                "/audio/transcriptions/:id/segments",
+                get_transcription_segments_handler,
+            )
+            .post("/index", create_index_job_handler)
+            .get("/index/jobs", list_index_jobs_handler)
+            .get("/index/jobs/:id", get_index_job_handler)
+            .post("/index/jobs/:id/cancel", cancel_index_job_handler)
+            .get("/pipes/list", list_pipes_handler)
+            .post("/pipes/download", download_pipe_handler)
+            .post("/pipes/enable", enable_pipe_handler)
+            .post("/pipes/disable", disable_pipe_handler)
+            .post("/pipes/update", update_pipe_config_handler)
+            .post("/pipes/update-version", update_pipe_version_handler)
+            .post("/pipes/delete", delete_pipe_handler)
+            .post("/pipes/purge", purge_pipes_handler)
+            .post("/webhooks", create_webhook_handler)
+            .get("/webhooks", list_webhooks_handler)
+            .delete("/webhooks/:id", delete_webhook_handler)
+            .get("/webhooks/dead-letters", list_dead_letters_handler)
+            .post(
+                "/webhooks/dead-letters/:id/retry",
+                retry_dead_letter_handler,
+            )
+            .delete("/webhooks/dead-letters/:id", delete_dead_letter_handler)
             .route_yaml_spec("/openapi.yaml")
             .route_json_spec("/openapi.json")
             .freeze();
@@ -1074,9 +1688,22 @@ impl SCServer {
                 "/mcp",
                 crate::mcp::server::create_mcp_service(app_state.clone()),
             )
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_token,
+            ))
             .with_state(app_state)
             .layer(cors)
-            .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::default()))
+            .layer(TraceLayer::new_for_http().make_span_with(
+                |request: &axum::http::Request<Body>| {
+                    tracing::debug_span!(
+                        "request",
+                        method = %request.method(),
+                        uri = %redacted_uri(request.uri()),
+                        version = ?request.version(),
+                    )
+                },
+            ))
     }
 }
 
@@ -1118,19 +1745,55 @@ struct RawSqlQuery {
     query: String,
 }
 
+/// Set on `/raw_sql` responses cut at the configured maximum number of rows
+const RAW_SQL_TRUNCATED_HEADER: &str = "x-cubby-truncated";
+/// JSON array of the `name` and `decl_type` of each column of a `/raw_sql` response
+const RAW_SQL_COLUMNS_HEADER: &str = "x-cubby-columns";
+
+/// JSON with non-ASCII characters escaped, as header values only take visible ASCII
+fn ascii_json<T: Serialize>(value: &T) -> String {
+    let json = serde_json::to_string(value).unwrap_or_default();
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                escaped.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    escaped
+}
+
+/// Responds with the bare array of rows, as before the row cap existed, and tells in
+/// headers whether rows were left out and what the columns are
 #[oasgen]
 async fn execute_raw_sql(
     State(state): State<Arc<AppState>>,
     JsonResponse(payload): JsonResponse<RawSqlQuery>,
-) -> Result<JsonResponse<serde_json::Value>, (StatusCode, JsonResponse<serde_json::Value>)> {
-    match state.db.execute_raw_sql(&payload.query).await {
-        Ok(result) => Ok(JsonResponse(result)),
+) -> Result<Response, (StatusCode, JsonResponse<serde_json::Value>)> {
+    match state
+        .db
+        .execute_read_only_sql(&payload.query, &state.raw_sql_options)
+        .await
+    {
+        Ok(result) => Ok((
+            [
+                (RAW_SQL_TRUNCATED_HEADER, result.truncated.to_string()),
+                (RAW_SQL_COLUMNS_HEADER, ascii_json(&result.columns)),
+            ],
+            JsonResponse(result.rows),
+        )
+            .into_response()),
         Err(e) => {
             error!("Failed to execute raw SQL query: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                JsonResponse(json!({"error": e.to_string()})),
-            ))
+            let status = match e {
+                RawSqlError::Rejected(_) => StatusCode::BAD_REQUEST,
+                RawSqlError::Timeout(_) => StatusCode::REQUEST_TIMEOUT,
+                RawSqlError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            Err((status, JsonResponse(json!({"error": e.to_string()}))))
         }
     }
 }
@@ -1578,7 +2241,7 @@ where
 }
 
 #[oasgen]
-async fn get_unnamed_speakers_handler(
+pub(crate) async fn get_unnamed_speakers_handler(
     State(state): State<Arc<AppState>>,
     Query(request): Query<GetUnnamedSpeakersRequest>,
 ) -> Result<JsonResponse<Vec<Speaker>>, (StatusCode, JsonResponse<Value>)> {
@@ -1612,7 +2275,7 @@ async fn get_unnamed_speakers_handler(
 }
 
 #[oasgen]
-async fn update_speaker_handler(
+pub(crate) async fn update_speaker_handler(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<UpdateSpeakerRequest>,
 ) -> Result<JsonResponse<Speaker>, (StatusCode, JsonResponse<Value>)> {
@@ -1646,7 +2309,7 @@ async fn update_speaker_handler(
 }
 
 #[oasgen]
-async fn search_speakers_handler(
+pub(crate) async fn search_speakers_handler(
     State(state): State<Arc<AppState>>,
     Query(request): Query<SearchSpeakersRequest>,
 ) -> Result<JsonResponse<Vec<Speaker>>, (StatusCode, JsonResponse<Value>)> {
@@ -1657,103 +2320,880 @@ async fn search_speakers_handler(
 }
 
 #[oasgen]
-async fn delete_speaker_handler(
+async fn delete_speaker_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<DeleteSpeakerRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    // get audio_chunks for this speaker
+    let audio_chunks = state
+        .db
+        .get_audio_chunks_for_speaker(payload.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    state.db.delete_speaker(payload.id).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": e.to_string()})),
+        )
+    })?;
+
+    // delete all audio chunks from the file system
+    for audio_chunk in audio_chunks {
+        if audio_chunk.start_time.is_some() && audio_chunk.end_time.is_some() {
+            std::fs::remove_file(audio_chunk.file_path).map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    JsonResponse(json!({"error": e.to_string()})),
+                )
+            })?;
+        }
+    }
+
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+#[oasgen]
+async fn mark_as_hallucination_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MarkAsHallucinationRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let speaker_id = payload.speaker_id;
+
+    state
+        .db
+        .mark_speaker_as_hallucination(speaker_id)
+        .await
+        .unwrap();
+
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+#[oasgen]
+pub(crate) async fn merge_speakers_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MergeSpeakersRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let speaker_to_keep_id = payload.speaker_to_keep_id;
+    let speaker_to_merge_id = payload.speaker_to_merge_id;
+
+    state
+        .db
+        .merge_speakers(speaker_to_keep_id, speaker_to_merge_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string(), "speaker_to_keep_id": speaker_to_keep_id, "speaker_to_merge_id": speaker_to_merge_id})),
+            )
+        })?;
+
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+#[oasgen]
+async fn recluster_speakers_handler(
+    State(state): State<Arc<AppState>>,
+    Json(options): Json<ReclusterOptions>,
+) -> Result<JsonResponse<ReclusterReport>, (StatusCode, JsonResponse<Value>)> {
+    if !(0.0..=2.0).contains(&options.threshold) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "threshold must be between 0 and 2"})),
+        ));
+    }
+
+    state
+        .db
+        .recluster_speakers(&options)
+        .await
+        .map(JsonResponse)
+        .map_err(|e| {
+            error!("Failed to recluster speakers: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })
+}
+
+#[oasgen]
+async fn get_similar_speakers_handler(
+    State(state): State<Arc<AppState>>,
+    Query(request): Query<GetSimilarSpeakersRequest>,
+) -> Result<JsonResponse<Vec<Speaker>>, (StatusCode, JsonResponse<Value>)> {
+    let speaker_id = request.speaker_id;
+    let limit = request.limit;
+
+    let similar_speakers = state
+        .db
+        .get_similar_speakers(speaker_id, limit)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    Ok(JsonResponse(similar_speakers))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct RetentionRequest {
+    /// One-off policy, the configured one is used when omitted
+    #[serde(default)]
+    policy: Option<RetentionPolicy>,
+}
+
+impl RetentionRequest {
+    fn policy_or(
+        self,
+        configured: &RetentionPolicy,
+    ) -> Result<RetentionPolicy, (StatusCode, JsonResponse<Value>)> {
+        let policy = self.policy.unwrap_or_else(|| configured.clone());
+        if policy.is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                JsonResponse(json!({"error": "no retention policy configured or provided"})),
+            ));
+        }
+        Ok(policy)
+    }
+}
+
+#[oasgen]
+async fn retention_preview_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RetentionRequest>,
+) -> Result<JsonResponse<RetentionReport>, (StatusCode, JsonResponse<Value>)> {
+    let policy = payload.policy_or(&state.retention_policy)?;
+
+    let plan = state
+        .db
+        .plan_retention(&policy, Utc::now())
+        .await
+        .map_err(|e| {
+            error!("Failed to plan retention: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    Ok(JsonResponse(plan.report()))
+}
+
+#[oasgen]
+async fn retention_run_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RetentionRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let policy = payload.policy_or(&state.retention_policy)?;
+
+    if matches!(
+        *state.retention_status.lock().await,
+        RetentionStatus::Running { .. } | RetentionStatus::Paused { .. }
+    ) {
+        return Err((
+            StatusCode::CONFLICT,
+            JsonResponse(json!({"error": "a retention purge is already running"})),
+        ));
+    }
+
+    state
+        .retention_tx
+        .send(RetentionCommand::Start(Some(policy)))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    Ok(JsonResponse(json!({"success": true})))
+}
+
+#[oasgen]
+async fn retention_status_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let status = state.retention_status.lock().await.clone();
+
+    Ok(JsonResponse(json!({ "status": status })))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct ForgetQuery {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    window_name: Option<String>,
+    #[serde(default)]
+    browser_url: Option<String>,
+    #[serde(default)]
+    device_name: Option<String>,
+    /// Only report what would be removed
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[oasgen]
+async fn forget_data_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ForgetQuery>,
+) -> Result<JsonResponse<ForgetReport>, (StatusCode, JsonResponse<Value>)> {
+    if query.start_time > query.end_time {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "start_time must be before end_time"})),
+        ));
+    }
+
+    let filter = ForgetFilter {
+        start_time: query.start_time,
+        end_time: query.end_time,
+        app_name: query.app_name,
+        window_name: query.window_name,
+        browser_url: query.browser_url,
+        device_name: query.device_name,
+    };
+
+    let internal_error = |e: String| {
+        error!("Failed to forget data: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": e})),
+        )
+    };
+
+    let plan = state
+        .db
+        .plan_forget(&filter)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    // an empty plan still goes through `apply_forget`, the event journal may hold text of
+    // the range that was never stored
+    if query.dry_run {
+        return Ok(JsonResponse(plan.report()));
+    }
+
+    // Re-encode partially covered chunks up front, so a failing ffmpeg leaves the database
+    // and the original files untouched
+    let mut reencoded: Vec<(String, String)> = Vec::new();
+    let mut reencode_error = None;
+    for chunk in plan
+        .video_chunks
+        .iter()
+        .filter(|c| !c.recording && !c.fully_covered)
+    {
+        let temp_path = format!("{}.forget", chunk.file_path);
+        match remove_frames_from_video(&chunk.file_path, &chunk.removed_offsets, &temp_path).await {
+            Ok(_) => reencoded.push((temp_path, chunk.file_path.clone())),
+            Err(e) => {
+                reencode_error = Some(e);
+                break;
+            }
+        }
+    }
+    if reencode_error.is_none() {
+        for chunk in plan.audio_chunks.iter().filter(|c| !c.fully_covered) {
+            let temp_path = format!("{}.forget", chunk.file_path);
+            match mute_audio_ranges(
+                &chunk.file_path,
+                &chunk.muted_ranges,
+                chunk.mute_all,
+                &temp_path,
+            )
+            .await
+            {
+                Ok(_) => reencoded.push((temp_path, chunk.file_path.clone())),
+                Err(e) => {
+                    reencode_error = Some(e);
+                    break;
+                }
+            }
+        }
+    }
+    if let Some(e) = reencode_error {
+        let temp_paths: Vec<String> = reencoded.into_iter().map(|(temp, _)| temp).collect();
+        remove_media_files(&temp_paths).await;
+        return Err(internal_error(e.to_string()));
+    }
+
+    let report = match state.db.apply_forget(&plan).await {
+        Ok(report) => report,
+        Err(e) => {
+            let temp_paths: Vec<String> = reencoded.into_iter().map(|(temp, _)| temp).collect();
+            remove_media_files(&temp_paths).await;
+            return Err(internal_error(e.to_string()));
+        }
+    };
+
+    for (temp_path, file_path) in &reencoded {
+        if let Err(e) = tokio::fs::rename(temp_path, file_path).await {
+            error!(
+                "Failed to replace {} with re-encoded chunk: {}",
+                file_path, e
+            );
+        }
+    }
+    remove_media_files(&report.deleted_files).await;
+
+    if let Some(cache) = &state.frame_cache {
+        let keys = plan
+            .frames
+            .iter()
+            .map(|f| (f.timestamp, f.device_name.clone()))
+            .collect();
+        // cached frames also carry the audio around them
+        let range =
+            (report.audio_transcriptions > 0).then_some((filter.start_time, filter.end_time));
+        if let Err(e) = cache.evict_frames(keys, range).await {
+            error!("Failed to evict forgotten frames from cache: {}", e);
+        }
+    }
+    if let Some(cache) = &state.frame_image_cache {
+        let mut cache = cache.lock().await;
+        for frame in &plan.frames {
+            cache.pop(&frame.id);
+        }
+    }
+
+    info!(
+        "Forgot {} frames, {} transcriptions and {} ui rows between {} and {}",
+        report.frames,
+        report.audio_transcriptions,
+        report.ui_monitoring,
+        filter.start_time,
+        filter.end_time
+    );
+
+    Ok(JsonResponse(report))
+}
+
+/// How often chunks holding forgotten or purged frames are checked
+const VIDEO_SCRUB_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Cuts frames forgotten while their chunk was recording, or purged by retention, out of
+/// the chunks that are now finished. A chunk that fails to re-encode is retried on the next
+/// pass.
+async fn scrub_finished_video_chunks(db: &DatabaseManager) {
+    let chunks = match db.pending_video_scrubs().await {
+        Ok(chunks) => chunks,
+        Err(e) => {
+            error!("Failed to list chunks to scrub: {}", e);
+            return;
+        }
+    };
+
+    for chunk in chunks {
+        if chunk.fully_covered {
+            if let Err(e) = db.finish_video_scrub(&chunk).await {
+                error!("Failed to scrub {}: {}", chunk.file_path, e);
+                continue;
+            }
+            remove_media_files(std::slice::from_ref(&chunk.file_path)).await;
+            continue;
+        }
+
+        let temp_path = format!("{}.forget", chunk.file_path);
+        if let Err(e) =
+            remove_frames_from_video(&chunk.file_path, &chunk.removed_offsets, &temp_path).await
+        {
+            error!("Failed to scrub {}: {}", chunk.file_path, e);
+            remove_media_files(&[temp_path]).await;
+            continue;
+        }
+        if let Err(e) = db.finish_video_scrub(&chunk).await {
+            error!("Failed to scrub {}: {}", chunk.file_path, e);
+            remove_media_files(&[temp_path]).await;
+            continue;
+        }
+        if let Err(e) = tokio::fs::rename(&temp_path, &chunk.file_path).await {
+            error!(
+                "Failed to replace {} with re-encoded chunk: {}",
+                chunk.file_path, e
+            );
+        }
+        info!(
+            "Cut {} forgotten frames out of {}",
+            chunk.removed_offsets.len(),
+            chunk.file_path
+        );
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+struct MeetingsQuery {
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default = "default_limit")]
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    limit: u32,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    offset: u32,
+}
+
+#[oasgen]
+async fn list_meetings_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MeetingsQuery>,
+) -> Result<JsonResponse<Vec<Meeting>>, (StatusCode, JsonResponse<Value>)> {
+    let meetings = state
+        .db
+        .list_meetings(query.start_time, query.end_time, query.limit, query.offset)
+        .await
+        .map_err(|e| {
+            error!("Failed to list meetings: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    Ok(JsonResponse(meetings))
+}
+
+#[oasgen]
+async fn get_meeting_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<MeetingTranscript>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.get_meeting(id).await {
+        Ok(Some(meeting)) => Ok(JsonResponse(meeting)),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("meeting {} not found", id)})),
+        )),
+        Err(e) => {
+            error!("Failed to get meeting {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+struct ActivityQuery {
+    /// A day before `end` by default
+    #[serde(default)]
+    start: Option<DateTime<Utc>>,
+    /// Now by default
+    #[serde(default)]
+    end: Option<DateTime<Utc>>,
+    #[serde(default)]
+    group_by: ActivityGroupBy,
+    /// Also list the sessions the report is made of
+    #[serde(default)]
+    include_sessions: bool,
+}
+
+/// Time spent per app, domain or window, from the focused frames rolled up into sessions
+#[oasgen]
+async fn activity_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<JsonResponse<ActivityReport>, (StatusCode, JsonResponse<Value>)> {
+    let end = query.end.unwrap_or_else(Utc::now);
+    let start = query.start.unwrap_or(end - chrono::Duration::days(1));
+    if start > end {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "start must be before end"})),
+        ));
+    }
+
+    let internal_error = |e: sqlx::Error| {
+        error!("Failed to report activity: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": e.to_string()})),
+        )
+    };
+    // sessions are brought up to date on read, frames since the last report are few
+    state
+        .db
+        .update_activity_sessions()
+        .await
+        .map_err(internal_error)?;
+    let report = state
+        .db
+        .activity_report(start, end, query.group_by, query.include_sessions)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(JsonResponse(report))
+}
+
+#[oasgen]
+async fn get_transcription_segments_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<TranscriptionDetails>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.get_transcription_details(id).await {
+        Ok(Some(details)) => Ok(JsonResponse(details)),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("audio transcription {} not found", id)})),
+        )),
+        Err(e) => {
+            error!("Failed to get segments of transcription {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+struct IndexRequest {
+    /// Video file or directory on the machine running cubby
+    path: String,
+    /// Only index files whose path matches this regex
+    #[serde(default)]
+    pattern: Option<String>,
+    /// unstructured, tesseract, windows-native, apple-native or custom, the platform
+    /// default when omitted
+    #[serde(default)]
+    ocr_engine: Option<String>,
+    /// JSON file with metadata overrides, on the machine running cubby
+    #[serde(default)]
+    metadata_override: Option<String>,
+    /// Copy the videos to the cubby data directory instead of indexing them in place
+    #[serde(default)]
+    copy_videos: bool,
+    #[serde(default)]
+    use_embedding: bool,
+}
+
+#[oasgen]
+async fn create_index_job_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<IndexRequest>,
+) -> Result<JsonResponse<IndexJob>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({ "error": error })),
+        )
+    };
+
+    if !std::path::Path::new(&payload.path).exists() {
+        return Err(bad_request(format!("{} does not exist", payload.path)));
+    }
+    if let Some(pattern) = &payload.pattern {
+        regex::Regex::new(pattern).map_err(|e| bad_request(format!("invalid pattern: {}", e)))?;
+    }
+    let ocr_engine = payload
+        .ocr_engine
+        .as_deref()
+        .map(|name| <CliOcrEngine as ValueEnum>::from_str(name, true))
+        .transpose()
+        .map_err(bad_request)?;
+
+    let options = IndexOptions {
+        ocr_engine,
+        metadata_override: payload.metadata_override.map(PathBuf::from),
+        copy_videos: payload.copy_videos,
+        use_embedding: payload.use_embedding,
+    };
+
+    state
+        .index_jobs
+        .create_job(&payload.path, payload.pattern.as_deref(), &options)
+        .await
+        .map(JsonResponse)
+        .map_err(|e| {
+            error!("Failed to create index job: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })
+}
+
+#[derive(OaSchema, Deserialize)]
+struct IndexJobsQuery {
+    #[serde(default = "default_limit")]
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    limit: u32,
+}
+
+#[oasgen]
+async fn list_index_jobs_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<IndexJobsQuery>,
+) -> Result<JsonResponse<Vec<IndexJob>>, (StatusCode, JsonResponse<Value>)> {
+    state
+        .db
+        .list_index_jobs(query.limit)
+        .await
+        .map(JsonResponse)
+        .map_err(|e| {
+            error!("Failed to list index jobs: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            )
+        })
+}
+
+#[oasgen]
+async fn get_index_job_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<IndexJob>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.get_index_job(id).await {
+        Ok(Some(job)) => Ok(JsonResponse(job)),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("index job {} not found", id)})),
+        )),
+        Err(e) => {
+            error!("Failed to get index job {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            ))
+        }
+    }
+}
+
+#[oasgen]
+async fn cancel_index_job_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let internal_error = |e: String| {
+        error!("Failed to cancel index job {}: {}", id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": e})),
+        )
+    };
+
+    if state
+        .index_jobs
+        .cancel_job(id)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?
+    {
+        return Ok(JsonResponse(json!({"success": true})));
+    }
+
+    match state.db.get_index_job(id).await {
+        Ok(Some(job)) => Err((
+            StatusCode::CONFLICT,
+            JsonResponse(json!({"error": format!("index job {} is already {}", id, job.status)})),
+        )),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("index job {} not found", id)})),
+        )),
+        Err(e) => Err(internal_error(e.to_string())),
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+struct CreateWebhookRequest {
+    /// http or https url the events are posted to
+    url: String,
+    /// Event names or patterns such as `meeting_*`, every event when empty
+    #[serde(default)]
+    events: Vec<String>,
+    /// Key the deliveries are signed with, generated when left out
+    secret: Option<String>,
+}
+
+#[derive(OaSchema, Serialize)]
+struct CreateWebhookResponse {
+    webhook: Webhook,
+    /// Only shown here, keep it to check the `x-cubby-signature` of deliveries
+    secret: String,
+}
+
+#[oasgen]
+async fn create_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> Result<JsonResponse<CreateWebhookResponse>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({ "error": error })),
+        )
+    };
+
+    match reqwest::Url::parse(&payload.url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+        Ok(url) => {
+            return Err(bad_request(format!(
+                "webhook urls must be http or https, not {}",
+                url.scheme()
+            )))
+        }
+        Err(e) => return Err(bad_request(format!("invalid url: {}", e))),
+    }
+    let secret = match payload.secret {
+        Some(secret) if secret.is_empty() => {
+            return Err(bad_request("the secret can't be empty".to_string()))
+        }
+        Some(secret) => secret,
+        None => generate_secret(),
+    };
+
+    match state
+        .webhooks
+        .create(&payload.url, &payload.events, &secret)
+        .await
+    {
+        Ok(webhook) => Ok(JsonResponse(CreateWebhookResponse { webhook, secret })),
+        Err(e) => {
+            error!("Failed to create webhook: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            ))
+        }
+    }
+}
+
+#[oasgen]
+async fn list_webhooks_handler(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<DeleteSpeakerRequest>,
-) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
-    // get audio_chunks for this speaker
-    let audio_chunks = state
+) -> Result<JsonResponse<Vec<Webhook>>, (StatusCode, JsonResponse<Value>)> {
+    state
         .db
-        .get_audio_chunks_for_speaker(payload.id)
+        .list_webhooks()
         .await
+        .map(JsonResponse)
         .map_err(|e| {
+            error!("Failed to list webhooks: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 JsonResponse(json!({"error": e.to_string()})),
             )
-        })?;
-
-    state.db.delete_speaker(payload.id).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            JsonResponse(json!({"error": e.to_string()})),
-        )
-    })?;
-
-    // delete all audio chunks from the file system
-    for audio_chunk in audio_chunks {
-        if audio_chunk.start_time.is_some() && audio_chunk.end_time.is_some() {
-            std::fs::remove_file(audio_chunk.file_path).map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    JsonResponse(json!({"error": e.to_string()})),
-                )
-            })?;
-        }
-    }
-
-    Ok(JsonResponse(json!({"success": true})))
+        })
 }
 
 #[oasgen]
-async fn mark_as_hallucination_handler(
+async fn delete_webhook_handler(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<MarkAsHallucinationRequest>,
+    Path(id): Path<i64>,
 ) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
-    let speaker_id = payload.speaker_id;
-
-    state
-        .db
-        .mark_speaker_as_hallucination(speaker_id)
-        .await
-        .unwrap();
+    match state.webhooks.delete(id).await {
+        Ok(true) => Ok(JsonResponse(json!({"success": true}))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("webhook {} not found", id)})),
+        )),
+        Err(e) => {
+            error!("Failed to delete webhook {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            ))
+        }
+    }
+}
 
-    Ok(JsonResponse(json!({"success": true})))
+#[derive(OaSchema, Deserialize)]
+struct DeadLettersQuery {
+    /// Only the dead letters of this webhook
+    webhook_id: Option<i64>,
+    #[serde(default = "default_limit")]
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    limit: u32,
 }
 
 #[oasgen]
-async fn merge_speakers_handler(
+async fn list_dead_letters_handler(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<MergeSpeakersRequest>,
-) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
-    let speaker_to_keep_id = payload.speaker_to_keep_id;
-    let speaker_to_merge_id = payload.speaker_to_merge_id;
-
+    Query(query): Query<DeadLettersQuery>,
+) -> Result<JsonResponse<Vec<WebhookDeadLetter>>, (StatusCode, JsonResponse<Value>)> {
     state
         .db
-        .merge_speakers(speaker_to_keep_id, speaker_to_merge_id)
+        .list_webhook_dead_letters(query.webhook_id, query.limit)
         .await
+        .map(JsonResponse)
         .map_err(|e| {
+            error!("Failed to list webhook dead letters: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                JsonResponse(json!({"error": e.to_string(), "speaker_to_keep_id": speaker_to_keep_id, "speaker_to_merge_id": speaker_to_merge_id})),
+                JsonResponse(json!({"error": e.to_string()})),
             )
-        })?;
-
-    Ok(JsonResponse(json!({"success": true})))
+        })
 }
 
+/// Posts a dead letter again, it is deleted if the webhook accepts it
 #[oasgen]
-async fn get_similar_speakers_handler(
+async fn retry_dead_letter_handler(
     State(state): State<Arc<AppState>>,
-    Query(request): Query<GetSimilarSpeakersRequest>,
-) -> Result<JsonResponse<Vec<Speaker>>, (StatusCode, JsonResponse<Value>)> {
-    let speaker_id = request.speaker_id;
-    let limit = request.limit;
-
-    let similar_speakers = state
-        .db
-        .get_similar_speakers(speaker_id, limit)
-        .await
-        .map_err(|e| {
-            (
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let dead_letter = match state.db.get_webhook_dead_letter(id).await {
+        Ok(Some(dead_letter)) => dead_letter,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                JsonResponse(json!({"error": format!("dead letter {} not found", id)})),
+            ))
+        }
+        Err(e) => {
+            error!("Failed to get webhook dead letter {}: {}", id, e);
+            return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 JsonResponse(json!({"error": e.to_string()})),
-            )
-        })?;
+            ));
+        }
+    };
 
-    Ok(JsonResponse(similar_speakers))
+    match state.webhooks.redeliver(&dead_letter).await {
+        Ok(()) => Ok(JsonResponse(json!({"success": true}))),
+        Err(e) => Err((
+            StatusCode::BAD_GATEWAY,
+            JsonResponse(json!({"error": format!("delivery failed: {}", e)})),
+        )),
+    }
+}
+
+#[oasgen]
+async fn delete_dead_letter_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.delete_webhook_dead_letter(id).await {
+        Ok(true) => Ok(JsonResponse(json!({"success": true}))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("dead letter {} not found", id)})),
+        )),
+        Err(e) => {
+            error!("Failed to delete webhook dead letter {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string()})),
+            ))
+        }
+    }
 }
+
 // #[derive(OaSchema, Deserialize)]
 // pub struct AudioDeviceControlRequest {
 //     device_name: String,
@@ -1860,6 +3300,21 @@ pub struct AudioDeviceControlResponse {
 #[derive(OaSchema, Deserialize)]
 struct EventsQuery {
     images: Option<bool>,
+    /// Replays the journaled events after this sequence number, or since this RFC 3339
+    /// timestamp, before the live ones
+    since: Option<String>,
+    /// Comma separated event names or patterns such as `meeting_*`
+    events: Option<String>,
+    /// Only events about this app, may be a pattern
+    app_name: Option<String>,
+    /// Only events from this audio device, may be a pattern
+    device: Option<String>,
+}
+
+/// Message a client sends on `/ws/events` to replace its filter
+#[derive(Deserialize)]
+struct SubscribeMessage {
+    subscribe: EventFilter,
 }
 
 #[derive(Debug, OaSchema, Deserialize)]
@@ -1867,24 +3322,51 @@ struct SemanticSearchQuery {
     text: String,
     limit: Option<u32>,
     threshold: Option<f32>,
+    #[serde(default)]
+    content_type: ContentType,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    window_name: Option<String>,
+    #[serde(
+        deserialize_with = "from_comma_separated_array",
+        default = "default_speaker_ids"
+    )]
+    speaker_ids: Option<Vec<i64>>,
 }
 
 #[oasgen]
 async fn semantic_search_handler(
     Query(query): Query<SemanticSearchQuery>,
     State(state): State<Arc<AppState>>,
-) -> Result<JsonResponse<Vec<cubby_db::OCRResult>>, (StatusCode, JsonResponse<Value>)> {
+) -> Result<JsonResponse<Vec<ContentItem>>, (StatusCode, JsonResponse<Value>)> {
     let limit = query.limit.unwrap_or(10);
     let threshold = query.threshold.unwrap_or(0.3);
 
     debug!(
-        "semantic search for '{}' with limit {} and threshold {}",
-        query.text, limit, threshold
+        "semantic search for '{}' with limit {} and threshold {}: {:?}",
+        query.text, limit, threshold, query
     );
 
+    let Some(embeddings) = &state.embeddings else {
+        return Err(embeddings_disabled());
+    };
+    let backend = embeddings.backend();
+
     // Generate embedding for search text
-    let embedding = match generate_embedding(&query.text, 0).await {
-        Ok(emb) => emb,
+    let embedding = match backend.embed(std::slice::from_ref(&query.text)).await {
+        Ok(mut vectors) if vectors.len() == 1 => vectors.remove(0),
+        Ok(_) => {
+            error!("{} returned no embedding", backend.model());
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": "failed to generate embedding"})),
+            ));
+        }
         Err(e) => {
             error!("failed to generate embedding: {}", e);
             return Err((
@@ -1894,15 +3376,29 @@ async fn semantic_search_handler(
         }
     };
 
-    // Search database for similar embeddings
+    let filter = SemanticSearchFilter {
+        content_type: query.content_type,
+        start_time: query.start_time,
+        end_time: query.end_time,
+        app_name: query.app_name,
+        window_name: query.window_name,
+        speaker_ids: query.speaker_ids,
+    };
+
+    // Search database for similar embeddings, closest first
     match state
         .db
-        .search_similar_embeddings(embedding, limit, threshold)
+        .semantic_search(&embedding, backend.model(), &filter, limit, threshold)
         .await
     {
-        Ok(results) => {
-            debug!("found {} similar results", results.len());
-            Ok(JsonResponse(results))
+        Ok(matches) => {
+            debug!("found {} similar results", matches.len());
+            Ok(JsonResponse(
+                matches
+                    .into_iter()
+                    .map(|m| ContentItem::from(m.result))
+                    .collect(),
+            ))
         }
         Err(e) => {
             error!("failed to search embeddings: {}", e);
@@ -1914,6 +3410,29 @@ async fn semantic_search_handler(
     }
 }
 
+#[oasgen]
+async fn embeddings_status_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<EmbeddingStatus>, (StatusCode, JsonResponse<Value>)> {
+    let Some(embeddings) = &state.embeddings else {
+        return Err(embeddings_disabled());
+    };
+    embeddings.status().await.map(JsonResponse).map_err(|e| {
+        error!("failed to read embedding status: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": format!("failed to read embedding status: {}", e)})),
+        )
+    })
+}
+
+fn embeddings_disabled() -> (StatusCode, JsonResponse<Value>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        JsonResponse(json!({"error": "embeddings are disabled, see --embedding-backend"})),
+    )
+}
+
 #[derive(Serialize, OaSchema, Deserialize)]
 pub struct VisionDeviceControlRequest {
     device_id: u32,
@@ -1999,51 +3518,79 @@ pub struct VisionDeviceControlResponse {
 // }
 
 // websocket events handler
-async fn ws_events_handler(ws: WebSocketUpgrade, query: Query<EventsQuery>) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, query))
+async fn ws_events_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventsQuery>,
+) -> Response {
+    let since = match query.since.as_deref().map(str::parse::<JournalPosition>) {
+        None => None,
+        Some(Ok(position)) => Some(position),
+        Some(Err(e)) => {
+            return (StatusCode::BAD_REQUEST, JsonResponse(json!({"error": e}))).into_response()
+        }
+    };
+    if since.is_some() && state.event_journal.is_none() {
+        return (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({
+                "error": "events are not journaled, start cubby with --event-journal-days to replay them"
+            })),
+        )
+            .into_response();
+    }
+
+    let filter = EventFilter {
+        events: query
+            .events
+            .as_deref()
+            .map(|events| {
+                events
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|event| !event.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        app_name: query.app_name,
+        device: query.device,
+    };
+
+    let images = query.images.unwrap_or(false);
+    let journal = state.event_journal.clone();
+    ws.on_upgrade(move |socket| handle_socket(socket, images, journal, since, filter))
 }
 
-async fn handle_socket(socket: WebSocket, query: Query<EventsQuery>) {
+async fn handle_socket(
+    socket: WebSocket,
+    images: bool,
+    journal: Option<Arc<EventJournal>>,
+    since: Option<JournalPosition>,
+    filter: EventFilter,
+) {
     let (mut sender, mut receiver) = socket.split();
+    let (filter_tx, filter) = watch::channel(filter);
 
+    // clients either replace their filter or send events to the bus
     let incoming = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
             if let Message::Text(t) = msg {
-                if let Ok(event) = serde_json::from_str::<cubbyEvent>(&t) {
+                if let Ok(message) = serde_json::from_str::<SubscribeMessage>(&t) {
+                    debug!("websocket subscribed to {:?}", message.subscribe);
+                    filter_tx.send_replace(message.subscribe);
+                } else if let Ok(event) = serde_json::from_str::<cubbyEvent>(&t) {
                     let _ = send_event(&event.name, event.data);
                 }
             }
         }
     });
-    // Handle the WebSocket connection here
-    // You can add your logic to handle messages, upgrades, etc.
 
+    // journaled events carry their sequence number, to reconnect with `since`
     let outgoing = tokio::spawn(async move {
-        let mut stream = subscribe_to_all_events();
-        loop {
-            tokio::select! {
-                event = stream.next() => {
-                    if let Some(mut event) = event {
-                        if !query.images.unwrap_or(false) && (event.name == "ocr_result" || event.name == "ui_frame") {
-                            if let Some(data) = event.data.as_object_mut() {
-                                data.remove("image");
-                            }
-                        }
-                        if let Err(e) = sender
-                            .send(Message::Text(
-                                serde_json::to_string(&event).unwrap_or_default(),
-                            ))
-                            .await
-                        {
-                            tracing::error!("Failed to send websocket message: {}", e);
-                            break;
-                        }
-                    }
-                }
-                _ = tokio::time::sleep(Duration::from_secs(1)) => {
-                    let _ = sender.send(Message::Ping(vec![])).await;
-                }
-            }
+        match journal {
+            Some(journal) => forward_journal(&mut sender, &journal, since, images, &filter).await,
+            None => forward_events(&mut sender, images, &filter).await,
         }
     });
 
@@ -2056,6 +3603,131 @@ async fn handle_socket(socket: WebSocket, query: Query<EventsQuery>) {
     debug!("WebSocket connection closed");
 }
 
+/// Events of the bus as they come
+async fn forward_events(
+    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    images: bool,
+    filter: &watch::Receiver<EventFilter>,
+) {
+    let mut stream = subscribe_to_all_events();
+    loop {
+        tokio::select! {
+            event = stream.next() => {
+                if let Some(mut event) = event {
+                    if !filter.borrow().matches(&event.name, &event.data) {
+                        continue;
+                    }
+                    if !images {
+                        strip_image(&event.name, &mut event.data);
+                    }
+                    if let Err(e) = sender
+                        .send(Message::Text(
+                            serde_json::to_string(&event).unwrap_or_default(),
+                        ))
+                        .await
+                    {
+                        tracing::error!("Failed to send websocket message: {}", e);
+                        break;
+                    }
+                }
+            }
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {
+                let _ = sender.send(Message::Ping(vec![])).await;
+            }
+        }
+    }
+}
+
+/// Journaled events from `since` then live ones, each sent once and in order. Events
+/// missed while falling behind are read back from the journal.
+async fn forward_journal(
+    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    journal: &EventJournal,
+    since: Option<JournalPosition>,
+    images: bool,
+    filter: &watch::Receiver<EventFilter>,
+) {
+    const REPLAY_PAGE: u32 = 500;
+
+    let mut live = journal.subscribe();
+    // events journaled after subscribing come after the head, so falling behind before
+    // the first one is sent still replays from there
+    let mut last_seq = match journal.head().await {
+        Ok(seq) => seq,
+        Err(e) => {
+            error!("failed to read the journal head: {}", e);
+            return;
+        }
+    };
+    let mut replay_from = since;
+
+    loop {
+        while let Some(position) = replay_from {
+            let events = match journal.replay(position, REPLAY_PAGE).await {
+                Ok(events) => events,
+                Err(e) => {
+                    error!("failed to replay journaled events: {}", e);
+                    return;
+                }
+            };
+            let caught_up = events.len() < REPLAY_PAGE as usize;
+            for event in events {
+                last_seq = event.seq;
+                if send_journal_event(sender, event, images, filter)
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            replay_from = (!caught_up).then_some(JournalPosition::After(last_seq));
+        }
+
+        tokio::select! {
+            event = live.recv() => match event {
+                Ok(event) => {
+                    // already replayed
+                    if event.seq <= last_seq {
+                        continue;
+                    }
+                    last_seq = event.seq;
+                    if send_journal_event(sender, event, images, filter).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    debug!("websocket fell behind the journal by {} events", missed);
+                    replay_from = Some(JournalPosition::After(last_seq));
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {
+                let _ = sender.send(Message::Ping(vec![])).await;
+            }
+        }
+    }
+}
+
+/// Sends a journaled event unless the client filters it out
+async fn send_journal_event(
+    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    mut event: JournalEvent,
+    images: bool,
+    filter: &watch::Receiver<EventFilter>,
+) -> Result<(), axum::Error> {
+    if !filter.borrow().matches(&event.name, &event.data) {
+        return Ok(());
+    }
+    if !images {
+        strip_image(&event.name, &mut event.data);
+    }
+    let message = Message::Text(serde_json::to_string(&event).unwrap_or_default());
+    sender.send(message).await.map_err(|e| {
+        tracing::error!("Failed to send websocket message: {}", e);
+        e
+    })
+}
+
 async fn ws_health_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
     ws.on_upgrade(move |socket| handle_health_socket(socket, state))
 }
@@ -2098,6 +3770,21 @@ struct AudioDeviceControlRequest {
     device_name: String,
 }
 
+/// The audio manager of the state, an error when audio isn't captured by this process
+fn audio_manager(
+    state: &AppState,
+) -> Result<&Arc<AudioManager>, (StatusCode, JsonResponse<Value>)> {
+    state.audio_manager.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            JsonResponse(json!({
+                "success": false,
+                "message": "audio is not captured by this process",
+            })),
+        )
+    })
+}
+
 #[oasgen]
 async fn start_audio_device(
     State(state): State<Arc<AppState>>,
@@ -2118,7 +3805,7 @@ async fn start_audio_device(
         }
     };
 
-    if let Err(e) = state.audio_manager.start_device(&device).await {
+    if let Err(e) = audio_manager(&state)?.start_device(&device).await {
         return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             JsonResponse(json!({
@@ -2141,7 +3828,7 @@ async fn stop_audio_device(
 ) -> Result<Json<AudioDeviceControlResponse>, (StatusCode, JsonResponse<Value>)> {
     let device_name = payload.device_name.clone();
 
-    if let Err(e) = state.audio_manager.stop_device(&device_name).await {
+    if let Err(e) = audio_manager(&state)?.stop_device(&device_name).await {
         return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             JsonResponse(json!({
@@ -2177,7 +3864,7 @@ struct ExportProgress {
 async fn start_audio(
     State(state): State<Arc<AppState>>,
 ) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
-    match state.audio_manager.start().await {
+    match audio_manager(&state)?.start().await {
         Ok(_) => Ok(Response::builder().status(200).body(Body::empty()).unwrap()),
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -2193,7 +3880,7 @@ async fn start_audio(
 async fn stop_audio(
     State(state): State<Arc<AppState>>,
 ) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
-    match state.audio_manager.stop().await {
+    match audio_manager(&state)?.stop().await {
         Ok(_) => Ok(Response::builder().status(200).body(Body::empty()).unwrap()),
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -2772,7 +4459,7 @@ pub struct DeletePipeRequest {
 }
 
 #[derive(OaSchema, Deserialize, Debug)]
-struct MergeSpeakersRequest {
+pub(crate) struct MergeSpeakersRequest {
     speaker_to_keep_id: i64,
     speaker_to_merge_id: i64,
 }
@@ -2855,12 +4542,40 @@ pub struct ActionResponse {
     message: String,
 }
 
+/// Sets the flag when dropped, along with the handler future when the client goes away
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
 // Handler functions for UI automation
 #[oasgen]
 async fn find_elements_handler(
     State(_): State<Arc<AppState>>,
     Json(request): Json<FindElementsRequest>,
 ) -> Result<JsonResponse<FindElementsResponse>, (StatusCode, JsonResponse<Value>)> {
+    // walking the tree can take many seconds, it stops if the request is dropped meanwhile
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _cancel_on_drop = CancelOnDrop(cancelled.clone());
+    match tokio::task::spawn_blocking(move || {
+        cubby_core::run_cancellable(cancelled, || find_elements(request))
+    })
+    .await
+    {
+        Ok(response) => response.map(JsonResponse),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({ "error": format!("element search failed: {}", e) })),
+        )),
+    }
+}
+
+fn find_elements(
+    request: FindElementsRequest,
+) -> Result<FindElementsResponse, (StatusCode, JsonResponse<Value>)> {
     let desktop = match Desktop::new(
         request.selector.use_background_apps.unwrap_or(false),
         request.selector.activate_app.unwrap_or(false),
@@ -2970,9 +4685,9 @@ async fn find_elements_handler(
         })
         .collect();
 
-    Ok(JsonResponse(FindElementsResponse {
+    Ok(FindElementsResponse {
         data: elements_info,
-    }))
+    })
 }
 
 #[oasgen]
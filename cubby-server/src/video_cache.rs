@@ -80,6 +80,11 @@ enum CacheMessage {
         cache_key: String,
         response: GetFrameResponse,
     },
+    Evict {
+        keys: Vec<(DateTime<Utc>, String)>,
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        response: oneshot::Sender<Result<usize>>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -342,6 +347,46 @@ impl FrameDiskCache {
         ))
     }
 
+    /// Drop the given frames, plus every frame within `range` when set, returning how
+    /// many entries were removed
+    async fn evict(
+        &mut self,
+        keys: &[(DateTime<Utc>, String)],
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<usize> {
+        let mut frames_to_remove: Vec<_> = keys
+            .iter()
+            .filter(|key| self.entries.contains_key(*key))
+            .cloned()
+            .collect();
+        if let Some((start, end)) = range {
+            frames_to_remove.extend(
+                self.entries
+                    .keys()
+                    .filter(|(timestamp, _)| *timestamp >= start && *timestamp <= end)
+                    .cloned(),
+            );
+        }
+
+        let mut removed = 0;
+        for key in frames_to_remove {
+            if let Some(entry) = self.entries.remove(&key) {
+                self.total_size = self.total_size.saturating_sub(entry.frame.frame_size);
+                if let Err(e) = fs::remove_file(&entry.path).await {
+                    debug!("failed to remove cached frame: {}", e);
+                }
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            self.save_index().await?;
+        }
+        debug!("evicted {} cached frames", removed);
+
+        Ok(removed)
+    }
+
     async fn cleanup(&mut self) -> Result<()> {
         debug!("starting cache cleanup");
 
@@ -414,6 +459,14 @@ async fn run_cache_manager(mut cache: FrameDiskCache, mut rx: mpsc::Receiver<Cac
                         let result = cache.get_frame_data(&cache_key).await;
                         let _ = response.send(result);
                     }
+                    CacheMessage::Evict {
+                        keys,
+                        range,
+                        response,
+                    } => {
+                        let result = cache.evict(&keys, range).await;
+                        let _ = response.send(result);
+                    }
                 }
             }
             _ = cleanup_interval.tick() => {
@@ -454,6 +507,25 @@ impl FrameCache {
         })
     }
 
+    /// Remove cached frames of forgotten data. `keys` are `(timestamp, device_name)` pairs
+    /// of deleted frames; `range` additionally drops every frame in that window, e.g. when
+    /// the audio attached to cached frames was removed.
+    pub async fn evict_frames(
+        &self,
+        keys: Vec<(DateTime<Utc>, String)>,
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<usize> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.cache_tx
+            .send(CacheMessage::Evict {
+                keys,
+                range,
+                response: response_tx,
+            })
+            .await?;
+        response_rx.await?
+    }
+
     async fn extract_frames_batch(
         &self,
         start_time: DateTime<Utc>,
@@ -214,6 +214,112 @@ pub async fn merge_videos(
     }
 }
 
+/// Collapse sorted frame offsets into inclusive `(first, last)` ranges
+fn offset_ranges(offsets: &[i64]) -> Vec<(i64, i64)> {
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+    for &offset in offsets {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 >= offset => *last = (*last).max(offset),
+            _ => ranges.push((offset, offset)),
+        }
+    }
+    ranges
+}
+
+/// Re-encode `input` into `output` without the frames at `offsets` (frame numbers within
+/// the chunk, as stored in `frames.offset_index`). Uses the same codec settings as recording.
+pub async fn remove_frames_from_video(input: &str, offsets: &[i64], output: &str) -> Result<()> {
+    let mut offsets = offsets.to_vec();
+    offsets.sort_unstable();
+    let keep = offset_ranges(&offsets)
+        .iter()
+        .map(|(first, last)| format!("between(n,{},{})", first, last))
+        .collect::<Vec<_>>()
+        .join("+");
+    let filter = format!("select='not({})',setpts=N/FRAME_RATE/TB", keep);
+
+    debug!("removing {} frames from {}", offsets.len(), input);
+
+    let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
+    let status = Command::new(ffmpeg_path)
+        .args([
+            "-v",
+            "error",
+            "-i",
+            input,
+            "-vf",
+            &filter,
+            "-an",
+            "-vcodec",
+            "libx265",
+            "-tag:v",
+            "hvc1",
+            "-preset",
+            "ultrafast",
+            "-crf",
+            "23",
+            "-pix_fmt",
+            "yuv420p",
+            "-f",
+            "mp4",
+            "-y",
+            output,
+        ])
+        .output()
+        .await?;
+
+    if status.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "ffmpeg failed to remove frames from {}: {}",
+            input,
+            String::from_utf8_lossy(&status.stderr)
+        ))
+    }
+}
+
+/// Re-encode `input` into `output` with the given `(start, end)` second ranges silenced,
+/// or the whole track when `mute_all` is set. The duration is kept so transcription
+/// timestamps of the rest of the chunk stay valid.
+pub async fn mute_audio_ranges(
+    input: &str,
+    ranges: &[(f64, f64)],
+    mute_all: bool,
+    output: &str,
+) -> Result<()> {
+    let filter = if mute_all || ranges.is_empty() {
+        "volume=0".to_string()
+    } else {
+        let enable = ranges
+            .iter()
+            .map(|(start, end)| format!("between(t,{},{})", start, end))
+            .collect::<Vec<_>>()
+            .join("+");
+        format!("volume=enable='{}':volume=0", enable)
+    };
+
+    debug!("muting {} ranges of {}", ranges.len(), input);
+
+    let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
+    let status = Command::new(ffmpeg_path)
+        .args([
+            "-v", "error", "-i", input, "-af", &filter, "-c:a", "aac", "-f", "mp4", "-y", output,
+        ])
+        .output()
+        .await?;
+
+    if status.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "ffmpeg failed to mute audio in {}: {}",
+            input,
+            String::from_utf8_lossy(&status.stderr)
+        ))
+    }
+}
+
 pub async fn extract_frames_from_video(
     video_path: &std::path::Path,
     output_path: Option<PathBuf>,
@@ -381,8 +487,25 @@ pub async fn get_video_metadata(video_path: &str) -> Result<VideoMetadata> {
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
     let ffprobe_path = ffmpeg_path.with_file_name("ffprobe");
 
+    let creation_time = get_media_creation_time(&ffprobe_path, video_path).await?;
+    let (fps, duration) = get_video_technical_metadata(&ffprobe_path, video_path).await?;
+
+    Ok(VideoMetadata {
+        creation_time,
+        fps,
+        duration,
+        device_name: None,
+        name: Some(video_path.to_string()),
+    })
+}
+
+/// When a video or audio file was recorded, from its tags, its name or its creation date
+pub async fn get_media_creation_time(
+    ffprobe_path: &Path,
+    media_path: &str,
+) -> Result<DateTime<Utc>> {
     // Try ffprobe first
-    let creation_time = match Command::new(&ffprobe_path)
+    let creation_time = match Command::new(ffprobe_path)
         .args([
             "-v",
             "quiet",
@@ -392,7 +515,7 @@ pub async fn get_video_metadata(video_path: &str) -> Result<VideoMetadata> {
             "-show_streams",
             "-show_entries",
             "format_tags=creation_time",
-            video_path,
+            media_path,
         ])
         .output()
         .await
@@ -417,13 +540,13 @@ pub async fn get_video_metadata(video_path: &str) -> Result<VideoMetadata> {
     };
 
     // Try filename if ffprobe failed
-    let creation_time = creation_time.or_else(|| parse_time_from_filename(video_path));
+    let creation_time = creation_time.or_else(|| parse_time_from_filename(media_path));
 
     // Try filesystem metadata if everything else failed
     let creation_time = match creation_time {
         Some(time) => time,
         None => {
-            if let Ok(metadata) = tokio::fs::metadata(video_path).await {
+            if let Ok(metadata) = tokio::fs::metadata(media_path).await {
                 if let Ok(created) = metadata.created() {
                     DateTime::<Utc>::from(created)
                 } else {
@@ -437,16 +560,7 @@ pub async fn get_video_metadata(video_path: &str) -> Result<VideoMetadata> {
         }
     };
 
-    // Rest of the metadata gathering (fps, duration) remains the same...
-    let (fps, duration) = get_video_technical_metadata(&ffprobe_path, video_path).await?;
-
-    Ok(VideoMetadata {
-        creation_time,
-        fps,
-        duration,
-        device_name: None,
-        name: Some(video_path.to_string()),
-    })
+    Ok(creation_time)
 }
 
 // Helper function to get fps and duration
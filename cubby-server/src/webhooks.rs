@@ -0,0 +1,608 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use cubby_db::{DatabaseManager, Webhook, WebhookDeadLetter};
+use cubby_events::{subscribe_to_all_events, EventFilter, EventSubscription, EVENTS_DROPPED};
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::event_journal::strip_image;
+
+/// Name of the event a delivery carries
+pub const EVENT_HEADER: &str = "x-cubby-event";
+/// Id of a delivery, the same for all its attempts
+pub const DELIVERY_HEADER: &str = "x-cubby-delivery";
+/// Unix time of an attempt, part of what is signed so that old requests can be refused
+pub const TIMESTAMP_HEADER: &str = "x-cubby-timestamp";
+/// `sha256=` followed by the signature made with `sign`
+pub const SIGNATURE_HEADER: &str = "x-cubby-signature";
+
+/// How long a receiver has to answer
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Deliveries waiting for a webhook, events past them are dead-lettered right away
+const QUEUE_CAPACITY: usize = 256;
+
+/// How often a delivery is attempted before it goes to the dead letters, waiting twice as
+/// long after each failure
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RetryPolicy {
+    pub max_attempts: u32,
+    pub first_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        // about a minute of retries
+        Self {
+            max_attempts: 6,
+            first_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Signature of a delivery: hex HMAC-SHA256, keyed with the webhook's secret, of the
+/// timestamp header, a dot and the body
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// A random secret for webhooks created without one
+pub fn generate_secret() -> String {
+    format!("whsec_{}", Uuid::new_v4().simple())
+}
+
+struct Target {
+    webhook: Webhook,
+    secret: String,
+    filter: EventFilter,
+}
+
+/// A target and its deliveries, posted one at a time and in order by a worker of its own
+/// that stops once the target is gone
+struct TargetQueue {
+    target: Arc<Target>,
+    deliveries: mpsc::Sender<Delivery>,
+}
+
+struct Delivery {
+    event_name: String,
+    body: String,
+}
+
+/// Why an attempt failed, and whether trying again may help
+struct DeliveryError {
+    message: String,
+    retryable: bool,
+}
+
+/// Posts the events of the bus to the webhooks registered with `POST /webhooks`, as
+/// `{"name", "data", "timestamp"}` without screenshots. Failed deliveries are retried with
+/// exponential backoff, those that fail every attempt are kept as dead letters, as are
+/// events sent while a webhook has too many deliveries waiting.
+pub struct WebhookManager {
+    db: Arc<DatabaseManager>,
+    client: reqwest::Client,
+    targets: RwLock<Vec<Arc<TargetQueue>>>,
+    /// Held while reloading, so that a reload that read the database before a change
+    /// can't overwrite the one that read it after
+    reloading: Mutex<()>,
+    retry: RetryPolicy,
+    queue_capacity: usize,
+    /// Whether targets get a worker posting their deliveries
+    delivers: bool,
+}
+
+impl WebhookManager {
+    /// Starts delivering events to the webhooks in the database
+    pub fn start(db: Arc<DatabaseManager>) -> Arc<Self> {
+        Self::start_with(db, RetryPolicy::default(), QUEUE_CAPACITY)
+    }
+
+    pub(crate) fn start_with(
+        db: Arc<DatabaseManager>,
+        retry: RetryPolicy,
+        queue_capacity: usize,
+    ) -> Arc<Self> {
+        let manager = Self::new(db, retry, queue_capacity, true);
+        // subscribe right away so that nothing sent after start is missed
+        let events = subscribe_to_all_events();
+        tokio::spawn(manager.clone().run(events));
+        manager
+    }
+
+    /// Manager for processes that don't deliver, webhooks can be managed but nothing is
+    /// posted to them
+    pub fn without_worker(db: Arc<DatabaseManager>) -> Arc<Self> {
+        Self::new(db, RetryPolicy::default(), QUEUE_CAPACITY, false)
+    }
+
+    fn new(
+        db: Arc<DatabaseManager>,
+        retry: RetryPolicy,
+        queue_capacity: usize,
+        delivers: bool,
+    ) -> Arc<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Arc::new(Self {
+            db,
+            client,
+            targets: RwLock::new(Vec::new()),
+            reloading: Mutex::new(()),
+            retry,
+            queue_capacity,
+            delivers,
+        })
+    }
+
+    /// Registers a webhook, events are posted to it from now on
+    pub async fn create(
+        self: &Arc<Self>,
+        url: &str,
+        events: &[String],
+        secret: &str,
+    ) -> Result<Webhook> {
+        let webhook = self.db.create_webhook(url, events, secret).await?;
+        self.reload().await?;
+        info!("created webhook {} for {}", webhook.id, webhook.url);
+        Ok(webhook)
+    }
+
+    /// Deletes a webhook and its dead letters, returns false when there is none with this
+    /// id. Deliveries already retrying finish without being dead-lettered, waiting ones
+    /// are dropped.
+    pub async fn delete(self: &Arc<Self>, id: i64) -> Result<bool> {
+        let deleted = self.db.delete_webhook(id).await?;
+        self.reload().await?;
+        Ok(deleted)
+    }
+
+    /// Posts a dead letter once more, and forgets it if that worked
+    pub async fn redeliver(&self, dead_letter: &WebhookDeadLetter) -> Result<()> {
+        let target = self
+            .target(dead_letter.webhook_id)
+            .ok_or_else(|| anyhow!("webhook {} not found", dead_letter.webhook_id))?;
+        let delivery_id = Uuid::new_v4().to_string();
+        self.post(
+            &target,
+            &dead_letter.event_name,
+            &delivery_id,
+            &dead_letter.body.to_string(),
+        )
+        .await
+        .map_err(|e| anyhow!(e.message))?;
+        self.db.delete_webhook_dead_letter(dead_letter.id).await?;
+        Ok(())
+    }
+
+    async fn reload(self: &Arc<Self>) -> Result<()> {
+        let _reloading = self.reloading.lock().await;
+        let webhooks = self.db.list_webhooks_with_secrets().await?;
+        let mut targets = self.targets.write().unwrap();
+        // webhooks don't change, those already loaded keep their queue
+        let loaded = std::mem::take(&mut *targets);
+        *targets = webhooks
+            .into_iter()
+            .map(|(webhook, secret)| {
+                if let Some(queue) = loaded.iter().find(|q| q.target.webhook.id == webhook.id) {
+                    return queue.clone();
+                }
+                let target = Arc::new(Target {
+                    filter: EventFilter {
+                        events: webhook.events.clone(),
+                        ..Default::default()
+                    },
+                    webhook,
+                    secret,
+                });
+                let (deliveries, queue) = mpsc::channel(self.queue_capacity);
+                if self.delivers {
+                    tokio::spawn(self.clone().work(target.clone(), queue));
+                }
+                Arc::new(TargetQueue { target, deliveries })
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn target(&self, webhook_id: i64) -> Option<Arc<Target>> {
+        self.targets
+            .read()
+            .unwrap()
+            .iter()
+            .find(|queue| queue.target.webhook.id == webhook_id)
+            .map(|queue| queue.target.clone())
+    }
+
+    async fn run(self: Arc<Self>, mut events: EventSubscription<Value>) {
+        if let Err(e) = self.reload().await {
+            error!("failed to load webhooks: {}", e);
+        }
+
+        while let Some(mut event) = events.next().await {
+            // like websocket subscribers, webhooks are told about the events they missed
+            if event.name == EVENTS_DROPPED {
+                warn!("webhooks fell behind the event bus: {}", event.data);
+            }
+            let targets: Vec<Arc<TargetQueue>> = self
+                .targets
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|queue| queue.target.filter.matches(&event.name, &event.data))
+                .cloned()
+                .collect();
+            if targets.is_empty() {
+                continue;
+            }
+
+            strip_image(&event.name, &mut event.data);
+            let body = json!({
+                "name": event.name,
+                "data": event.data,
+                "timestamp": Utc::now(),
+            })
+            .to_string();
+            for queue in targets {
+                let delivery = Delivery {
+                    event_name: event.name.clone(),
+                    body: body.clone(),
+                };
+                // a slow receiver doesn't hold back the others
+                if let Err(mpsc::error::TrySendError::Full(delivery)) =
+                    queue.deliveries.try_send(delivery)
+                {
+                    warn!(
+                        "too many deliveries waiting for webhook {}, dropping {}",
+                        queue.target.webhook.id, delivery.event_name
+                    );
+                    self.dead_letter(
+                        &queue.target,
+                        &delivery.event_name,
+                        &delivery.body,
+                        0,
+                        "too many deliveries waiting",
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+
+    /// Posts the deliveries of a target in turn until it is deleted
+    async fn work(self: Arc<Self>, target: Arc<Target>, mut deliveries: mpsc::Receiver<Delivery>) {
+        while let Some(delivery) = deliveries.recv().await {
+            if self.target(target.webhook.id).is_none() {
+                break;
+            }
+            self.deliver(&target, &delivery.event_name, &delivery.body)
+                .await;
+        }
+        debug!("stopped delivering to webhook {}", target.webhook.id);
+    }
+
+    async fn deliver(&self, target: &Target, event_name: &str, body: &str) {
+        let delivery_id = Uuid::new_v4().to_string();
+        let mut delay = self.retry.first_delay;
+        let mut attempts = 0;
+
+        let error = loop {
+            attempts += 1;
+            match self.post(target, event_name, &delivery_id, body).await {
+                Ok(()) => {
+                    debug!(
+                        "delivered {} to webhook {} after {} attempts",
+                        event_name, target.webhook.id, attempts
+                    );
+                    return;
+                }
+                Err(e) if !e.retryable || attempts >= self.retry.max_attempts => break e.message,
+                Err(e) => {
+                    debug!(
+                        "delivery of {} to webhook {} failed, retrying in {:?}: {}",
+                        event_name, target.webhook.id, delay, e.message
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        };
+
+        if self.target(target.webhook.id).is_none() {
+            debug!(
+                "webhook {} was deleted, dropping {}",
+                target.webhook.id, event_name
+            );
+            return;
+        }
+        warn!(
+            "giving up on delivering {} to webhook {} after {} attempts: {}",
+            event_name, target.webhook.id, attempts, error
+        );
+        self.dead_letter(target, event_name, body, attempts, &error)
+            .await;
+    }
+
+    async fn dead_letter(
+        &self,
+        target: &Target,
+        event_name: &str,
+        body: &str,
+        attempts: u32,
+        error: &str,
+    ) {
+        let body = serde_json::from_str(body).unwrap_or_default();
+        if let Err(e) = self
+            .db
+            .insert_webhook_dead_letter(target.webhook.id, event_name, &body, attempts, error)
+            .await
+        {
+            error!(
+                "failed to keep dead letter for webhook {}: {}",
+                target.webhook.id, e
+            );
+        }
+    }
+
+    async fn post(
+        &self,
+        target: &Target,
+        event_name: &str,
+        delivery_id: &str,
+        body: &str,
+    ) -> Result<(), DeliveryError> {
+        let timestamp = Utc::now().timestamp();
+        let response = self
+            .client
+            .post(&target.webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event_name)
+            .header(DELIVERY_HEADER, delivery_id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(
+                SIGNATURE_HEADER,
+                format!("sha256={}", sign(&target.secret, timestamp, body)),
+            )
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| DeliveryError {
+                message: e.to_string(),
+                retryable: true,
+            })?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        Err(DeliveryError {
+            message: format!("HTTP {}", status),
+            // other client errors won't go away by sending the same request again
+            retryable: status.is_server_error()
+                || status == StatusCode::TOO_MANY_REQUESTS
+                || status == StatusCode::REQUEST_TIMEOUT,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, routing::post, Router};
+    use cubby_events::send_event;
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+
+    type Request = (HeaderMap, String);
+
+    /// A receiver answering with its statuses in turn, then 200, and reporting the
+    /// requests it gets
+    #[derive(Clone)]
+    struct StandIn {
+        statuses: Arc<Mutex<std::vec::IntoIter<u16>>>,
+        requests: mpsc::UnboundedSender<Request>,
+    }
+
+    async fn receive(
+        State(stand_in): State<StandIn>,
+        headers: HeaderMap,
+        body: String,
+    ) -> axum::http::StatusCode {
+        // picked before the test hears of the request, which may send the next one
+        let status = stand_in.statuses.lock().unwrap().next().unwrap_or(200);
+        let _ = stand_in.requests.send((headers, body));
+        axum::http::StatusCode::from_u16(status).unwrap()
+    }
+
+    async fn stand_in(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<Request>) {
+        let (requests, requests_rx) = mpsc::unbounded_channel();
+        let stand_in = StandIn {
+            statuses: Arc::new(Mutex::new(statuses.into_iter())),
+            requests,
+        };
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(stand_in);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests_rx)
+    }
+
+    async fn manager_with(first_delay: Duration, queue_capacity: usize) -> Arc<WebhookManager> {
+        let db = Arc::new(DatabaseManager::new("sqlite::memory:").await.unwrap());
+        WebhookManager::start_with(
+            db,
+            RetryPolicy {
+                max_attempts: 3,
+                first_delay,
+            },
+            queue_capacity,
+        )
+    }
+
+    async fn manager() -> Arc<WebhookManager> {
+        manager_with(Duration::from_millis(10), QUEUE_CAPACITY).await
+    }
+
+    fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+        headers.get(name).unwrap().to_str().unwrap()
+    }
+
+    #[test]
+    fn test_sign() {
+        // echo -n '1700000000.{}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", 1_700_000_000, "{}"),
+            "b8569b78799ff9e3cbff0fc2d63a33a2b57f3282abd07c37ae5e8e7d79a5f163"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed_and_retried() {
+        let (url, mut requests) = stand_in(vec![500, 503]).await;
+        let manager = manager().await;
+        manager
+            .create(&url, &["webhook_test_retried".to_string()], "secret")
+            .await
+            .unwrap();
+
+        send_event("webhook_test_ignored", json!({})).unwrap();
+        send_event("webhook_test_retried", json!({"app": "zoom"})).unwrap();
+
+        let mut deliveries = Vec::new();
+        for _ in 0..3 {
+            deliveries.push(requests.recv().await.unwrap());
+        }
+        let (headers, body) = &deliveries[2];
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["name"], "webhook_test_retried");
+        assert_eq!(body["data"]["app"], "zoom");
+        assert_eq!(header(headers, EVENT_HEADER), "webhook_test_retried");
+
+        let timestamp: i64 = header(headers, TIMESTAMP_HEADER).parse().unwrap();
+        assert_eq!(
+            header(headers, SIGNATURE_HEADER),
+            format!("sha256={}", sign("secret", timestamp, &deliveries[2].1))
+        );
+        // all attempts are the same delivery
+        assert!(deliveries
+            .iter()
+            .all(|(h, _)| header(h, DELIVERY_HEADER) == header(headers, DELIVERY_HEADER)));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(requests.try_recv().is_err());
+        assert!(manager
+            .db
+            .list_webhook_dead_letters(None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_deliveries_are_dead_lettered() {
+        let (url, mut requests) = stand_in(vec![500, 500, 500, 404]).await;
+        let manager = manager().await;
+        let webhook = manager
+            .create(&url, &["webhook_test_dead_*".to_string()], "secret")
+            .await
+            .unwrap();
+
+        send_event("webhook_test_dead_letter", json!({"n": 1})).unwrap();
+        for _ in 0..3 {
+            requests.recv().await.unwrap();
+        }
+        // a client error is not retried
+        send_event("webhook_test_dead_client_error", json!({"n": 2})).unwrap();
+        requests.recv().await.unwrap();
+
+        let mut dead_letters = Vec::new();
+        for _ in 0..50 {
+            dead_letters = manager
+                .db
+                .list_webhook_dead_letters(Some(webhook.id), 10)
+                .await
+                .unwrap();
+            if dead_letters.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(dead_letters.len(), 2);
+        let dead_letter = |name: &str| {
+            dead_letters
+                .iter()
+                .find(|dead_letter| dead_letter.event_name == name)
+                .unwrap()
+        };
+        let client_error = dead_letter("webhook_test_dead_client_error");
+        assert_eq!(client_error.attempts, 1);
+        assert_eq!(client_error.last_error, "HTTP 404 Not Found");
+        let server_error = dead_letter("webhook_test_dead_letter");
+        assert_eq!(server_error.attempts, 3);
+        assert_eq!(server_error.body["data"]["n"], 1);
+
+        // the stand-in answers 200 from now on
+        manager.redeliver(server_error).await.unwrap();
+        let (_, body) = requests.recv().await.unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            server_error.body
+        );
+        assert_eq!(
+            manager
+                .db
+                .list_webhook_dead_letters(Some(webhook.id), 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliveries_are_queued_in_order() {
+        let (url, mut requests) = stand_in(vec![500]).await;
+        // one delivery waits while the first one is retried
+        let manager = manager_with(Duration::from_millis(300), 1).await;
+        let webhook = manager
+            .create(&url, &["webhook_test_queued".to_string()], "secret")
+            .await
+            .unwrap();
+
+        send_event("webhook_test_queued", json!({"n": 1})).unwrap();
+        requests.recv().await.unwrap();
+        send_event("webhook_test_queued", json!({"n": 2})).unwrap();
+        send_event("webhook_test_queued", json!({"n": 3})).unwrap();
+
+        let mut delivered = Vec::new();
+        for _ in 0..2 {
+            let (_, body) = requests.recv().await.unwrap();
+            delivered.push(serde_json::from_str::<Value>(&body).unwrap()["data"]["n"].clone());
+        }
+        assert_eq!(delivered, vec![json!(1), json!(2)]);
+
+        let dead_letters = manager
+            .db
+            .list_webhook_dead_letters(Some(webhook.id), 10)
+            .await
+            .unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].body["data"]["n"], 3);
+        assert_eq!(dead_letters[0].attempts, 0);
+    }
+}
@@ -1,4 +1,4 @@
-use cubby_server::chunking::text_chunking_simple;
+use cubby_server::chunking::{text_chunking_by_lines, text_chunking_simple};
 
 #[test]
 fn test_text_chunking_with_chinese_characters() {
@@ -21,3 +21,21 @@ fn test_text_chunking_with_chinese_characters() {
         );
     }
 }
+
+#[test]
+fn test_text_chunking_by_lines() {
+    let text = "File  Edit  View\n\n  pricing change  \nnext quarter\n".to_string()
+        + &"x".repeat(50)
+        + "\nlast line";
+    let chunks = text_chunking_by_lines(&text, 30).unwrap();
+
+    assert_eq!(chunks[0], "File  Edit  View");
+    assert_eq!(chunks[1], "pricing change\nnext quarter");
+    // a line longer than a chunk is cut every 30 characters
+    assert_eq!(chunks[2], "x".repeat(30));
+    assert_eq!(chunks[3], "x".repeat(20));
+    assert_eq!(chunks[4], "last line");
+    assert_eq!(chunks.len(), 5);
+
+    assert!(text_chunking_by_lines(" \n\n", 30).unwrap().is_empty());
+}
@@ -7,7 +7,10 @@ mod tests {
     use chrono::DateTime;
     use chrono::{Duration, Utc};
     use cubby_audio::audio_manager::AudioManagerBuilder;
-    use cubby_db::{ContentType, DatabaseManager, SearchResult};
+    use cubby_db::{
+        ContentType, DatabaseManager, RawSqlColumn, RawSqlOptions, RetentionConfig, SearchResult,
+    };
+    use cubby_server::auth::AuthConfig;
     use cubby_server::PipeManager;
     use cubby_server::SCServer;
     use cubby_server::{ContentItem, PaginatedResponse};
@@ -48,7 +51,11 @@ mod tests {
             false,
             false,
             audio_manager,
-            true,
+            RetentionConfig::default(),
+            RawSqlOptions::default(),
+            AuthConfig::default(),
+            None,
+            None,
         );
 
         let router = app.create_router(true).await;
@@ -169,6 +176,42 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_raw_sql_reports_columns() {
+        let (app, db) = setup_test_app().await;
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/raw_sql")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"query": "SELECT id, file_path FROM video_chunks"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-cubby-truncated"], "false");
+        let columns: Vec<RawSqlColumn> =
+            serde_json::from_slice(response.headers()["x-cubby-columns"].as_bytes()).unwrap();
+        let columns: Vec<(&str, &str)> = columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.decl_type.as_str()))
+            .collect();
+        assert_eq!(columns, [("id", "INTEGER"), ("file_path", "TEXT")]);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rows: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["file_path"], "test_video.mp4");
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_count_search_results() {
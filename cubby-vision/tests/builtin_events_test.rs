@@ -0,0 +1,60 @@
+use cubby_events::BuiltinEvent;
+use cubby_vision::core::WindowOcr;
+use cubby_vision::UIFrame;
+use image::{DynamicImage, RgbImage};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// An event as `send_event` publishes it and `/ws/events` sends it
+fn published(name: &str, data: impl Serialize) -> Value {
+    json!({"name": name, "data": serde_json::to_value(data).unwrap()})
+}
+
+/// The schema of a built-in event reads back everything its producer sends, fields the
+/// producer gains or changes and the schema misses fail here
+fn assert_round_trips(event: Value) {
+    let builtin: BuiltinEvent = serde_json::from_value(event.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&builtin).unwrap(), event);
+}
+
+#[test]
+fn test_ocr_result_matches_window_ocr() {
+    let text_block = HashMap::from([
+        ("text".to_string(), "hello".to_string()),
+        ("left".to_string(), "12".to_string()),
+    ]);
+    for image in [None, Some(DynamicImage::ImageRgb8(RgbImage::new(4, 4)))] {
+        for browser_url in [None, Some("https://example.com".to_string())] {
+            let event = published(
+                "ocr_result",
+                WindowOcr {
+                    image: image.clone(),
+                    window_name: "inbox".to_string(),
+                    app_name: "Mail".to_string(),
+                    text: "hello".to_string(),
+                    text_json: vec![text_block.clone()],
+                    focused: true,
+                    confidence: 0.5,
+                    timestamp: Instant::now(),
+                    browser_url,
+                },
+            );
+            assert_round_trips(event);
+        }
+    }
+}
+
+#[test]
+fn test_ui_frame_matches_ui_frame() {
+    assert_round_trips(published(
+        "ui_frame",
+        UIFrame {
+            window: "inbox".to_string(),
+            app: "Mail".to_string(),
+            text_output: "hello".to_string(),
+            initial_traversal_at: "2026-10-17T12:00:00Z".to_string(),
+        },
+    ));
+}